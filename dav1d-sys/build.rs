@@ -0,0 +1,42 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+    println!("cargo:rerun-if-changed=wrapper.h");
+
+    // Use pkg-config to find dav1d (also emits the link flags)
+    let lib = pkg_config::Config::new()
+        .atleast_version("1.0.0")
+        .probe("dav1d")
+        .expect("dav1d not found. Install with: brew install dav1d");
+
+    // Get include path from pkg-config
+    let include_path = lib
+        .include_paths
+        .first()
+        .expect("No include path from pkg-config");
+
+    // Generate bindings
+    let bindings = bindgen::Builder::default()
+        .header("wrapper.h")
+        .clang_arg(format!("-I{}", include_path.display()))
+        // Only generate bindings for dav1d types
+        .allowlist_type("Dav1d.*")
+        .allowlist_function("dav1d_.*")
+        .allowlist_var("DAV1D_.*")
+        // Use core instead of std
+        .use_core()
+        // Add derives
+        .derive_debug(true)
+        .derive_default(true)
+        // Generate
+        .parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+        .generate()
+        .expect("Failed to generate bindings");
+
+    // Write bindings to OUT_DIR
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    bindings
+        .write_to_file(out_path.join("bindings.rs"))
+        .expect("Failed to write bindings");
+}
@@ -0,0 +1,61 @@
+//! Low-level FFI bindings to the dav1d AV1 decoder
+//!
+//! This crate provides unsafe bindings to the dav1d C library.
+//! For safe wrappers, see the `mead` CLI crate.
+//!
+//! # Safety
+//! This is a `-sys` crate containing raw FFI bindings. All functions are `unsafe`.
+//! Users must ensure:
+//! - Every `dav1d_open` is paired with `dav1d_close`
+//! - Every picture returned by `dav1d_get_picture` is released with `dav1d_picture_unref`
+//! - Data buffers are created with `dav1d_data_create` and never used after being sent
+//!
+//! # Architecture Note
+//! This crate is NOT used by `mead-core`, which remains `#![forbid(unsafe_code)]`.
+//! Only the `mead` CLI uses these bindings, behind its `dav1d` feature.
+
+#![allow(non_upper_case_globals)]
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(dead_code)]
+
+// Include generated bindings
+include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+/// `DAV1D_ERR(EAGAIN)`: the decoder needs pictures drained before taking more data
+///
+/// `DAV1D_ERR` is a function-like macro, which bindgen cannot translate.
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+))]
+pub const DAV1D_EAGAIN: core::ffi::c_int = -35;
+
+/// `DAV1D_ERR(EAGAIN)`: the decoder needs pictures drained before taking more data
+///
+/// `DAV1D_ERR` is a function-like macro, which bindgen cannot translate.
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+)))]
+pub const DAV1D_EAGAIN: core::ffi::c_int = -11;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constants_exist() {
+        // Verify key constants are generated
+        let _ = DAV1D_API_VERSION_MAJOR;
+        let _ = Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I420;
+    }
+}
@@ -0,0 +1,3 @@
+// Wrapper header for dav1d decoder bindings
+
+#include <dav1d/dav1d.h>
@@ -1,33 +1,497 @@
 //! AAC audio codec support using symphonia
 //!
-//! Note: This is a placeholder implementation. Proper AAC decoding
-//! requires ADTS header parsing and packetization.
+//! Decodes AAC-LC (mono or stereo) from raw access units, as stored in MP4
+//! with an `esds` AudioSpecificConfig, or from ADTS streams, where every
+//! frame carries its own header.
 
-use crate::{Error, Result};
 use super::AudioDecoder;
+use crate::{Error, Result};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CODEC_TYPE_AAC, CodecParameters, Decoder, DecoderOptions};
+use symphonia::core::formats::Packet as SymphoniaPacket;
+use symphonia::default::codecs::AacDecoder as SymphoniaAacDecoder;
+
+/// Sampling frequencies indexed by `samplingFrequencyIndex` (ISO/IEC 14496-3)
+const SAMPLE_RATES: [u32; 13] = [
+    96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+];
+
+/// Audio object type for AAC-LC
+pub const AOT_AAC_LC: u8 = 2;
+
+/// Samples per channel in one AAC-LC frame
+pub const FRAME_SAMPLES: usize = 1024;
+
+/// Upper bound on buffered ADTS data while searching for a sync word
+const MAX_ADTS_BUFFER: usize = 64 * 1024;
+
+/// AAC stream configuration
+///
+/// Parsed from an AudioSpecificConfig (MP4 `esds`, Matroska CodecPrivate) or
+/// an ADTS header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AacConfig {
+    /// Audio object type (2 = AAC-LC)
+    pub object_type: u8,
+    /// Sampling frequency in Hz
+    pub sample_rate: u32,
+    /// Channel configuration (1 = mono, 2 = stereo, 0 = defined in-band)
+    pub channels: u8,
+}
+
+impl AacConfig {
+    /// Create an AAC-LC configuration
+    pub fn lc(sample_rate: u32, channels: u8) -> Self {
+        Self {
+            object_type: AOT_AAC_LC,
+            sample_rate,
+            channels,
+        }
+    }
+
+    /// Build a configuration from `esds` fields (object type, frequency index, channels)
+    pub fn from_indices(object_type: u8, frequency_index: u8, channels: u8) -> Result<Self> {
+        let sample_rate = *SAMPLE_RATES
+            .get(usize::from(frequency_index))
+            .ok_or_else(|| {
+                Error::Codec(format!(
+                    "Invalid AAC sampling frequency index {}",
+                    frequency_index
+                ))
+            })?;
+        Ok(Self {
+            object_type,
+            sample_rate,
+            channels,
+        })
+    }
+
+    /// Parse an AudioSpecificConfig
+    pub fn parse_audio_specific_config(data: &[u8]) -> Result<Self> {
+        let invalid = || Error::Codec("Truncated AAC AudioSpecificConfig".to_string());
+        let mut bits = BitReader::new(data);
+
+        let mut object_type = bits.read(5).ok_or_else(invalid)? as u8;
+        if object_type == 31 {
+            object_type = 32 + bits.read(6).ok_or_else(invalid)? as u8;
+        }
+
+        let frequency_index = bits.read(4).ok_or_else(invalid)? as u8;
+        let sample_rate = if frequency_index == 15 {
+            bits.read(24).ok_or_else(invalid)?
+        } else {
+            Self::from_indices(object_type, frequency_index, 0)?.sample_rate
+        };
+        let channels = bits.read(4).ok_or_else(invalid)? as u8;
+
+        Ok(Self {
+            object_type,
+            sample_rate,
+            channels,
+        })
+    }
 
-/// AAC audio decoder (placeholder)
-pub struct AacDecoder;
+    /// Serialize as a 2-byte AudioSpecificConfig (5-byte with an explicit rate)
+    pub fn to_audio_specific_config(&self) -> Vec<u8> {
+        let mut value: u64;
+        let mut len: u32;
+        match self.frequency_index() {
+            Some(index) => {
+                value = (u64::from(self.object_type) << 4) | u64::from(index);
+                len = 9;
+            }
+            None => {
+                value = (u64::from(self.object_type) << 28)
+                    | (0xF << 24)
+                    | u64::from(self.sample_rate & 0xFF_FFFF);
+                len = 33;
+            }
+        }
+        // channelConfiguration, then the GASpecificConfig flags (all zero)
+        value = ((value << 4) | u64::from(self.channels & 0x0F)) << 3;
+        len += 4 + 3;
+
+        let padded = len.div_ceil(8) * 8;
+        value <<= padded - len;
+        (0..padded / 8)
+            .rev()
+            .map(|i| (value >> (i * 8)) as u8)
+            .collect()
+    }
+
+    /// `samplingFrequencyIndex` for standard rates
+    pub fn frequency_index(&self) -> Option<u8> {
+        SAMPLE_RATES
+            .iter()
+            .position(|&rate| rate == self.sample_rate)
+            .map(|index| index as u8)
+    }
+}
+
+/// ADTS frame header (ISO/IEC 13818-7 section 6.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdtsHeader {
+    /// Stream configuration
+    pub config: AacConfig,
+    /// Header length: 7 bytes, or 9 with a CRC
+    pub header_len: usize,
+    /// Frame length including the header
+    pub frame_len: usize,
+    /// Raw data blocks in the frame
+    pub raw_blocks: u8,
+}
+
+impl AdtsHeader {
+    /// Parse an ADTS header from the start of `data`
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 7 {
+            return Err(Error::Codec("Truncated ADTS header".to_string()));
+        }
+        if data[0] != 0xFF || data[1] & 0xF6 != 0xF0 {
+            return Err(Error::Codec("Missing ADTS sync word".to_string()));
+        }
+
+        let protection_absent = data[1] & 0x01 != 0;
+        let object_type = (data[2] >> 6) + 1;
+        let frequency_index = (data[2] >> 2) & 0x0F;
+        let channels = ((data[2] & 0x01) << 2) | (data[3] >> 6);
+        let frame_len = (usize::from(data[3] & 0x03) << 11)
+            | (usize::from(data[4]) << 3)
+            | usize::from(data[5] >> 5);
+        let raw_blocks = (data[6] & 0x03) + 1;
+        let header_len = if protection_absent { 7 } else { 9 };
+
+        if frame_len < header_len {
+            return Err(Error::Codec(format!(
+                "Invalid ADTS frame length {}",
+                frame_len
+            )));
+        }
+
+        Ok(Self {
+            config: AacConfig::from_indices(object_type, frequency_index, channels)?,
+            header_len,
+            frame_len,
+            raw_blocks,
+        })
+    }
+}
+
+/// AAC-LC audio decoder
+///
+/// Decoded samples are interleaved f32 at the stream's sample rate.
+///
+/// # Example
+/// ```no_run
+/// use mead_core::codec::aac::{AacConfig, AacDecoder};
+/// use mead_core::codec::AudioDecoder;
+///
+/// // AudioSpecificConfig from the MP4 esds box
+/// let config = AacConfig::parse_audio_specific_config(&[0x12, 0x10])?;
+/// let mut decoder = AacDecoder::new(config)?;
+///
+/// let access_unit: Vec<u8> = vec![/* raw AAC frame */];
+/// if let Some(samples) = decoder.decode(&access_unit)? {
+///     println!("{} samples", samples.len() / decoder.channels());
+/// }
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+pub struct AacDecoder {
+    /// Created from the first ADTS header when not configured up front
+    decoder: Option<SymphoniaAacDecoder>,
+    config: Option<AacConfig>,
+    adts: bool,
+    /// Partial ADTS frame carried over to the next call
+    pending: Vec<u8>,
+    sample_buf: Option<SampleBuffer<f32>>,
+}
 
 impl std::fmt::Debug for AacDecoder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("AacDecoder").finish()
+        f.debug_struct("AacDecoder")
+            .field("config", &self.config)
+            .field("adts", &self.adts)
+            .field("pending", &self.pending.len())
+            .finish()
     }
 }
 
 impl AacDecoder {
-    /// Create a new AAC decoder
-    pub fn new() -> Result<Self> {
-        // AAC decoding requires proper implementation with:
-        // 1. ADTS header parsing
-        // 2. Packetization
-        // 3. Symphonia format probing
-        Err(Error::Codec("AAC decoder not yet implemented - requires ADTS parsing".to_string()))
+    /// Create a decoder for raw access units (MP4 samples)
+    pub fn new(config: AacConfig) -> Result<Self> {
+        Ok(Self {
+            decoder: Some(create_decoder(&config)?),
+            config: Some(config),
+            adts: false,
+            pending: Vec::new(),
+            sample_buf: None,
+        })
+    }
+
+    /// Create a decoder for an ADTS stream
+    ///
+    /// Input may be split anywhere: incomplete frames are kept until the
+    /// next call, and the decoder is configured from the first header.
+    pub fn new_adts() -> Self {
+        Self {
+            decoder: None,
+            config: None,
+            adts: true,
+            pending: Vec::new(),
+            sample_buf: None,
+        }
+    }
+
+    /// Get the stream configuration (`None` until the first ADTS header)
+    pub fn config(&self) -> Option<AacConfig> {
+        self.config
+    }
+
+    /// Number of interleaved output channels
+    pub fn channels(&self) -> usize {
+        self.config.map_or(0, |config| usize::from(config.channels))
+    }
+
+    /// Output sample rate in Hz
+    pub fn sample_rate(&self) -> u32 {
+        self.config.map_or(0, |config| config.sample_rate)
+    }
+
+    /// Decode one raw access unit, appending interleaved samples to `output`
+    fn decode_frame(&mut self, data: &[u8], output: &mut Vec<f32>) -> Result<()> {
+        let decoder = self
+            .decoder
+            .as_mut()
+            .ok_or_else(|| Error::Codec("AAC decoder is not configured".to_string()))?;
+
+        let packet = SymphoniaPacket::new_from_slice(0, 0, FRAME_SAMPLES as u64, data);
+        let decoded = decoder
+            .decode(&packet)
+            .map_err(|e| Error::Codec(format!("AAC decoding error: {}", e)))?;
+
+        let spec = *decoded.spec();
+        let frames = decoded.frames();
+        let sample_buf = match &mut self.sample_buf {
+            Some(buf) if buf.capacity() >= frames * spec.channels.count() => buf,
+            slot => slot.insert(SampleBuffer::new(frames as u64, spec)),
+        };
+        sample_buf.copy_interleaved_ref(decoded);
+        output.extend_from_slice(sample_buf.samples());
+        Ok(())
+    }
+
+    /// Decode every complete ADTS frame in the pending buffer
+    fn decode_adts(&mut self, output: &mut Vec<f32>) -> Result<()> {
+        let mut pos = 0;
+
+        while self.pending.len() - pos >= 7 {
+            // Resynchronize on the next sync word after corrupt data
+            if self.pending[pos] != 0xFF || self.pending[pos + 1] & 0xF6 != 0xF0 {
+                pos += 1;
+                continue;
+            }
+
+            // A sync word inside junk may not start a valid header
+            let Ok(header) = AdtsHeader::parse(&self.pending[pos..]) else {
+                pos += 1;
+                continue;
+            };
+            if self.pending.len() - pos < header.frame_len {
+                break;
+            }
+            if header.raw_blocks != 1 {
+                return Err(Error::UnsupportedFormat(format!(
+                    "ADTS frames with {} raw data blocks",
+                    header.raw_blocks
+                )));
+            }
+
+            match self.config {
+                None => {
+                    self.decoder = Some(create_decoder(&header.config)?);
+                    self.config = Some(header.config);
+                }
+                Some(config) if config != header.config => {
+                    return Err(Error::UnsupportedFormat(
+                        "AAC configuration change mid-stream".to_string(),
+                    ));
+                }
+                Some(_) => {}
+            }
+
+            let frame = self.pending[pos + header.header_len..pos + header.frame_len].to_vec();
+            self.decode_frame(&frame, output)?;
+            pos += header.frame_len;
+        }
+
+        self.pending.drain(..pos);
+        if self.pending.len() > MAX_ADTS_BUFFER {
+            return Err(Error::Codec("No ADTS sync word found".to_string()));
+        }
+        Ok(())
     }
 }
 
 impl AudioDecoder for AacDecoder {
-    fn decode(&mut self, _data: &[u8]) -> Result<Option<Vec<f32>>> {
-        Err(Error::Codec("AAC decoding requires proper packetization".to_string()))
+    fn decode(&mut self, data: &[u8]) -> Result<Option<Vec<f32>>> {
+        let mut output = Vec::new();
+
+        if self.adts {
+            self.pending.extend_from_slice(data);
+            self.decode_adts(&mut output)?;
+        } else {
+            self.decode_frame(data, &mut output)?;
+        }
+
+        Ok((!output.is_empty()).then_some(output))
+    }
+}
+
+fn create_decoder(config: &AacConfig) -> Result<SymphoniaAacDecoder> {
+    if config.object_type != AOT_AAC_LC {
+        return Err(Error::UnsupportedFormat(format!(
+            "AAC object type {} (only AAC-LC is supported)",
+            config.object_type
+        )));
+    }
+    if !matches!(config.channels, 1 | 2) {
+        return Err(Error::UnsupportedFormat(format!(
+            "AAC channel configuration {} (only mono and stereo are supported)",
+            config.channels
+        )));
+    }
+
+    let mut params = CodecParameters::new();
+    params
+        .for_codec(CODEC_TYPE_AAC)
+        .with_sample_rate(config.sample_rate)
+        .with_extra_data(config.to_audio_specific_config().into_boxed_slice());
+
+    SymphoniaAacDecoder::try_new(&params, &DecoderOptions::default())
+        .map_err(|e| Error::Codec(format!("Failed to create AAC decoder: {}", e)))
+}
+
+/// MSB-first bit reader for AudioSpecificConfig
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn read(&mut self, n: usize) -> Option<u32> {
+        let mut value = 0u32;
+        for _ in 0..n {
+            let byte = self.data.get(self.pos / 8)?;
+            value = (value << 1) | u32::from((byte >> (7 - self.pos % 8)) & 1);
+            self.pos += 1;
+        }
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Silent AAC-LC stereo frame (a single CPE with zero spectra)
+    const SILENT_STEREO_FRAME: [u8; 6] = [0x21, 0x10, 0x04, 0x60, 0x8C, 0x1C];
+
+    fn adts_frame(config: &AacConfig, payload: &[u8]) -> Vec<u8> {
+        let frame_len = payload.len() + 7;
+        let index = config.frequency_index().unwrap();
+        let mut frame = vec![
+            0xFF,
+            0xF1, // MPEG-4, no CRC
+            ((config.object_type - 1) << 6) | (index << 2) | (config.channels >> 2),
+            ((config.channels & 0x03) << 6) | (frame_len >> 11) as u8,
+            (frame_len >> 3) as u8,
+            ((frame_len & 0x07) << 5) as u8 | 0x1F,
+            0xFC,
+        ];
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    #[test]
+    fn test_audio_specific_config_round_trip() {
+        // AAC-LC, 44.1 kHz, stereo
+        let config = AacConfig::parse_audio_specific_config(&[0x12, 0x10]).unwrap();
+        assert_eq!(config, AacConfig::lc(44100, 2));
+        assert_eq!(config.to_audio_specific_config(), vec![0x12, 0x10]);
+
+        // Explicit sampling frequency
+        let odd = AacConfig::lc(40000, 1);
+        assert_eq!(odd.frequency_index(), None);
+        let asc = odd.to_audio_specific_config();
+        assert_eq!(asc.len(), 5);
+        assert_eq!(AacConfig::parse_audio_specific_config(&asc).unwrap(), odd);
+
+        assert!(AacConfig::parse_audio_specific_config(&[0x12]).is_err());
+        assert!(AacConfig::from_indices(2, 13, 2).is_err());
+    }
+
+    #[test]
+    fn test_adts_header() {
+        let config = AacConfig::lc(48000, 2);
+        let frame = adts_frame(&config, &SILENT_STEREO_FRAME);
+        let header = AdtsHeader::parse(&frame).unwrap();
+        assert_eq!(header.config, config);
+        assert_eq!(
+            (header.header_len, header.frame_len, header.raw_blocks),
+            (7, 13, 1)
+        );
+
+        assert!(AdtsHeader::parse(&frame[..5]).is_err());
+        assert!(AdtsHeader::parse(&[0u8; 7]).is_err());
+    }
+
+    #[test]
+    fn test_raw_decode() {
+        let mut decoder = AacDecoder::new(AacConfig::lc(44100, 2)).unwrap();
+        let samples = decoder.decode(&SILENT_STEREO_FRAME).unwrap().unwrap();
+        assert_eq!(samples.len(), FRAME_SAMPLES * 2);
+        assert!(samples.iter().all(|s| s.abs() < 1e-6));
+        assert_eq!((decoder.channels(), decoder.sample_rate()), (2, 44100));
+    }
+
+    #[test]
+    fn test_adts_decode_split_input() {
+        let config = AacConfig::lc(48000, 2);
+        // Garbage before the first sync word, including a false one with a
+        // zero frame length
+        let mut stream = vec![0x00, 0x42, 0xFF, 0xF1, 0x50, 0x80, 0x00, 0x00, 0xFC];
+        for _ in 0..3 {
+            stream.extend_from_slice(&adts_frame(&config, &SILENT_STEREO_FRAME));
+        }
+
+        let mut decoder = AacDecoder::new_adts();
+        let mut total = 0;
+        for chunk in stream.chunks(5) {
+            if let Some(samples) = decoder.decode(chunk).unwrap() {
+                total += samples.len();
+            }
+        }
+        assert_eq!(total, 3 * FRAME_SAMPLES * 2);
+        assert_eq!(decoder.config(), Some(config));
+    }
+
+    #[test]
+    fn test_unsupported_configs() {
+        // HE-AAC (SBR) and 5.1 are beyond symphonia's AAC-LC decoder
+        assert!(matches!(
+            AacDecoder::new(AacConfig {
+                object_type: 5,
+                ..AacConfig::lc(44100, 2)
+            }),
+            Err(Error::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            AacDecoder::new(AacConfig::lc(48000, 6)),
+            Err(Error::UnsupportedFormat(_))
+        ));
     }
 }
@@ -1,40 +1,107 @@
 //! AV1 codec support using rav1e
 
-use crate::{ArcFrame, Error, PixelFormat, Result};
-use super::VideoEncoder;
+pub mod obu;
+
+use super::preset::{Preset, Tune};
+use super::rate::{Pass, PassStats, RateControl};
+use super::registry::{EncoderCapabilities, EncoderFactory, EncoderOptions};
+use super::{EncodedPacket, FrameStats, VideoEncoder};
+use crate::{ArcFrame, Colorimetry, Error, Frame, PixelFormat, Result, color};
 use rav1e::prelude::*;
+use std::collections::VecDeque;
+
+/// Name first pass statistics are tagged with
+const STATS_ENCODER: &str = "rav1e";
 
 /// AV1 encoder configuration
 #[derive(Debug, Clone)]
 pub struct Av1Config {
     /// Encoding speed preset (0-10, lower is slower but better quality)
     pub speed: u8,
-    /// Quantizer (0-255, lower is better quality)
-    pub quantizer: u8,
-    /// Bitrate in kilobits per second
-    pub bitrate_kbps: Option<u32>,
+    /// Constant quality or target bitrate
+    pub rate_control: RateControl,
+    /// Frame rate as (numerator, denominator), which bitrates are spread over
+    pub framerate: (u64, u64),
     /// Number of tiles horizontally (power of 2, 0 = auto)
     pub tile_cols: usize,
     /// Number of tiles vertically (power of 2, 0 = auto)
     pub tile_rows: usize,
     /// Number of threads (0 = auto-detect from CPU cores)
     pub threads: usize,
+    /// Input pixel format; sets the encoded bit depth and chroma subsampling
+    pub pixel_format: PixelFormat,
+    /// Color description and HDR metadata written to the sequence header
+    pub colorimetry: Colorimetry,
+    /// Film grain the decoder adds back after decoding
+    pub film_grain: Option<FilmGrain>,
+    /// Single pass, or which pass of a two-pass encode
+    pub pass: Pass,
+    /// Content to tune for, psychovisual by default
+    pub tune: Option<Tune>,
+    /// Fewest frames between keyframes placed at scene changes
+    pub min_keyframe_interval: u64,
+    /// Most frames between keyframes
+    pub max_keyframe_interval: u64,
 }
 
 impl Default for Av1Config {
     fn default() -> Self {
         Self {
             speed: 6,
-            quantizer: 100,
-            bitrate_kbps: None,
-            tile_cols: 0,  // Auto-calculate based on resolution
-            tile_rows: 0,  // Auto-calculate based on resolution
-            threads: 0,    // Auto-detect CPU cores
+            rate_control: RateControl::Crf(25),
+            framerate: (30, 1),
+            tile_cols: 0, // Auto-calculate based on resolution
+            tile_rows: 0, // Auto-calculate based on resolution
+            threads: 0,   // Auto-detect CPU cores
+            pixel_format: PixelFormat::Yuv420p,
+            colorimetry: Colorimetry::default(),
+            film_grain: None,
+            pass: Pass::Single,
+            tune: None,
+            min_keyframe_interval: 12,
+            max_keyframe_interval: 240,
         }
     }
 }
 
 impl Av1Config {
+    /// Configuration for a preset, and the content's quality if tuned
+    ///
+    /// Slow presets encode whole frames, since tiles cost compression.
+    pub fn from_preset(preset: Preset, tune: Option<Tune>) -> Self {
+        let tiles = if preset.tiles() { 0 } else { 1 };
+        let defaults = Self::default();
+        Self {
+            speed: preset.rav1e_speed(),
+            rate_control: tune.map_or(defaults.rate_control, |tune| RateControl::Crf(tune.crf())),
+            tile_cols: tiles,
+            tile_rows: tiles,
+            tune,
+            ..defaults
+        }
+    }
+
+    /// Configuration for backend-independent options
+    pub fn from_options(options: &EncoderOptions) -> Self {
+        let defaults = Self::from_preset(options.preset, options.tune);
+        Self {
+            rate_control: options.rate_control.unwrap_or(defaults.rate_control),
+            framerate: options.framerate,
+            threads: options.threads,
+            pixel_format: options.pixel_format,
+            colorimetry: options.colorimetry,
+            film_grain: options.film_grain.clone(),
+            pass: options.pass.clone(),
+            min_keyframe_interval: options
+                .min_keyframe_interval
+                .unwrap_or(defaults.min_keyframe_interval),
+            max_keyframe_interval: options
+                .max_keyframe_interval
+                .unwrap_or(defaults.max_keyframe_interval),
+            ..defaults
+        }
+    }
+
     /// Calculate optimal tile configuration for given resolution
     ///
     /// Rules:
@@ -79,11 +146,425 @@ impl Av1Config {
     }
 }
 
+/// AV1 film grain synthesis parameters
+///
+/// Each plane's grain strength is a piecewise linear function of the
+/// sample level, given as `(level, scaling)` points on an 8-bit scale with
+/// increasing levels. The decoder adds Gaussian grain with a standard
+/// deviation of about `scaling / 7.88` 8-bit steps; a plane without points
+/// gets no grain. Chroma grain follows the chroma level.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FilmGrain {
+    /// Luma scaling points, at most 14
+    pub luma: Vec<(u8, u8)>,
+    /// Cb scaling points, at most 10
+    pub cb: Vec<(u8, u8)>,
+    /// Cr scaling points, at most 10
+    pub cr: Vec<(u8, u8)>,
+    /// Seed of the grain pattern
+    pub seed: u16,
+}
+
+impl FilmGrain {
+    /// Most luma scaling points AV1 can signal
+    pub const MAX_LUMA_POINTS: usize = 14;
+    /// Most scaling points per chroma plane AV1 can signal
+    pub const MAX_CHROMA_POINTS: usize = 10;
+
+    /// Check the points fit the AV1 film grain syntax
+    pub fn validate(&self) -> Result<()> {
+        let planes = [
+            ("luma", &self.luma, Self::MAX_LUMA_POINTS),
+            ("Cb", &self.cb, Self::MAX_CHROMA_POINTS),
+            ("Cr", &self.cr, Self::MAX_CHROMA_POINTS),
+        ];
+        for (name, points, max) in planes {
+            if points.len() > max {
+                return Err(Error::InvalidInput(format!(
+                    "Film grain has {} {} points, at most {} are allowed",
+                    points.len(),
+                    name,
+                    max
+                )));
+            }
+            if points.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+                return Err(Error::InvalidInput(format!(
+                    "Film grain {} points must have increasing levels",
+                    name
+                )));
+            }
+        }
+        // 4:2:0 streams cannot signal Cr grain without Cb grain
+        if self.cb.is_empty() != self.cr.is_empty() {
+            return Err(Error::InvalidInput(
+                "Film grain needs both or neither chroma planes".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// rav1e grain table covering the whole stream
+    fn segment(&self) -> GrainTableSegment {
+        let chroma = !self.cb.is_empty();
+        GrainTableSegment {
+            start_time: 0,
+            end_time: u64::MAX,
+            scaling_points_y: self
+                .luma
+                .iter()
+                .map(|&(level, scaling)| [level, scaling])
+                .collect(),
+            scaling_points_cb: self
+                .cb
+                .iter()
+                .map(|&(level, scaling)| [level, scaling])
+                .collect(),
+            scaling_points_cr: self
+                .cr
+                .iter()
+                .map(|&(level, scaling)| [level, scaling])
+                .collect(),
+            scaling_shift: 8,
+            ar_coeff_lag: 0,
+            ar_coeffs_y: Default::default(),
+            // With lag 0, chroma planes with grain take one coefficient
+            // for their correlation with luma grain
+            ar_coeffs_cb: (if chroma { [0].as_slice() } else { &[] })
+                .iter()
+                .copied()
+                .collect(),
+            ar_coeffs_cr: (if chroma { [0].as_slice() } else { &[] })
+                .iter()
+                .copied()
+                .collect(),
+            ar_coeff_shift: 6,
+            // Index chroma scaling by the chroma level alone
+            cb_mult: 192,
+            cb_luma_mult: 128,
+            cb_offset: 256,
+            cr_mult: 192,
+            cr_luma_mult: 128,
+            cr_offset: 256,
+            overlap_flag: true,
+            chroma_scaling_from_luma: false,
+            grain_scale_shift: 0,
+            random_seed: self.seed,
+        }
+    }
+}
+
+/// Map rate control onto rav1e's quantizer, bitrate and reservoir
+fn apply_rate_control(
+    enc_config: &mut EncoderConfig,
+    rate_control: &RateControl,
+    framerate: (u64, u64),
+) {
+    match *rate_control {
+        // rav1e quantizers are AV1 quantizer indices, four per CRF step
+        RateControl::Crf(crf) => enc_config.quantizer = usize::from(crf) * 4,
+        RateControl::Vbr {
+            bitrate,
+            max_bitrate,
+            ..
+        } => {
+            if max_bitrate.is_some() {
+                tracing::warn!("rav1e cannot cap the bitrate, ignoring the max bitrate");
+            }
+            enc_config.bitrate = i32::try_from(u64::from(bitrate) * 1000).unwrap_or(i32::MAX);
+            // In bitrate mode the quantizer is an upper bound; leave it open
+            enc_config.quantizer = 255;
+        }
+        RateControl::Cbr { bitrate, .. } => {
+            enc_config.bitrate = i32::try_from(u64::from(bitrate) * 1000).unwrap_or(i32::MAX);
+            enc_config.quantizer = 255;
+            enc_config.low_latency = true;
+        }
+    }
+
+    // The rate buffer is a reservoir counted in frames
+    let buffer_ms = match rate_control {
+        RateControl::Cbr {
+            buffer_size: None, ..
+        } => Some(1000),
+        _ => rate_control.buffer_ms(),
+    };
+    if let Some(buffer_ms) = buffer_ms {
+        let (num, den) = framerate;
+        let frames = buffer_ms * num / (den * 1000);
+        enc_config.reservoir_frame_delay = Some(frames.clamp(12, 131_072) as i32);
+    }
+}
+
+/// Map colorimetry onto the rav1e sequence header and HDR metadata fields
+fn apply_colorimetry(enc_config: &mut EncoderConfig, colorimetry: &Colorimetry) {
+    if colorimetry.has_color_description() {
+        enc_config.color_description = Some(ColorDescription {
+            color_primaries: rav1e_primaries(colorimetry.primaries),
+            transfer_characteristics: rav1e_transfer(colorimetry.transfer),
+            matrix_coefficients: rav1e_matrix(colorimetry.matrix),
+        });
+    }
+
+    enc_config.pixel_range = match colorimetry.range {
+        color::ColorRange::Limited => PixelRange::Limited,
+        color::ColorRange::Full => PixelRange::Full,
+    };
+
+    enc_config.chroma_sample_position = match colorimetry.chroma_position {
+        color::ChromaSamplePosition::Unknown => ChromaSamplePosition::Unknown,
+        color::ChromaSamplePosition::Vertical => ChromaSamplePosition::Vertical,
+        color::ChromaSamplePosition::Colocated => ChromaSamplePosition::Colocated,
+    };
+
+    enc_config.mastering_display = colorimetry.mastering_display.map(|display| {
+        let point = |(x, y)| ChromaticityPoint { x, y };
+        MasteringDisplay {
+            primaries: display.primaries.map(point),
+            white_point: point(display.white_point),
+            max_luminance: display.max_luminance,
+            min_luminance: display.min_luminance,
+        }
+    });
+
+    enc_config.content_light = colorimetry.content_light.map(|level| ContentLight {
+        max_content_light_level: level.max_cll,
+        max_frame_average_light_level: level.max_fall,
+    });
+}
+
+fn rav1e_primaries(primaries: color::ColorPrimaries) -> ColorPrimaries {
+    use color::ColorPrimaries as P;
+    match primaries {
+        P::Bt709 => ColorPrimaries::BT709,
+        P::Unspecified => ColorPrimaries::Unspecified,
+        P::Bt470M => ColorPrimaries::BT470M,
+        P::Bt470Bg => ColorPrimaries::BT470BG,
+        P::Bt601 => ColorPrimaries::BT601,
+        P::Smpte240 => ColorPrimaries::SMPTE240,
+        P::GenericFilm => ColorPrimaries::GenericFilm,
+        P::Bt2020 => ColorPrimaries::BT2020,
+        P::Xyz => ColorPrimaries::XYZ,
+        P::Smpte431 => ColorPrimaries::SMPTE431,
+        P::Smpte432 => ColorPrimaries::SMPTE432,
+        P::Ebu3213 => ColorPrimaries::EBU3213,
+    }
+}
+
+fn rav1e_transfer(transfer: color::TransferCharacteristics) -> TransferCharacteristics {
+    use color::TransferCharacteristics as T;
+    match transfer {
+        T::Bt709 => TransferCharacteristics::BT709,
+        T::Unspecified => TransferCharacteristics::Unspecified,
+        T::Bt470M => TransferCharacteristics::BT470M,
+        T::Bt470Bg => TransferCharacteristics::BT470BG,
+        T::Bt601 => TransferCharacteristics::BT601,
+        T::Smpte240 => TransferCharacteristics::SMPTE240,
+        T::Linear => TransferCharacteristics::Linear,
+        T::Log100 => TransferCharacteristics::Log100,
+        T::Log100Sqrt10 => TransferCharacteristics::Log100Sqrt10,
+        T::Iec61966 => TransferCharacteristics::IEC61966,
+        T::Bt1361 => TransferCharacteristics::BT1361,
+        T::Srgb => TransferCharacteristics::SRGB,
+        T::Bt2020Ten => TransferCharacteristics::BT2020_10Bit,
+        T::Bt2020Twelve => TransferCharacteristics::BT2020_12Bit,
+        T::Smpte2084 => TransferCharacteristics::SMPTE2084,
+        T::Smpte428 => TransferCharacteristics::SMPTE428,
+        T::Hlg => TransferCharacteristics::HLG,
+    }
+}
+
+fn rav1e_matrix(matrix: color::MatrixCoefficients) -> MatrixCoefficients {
+    use color::MatrixCoefficients as M;
+    match matrix {
+        M::Identity => MatrixCoefficients::Identity,
+        M::Bt709 => MatrixCoefficients::BT709,
+        M::Unspecified => MatrixCoefficients::Unspecified,
+        M::Fcc => MatrixCoefficients::FCC,
+        M::Bt470Bg => MatrixCoefficients::BT470BG,
+        M::Bt601 => MatrixCoefficients::BT601,
+        M::Smpte240 => MatrixCoefficients::SMPTE240,
+        M::YCgCo => MatrixCoefficients::YCgCo,
+        M::Bt2020Ncl => MatrixCoefficients::BT2020NCL,
+        M::Bt2020Cl => MatrixCoefficients::BT2020CL,
+        M::Smpte2085 => MatrixCoefficients::SMPTE2085,
+        M::ChromatNcl => MatrixCoefficients::ChromatNCL,
+        M::ChromatCl => MatrixCoefficients::ChromatCL,
+        M::ICtCp => MatrixCoefficients::ICtCp,
+    }
+}
+
+/// rav1e context for the input bit depth
+enum EncoderContext {
+    /// 8-bit input
+    Low(Context<u8>),
+    /// 10-bit and 12-bit input
+    High(Context<u16>),
+}
+
+impl EncoderContext {
+    fn send_frame(&mut self, frame: &Frame, pts: i64) -> Result<()> {
+        match self {
+            Self::Low(context) => send_to_context(context, frame, pts),
+            Self::High(context) => send_to_context(context, frame, pts),
+        }
+    }
+
+    fn receive_packet(&mut self) -> std::result::Result<EncodedPacket, EncoderStatus> {
+        match self {
+            Self::Low(context) => context.receive_packet().map(encoded_packet),
+            Self::High(context) => context.receive_packet().map(encoded_packet),
+        }
+    }
+
+    fn flush(&mut self) {
+        match self {
+            Self::Low(context) => context.flush(),
+            Self::High(context) => context.flush(),
+        }
+    }
+
+    fn receive_pass_data(&mut self) -> Option<RcData> {
+        match self {
+            Self::Low(context) => context.rc_receive_pass_data(),
+            Self::High(context) => context.rc_receive_pass_data(),
+        }
+    }
+
+    fn pass_data_required(&self) -> usize {
+        match self {
+            Self::Low(context) => context.rc_second_pass_data_required(),
+            Self::High(context) => context.rc_second_pass_data_required(),
+        }
+    }
+
+    fn send_pass_data(&mut self, data: &[u8]) -> std::result::Result<(), EncoderStatus> {
+        match self {
+            Self::Low(context) => context.rc_send_pass_data(data),
+            Self::High(context) => context.rc_send_pass_data(data),
+        }
+    }
+}
+
+/// Rate control data passed between the passes of a two-pass encode
+enum TwoPass {
+    /// Single pass
+    Off,
+    /// First pass: per-frame data so far, and the summary once flushed
+    Collect {
+        frames: Vec<Box<[u8]>>,
+        summary: Option<Box<[u8]>>,
+    },
+    /// Second pass: per-frame data not yet fed back to rav1e
+    Replay(VecDeque<Box<[u8]>>),
+}
+
+impl TwoPass {
+    /// Serialize first pass data as the summary then each frame's data,
+    /// all prefixed with little-endian u32 lengths
+    fn stats(frames: &[Box<[u8]>], summary: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for packet in std::iter::once(summary).chain(frames.iter().map(|frame| &frame[..])) {
+            data.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+            data.extend_from_slice(packet);
+        }
+        data
+    }
+
+    /// Split first pass data back into the second pass rate control and
+    /// each frame's data
+    fn replay(mut data: &[u8]) -> Result<(RateControlConfig, Self)> {
+        let corrupt = || Error::InvalidInput("Corrupt rav1e first pass stats".to_string());
+        let mut packets = VecDeque::new();
+        while !data.is_empty() {
+            let len = data.get(..4).ok_or_else(corrupt)?;
+            let len = u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize;
+            let packet = data.get(4..4 + len).ok_or_else(corrupt)?;
+            packets.push_back(Box::from(packet));
+            data = &data[4 + len..];
+        }
+        let summary = packets.pop_front().ok_or_else(corrupt)?;
+        let rate_config = RateControlConfig::from_summary_slice(&summary)
+            .map_err(|e| Error::InvalidInput(format!("Invalid rav1e first pass stats: {}", e)))?;
+        Ok((rate_config, Self::Replay(packets)))
+    }
+}
+
+/// Convert a rav1e packet, recovering the pts sent with its frame
+fn encoded_packet<T: Pixel>(packet: Packet<T>) -> EncodedPacket {
+    let pts = packet
+        .opaque
+        .and_then(|opaque| opaque.downcast::<i64>().ok())
+        .map_or(packet.input_frameno as i64, |pts| *pts);
+    let frame_type = match packet.frame_type {
+        FrameType::KEY => super::FrameType::Key,
+        FrameType::INTRA_ONLY => super::FrameType::IntraOnly,
+        FrameType::INTER | FrameType::SWITCH => super::FrameType::Inter,
+    };
+    let blocks: usize = packet.enc_stats.block_size_counts.iter().sum();
+    EncodedPacket {
+        data: packet.data,
+        pts,
+        dts: pts,
+        frame_type,
+        qp: Some(packet.qp),
+        stats: FrameStats {
+            skip_ratio: (blocks > 0)
+                .then(|| packet.enc_stats.skip_block_count as f32 / blocks as f32),
+            ..Default::default()
+        },
+    }
+}
+
+/// Copy a frame into a new rav1e frame and queue it for encoding
+fn send_to_context<T: Pixel>(context: &mut Context<T>, frame: &Frame, pts: i64) -> Result<()> {
+    let mut rav1e_frame = context.new_frame();
+
+    // rav1e owns its padded frame buffers, so this is the one copy on the
+    // input path. Going row by row reads only visible samples, which lets
+    // padded planes and cropped views be encoded without repacking first.
+    for (rav1e_plane, plane) in rav1e_frame.planes.iter_mut().zip(frame.planes()) {
+        let stride = rav1e_plane.cfg.stride;
+        let rows = rav1e_plane
+            .data_origin_mut()
+            .chunks_mut(stride)
+            .take(plane.height());
+        for (y, dst) in rows.enumerate() {
+            let src = plane.row(y);
+            // Planes hold little-endian samples, which rav1e widens to `T`
+            if plane.bytes_per_sample() == 2 {
+                for (dst, src) in dst.iter_mut().zip(src.chunks_exact(2)) {
+                    *dst = T::cast_from(u16::from_le_bytes([src[0], src[1]]));
+                }
+            } else {
+                for (dst, &src) in dst.iter_mut().zip(src) {
+                    *dst = T::cast_from(src);
+                }
+            }
+        }
+    }
+
+    // The pts rides along to the packet showing the frame
+    let parameters = FrameParameters {
+        opaque: Some(Opaque::new(pts)),
+        ..Default::default()
+    };
+    context
+        .send_frame((rav1e_frame, parameters))
+        .map_err(|e| Error::Codec(format!("Failed to send frame: {:?}", e)))
+}
+
 /// AV1 encoder
+///
+/// Accepts 8-bit, 10-bit and 12-bit planar YUV frames; the input format is
+/// set with [`Av1Config::pixel_format`].
 pub struct Av1Encoder {
-    context: Context<u8>,
+    context: EncoderContext,
+    two_pass: TwoPass,
+    /// Frames sent so far, the pts of frames without one
+    frame_count: u64,
     width: u32,
     height: u32,
+    pixel_format: PixelFormat,
 }
 
 impl std::fmt::Debug for Av1Encoder {
@@ -91,6 +572,8 @@ impl std::fmt::Debug for Av1Encoder {
         f.debug_struct("Av1Encoder")
             .field("width", &self.width)
             .field("height", &self.height)
+            .field("pixel_format", &self.pixel_format)
+            .field("two_pass", &!matches!(self.two_pass, TwoPass::Off))
             .finish()
     }
 }
@@ -103,6 +586,24 @@ impl Av1Encoder {
 
     /// Create a new AV1 encoder with custom configuration
     pub fn with_config(width: u32, height: u32, config: Av1Config) -> Result<Self> {
+        let chroma_sampling = match config.pixel_format {
+            PixelFormat::Yuv420p | PixelFormat::Yuv420p10 | PixelFormat::Yuv420p12 => {
+                ChromaSampling::Cs420
+            }
+            PixelFormat::Yuv422p | PixelFormat::Yuv422p10 | PixelFormat::Yuv422p12 => {
+                ChromaSampling::Cs422
+            }
+            PixelFormat::Yuv444p | PixelFormat::Yuv444p10 | PixelFormat::Yuv444p12 => {
+                ChromaSampling::Cs444
+            }
+            PixelFormat::Rgb24 | PixelFormat::Nv12 | PixelFormat::P010 => {
+                return Err(Error::UnsupportedFormat(
+                    "AV1 encoder requires planar YUV input".to_string(),
+                ));
+            }
+        };
+        let bit_depth = usize::from(config.pixel_format.bit_depth());
+
         // Auto-detect threads if not specified
         let threads = if config.threads == 0 {
             num_cpus::get()
@@ -117,61 +618,151 @@ impl Av1Encoder {
             (config.tile_cols, config.tile_rows)
         };
 
+        config.rate_control.validate()?;
+        config.pass.validate(&config.rate_control)?;
+        if config.max_keyframe_interval == 0
+            || config.min_keyframe_interval > config.max_keyframe_interval
+        {
+            return Err(Error::InvalidInput(format!(
+                "Invalid keyframe interval {}-{}",
+                config.min_keyframe_interval, config.max_keyframe_interval
+            )));
+        }
+        let (fps_num, fps_den) = config.framerate;
+        if fps_num == 0 || fps_den == 0 {
+            return Err(Error::InvalidInput(format!(
+                "Invalid frame rate {}/{}",
+                fps_num, fps_den
+            )));
+        }
+
         tracing::debug!(
-            "AV1 encoder config: {}x{}, speed={}, tiles={}x{}, threads={}",
-            width, height, config.speed, tile_cols, tile_rows, threads
+            "AV1 encoder config: {}x{} {:?}, speed={}, {:?}, tiles={}x{}, threads={}",
+            width,
+            height,
+            config.pixel_format,
+            config.speed,
+            config.rate_control,
+            tile_cols,
+            tile_rows,
+            threads
         );
 
         let mut enc_config = EncoderConfig {
             width: width as usize,
             height: height as usize,
+            bit_depth,
+            chroma_sampling,
             speed_settings: SpeedSettings::from_preset(config.speed),
-            quantizer: config.quantizer as usize,
+            time_base: Rational::new(fps_den, fps_num),
             tile_cols,
             tile_rows,
+            tune: match config.tune {
+                Some(tune) if !tune.psychovisual() => rav1e::prelude::Tune::Psnr,
+                _ => rav1e::prelude::Tune::Psychovisual,
+            },
+            min_key_frame_interval: config.min_keyframe_interval,
+            max_key_frame_interval: config.max_keyframe_interval,
             ..Default::default()
         };
-
-        if let Some(br) = config.bitrate_kbps {
-            enc_config.bitrate = (br as i32) * 1000;
+        apply_colorimetry(&mut enc_config, &config.colorimetry);
+        if let Some(grain) = &config.film_grain {
+            grain.validate()?;
+            enc_config.film_grain_params = Some(vec![grain.segment()]);
         }
 
+        apply_rate_control(&mut enc_config, &config.rate_control, config.framerate);
+
+        let (rate_config, two_pass) = match &config.pass {
+            Pass::Single => (RateControlConfig::new(), TwoPass::Off),
+            Pass::First => (
+                RateControlConfig::new().with_emit_data(true),
+                TwoPass::Collect {
+                    frames: Vec::new(),
+                    summary: None,
+                },
+            ),
+            Pass::Second(stats) => TwoPass::replay(stats.data_for(STATS_ENCODER)?)?,
+        };
+
         let cfg = Config::new()
             .with_encoder_config(enc_config)
+            .with_rate_control(rate_config)
             .with_threads(threads);
 
-        let context = cfg
-            .new_context()
-            .map_err(|e| Error::Codec(format!("Failed to create AV1 encoder: {:?}", e)))?;
+        let context = if bit_depth > 8 {
+            cfg.new_context().map(EncoderContext::High)
+        } else {
+            cfg.new_context().map(EncoderContext::Low)
+        }
+        .map_err(|e| Error::Codec(format!("Failed to create AV1 encoder: {:?}", e)))?;
 
         Ok(Self {
             context,
+            two_pass,
+            frame_count: 0,
             width,
             height,
+            pixel_format: config.pixel_format,
         })
     }
 
     /// Flush the encoder and retrieve any remaining packets
-    pub fn flush(&mut self) -> Result<Vec<Vec<u8>>> {
+    pub fn flush(&mut self) -> Result<Vec<EncodedPacket>> {
         self.context.flush();
 
         let mut packets = Vec::new();
 
         loop {
-            match self.context.receive_packet() {
+            match self.next_packet() {
                 Ok(packet) => {
-                    packets.push(packet.data.to_vec());
+                    packets.push(packet);
                 }
                 Err(EncoderStatus::Encoded) => continue,
                 Err(EncoderStatus::LimitReached) => break,
-                Err(e) => {
-                    return Err(Error::Codec(format!("Encoder error during flush: {:?}", e)))
-                }
+                Err(e) => return Err(Error::Codec(format!("Encoder error during flush: {:?}", e))),
             }
         }
 
         Ok(packets)
     }
+
+    /// Receive from rav1e, exchanging two-pass data around the call
+    fn next_packet(&mut self) -> std::result::Result<EncodedPacket, EncoderStatus> {
+        if let TwoPass::Replay(frames) = &mut self.two_pass {
+            while self.context.pass_data_required() > 0 {
+                // Running out leaves rav1e NotReady, reported by the caller
+                let Some(frame) = frames.pop_front() else {
+                    break;
+                };
+                self.context.send_pass_data(&frame)?;
+            }
+        }
+
+        let result = self.context.receive_packet();
+
+        // rav1e has data for each frame it finishes, and the summary once
+        // it has finished them all
+        if let TwoPass::Collect { frames, summary } = &mut self.two_pass {
+            if matches!(
+                result,
+                Ok(_) | Err(EncoderStatus::Encoded | EncoderStatus::LimitReached)
+            ) {
+                // A frame's data and the summary can be pending together;
+                // once finished, rav1e returns the summary on every call
+                while let Some(data) = self.context.receive_pass_data() {
+                    match data {
+                        RcData::Frame(data) => frames.push(data),
+                        RcData::Summary(data) => {
+                            *summary = Some(data);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
 }
 
 impl VideoEncoder for Av1Encoder {
@@ -190,61 +781,18 @@ impl VideoEncoder for Av1Encoder {
                 }
 
                 // Validate format
-                if arc_frame.format() != PixelFormat::Yuv420p {
-                    return Err(Error::InvalidInput(
-                        "AV1 encoder currently only supports YUV420p format".to_string()
-                    ));
-                }
-
-                // Create rav1e frame
-                let mut rav1e_frame = self.context.new_frame();
-
-                // Copy Y plane
-                let y_plane = arc_frame.plane_y().ok_or_else(|| {
-                    Error::InvalidInput("Frame missing Y plane".to_string())
-                })?;
-                let rav1e_y = &mut rav1e_frame.planes[0];
-                let y_stride = rav1e_y.cfg.stride;
-                let y_data = rav1e_y.data_origin_mut();
-
-                for y in 0..self.height as usize {
-                    let row_offset = y * y_stride;
-                    let row = y_plane.row(y);
-                    y_data[row_offset..row_offset + row.len()].copy_from_slice(row);
-                }
-
-                // Copy U plane
-                let u_plane = arc_frame.plane_u().ok_or_else(|| {
-                    Error::InvalidInput("Frame missing U plane".to_string())
-                })?;
-                let rav1e_u = &mut rav1e_frame.planes[1];
-                let u_stride = rav1e_u.cfg.stride;
-                let u_data = rav1e_u.data_origin_mut();
-
-                for y in 0..(self.height / 2) as usize {
-                    let row_offset = y * u_stride;
-                    let row = u_plane.row(y);
-                    u_data[row_offset..row_offset + row.len()].copy_from_slice(row);
-                }
-
-                // Copy V plane
-                let v_plane = arc_frame.plane_v().ok_or_else(|| {
-                    Error::InvalidInput("Frame missing V plane".to_string())
-                })?;
-                let rav1e_v = &mut rav1e_frame.planes[2];
-                let v_stride = rav1e_v.cfg.stride;
-                let v_data = rav1e_v.data_origin_mut();
-
-                for y in 0..(self.height / 2) as usize {
-                    let row_offset = y * v_stride;
-                    let row = v_plane.row(y);
-                    v_data[row_offset..row_offset + row.len()].copy_from_slice(row);
+                if arc_frame.format() != self.pixel_format {
+                    return Err(Error::InvalidInput(format!(
+                        "Frame format {:?} does not match encoder {:?}",
+                        arc_frame.format(),
+                        self.pixel_format
+                    )));
                 }
 
                 // Send frame to encoder
-                self.context
-                    .send_frame(rav1e_frame)
-                    .map_err(|e| Error::Codec(format!("Failed to send frame: {:?}", e)))?;
+                let pts = arc_frame.pts().unwrap_or(self.frame_count as i64);
+                self.context.send_frame(&arc_frame, pts)?;
+                self.frame_count += 1;
 
                 Ok(())
             }
@@ -256,18 +804,89 @@ impl VideoEncoder for Av1Encoder {
         }
     }
 
-    fn receive_packet(&mut self) -> Result<Option<Vec<u8>>> {
-        match self.context.receive_packet() {
-            Ok(packet) => Ok(Some(packet.data.to_vec())),
+    fn receive_packet(&mut self) -> Result<Option<EncodedPacket>> {
+        match self.next_packet() {
+            Ok(packet) => Ok(Some(packet)),
             Err(EncoderStatus::Encoded) => {
                 // Encoder is processing, try again
                 self.receive_packet()
             }
             Err(EncoderStatus::NeedMoreData) => Ok(None),
             Err(EncoderStatus::LimitReached) => Ok(None),
+            Err(EncoderStatus::NotReady) => Err(Error::InvalidInput(
+                "First pass stats cover fewer frames than the input".to_string(),
+            )),
+            Err(EncoderStatus::Failure) if matches!(self.two_pass, TwoPass::Replay(_)) => Err(
+                Error::InvalidInput("First pass stats do not match the input".to_string()),
+            ),
             Err(e) => Err(Error::Codec(format!("Encoder error: {:?}", e))),
         }
     }
+
+    fn pass_stats(&self) -> Result<Option<PassStats>> {
+        match &self.two_pass {
+            TwoPass::Collect {
+                frames,
+                summary: Some(summary),
+            } => Ok(Some(PassStats::new(
+                STATS_ENCODER,
+                TwoPass::stats(frames, summary),
+            )?)),
+            TwoPass::Collect { summary: None, .. } => Err(Error::InvalidInput(
+                "First pass stats are only available once the encoder is flushed".to_string(),
+            )),
+            TwoPass::Off | TwoPass::Replay(_) => Ok(None),
+        }
+    }
+}
+
+/// Builds rav1e encoders for an [`EncoderRegistry`](super::registry::EncoderRegistry)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Rav1eFactory;
+
+impl EncoderFactory for Rav1eFactory {
+    fn name(&self) -> &'static str {
+        STATS_ENCODER
+    }
+
+    fn capabilities(&self) -> EncoderCapabilities {
+        EncoderCapabilities {
+            pixel_formats: &[
+                PixelFormat::Yuv420p,
+                PixelFormat::Yuv422p,
+                PixelFormat::Yuv444p,
+                PixelFormat::Yuv420p10,
+                PixelFormat::Yuv422p10,
+                PixelFormat::Yuv444p10,
+                PixelFormat::Yuv420p12,
+                PixelFormat::Yuv422p12,
+                PixelFormat::Yuv444p12,
+            ],
+            bit_depths: &[8, 10, 12],
+            two_pass: true,
+            film_grain: true,
+            max_bitrate: false,
+            plane_layout: crate::PlaneLayout::default(),
+        }
+    }
+
+    fn settings(&self, options: &EncoderOptions) -> String {
+        format!(
+            "{}x{}\n{:#?}",
+            options.width,
+            options.height,
+            Av1Config::from_options(options)
+        )
+    }
+
+    fn create(&self, options: &EncoderOptions) -> Result<Box<dyn VideoEncoder>> {
+        let encoder = Av1Encoder::with_config(
+            options.width,
+            options.height,
+            Av1Config::from_options(options),
+        )?;
+        Ok(Box::new(encoder))
+    }
 }
 
 #[cfg(test)]
@@ -286,11 +905,22 @@ mod tests {
     fn test_av1_encoder_with_config() {
         let config = Av1Config {
             speed: 10,
-            quantizer: 50,
-            bitrate_kbps: Some(1000),
+            rate_control: RateControl::Vbr {
+                bitrate: 1000,
+                max_bitrate: None,
+                buffer_size: Some(2000),
+            },
+            framerate: (25, 1),
             tile_cols: 1,
             tile_rows: 1,
             threads: 2,
+            pixel_format: PixelFormat::Yuv420p,
+            colorimetry: Colorimetry::default(),
+            film_grain: None,
+            pass: Pass::Single,
+            tune: None,
+            min_keyframe_interval: 12,
+            max_keyframe_interval: 240,
         };
         let encoder = Av1Encoder::with_config(64, 64, config);
         assert!(encoder.is_ok());
@@ -320,6 +950,281 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_av1_wrong_format() {
+        let mut encoder = Av1Encoder::new(64, 64).unwrap();
+
+        let frame = Arc::new(Frame::new(64, 64, PixelFormat::Yuv420p10));
+
+        let result = encoder.send_frame(Some(frame));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_av1_encodes_view() {
+        let mut encoder = Av1Encoder::with_config(
+            64,
+            64,
+            Av1Config {
+                speed: 10,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // A cropped view of a padded frame goes in without repacking
+        let layout = crate::PlaneLayout {
+            alignment: 64,
+            padding: 16,
+        };
+        let frame = Frame::with_layout(128, 96, PixelFormat::Yuv420p, layout);
+        let view = frame.view(32, 16, 64, 64).unwrap();
+        encoder.send_frame(Some(Arc::new(view))).unwrap();
+
+        assert!(!encoder.finish().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_av1_10bit() {
+        let config = Av1Config {
+            speed: 10,
+            pixel_format: PixelFormat::Yuv420p10,
+            ..Default::default()
+        };
+        let mut encoder = Av1Encoder::with_config(64, 64, config).unwrap();
+
+        let mut frame = Frame::new(64, 64, PixelFormat::Yuv420p10);
+        for plane in frame.planes_mut() {
+            for sample in plane.data_mut().chunks_exact_mut(2) {
+                sample.copy_from_slice(&512u16.to_le_bytes());
+            }
+        }
+        encoder.send_frame(Some(Arc::new(frame))).unwrap();
+
+        let packets = encoder.finish().unwrap();
+        let mut parser = obu::Av1Parser::new();
+        parser.parse_temporal_unit(&packets[0].data).unwrap();
+        let header = parser.sequence_header().unwrap();
+        assert_eq!(header.color_config.bit_depth, 10);
+        assert!(header.color_config.subsampling_x && header.color_config.subsampling_y);
+    }
+
+    #[test]
+    fn test_av1_hdr10_signalling() {
+        let colorimetry = Colorimetry {
+            primaries: color::ColorPrimaries::Bt2020,
+            transfer: color::TransferCharacteristics::Smpte2084,
+            matrix: color::MatrixCoefficients::Bt2020Ncl,
+            range: color::ColorRange::Limited,
+            chroma_position: color::ChromaSamplePosition::Colocated,
+            mastering_display: Some(
+                "G(0.17,0.797)B(0.131,0.046)R(0.708,0.292)WP(0.3127,0.329)L(1000,0.005)"
+                    .parse()
+                    .unwrap(),
+            ),
+            content_light: Some("1000,400".parse().unwrap()),
+        };
+        let config = Av1Config {
+            speed: 10,
+            pixel_format: PixelFormat::Yuv420p10,
+            colorimetry,
+            ..Default::default()
+        };
+        let mut encoder = Av1Encoder::with_config(64, 64, config).unwrap();
+        encoder
+            .send_frame(Some(Arc::new(Frame::new(64, 64, PixelFormat::Yuv420p10))))
+            .unwrap();
+
+        let packets = encoder.finish().unwrap();
+        let mut parser = obu::Av1Parser::new();
+        parser.parse_temporal_unit(&packets[0].data).unwrap();
+        assert_eq!(parser.colorimetry(), Some(colorimetry));
+    }
+
+    #[test]
+    fn test_av1_rate_control() {
+        let mut enc_config = EncoderConfig::default();
+        apply_rate_control(&mut enc_config, &RateControl::Crf(30), (30, 1));
+        assert_eq!((enc_config.quantizer, enc_config.bitrate), (120, 0));
+
+        // A 2 second buffer at 25 fps is a 50 frame reservoir
+        let vbr = RateControl::Vbr {
+            bitrate: 3000,
+            max_bitrate: None,
+            buffer_size: Some(6000),
+        };
+        let mut enc_config = EncoderConfig::default();
+        apply_rate_control(&mut enc_config, &vbr, (25, 1));
+        assert_eq!(enc_config.bitrate, 3_000_000);
+        assert_eq!(enc_config.reservoir_frame_delay, Some(50));
+        assert!(!enc_config.low_latency);
+
+        let cbr = RateControl::Cbr {
+            bitrate: 2000,
+            buffer_size: None,
+        };
+        let mut enc_config = EncoderConfig::default();
+        apply_rate_control(&mut enc_config, &cbr, (60000, 1001));
+        assert_eq!(enc_config.reservoir_frame_delay, Some(59));
+        assert!(enc_config.low_latency);
+
+        assert!(
+            Av1Encoder::with_config(
+                64,
+                64,
+                Av1Config {
+                    rate_control: RateControl::Crf(70),
+                    ..Default::default()
+                }
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_av1_film_grain() {
+        let grain = FilmGrain {
+            luma: vec![(0, 20), (128, 32), (255, 16)],
+            cb: vec![(128, 8)],
+            cr: vec![(128, 8)],
+            seed: 7391,
+        };
+        let config = Av1Config {
+            speed: 10,
+            film_grain: Some(grain.clone()),
+            ..Default::default()
+        };
+        let mut encoder = Av1Encoder::with_config(64, 64, config).unwrap();
+        encoder
+            .send_frame(Some(Arc::new(Frame::new(64, 64, PixelFormat::Yuv420p))))
+            .unwrap();
+
+        let packets = encoder.finish().unwrap();
+        let mut parser = obu::Av1Parser::new();
+        parser.parse_temporal_unit(&packets[0].data).unwrap();
+        assert!(parser.sequence_header().unwrap().film_grain_params_present);
+
+        let unordered = FilmGrain {
+            luma: vec![(128, 32), (0, 20)],
+            ..grain.clone()
+        };
+        assert!(unordered.validate().is_err());
+        let cb_only = FilmGrain {
+            cr: vec![],
+            ..grain
+        };
+        assert!(cb_only.validate().is_err());
+    }
+
+    #[test]
+    fn test_av1_preset() {
+        let config = Av1Config::from_preset(Preset::Archival, Some(Tune::Animation));
+        assert_eq!(config.speed, 1);
+        assert_eq!((config.tile_cols, config.tile_rows), (1, 1));
+        assert_eq!(config.rate_control, RateControl::Crf(34));
+
+        // Untuned presets keep the default quality and automatic tiles
+        let config = Av1Config::from_preset(Preset::Fast, None);
+        assert_eq!(config.speed, 10);
+        assert_eq!(config.tile_cols, 0);
+        assert_eq!(config.rate_control, Av1Config::default().rate_control);
+        assert!(
+            Av1Encoder::with_config(
+                64,
+                64,
+                Av1Config::from_preset(Preset::Fast, Some(Tune::Screen))
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_av1_from_options() {
+        let options = EncoderOptions {
+            width: 64,
+            height: 64,
+            preset: Preset::Quality,
+            tune: Some(Tune::Film),
+            pixel_format: PixelFormat::Yuv444p10,
+            max_keyframe_interval: Some(48),
+            ..Default::default()
+        };
+        let config = Av1Config::from_options(&options);
+        assert_eq!(config.speed, Preset::Quality.rav1e_speed());
+        assert_eq!(config.rate_control, RateControl::Crf(30));
+        assert_eq!(config.pixel_format, PixelFormat::Yuv444p10);
+        assert_eq!(
+            (config.min_keyframe_interval, config.max_keyframe_interval),
+            (12, 48)
+        );
+
+        let config = Av1Config {
+            min_keyframe_interval: 60,
+            max_keyframe_interval: 30,
+            ..Default::default()
+        };
+        assert!(Av1Encoder::with_config(64, 64, config).is_err());
+    }
+
+    #[test]
+    fn test_av1_two_pass() {
+        let rate_control = RateControl::Vbr {
+            bitrate: 200,
+            max_bitrate: None,
+            buffer_size: None,
+        };
+        let encode = |pass: Pass| {
+            let config = Av1Config {
+                speed: 10,
+                rate_control,
+                pass,
+                ..Default::default()
+            };
+            let mut encoder = Av1Encoder::with_config(64, 64, config).unwrap();
+            for i in 0..8u8 {
+                let mut frame = Frame::new(64, 64, PixelFormat::Yuv420p);
+                for (j, sample) in frame.planes_mut()[0].data_mut().iter_mut().enumerate() {
+                    *sample = (j as u8).wrapping_mul(3).wrapping_add(i * 16);
+                }
+                encoder.send_frame(Some(Arc::new(frame))).unwrap();
+            }
+            let packets = encoder.finish().unwrap();
+            (packets, encoder.pass_stats().unwrap())
+        };
+
+        let (_, stats) = encode(Pass::First);
+        let stats = stats.unwrap();
+        assert_eq!(stats.encoder(), "rav1e");
+        let (packets, no_stats) = encode(Pass::Second(stats));
+        assert_eq!(packets.len(), 8);
+        assert!(no_stats.is_none());
+
+        // Two passes need a bitrate to spread, and stats from the same backend
+        assert!(
+            Av1Encoder::with_config(
+                64,
+                64,
+                Av1Config {
+                    pass: Pass::First,
+                    ..Default::default()
+                }
+            )
+            .is_err()
+        );
+        assert!(
+            Av1Encoder::with_config(
+                64,
+                64,
+                Av1Config {
+                    rate_control,
+                    pass: Pass::Second(PassStats::new("svt-av1", Vec::new()).unwrap()),
+                    ..Default::default()
+                }
+            )
+            .is_err()
+        );
+    }
+
     #[test]
     fn test_av1_finish() {
         let mut encoder = Av1Encoder::new(64, 64).unwrap();
@@ -335,6 +1240,41 @@ mod tests {
         assert!(!packets.is_empty());
     }
 
+    #[test]
+    fn test_av1_packet_timestamps() {
+        let mut encoder = Av1Encoder::with_config(
+            64,
+            64,
+            Av1Config {
+                speed: 10,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Input pts come back on the packets showing each frame, in order,
+        // and frames without one are numbered by position
+        for pts in [100, 102, 104] {
+            let mut frame = Frame::new(64, 64, PixelFormat::Yuv420p);
+            frame.set_pts(pts);
+            encoder.send_frame(Some(Arc::new(frame))).unwrap();
+        }
+        encoder
+            .send_frame(Some(Arc::new(Frame::new(64, 64, PixelFormat::Yuv420p))))
+            .unwrap();
+
+        let packets = encoder.finish().unwrap();
+        let pts: Vec<_> = packets.iter().map(|packet| packet.pts).collect();
+        assert_eq!(pts, [100, 102, 104, 3]);
+        assert!(
+            packets
+                .iter()
+                .all(|packet| packet.dts == packet.pts && packet.qp.is_some())
+        );
+        assert!(packets[0].is_keyframe());
+        assert_eq!(packets[1].frame_type, super::super::FrameType::Inter);
+    }
+
     #[test]
     fn test_tile_calculation() {
         // Small resolution: 640x480 with 4 threads
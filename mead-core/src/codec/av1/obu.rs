@@ -0,0 +1,1023 @@
+//! AV1 bitstream parsing: OBU headers, sequence headers and frame headers
+//!
+//! Only the syntax containers and tools need is parsed: the full sequence
+//! header (profile, level, color config, timing info), HDR metadata OBUs
+//! and the leading fields of the uncompressed frame header (frame type and
+//! visibility). Tile data is never touched.
+//!
+//! # Example
+//! ```no_run
+//! use mead_core::codec::av1::obu::Av1Parser;
+//!
+//! let temporal_unit: Vec<u8> = vec![/* low-overhead AV1 temporal unit */];
+//!
+//! let mut parser = Av1Parser::new();
+//! let unit = parser.parse_temporal_unit(&temporal_unit)?;
+//! if let Some(header) = parser.sequence_header() {
+//!     println!("Profile {}, {}-bit", header.profile, header.color_config.bit_depth);
+//! }
+//! println!("Keyframe: {}", unit.is_keyframe());
+//! # Ok::<(), mead_core::Error>(())
+//! ```
+
+use crate::color::{
+    ChromaSamplePosition, ColorPrimaries, ColorRange, Colorimetry, ContentLightLevel,
+    MasteringDisplay, MatrixCoefficients, TransferCharacteristics,
+};
+use crate::{Error, Result};
+
+/// OBU type (AV1 spec section 6.2.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObuType {
+    /// Sequence header
+    SequenceHeader,
+    /// Temporal delimiter, starts every temporal unit
+    TemporalDelimiter,
+    /// Frame header without tile data
+    FrameHeader,
+    /// Tile group
+    TileGroup,
+    /// Metadata (HDR, scalability, timecode, ...)
+    Metadata,
+    /// Frame header followed by a tile group
+    Frame,
+    /// Repeated copy of a frame header
+    RedundantFrameHeader,
+    /// Tile list (large scale tile decoding)
+    TileList,
+    /// Padding
+    Padding,
+    /// Reserved OBU type
+    Reserved(u8),
+}
+
+impl ObuType {
+    /// Map a 4-bit `obu_type` value
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::SequenceHeader,
+            2 => Self::TemporalDelimiter,
+            3 => Self::FrameHeader,
+            4 => Self::TileGroup,
+            5 => Self::Metadata,
+            6 => Self::Frame,
+            7 => Self::RedundantFrameHeader,
+            8 => Self::TileList,
+            15 => Self::Padding,
+            other => Self::Reserved(other),
+        }
+    }
+
+    /// Get the 4-bit `obu_type` value
+    pub fn to_u8(self) -> u8 {
+        match self {
+            Self::SequenceHeader => 1,
+            Self::TemporalDelimiter => 2,
+            Self::FrameHeader => 3,
+            Self::TileGroup => 4,
+            Self::Metadata => 5,
+            Self::Frame => 6,
+            Self::RedundantFrameHeader => 7,
+            Self::TileList => 8,
+            Self::Padding => 15,
+            Self::Reserved(value) => value,
+        }
+    }
+}
+
+/// Parsed OBU header (AV1 spec section 5.3.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObuHeader {
+    /// OBU type
+    pub obu_type: ObuType,
+    /// An extension byte with layer ids follows the header
+    pub has_extension: bool,
+    /// An `obu_size` field follows the header
+    pub has_size_field: bool,
+    /// Temporal layer (0 without extension)
+    pub temporal_id: u8,
+    /// Spatial layer (0 without extension)
+    pub spatial_id: u8,
+}
+
+/// One OBU within a buffer
+#[derive(Debug, Clone, Copy)]
+pub struct Obu<'a> {
+    /// Parsed header
+    pub header: ObuHeader,
+    /// Offset of the OBU within the parsed buffer
+    pub offset: usize,
+    /// The whole OBU: header, optional size field and payload
+    pub data: &'a [u8],
+    /// The OBU payload
+    pub payload: &'a [u8],
+}
+
+impl Obu<'_> {
+    /// Get the OBU type
+    pub fn obu_type(&self) -> ObuType {
+        self.header.obu_type
+    }
+
+    /// Byte range of the whole OBU within the parsed buffer
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.offset..self.offset + self.data.len()
+    }
+
+    /// Header bytes (1 or 2), excluding the size field
+    pub fn header_bytes(&self) -> &[u8] {
+        &self.data[..1 + usize::from(self.header.has_extension)]
+    }
+}
+
+/// Split a low-overhead AV1 bitstream into OBUs
+///
+/// An OBU without a size field extends to the end of the buffer.
+pub fn parse_obus(data: &[u8]) -> Result<Vec<Obu<'_>>> {
+    let mut obus = Vec::new();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let byte = data[pos];
+        if byte & 0x80 != 0 {
+            return Err(Error::InvalidInput("AV1 OBU forbidden bit set".to_string()));
+        }
+        let has_extension = byte & 0x04 != 0;
+        let has_size_field = byte & 0x02 != 0;
+
+        let (temporal_id, spatial_id) = if has_extension {
+            let extension = *data
+                .get(pos + 1)
+                .ok_or_else(|| Error::InvalidInput("Truncated AV1 OBU header".to_string()))?;
+            (extension >> 5, (extension >> 3) & 0x03)
+        } else {
+            (0, 0)
+        };
+
+        let mut header_len = 1 + usize::from(has_extension);
+        let payload_len = if has_size_field {
+            let (value, len) = read_leb128(data.get(pos + header_len..).unwrap_or(&[]))?;
+            header_len += len;
+            usize::try_from(value)
+                .map_err(|_| Error::InvalidInput("AV1 OBU size overflow".to_string()))?
+        } else {
+            data.len().saturating_sub(pos + header_len)
+        };
+
+        let end = pos
+            .checked_add(header_len)
+            .and_then(|p| p.checked_add(payload_len))
+            .filter(|&end| end <= data.len())
+            .ok_or_else(|| Error::InvalidInput("Truncated AV1 OBU".to_string()))?;
+
+        obus.push(Obu {
+            header: ObuHeader {
+                obu_type: ObuType::from_u8((byte >> 3) & 0x0F),
+                has_extension,
+                has_size_field,
+                temporal_id,
+                spatial_id,
+            },
+            offset: pos,
+            data: &data[pos..end],
+            payload: &data[pos + header_len..end],
+        });
+        pos = end;
+    }
+
+    Ok(obus)
+}
+
+/// Decode an unsigned LEB128 value, returning (value, bytes consumed)
+pub fn read_leb128(data: &[u8]) -> Result<(u64, usize)> {
+    let mut value = 0u64;
+    for i in 0..8 {
+        let byte = *data
+            .get(i)
+            .ok_or_else(|| Error::InvalidInput("Truncated AV1 leb128".to_string()))?;
+        value |= u64::from(byte & 0x7F) << (i * 7);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+    Err(Error::InvalidInput(
+        "AV1 leb128 longer than 8 bytes".to_string(),
+    ))
+}
+
+/// Encode an unsigned LEB128 value
+pub fn write_leb128(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// One operating point of a sequence header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperatingPoint {
+    /// Layers included in this operating point (0 = all)
+    pub idc: u16,
+    /// `seq_level_idx` (level X.Y is `(X - 2) * 4 + Y`, 31 = unconstrained)
+    pub level: u8,
+    /// High tier
+    pub tier: bool,
+}
+
+/// Timing information from a sequence header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingInfo {
+    /// Time units per display tick
+    pub num_units_in_display_tick: u32,
+    /// Time units per second
+    pub time_scale: u32,
+    /// Ticks per picture for constant frame rate streams
+    pub num_ticks_per_picture: Option<u32>,
+}
+
+/// Color configuration from a sequence header (AV1 spec section 5.5.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorConfig {
+    /// Bits per sample (8, 10 or 12)
+    pub bit_depth: u8,
+    /// Luma only
+    pub mono_chrome: bool,
+    /// Color primaries (ISO/IEC 23091-4, 2 = unspecified)
+    pub color_primaries: u8,
+    /// Transfer characteristics (2 = unspecified)
+    pub transfer_characteristics: u8,
+    /// Matrix coefficients (2 = unspecified)
+    pub matrix_coefficients: u8,
+    /// Full (PC) range rather than limited (TV) range
+    pub full_range: bool,
+    /// Chroma is horizontally subsampled
+    pub subsampling_x: bool,
+    /// Chroma is vertically subsampled
+    pub subsampling_y: bool,
+    /// Chroma sample position for 4:2:0 (0 = unknown, 1 = vertical, 2 = colocated)
+    pub chroma_sample_position: u8,
+}
+
+impl ColorConfig {
+    /// Color description, without HDR metadata (see [`Av1Parser::colorimetry`])
+    pub fn colorimetry(&self) -> Colorimetry {
+        Colorimetry {
+            primaries: ColorPrimaries::from_code(self.color_primaries),
+            transfer: TransferCharacteristics::from_code(self.transfer_characteristics),
+            matrix: MatrixCoefficients::from_code(self.matrix_coefficients),
+            range: if self.full_range {
+                ColorRange::Full
+            } else {
+                ColorRange::Limited
+            },
+            chroma_position: ChromaSamplePosition::from_code(self.chroma_sample_position),
+            mastering_display: None,
+            content_light: None,
+        }
+    }
+}
+
+/// Parsed sequence header (AV1 spec section 5.5)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceHeader {
+    /// `seq_profile` (0 = Main, 1 = High, 2 = Professional)
+    pub profile: u8,
+    /// Stream contains a single still picture
+    pub still_picture: bool,
+    /// Abbreviated header for still pictures
+    pub reduced_still_picture_header: bool,
+    /// Operating points, the first being the default
+    pub operating_points: Vec<OperatingPoint>,
+    /// Timing information, if signalled
+    pub timing_info: Option<TimingInfo>,
+    /// Maximum frame width in pixels
+    pub max_frame_width: u32,
+    /// Maximum frame height in pixels
+    pub max_frame_height: u32,
+    /// Color configuration
+    pub color_config: ColorConfig,
+    /// Frames may carry film grain parameters
+    pub film_grain_params_present: bool,
+    /// Bits of `frame_presentation_time` in frame headers, when present
+    frame_presentation_time_length: Option<u32>,
+    /// Bits of `display_frame_id` in frame headers, when present
+    frame_id_length: Option<u32>,
+}
+
+impl SequenceHeader {
+    /// Parse a sequence header OBU payload
+    pub fn parse(payload: &[u8]) -> Result<Self> {
+        let mut r = BitReader::new(payload);
+
+        let profile = r.bits(3)? as u8;
+        if profile > 2 {
+            return Err(Error::InvalidInput(format!(
+                "Invalid AV1 seq_profile {}",
+                profile
+            )));
+        }
+        let still_picture = r.bit()?;
+        let reduced_still_picture_header = r.bit()?;
+
+        let mut timing_info = None;
+        let mut frame_presentation_time_length = None;
+        let mut operating_points = Vec::new();
+
+        if reduced_still_picture_header {
+            operating_points.push(OperatingPoint {
+                idc: 0,
+                level: r.bits(5)? as u8,
+                tier: false,
+            });
+        } else {
+            let mut decoder_model_info_present = false;
+            let mut buffer_delay_length = 0;
+            if r.bit()? {
+                let num_units_in_display_tick = r.bits(32)?;
+                let time_scale = r.bits(32)?;
+                let num_ticks_per_picture = if r.bit()? {
+                    Some(r.uvlc()?.saturating_add(1))
+                } else {
+                    None
+                };
+                timing_info = Some(TimingInfo {
+                    num_units_in_display_tick,
+                    time_scale,
+                    num_ticks_per_picture,
+                });
+
+                decoder_model_info_present = r.bit()?;
+                if decoder_model_info_present {
+                    buffer_delay_length = r.bits(5)? + 1;
+                    r.bits(32)?; // num_units_in_decoding_tick
+                    r.bits(5)?; // buffer_removal_time_length_minus_1
+                    let presentation_length = r.bits(5)? + 1;
+                    if num_ticks_per_picture.is_none() {
+                        frame_presentation_time_length = Some(presentation_length);
+                    }
+                }
+            }
+
+            let initial_display_delay_present = r.bit()?;
+            let count = r.bits(5)? + 1;
+            for _ in 0..count {
+                let idc = r.bits(12)? as u16;
+                let level = r.bits(5)? as u8;
+                let tier = level > 7 && r.bit()?;
+                if decoder_model_info_present && r.bit()? {
+                    r.bits(buffer_delay_length)?; // decoder_buffer_delay
+                    r.bits(buffer_delay_length)?; // encoder_buffer_delay
+                    r.bit()?; // low_delay_mode_flag
+                }
+                if initial_display_delay_present && r.bit()? {
+                    r.bits(4)?; // initial_display_delay_minus_1
+                }
+                operating_points.push(OperatingPoint { idc, level, tier });
+            }
+        }
+
+        let frame_width_bits = r.bits(4)? + 1;
+        let frame_height_bits = r.bits(4)? + 1;
+        let max_frame_width = r.bits(frame_width_bits)? + 1;
+        let max_frame_height = r.bits(frame_height_bits)? + 1;
+
+        let mut frame_id_length = None;
+        if !reduced_still_picture_header && r.bit()? {
+            let delta_frame_id_length = r.bits(4)? + 2;
+            let additional_frame_id_length = r.bits(3)? + 1;
+            frame_id_length = Some(delta_frame_id_length + additional_frame_id_length);
+        }
+
+        r.bit()?; // use_128x128_superblock
+        r.bit()?; // enable_filter_intra
+        r.bit()?; // enable_intra_edge_filter
+
+        if !reduced_still_picture_header {
+            r.bit()?; // enable_interintra_compound
+            r.bit()?; // enable_masked_compound
+            r.bit()?; // enable_warped_motion
+            r.bit()?; // enable_dual_filter
+            let enable_order_hint = r.bit()?;
+            if enable_order_hint {
+                r.bit()?; // enable_jnt_comp
+                r.bit()?; // enable_ref_frame_mvs
+            }
+            let seq_force_screen_content_tools = if r.bit()? { 2 } else { r.bits(1)? };
+            if seq_force_screen_content_tools > 0 && !r.bit()? {
+                r.bit()?; // seq_force_integer_mv
+            }
+            if enable_order_hint {
+                r.bits(3)?; // order_hint_bits_minus_1
+            }
+        }
+
+        r.bit()?; // enable_superres
+        r.bit()?; // enable_cdef
+        r.bit()?; // enable_restoration
+
+        let color_config = parse_color_config(&mut r, profile)?;
+        let film_grain_params_present = r.bit()?;
+
+        Ok(Self {
+            profile,
+            still_picture,
+            reduced_still_picture_header,
+            operating_points,
+            timing_info,
+            max_frame_width,
+            max_frame_height,
+            color_config,
+            film_grain_params_present,
+            frame_presentation_time_length,
+            frame_id_length,
+        })
+    }
+
+    /// `seq_level_idx` of the default operating point
+    pub fn level(&self) -> u8 {
+        self.operating_points.first().map_or(31, |op| op.level)
+    }
+
+    /// High tier flag of the default operating point
+    pub fn tier(&self) -> bool {
+        self.operating_points.first().is_some_and(|op| op.tier)
+    }
+
+    /// Level as (major, minor), or `None` for the unconstrained level 31
+    pub fn level_number(&self) -> Option<(u8, u8)> {
+        let level = self.level();
+        (level < 31).then_some((2 + (level >> 2), level & 3))
+    }
+
+    /// Constant frame rate from the timing info, if signalled
+    pub fn frame_rate(&self) -> Option<f64> {
+        let timing = self.timing_info?;
+        let ticks = timing.num_ticks_per_picture?;
+        let units = u64::from(timing.num_units_in_display_tick) * u64::from(ticks);
+        (units > 0).then(|| f64::from(timing.time_scale) / units as f64)
+    }
+
+    /// Build an `av1C` record (AV1CodecConfigurationRecord)
+    ///
+    /// `sequence_header_obu` is the complete OBU this header was parsed from
+    /// and is stored as the record's configOBUs. Matroska uses the same
+    /// record as `V_AV1` CodecPrivate.
+    pub fn av1c(&self, sequence_header_obu: &[u8]) -> Vec<u8> {
+        let color = &self.color_config;
+        let mut av1c = Vec::with_capacity(4 + sequence_header_obu.len());
+        av1c.push(0x81); // marker + version 1
+        av1c.push((self.profile << 5) | self.level());
+        av1c.push(
+            (u8::from(self.tier()) << 7)
+                | (u8::from(color.bit_depth > 8) << 6)
+                | (u8::from(color.bit_depth == 12) << 5)
+                | (u8::from(color.mono_chrome) << 4)
+                | (u8::from(color.subsampling_x) << 3)
+                | (u8::from(color.subsampling_y) << 2)
+                | color.chroma_sample_position,
+        );
+        av1c.push(0); // no initial_presentation_delay
+        av1c.extend_from_slice(sequence_header_obu);
+        av1c
+    }
+}
+
+fn parse_color_config(r: &mut BitReader<'_>, profile: u8) -> Result<ColorConfig> {
+    let high_bitdepth = r.bit()?;
+    let bit_depth = if profile == 2 && high_bitdepth {
+        if r.bit()? { 12 } else { 10 }
+    } else if high_bitdepth {
+        10
+    } else {
+        8
+    };
+
+    let mono_chrome = profile != 1 && r.bit()?;
+    let (color_primaries, transfer_characteristics, matrix_coefficients) = if r.bit()? {
+        (r.bits(8)? as u8, r.bits(8)? as u8, r.bits(8)? as u8)
+    } else {
+        (2, 2, 2)
+    };
+
+    let mut config = ColorConfig {
+        bit_depth,
+        mono_chrome,
+        color_primaries,
+        transfer_characteristics,
+        matrix_coefficients,
+        full_range: false,
+        subsampling_x: true,
+        subsampling_y: true,
+        chroma_sample_position: 0,
+    };
+
+    if mono_chrome {
+        config.full_range = r.bit()?;
+        return Ok(config);
+    }
+
+    if color_primaries == 1 && transfer_characteristics == 13 && matrix_coefficients == 0 {
+        // sRGB: 4:4:4, full range
+        config.full_range = true;
+        config.subsampling_x = false;
+        config.subsampling_y = false;
+    } else {
+        config.full_range = r.bit()?;
+        let (x, y) = match profile {
+            0 => (true, true),
+            1 => (false, false),
+            _ if bit_depth == 12 => {
+                let x = r.bit()?;
+                (x, x && r.bit()?)
+            }
+            _ => (true, false),
+        };
+        config.subsampling_x = x;
+        config.subsampling_y = y;
+        if x && y {
+            config.chroma_sample_position = r.bits(2)? as u8;
+        }
+    }
+    r.bit()?; // separate_uv_delta_q
+
+    Ok(config)
+}
+
+/// Frame type (AV1 spec section 6.8.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    /// Intra frame that resets decoding (random access point when shown)
+    Key,
+    /// Inter frame
+    Inter,
+    /// Intra frame that keeps reference state
+    IntraOnly,
+    /// Switch frame
+    Switch,
+}
+
+/// Leading fields of an uncompressed frame header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// Repeats a previously decoded frame instead of coding a new one
+    pub show_existing_frame: bool,
+    /// Reference slot shown when `show_existing_frame` is set
+    pub frame_to_show_map_idx: u8,
+    /// Frame type; `None` for `show_existing_frame`, whose type depends on
+    /// decoder state
+    pub frame_type: Option<FrameType>,
+    /// Frame is output after decoding
+    pub show_frame: bool,
+    /// Frame may later be output with `show_existing_frame`
+    pub showable_frame: bool,
+    /// Frame does not depend on previous frame state
+    pub error_resilient_mode: bool,
+}
+
+impl FrameHeader {
+    /// Parse the start of a frame header (or frame) OBU payload
+    pub fn parse(payload: &[u8], sequence_header: &SequenceHeader) -> Result<Self> {
+        if sequence_header.reduced_still_picture_header {
+            return Ok(Self {
+                show_existing_frame: false,
+                frame_to_show_map_idx: 0,
+                frame_type: Some(FrameType::Key),
+                show_frame: true,
+                showable_frame: false,
+                error_resilient_mode: true,
+            });
+        }
+
+        let mut r = BitReader::new(payload);
+
+        if r.bit()? {
+            let frame_to_show_map_idx = r.bits(3)? as u8;
+            if let Some(length) = sequence_header.frame_presentation_time_length {
+                r.bits(length)?; // frame_presentation_time
+            }
+            if let Some(length) = sequence_header.frame_id_length {
+                r.bits(length)?; // display_frame_id
+            }
+            return Ok(Self {
+                show_existing_frame: true,
+                frame_to_show_map_idx,
+                frame_type: None,
+                show_frame: true,
+                showable_frame: false,
+                error_resilient_mode: false,
+            });
+        }
+
+        let frame_type = match r.bits(2)? {
+            0 => FrameType::Key,
+            1 => FrameType::Inter,
+            2 => FrameType::IntraOnly,
+            _ => FrameType::Switch,
+        };
+        let show_frame = r.bit()?;
+        if show_frame {
+            if let Some(length) = sequence_header.frame_presentation_time_length {
+                r.bits(length)?; // frame_presentation_time
+            }
+        }
+        let showable_frame = if show_frame {
+            frame_type != FrameType::Key
+        } else {
+            r.bit()?
+        };
+        let error_resilient_mode =
+            if frame_type == FrameType::Switch || (frame_type == FrameType::Key && show_frame) {
+                true
+            } else {
+                r.bit()?
+            };
+
+        Ok(Self {
+            show_existing_frame: false,
+            frame_to_show_map_idx: 0,
+            frame_type: Some(frame_type),
+            show_frame,
+            showable_frame,
+            error_resilient_mode,
+        })
+    }
+
+    /// Shown key frame: a random access point
+    pub fn is_keyframe(&self) -> bool {
+        self.frame_type == Some(FrameType::Key) && self.show_frame
+    }
+}
+
+/// Summary of one temporal unit
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TemporalUnit {
+    /// The temporal unit carries a sequence header
+    pub has_sequence_header: bool,
+    /// Frame headers, in bitstream order (empty before any sequence header)
+    pub frames: Vec<FrameHeader>,
+}
+
+impl TemporalUnit {
+    /// Contains a shown key frame
+    pub fn is_keyframe(&self) -> bool {
+        self.frames.iter().any(FrameHeader::is_keyframe)
+    }
+
+    /// Number of frames output by this temporal unit
+    pub fn shown_frames(&self) -> usize {
+        self.frames.iter().filter(|f| f.show_frame).count()
+    }
+}
+
+/// Stateful AV1 stream parser
+///
+/// Keeps the most recent sequence header, which frame header parsing
+/// depends on, and HDR metadata across temporal units.
+#[derive(Debug, Clone, Default)]
+pub struct Av1Parser {
+    sequence_header: Option<SequenceHeader>,
+    sequence_header_obu: Vec<u8>,
+    mastering_display: Option<MasteringDisplay>,
+    content_light: Option<ContentLightLevel>,
+}
+
+impl Av1Parser {
+    /// Create a new parser
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the active sequence header
+    pub fn sequence_header(&self) -> Option<&SequenceHeader> {
+        self.sequence_header.as_ref()
+    }
+
+    /// Get the most recent mastering display metadata (`METADATA_TYPE_HDR_MDCV`)
+    pub fn mastering_display(&self) -> Option<&MasteringDisplay> {
+        self.mastering_display.as_ref()
+    }
+
+    /// Get the most recent content light level metadata (`METADATA_TYPE_HDR_CLL`)
+    pub fn content_light(&self) -> Option<&ContentLightLevel> {
+        self.content_light.as_ref()
+    }
+
+    /// Get the stream colorimetry, including any HDR metadata seen so far
+    pub fn colorimetry(&self) -> Option<Colorimetry> {
+        self.sequence_header.as_ref().map(|header| Colorimetry {
+            mastering_display: self.mastering_display,
+            content_light: self.content_light,
+            ..header.color_config.colorimetry()
+        })
+    }
+
+    /// Build an `av1C` record from the active sequence header
+    pub fn av1c(&self) -> Option<Vec<u8>> {
+        self.sequence_header
+            .as_ref()
+            .map(|header| header.av1c(&self.sequence_header_obu))
+    }
+
+    /// Parse a low-overhead temporal unit
+    ///
+    /// Every frame header and frame OBU is treated as a new frame; encoders
+    /// mark repeated headers as redundant frame headers, which are skipped.
+    pub fn parse_temporal_unit(&mut self, data: &[u8]) -> Result<TemporalUnit> {
+        let mut unit = TemporalUnit::default();
+
+        for obu in parse_obus(data)? {
+            match obu.obu_type() {
+                ObuType::SequenceHeader => {
+                    unit.has_sequence_header = true;
+                    if obu.data != self.sequence_header_obu.as_slice() {
+                        self.sequence_header = Some(SequenceHeader::parse(obu.payload)?);
+                        self.sequence_header_obu = obu.data.to_vec();
+                    }
+                }
+                ObuType::FrameHeader | ObuType::Frame => {
+                    if let Some(sequence_header) = &self.sequence_header {
+                        unit.frames
+                            .push(FrameHeader::parse(obu.payload, sequence_header)?);
+                    }
+                }
+                ObuType::Metadata => self.parse_metadata(obu.payload)?,
+                _ => {}
+            }
+        }
+
+        Ok(unit)
+    }
+
+    /// Parse a metadata OBU (AV1 spec section 5.8), keeping HDR metadata
+    fn parse_metadata(&mut self, payload: &[u8]) -> Result<()> {
+        const METADATA_TYPE_HDR_CLL: u64 = 1;
+        const METADATA_TYPE_HDR_MDCV: u64 = 2;
+
+        let (metadata_type, len) = read_leb128(payload)?;
+        let mut r = BitReader::new(&payload[len..]);
+
+        match metadata_type {
+            METADATA_TYPE_HDR_CLL => {
+                self.content_light = Some(ContentLightLevel {
+                    max_cll: r.bits(16)? as u16,
+                    max_fall: r.bits(16)? as u16,
+                });
+            }
+            METADATA_TYPE_HDR_MDCV => {
+                let mut primaries = [(0, 0); 3];
+                for primary in &mut primaries {
+                    *primary = (r.bits(16)? as u16, r.bits(16)? as u16);
+                }
+                self.mastering_display = Some(MasteringDisplay {
+                    primaries,
+                    white_point: (r.bits(16)? as u16, r.bits(16)? as u16),
+                    max_luminance: r.bits(32)?,
+                    min_luminance: r.bits(32)?,
+                });
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// MSB-first bit reader for AV1 header syntax
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bit(&mut self) -> Result<bool> {
+        let byte = self
+            .data
+            .get(self.pos / 8)
+            .ok_or_else(|| Error::InvalidInput("Truncated AV1 header".to_string()))?;
+        let bit = (byte >> (7 - self.pos % 8)) & 1;
+        self.pos += 1;
+        Ok(bit == 1)
+    }
+
+    fn bits(&mut self, n: u32) -> Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..n {
+            value = (value << 1) | u32::from(self.bit()?);
+        }
+        Ok(value)
+    }
+
+    fn uvlc(&mut self) -> Result<u32> {
+        let mut leading_zeros = 0;
+        while !self.bit()? {
+            leading_zeros += 1;
+            if leading_zeros >= 32 {
+                return Err(Error::InvalidInput("Invalid AV1 uvlc".to_string()));
+            }
+        }
+        Ok(self.bits(leading_zeros)? + ((1u32 << leading_zeros) - 1))
+    }
+}
+
+/// AV1 streams shared by the codec and container tests
+#[cfg(test)]
+pub(crate) mod test_data {
+    use crate::container::Packet;
+
+    /// Temporal delimiter + sequence header OBU (64x64, 8-bit 4:2:0) from rav1e
+    pub(crate) const TD_AND_SEQUENCE_HEADER: [u8; 14] = [
+        0x12, 0x00, 0x0a, 0x0a, 0x00, 0x00, 0x00, 0xf9, 0x57, 0xff, 0xc4, 0x21, 0x50, 0x14,
+    ];
+
+    /// Temporal unit with a shown key frame (after a sequence header) or
+    /// inter frame, tagged with `index`
+    pub(crate) fn temporal_unit(index: u8, keyframe: bool) -> Vec<u8> {
+        let mut data = if keyframe {
+            TD_AND_SEQUENCE_HEADER.to_vec()
+        } else {
+            vec![0x12, 0x00]
+        };
+        // Frame OBU: shown key or inter frame header, then filler
+        let frame_header = if keyframe { 0x10 } else { 0x30 };
+        data.extend_from_slice(&[0x32, 0x03, frame_header, index, 0xAA]);
+        data
+    }
+
+    /// [`temporal_unit`] as a packet on stream 0 with `pts` = `index`
+    pub(crate) fn av1_packet(index: i64, keyframe: bool) -> Packet {
+        Packet {
+            stream_index: 0,
+            data: temporal_unit(index as u8, keyframe),
+            pts: Some(index),
+            dts: None,
+            is_keyframe: keyframe,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_data::TD_AND_SEQUENCE_HEADER;
+    use super::*;
+
+    /// Frame OBUs whose headers start with a shown key frame / shown inter frame
+    const KEY_FRAME: [u8; 4] = [0x32, 0x02, 0x10, 0x00];
+    const INTER_FRAME: [u8; 4] = [0x32, 0x02, 0x30, 0x00];
+
+    #[test]
+    fn test_parse_obus() {
+        let mut data = TD_AND_SEQUENCE_HEADER.to_vec();
+        data.extend_from_slice(&KEY_FRAME);
+        let obus = parse_obus(&data).unwrap();
+
+        let types: Vec<ObuType> = obus.iter().map(Obu::obu_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                ObuType::TemporalDelimiter,
+                ObuType::SequenceHeader,
+                ObuType::Frame
+            ]
+        );
+        assert_eq!(obus[1].range(), 2..14);
+        assert_eq!(obus[1].payload.len(), 10);
+        assert_eq!(obus[2].header_bytes(), &[0x32]);
+
+        assert!(parse_obus(&[0x32, 0x05, 0x00]).is_err(), "truncated OBU");
+        assert!(parse_obus(&[0x92, 0x00]).is_err(), "forbidden bit");
+
+        // Extension byte carries the layer ids
+        let obus = parse_obus(&[0x36, 0b0100_1000, 0x00]).unwrap();
+        assert_eq!(
+            (obus[0].header.temporal_id, obus[0].header.spatial_id),
+            (2, 1)
+        );
+    }
+
+    #[test]
+    fn test_leb128_round_trip() {
+        for value in [0u64, 1, 127, 128, 16383, 16384, u32::MAX as u64] {
+            let mut buf = Vec::new();
+            write_leb128(&mut buf, value);
+            assert_eq!(read_leb128(&buf).unwrap(), (value, buf.len()));
+        }
+        assert!(read_leb128(&[0x80; 9]).is_err());
+    }
+
+    #[test]
+    fn test_sequence_header() {
+        let header = SequenceHeader::parse(&TD_AND_SEQUENCE_HEADER[4..]).unwrap();
+        assert_eq!(header.profile, 0);
+        assert!(!header.still_picture);
+        assert_eq!(header.level(), 31);
+        assert_eq!(header.level_number(), None);
+        assert_eq!((header.max_frame_width, header.max_frame_height), (64, 64));
+        assert_eq!(header.color_config.bit_depth, 8);
+        assert!(header.color_config.subsampling_x && header.color_config.subsampling_y);
+        assert_eq!(header.timing_info, None);
+        assert_eq!(header.frame_rate(), None);
+
+        // Matches rav1e's container_sequence_header() for the same stream
+        let av1c = header.av1c(&TD_AND_SEQUENCE_HEADER[2..]);
+        assert_eq!(&av1c[..4], &[0x81, 0x1f, 0x0c, 0x00]);
+        assert_eq!(&av1c[4..], &TD_AND_SEQUENCE_HEADER[2..]);
+
+        assert!(SequenceHeader::parse(&TD_AND_SEQUENCE_HEADER[4..8]).is_err());
+    }
+
+    #[test]
+    fn test_frame_header() {
+        let header = SequenceHeader::parse(&TD_AND_SEQUENCE_HEADER[4..]).unwrap();
+
+        let key = FrameHeader::parse(&KEY_FRAME[2..], &header).unwrap();
+        assert_eq!(key.frame_type, Some(FrameType::Key));
+        assert!(key.show_frame && key.error_resilient_mode && key.is_keyframe());
+
+        let inter = FrameHeader::parse(&INTER_FRAME[2..], &header).unwrap();
+        assert_eq!(inter.frame_type, Some(FrameType::Inter));
+        assert!(inter.show_frame && inter.showable_frame && !inter.is_keyframe());
+
+        // Hidden key frame (e.g. a forward keyframe) is not a random access point
+        let hidden = FrameHeader::parse(&[0x00, 0x00], &header).unwrap();
+        assert_eq!(hidden.frame_type, Some(FrameType::Key));
+        assert!(!hidden.show_frame && !hidden.is_keyframe());
+
+        // show_existing_frame of slot 5
+        let existing = FrameHeader::parse(&[0b1101_0000], &header).unwrap();
+        assert!(existing.show_existing_frame && existing.show_frame);
+        assert_eq!(
+            (existing.frame_to_show_map_idx, existing.frame_type),
+            (5, None)
+        );
+    }
+
+    #[test]
+    fn test_parser_temporal_units() {
+        let mut parser = Av1Parser::new();
+
+        // Frames before any sequence header can't be parsed
+        let mut data = vec![0x12, 0x00];
+        data.extend_from_slice(&INTER_FRAME);
+        let unit = parser.parse_temporal_unit(&data).unwrap();
+        assert!(unit.frames.is_empty() && !unit.is_keyframe());
+        assert!(parser.av1c().is_none());
+
+        let mut data = TD_AND_SEQUENCE_HEADER.to_vec();
+        data.extend_from_slice(&KEY_FRAME);
+        let unit = parser.parse_temporal_unit(&data).unwrap();
+        assert!(unit.has_sequence_header && unit.is_keyframe());
+        assert_eq!(unit.shown_frames(), 1);
+        assert_eq!(&parser.av1c().unwrap()[..4], &[0x81, 0x1f, 0x0c, 0x00]);
+
+        let mut data = vec![0x12, 0x00];
+        data.extend_from_slice(&INTER_FRAME);
+        let unit = parser.parse_temporal_unit(&data).unwrap();
+        assert!(!unit.has_sequence_header && !unit.is_keyframe());
+        assert_eq!(unit.frames.len(), 1);
+    }
+
+    #[test]
+    fn test_parser_hdr_metadata() {
+        let mut data = TD_AND_SEQUENCE_HEADER.to_vec();
+        // HDR_CLL: MaxCLL 1000, MaxFALL 400
+        data.extend_from_slice(&[0x2a, 0x05, 0x01, 0x03, 0xe8, 0x01, 0x90]);
+        // HDR_MDCV: BT.2020 primaries, D65, 1000 / 0.0001 cd/m²
+        let mut mdcv = vec![0x02];
+        for value in [34000u16, 16000, 13250, 34500, 7500, 3000, 15635, 16450] {
+            mdcv.extend_from_slice(&value.to_be_bytes());
+        }
+        mdcv.extend_from_slice(&(1000u32 << 8).to_be_bytes());
+        mdcv.extend_from_slice(&2u32.to_be_bytes());
+        data.extend_from_slice(&[0x2a, mdcv.len() as u8]);
+        data.extend_from_slice(&mdcv);
+
+        let mut parser = Av1Parser::new();
+        parser.parse_temporal_unit(&data).unwrap();
+
+        assert_eq!(
+            parser.content_light(),
+            Some(&ContentLightLevel {
+                max_cll: 1000,
+                max_fall: 400
+            })
+        );
+        let display = parser.mastering_display().unwrap();
+        assert_eq!(display.primaries[0], (34000, 16000));
+        assert_eq!(display.white_point, (15635, 16450));
+        assert_eq!(display.max_luminance_nits(), 1000.0);
+        assert_eq!(display.min_luminance, 2);
+
+        let colorimetry = parser.colorimetry().unwrap();
+        assert_eq!(colorimetry.primaries, ColorPrimaries::Unspecified);
+        assert_eq!(colorimetry.mastering_display.as_ref(), Some(display));
+    }
+}
@@ -1,15 +1,24 @@
 //! Codec implementations
 
-pub mod av1;
 pub mod aac;
+pub mod av1;
 pub mod opus;
+pub mod preset;
+pub mod rate;
+pub mod registry;
 
 use crate::{ArcFrame, Result};
+use rate::PassStats;
 
 /// Trait for video decoders
 pub trait VideoDecoder {
     /// Decode a packet into a frame
     fn decode(&mut self, data: &[u8]) -> Result<Option<ArcFrame>>;
+
+    /// Drain frames still buffered in the decoder at end of stream
+    fn flush(&mut self) -> Result<Vec<ArcFrame>> {
+        Ok(Vec::new())
+    }
 }
 
 /// Trait for audio decoders
@@ -18,16 +27,67 @@ pub trait AudioDecoder {
     fn decode(&mut self, data: &[u8]) -> Result<Option<Vec<f32>>>;
 }
 
+/// Kind of frame an encoded packet shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    /// Keyframe: decoding can start here
+    Key,
+    /// Intra frame that keeps earlier references, so decoding cannot start here
+    IntraOnly,
+    /// Predicted from other frames
+    Inter,
+}
+
+/// Encoder measurements for one frame, each `None` if the backend did not take it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameStats {
+    /// Luma, Cb and Cr sum of squared errors against the input
+    pub sse: Option<[u64; 3]>,
+    /// Luma, Cb and Cr SSIM against the input
+    pub ssim: Option<[f64; 3]>,
+    /// Share of blocks coded as skip, 0-1
+    pub skip_ratio: Option<f32>,
+}
+
+/// An encoded frame, with any hidden frames it depends on (an AV1
+/// temporal unit)
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncodedPacket {
+    /// Encoded data
+    pub data: Vec<u8>,
+    /// Presentation timestamp: the pts of the input frame shown, or its
+    /// position among the frames sent if it had none
+    pub pts: i64,
+    /// Decode timestamp; AV1 temporal units are stored in presentation
+    /// order, so this is the pts
+    pub dts: i64,
+    /// Kind of frame shown
+    pub frame_type: FrameType,
+    /// AV1 quantizer index (0-255) of the frame, if reported
+    pub qp: Option<u8>,
+    /// Per-frame measurements
+    pub stats: FrameStats,
+}
+
+impl EncodedPacket {
+    /// Whether decoding can start at this packet
+    pub fn is_keyframe(&self) -> bool {
+        self.frame_type == FrameType::Key
+    }
+}
+
 /// Trait for video encoders (send-receive pattern)
 pub trait VideoEncoder {
     /// Send a frame to the encoder (None signals end-of-stream)
+    ///
+    /// The frame's pts comes back on the packet that shows it.
     fn send_frame(&mut self, frame: Option<ArcFrame>) -> Result<()>;
 
     /// Receive an encoded packet (None means encoder needs more frames)
-    fn receive_packet(&mut self) -> Result<Option<Vec<u8>>>;
+    fn receive_packet(&mut self) -> Result<Option<EncodedPacket>>;
 
     /// Convenience method to flush all remaining packets
-    fn finish(&mut self) -> Result<Vec<Vec<u8>>> {
+    fn finish(&mut self) -> Result<Vec<EncodedPacket>> {
         self.send_frame(None)?;
         let mut packets = Vec::new();
         while let Some(packet) = self.receive_packet()? {
@@ -35,4 +95,11 @@ pub trait VideoEncoder {
         }
         Ok(packets)
     }
+
+    /// Statistics of a first pass, once the encoder has been flushed
+    ///
+    /// Encoders not running a first pass return `None`.
+    fn pass_stats(&self) -> Result<Option<PassStats>> {
+        Ok(None)
+    }
 }
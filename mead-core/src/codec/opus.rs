@@ -1,17 +1,220 @@
 //! Opus audio codec support using audiopus
 
-use crate::{Error, Result};
 use super::AudioDecoder;
-use audiopus::{coder::Decoder as OpusDecoder, SampleRate, Channels};
+use crate::{Error, Result};
+use audiopus::{Channels, SampleRate, coder::Decoder as OpusDecoder};
+
+/// Number of samples at 48 kHz in an Opus packet, parsed from its TOC byte
+///
+/// Returns `None` for empty or malformed packets (RFC 6716 section 3.1).
+pub fn packet_duration(data: &[u8]) -> Option<u32> {
+    let toc = *data.first()?;
+    let config = toc >> 3;
+
+    let frame_samples: u32 = match config {
+        // SILK-only: 10, 20, 40, 60 ms
+        0..=11 => [480, 960, 1920, 2880][(config % 4) as usize],
+        // Hybrid: 10, 20 ms
+        12..=15 => [480, 960][(config % 2) as usize],
+        // CELT-only: 2.5, 5, 10, 20 ms
+        _ => [120, 240, 480, 960][(config % 4) as usize],
+    };
+
+    let frame_count = match toc & 0x03 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => u32::from(*data.get(1)? & 0x3F),
+    };
+
+    let samples = frame_samples * frame_count;
+    // A packet may not exceed 120 ms
+    (frame_count > 0 && samples <= 5760).then_some(samples)
+}
+
+/// Maximum samples per channel in one Opus packet (120 ms at 48 kHz)
+const MAX_PACKET_SAMPLES: usize = 5760;
+
+/// Opus identification header (RFC 7845 section 5.1)
+///
+/// Carried as the first Ogg packet, as Matroska `A_OPUS` CodecPrivate and
+/// (re-encoded) as the MP4 `dOps` box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpusHead {
+    /// Output channel count
+    pub channels: u8,
+    /// Samples (at 48 kHz) to discard from the start of the decoded stream
+    pub pre_skip: u16,
+    /// Sample rate of the original input (informational)
+    pub input_sample_rate: u32,
+    /// Output gain in Q7.8 dB
+    pub output_gain: i16,
+    /// Channel mapping family (0 = mono/stereo, 1 = Vorbis order, ...)
+    pub mapping_family: u8,
+    /// Stream count, coupled count and channel mapping (empty for family 0)
+    pub mapping_table: Vec<u8>,
+}
+
+impl OpusHead {
+    /// Create a header for channel mapping family 0 (mono or stereo)
+    pub fn new(channels: u8, pre_skip: u16, input_sample_rate: u32) -> Self {
+        Self {
+            channels,
+            pre_skip,
+            input_sample_rate,
+            output_gain: 0,
+            mapping_family: 0,
+            mapping_table: Vec::new(),
+        }
+    }
+
+    /// Parse an `OpusHead` packet
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < 19 || &data[..8] != b"OpusHead" {
+            return Err(Error::Codec("Invalid OpusHead packet".to_string()));
+        }
+
+        // Only the major version (upper nibble) breaks compatibility
+        let version = data[8];
+        if version >> 4 != 0 {
+            return Err(Error::UnsupportedFormat(format!(
+                "OpusHead version {}",
+                version
+            )));
+        }
+
+        let channels = data[9];
+        let mapping_family = data[18];
+        if channels == 0 {
+            return Err(Error::Codec("OpusHead with zero channels".to_string()));
+        }
+
+        let mapping_table = if mapping_family == 0 {
+            if channels > 2 {
+                return Err(Error::Codec(format!(
+                    "OpusHead mapping family 0 with {} channels",
+                    channels
+                )));
+            }
+            Vec::new()
+        } else {
+            let table_len = 2 + usize::from(channels);
+            data.get(19..19 + table_len)
+                .ok_or_else(|| Error::Codec("Truncated OpusHead channel mapping".to_string()))?
+                .to_vec()
+        };
+
+        Ok(Self {
+            channels,
+            pre_skip: u16::from_le_bytes([data[10], data[11]]),
+            input_sample_rate: u32::from_le_bytes([data[12], data[13], data[14], data[15]]),
+            output_gain: i16::from_le_bytes([data[16], data[17]]),
+            mapping_family,
+            mapping_table,
+        })
+    }
+
+    /// Serialize as an `OpusHead` packet
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut head = Vec::with_capacity(19 + self.mapping_table.len());
+        head.extend_from_slice(b"OpusHead");
+        head.push(1); // version
+        head.push(self.channels);
+        head.extend_from_slice(&self.pre_skip.to_le_bytes());
+        head.extend_from_slice(&self.input_sample_rate.to_le_bytes());
+        head.extend_from_slice(&self.output_gain.to_le_bytes());
+        head.push(self.mapping_family);
+        head.extend_from_slice(&self.mapping_table);
+        head
+    }
+
+    /// Linear gain factor for the `output_gain` field
+    pub fn gain_factor(&self) -> f32 {
+        10f32.powf(f32::from(self.output_gain) / (20.0 * 256.0))
+    }
+}
+
+/// Opus comment header (RFC 7845 section 5.2)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpusTags {
+    /// Encoder vendor string
+    pub vendor: String,
+    /// User comments, conventionally `KEY=value`
+    pub comments: Vec<String>,
+}
+
+impl OpusTags {
+    /// Parse an `OpusTags` packet
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let truncated = || Error::Codec("Truncated OpusTags packet".to_string());
+
+        if data.get(..8) != Some(b"OpusTags".as_slice()) {
+            return Err(Error::Codec("Invalid OpusTags packet".to_string()));
+        }
+
+        let mut pos = 8;
+        let read_string = |pos: &mut usize| -> Result<String> {
+            let len_bytes = data.get(*pos..*pos + 4).ok_or_else(truncated)?;
+            let len = u32::from_le_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]])
+                as usize;
+            let start = *pos + 4;
+            let bytes = start
+                .checked_add(len)
+                .and_then(|end| data.get(start..end))
+                .ok_or_else(truncated)?;
+            *pos = start + len;
+            Ok(String::from_utf8_lossy(bytes).into_owned())
+        };
+
+        let vendor = read_string(&mut pos)?;
+        let count_bytes = data.get(pos..pos + 4).ok_or_else(truncated)?;
+        let count = u32::from_le_bytes([
+            count_bytes[0],
+            count_bytes[1],
+            count_bytes[2],
+            count_bytes[3],
+        ]);
+        pos += 4;
+
+        // Each comment needs at least its 4-byte length, which bounds the count
+        if count as usize > (data.len() - pos) / 4 {
+            return Err(truncated());
+        }
+
+        let comments = (0..count)
+            .map(|_| read_string(&mut pos))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { vendor, comments })
+    }
+
+    /// Serialize as an `OpusTags` packet
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut tags = Vec::new();
+        tags.extend_from_slice(b"OpusTags");
+        tags.extend_from_slice(&(self.vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(self.vendor.as_bytes());
+        tags.extend_from_slice(&(self.comments.len() as u32).to_le_bytes());
+        for comment in &self.comments {
+            tags.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            tags.extend_from_slice(comment.as_bytes());
+        }
+        tags
+    }
+}
 
 /// Opus audio decoder
+///
+/// Decoded samples are interleaved f32 at 48 kHz.
 pub struct OpusDecoderImpl {
     decoder: OpusDecoder,
+    channels: usize,
 }
 
 impl std::fmt::Debug for OpusDecoderImpl {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("OpusDecoder").finish()
+        f.debug_struct("OpusDecoder")
+            .field("channels", &self.channels)
+            .finish()
     }
 }
 
@@ -21,20 +224,30 @@ impl OpusDecoderImpl {
         let decoder = OpusDecoder::new(sample_rate, channels)
             .map_err(|e| Error::Codec(format!("Failed to create Opus decoder: {:?}", e)))?;
 
-        Ok(Self { decoder })
+        let channels = match channels {
+            Channels::Mono => 1,
+            _ => 2,
+        };
+
+        Ok(Self { decoder, channels })
+    }
+
+    /// Number of interleaved output channels
+    pub fn channels(&self) -> usize {
+        self.channels
     }
 }
 
 impl AudioDecoder for OpusDecoderImpl {
     fn decode(&mut self, data: &[u8]) -> Result<Option<Vec<f32>>> {
-        // For simplicity, assume we have enough space for decoded samples
-        // In a real implementation, you'd need to know the frame size
-        let mut output = vec![0.0f32; 4096]; // Temporary buffer
+        // Large enough for the longest packet Opus allows
+        let mut output = vec![0.0f32; MAX_PACKET_SAMPLES * self.channels];
 
         match self.decoder.decode_float(Some(data), &mut output, false) {
             Ok(samples_decoded) => {
                 if samples_decoded > 0 {
-                    output.truncate(samples_decoded);
+                    // The decoder reports samples per channel
+                    output.truncate(samples_decoded * self.channels);
                     Ok(Some(output))
                 } else {
                     Ok(None)
@@ -54,4 +267,50 @@ mod tests {
         let decoder = OpusDecoderImpl::new(SampleRate::Hz48000, Channels::Stereo);
         assert!(decoder.is_ok());
     }
+
+    #[test]
+    fn test_packet_duration() {
+        // CELT 20 ms, single frame
+        assert_eq!(packet_duration(&[0xFC]), Some(960));
+        // SILK 60 ms, two frames
+        assert_eq!(packet_duration(&[(3 << 3) | 1]), Some(5760));
+        // CELT 2.5 ms, code 3 with 4 frames
+        assert_eq!(packet_duration(&[(16 << 3) | 3, 4]), Some(480));
+        // Code 3 exceeding 120 ms is invalid
+        assert_eq!(packet_duration(&[(3 << 3) | 3, 3]), None);
+        assert_eq!(packet_duration(&[]), None);
+    }
+
+    #[test]
+    fn test_opus_head_round_trip() {
+        let head = OpusHead {
+            output_gain: -256,
+            ..OpusHead::new(2, 312, 44100)
+        };
+        let bytes = head.to_bytes();
+        assert_eq!(bytes.len(), 19);
+        assert_eq!(OpusHead::parse(&bytes).unwrap(), head);
+        assert!((head.gain_factor() - 0.891).abs() < 0.001);
+
+        // Family 0 cannot carry more than two channels
+        let mut bad = bytes.clone();
+        bad[9] = 6;
+        assert!(OpusHead::parse(&bad).is_err());
+        assert!(OpusHead::parse(&bytes[..18]).is_err());
+    }
+
+    #[test]
+    fn test_opus_tags_round_trip() {
+        let tags = OpusTags {
+            vendor: "mead".to_string(),
+            comments: vec!["TITLE=Test".to_string(), "ARTIST=Nobody".to_string()],
+        };
+        let bytes = tags.to_bytes();
+        assert_eq!(OpusTags::parse(&bytes).unwrap(), tags);
+
+        // Comment count larger than the packet can hold
+        let mut bad = bytes[..20].to_vec();
+        bad[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(OpusTags::parse(&bad).is_err());
+    }
 }
@@ -0,0 +1,207 @@
+//! Encoder presets and tuning
+//!
+//! [`Preset`] trades encoding speed for compression, and [`Tune`] adapts
+//! the encoder to a kind of content, the same way for every backend. Each
+//! backend maps them onto its own settings: rav1e through
+//! [`Av1Config::from_preset`](super::av1::Av1Config::from_preset), SVT-AV1
+//! through its preset numbers and tuning switches.
+//!
+//! # Example
+//! ```no_run
+//! use mead_core::codec::av1::{Av1Config, Av1Encoder};
+//! use mead_core::codec::preset::{Preset, Tune};
+//!
+//! let config = Av1Config::from_preset("quality".parse()?, Some(Tune::Animation));
+//! assert_eq!(config.speed, Preset::Quality.rav1e_speed());
+//! let encoder = Av1Encoder::with_config(1920, 1080, config)?;
+//! # Ok::<(), mead_core::Error>(())
+//! ```
+
+use crate::{Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// Speed against compression trade-off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Preset {
+    /// Real-time and previews
+    Fast,
+    /// Everyday encoding
+    #[default]
+    Balanced,
+    /// Distribution encodes, several times slower
+    Quality,
+    /// Masters and archives, as small as the encoder can manage
+    Archival,
+}
+
+impl Preset {
+    /// Every preset, fastest first
+    pub const ALL: [Self; 4] = [Self::Fast, Self::Balanced, Self::Quality, Self::Archival];
+
+    /// Name used by [`FromStr`] and [`Display`](fmt::Display)
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Fast => "fast",
+            Self::Balanced => "balanced",
+            Self::Quality => "quality",
+            Self::Archival => "archival",
+        }
+    }
+
+    /// rav1e speed, 0-10
+    pub fn rav1e_speed(self) -> u8 {
+        match self {
+            Self::Fast => 10,
+            Self::Balanced => 6,
+            Self::Quality => 3,
+            Self::Archival => 1,
+        }
+    }
+
+    /// SVT-AV1 preset, 0-13
+    pub fn svt_av1_preset(self) -> u8 {
+        match self {
+            Self::Fast => 10,
+            Self::Balanced => 8,
+            Self::Quality => 4,
+            Self::Archival => 2,
+        }
+    }
+
+    /// Whether frames are split into tiles, which encode in parallel but
+    /// cost some compression at tile edges
+    pub fn tiles(self) -> bool {
+        matches!(self, Self::Fast | Self::Balanced)
+    }
+}
+
+/// Parses `fast`, `balanced`, `quality` and `archival`
+impl FromStr for Preset {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown preset: {}", s)))
+    }
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Content an encoder is tuned for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tune {
+    /// Live action video
+    Film,
+    /// Cel animation: flat areas and sharp lines
+    Animation,
+    /// Film with grain or noise worth keeping
+    Grain,
+    /// Screen recordings, slides and text
+    Screen,
+}
+
+impl Tune {
+    /// Every tuning
+    pub const ALL: [Self; 4] = [Self::Film, Self::Animation, Self::Grain, Self::Screen];
+
+    /// Name used by [`FromStr`] and [`Display`](fmt::Display)
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Film => "film",
+            Self::Animation => "animation",
+            Self::Grain => "grain",
+            Self::Screen => "screen",
+        }
+    }
+
+    /// Constant quality for the content, used unless rate control is given
+    ///
+    /// Flat animation and screen content hide coarser quantizers, grain
+    /// needs finer ones to survive.
+    pub fn crf(self) -> u8 {
+        match self {
+            Self::Film => 30,
+            Self::Animation => 34,
+            Self::Grain => 26,
+            Self::Screen => 32,
+        }
+    }
+
+    /// Whether to optimize for perceived quality rather than PSNR
+    ///
+    /// Psychovisual tuning keeps texture at the expense of flat areas,
+    /// which suits camera footage but not synthetic content.
+    pub fn psychovisual(self) -> bool {
+        matches!(self, Self::Film | Self::Grain)
+    }
+
+    /// Whether to enable the AV1 screen content tools (palette and intra
+    /// block copy)
+    pub fn screen_content(self) -> bool {
+        self == Self::Screen
+    }
+
+    /// Whether to filter noise across frames before encoding, which
+    /// smooths away grain
+    pub fn temporal_filter(self) -> bool {
+        self != Self::Grain
+    }
+}
+
+/// Parses `film`, `animation`, `grain` and `screen`
+impl FromStr for Tune {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|tune| tune.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::InvalidInput(format!("Unknown tune: {}", s)))
+    }
+}
+
+impl fmt::Display for Tune {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        for preset in Preset::ALL {
+            assert_eq!(preset.to_string().parse::<Preset>().unwrap(), preset);
+        }
+        for tune in Tune::ALL {
+            assert_eq!(tune.to_string().parse::<Tune>().unwrap(), tune);
+        }
+        assert_eq!("Quality".parse::<Preset>().unwrap(), Preset::Quality);
+        assert!("slow".parse::<Preset>().is_err());
+        assert!("anime".parse::<Tune>().is_err());
+    }
+
+    #[test]
+    fn test_presets_slow_down() {
+        // Later presets are slower on every backend
+        for pair in Preset::ALL.windows(2) {
+            assert!(pair[0].rav1e_speed() > pair[1].rav1e_speed());
+            assert!(pair[0].svt_av1_preset() > pair[1].svt_av1_preset());
+        }
+        assert!(
+            Preset::ALL
+                .iter()
+                .all(|preset| preset.rav1e_speed() <= 10 && preset.svt_av1_preset() <= 13)
+        );
+        assert_eq!(Preset::default(), Preset::Balanced);
+    }
+}
@@ -0,0 +1,381 @@
+//! Rate control
+//!
+//! [`RateControl`] describes how an encoder spends bits, independent of the
+//! backend: constant quality, a variable bitrate held to an average, or a
+//! constant bitrate for live delivery. Quality uses the 0-63 scale of AV1
+//! quantizer indices shared by SVT-AV1 and aomenc; bitrates are in
+//! kilobits per second and buffer sizes in kilobits, as with ffmpeg's
+//! `-b:v`, `-maxrate` and `-bufsize`.
+//!
+//! A VBR encode can also run in two passes: the first [`Pass::First`]
+//! collects statistics, which the encoder hands back as [`PassStats`] once
+//! flushed, and [`Pass::Second`] spends the bits where the statistics say
+//! they are needed.
+//!
+//! # Example
+//! ```no_run
+//! use mead_core::codec::av1::{Av1Config, Av1Encoder};
+//! use mead_core::codec::rate::{parse_bitrate, RateControl};
+//!
+//! let rate_control = RateControl::Vbr {
+//!     bitrate: parse_bitrate("4M")?,
+//!     max_bitrate: Some(parse_bitrate("6M")?),
+//!     buffer_size: Some(parse_bitrate("8M")?),
+//! };
+//! rate_control.validate()?;
+//!
+//! let encoder = Av1Encoder::with_config(1920, 1080, Av1Config {
+//!     rate_control,
+//!     framerate: (24000, 1001),
+//!     ..Default::default()
+//! })?;
+//! # Ok::<(), mead_core::Error>(())
+//! ```
+
+use crate::{Error, Result};
+use std::fmt;
+use std::path::Path;
+
+/// Highest constant quality value, the largest AV1 quantizer index over 4
+pub const MAX_CRF: u8 = 63;
+
+/// How an encoder distributes bits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateControl {
+    /// Constant quality (CRF): 0-63, lower is better
+    Crf(u8),
+    /// Variable bitrate averaging `bitrate`
+    Vbr {
+        /// Average bitrate in kbps
+        bitrate: u32,
+        /// Peak bitrate in kbps, for backends that can cap it
+        max_bitrate: Option<u32>,
+        /// Rate buffer in kilobits; larger buffers allow bigger swings
+        buffer_size: Option<u32>,
+    },
+    /// Constant bitrate with low-delay prediction, for live streaming
+    Cbr {
+        /// Bitrate in kbps
+        bitrate: u32,
+        /// Rate buffer in kilobits, one second of video by default
+        buffer_size: Option<u32>,
+    },
+}
+
+impl RateControl {
+    /// Target bitrate in kbps, or `None` for constant quality
+    pub fn bitrate(&self) -> Option<u32> {
+        match *self {
+            Self::Crf(_) => None,
+            Self::Vbr { bitrate, .. } | Self::Cbr { bitrate, .. } => Some(bitrate),
+        }
+    }
+
+    /// Rate buffer in kilobits, if one is set
+    pub fn buffer_size(&self) -> Option<u32> {
+        match *self {
+            Self::Crf(_) => None,
+            Self::Vbr { buffer_size, .. } | Self::Cbr { buffer_size, .. } => buffer_size,
+        }
+    }
+
+    /// Rate buffer as a duration in milliseconds, if one is set
+    pub fn buffer_ms(&self) -> Option<u64> {
+        let bitrate = self.bitrate()?;
+        Some(u64::from(self.buffer_size()?) * 1000 / u64::from(bitrate.max(1)))
+    }
+
+    /// Check the values are in range and consistent
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Self::Crf(crf) if crf > MAX_CRF => Err(Error::InvalidInput(format!(
+                "CRF must be 0-{}, got {}",
+                MAX_CRF, crf
+            ))),
+            Self::Crf(_) => Ok(()),
+            Self::Vbr {
+                max_bitrate: Some(max),
+                bitrate,
+                ..
+            } if max < bitrate => Err(Error::InvalidInput(format!(
+                "Max bitrate {} kbps is below the {} kbps target",
+                max, bitrate
+            ))),
+            Self::Vbr { bitrate: 0, .. } | Self::Cbr { bitrate: 0, .. } => {
+                Err(Error::InvalidInput("Bitrate must be above 0".to_string()))
+            }
+            _ if self.buffer_size() == Some(0) => Err(Error::InvalidInput(
+                "Buffer size must be above 0".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Formats as `CRF 30`, `VBR 4000 kbps, max 6000 kbps, 8000 kbit buffer`
+/// or `CBR 2000 kbps`
+impl fmt::Display for RateControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Crf(crf) => write!(f, "CRF {}", crf),
+            Self::Vbr {
+                bitrate,
+                max_bitrate,
+                ..
+            } => {
+                write!(f, "VBR {} kbps", bitrate)?;
+                if let Some(max_bitrate) = max_bitrate {
+                    write!(f, ", max {} kbps", max_bitrate)?;
+                }
+                Ok(())
+            }
+            Self::Cbr { bitrate, .. } => write!(f, "CBR {} kbps", bitrate),
+        }?;
+        match self.buffer_size() {
+            Some(buffer_size) => write!(f, ", {} kbit buffer", buffer_size),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Which pass of an encode an encoder runs
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Pass {
+    /// A single pass
+    #[default]
+    Single,
+    /// First of two passes, collecting statistics instead of a stream worth keeping
+    First,
+    /// Second of two passes, using the first pass's statistics
+    Second(PassStats),
+}
+
+impl Pass {
+    /// Whether this pass is part of a two-pass encode
+    pub fn is_two_pass(&self) -> bool {
+        !matches!(self, Self::Single)
+    }
+
+    /// Check the pass suits the rate control
+    ///
+    /// Two-pass encoding spreads a bitrate over the whole input, so it
+    /// needs a VBR target.
+    pub fn validate(&self, rate_control: &RateControl) -> Result<()> {
+        match (self, rate_control) {
+            (Self::Single, _) | (_, RateControl::Vbr { .. }) => Ok(()),
+            (_, rate_control) => Err(Error::InvalidInput(format!(
+                "Two-pass encoding needs a VBR target bitrate, not {}",
+                rate_control
+            ))),
+        }
+    }
+}
+
+/// Statistics from a first pass
+///
+/// The data is specific to the encoder that wrote it, which is recorded
+/// alongside so a second pass with another backend is caught early.
+#[derive(Clone, PartialEq, Eq)]
+pub struct PassStats {
+    encoder: String,
+    data: Vec<u8>,
+}
+
+impl fmt::Debug for PassStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PassStats")
+            .field("encoder", &self.encoder)
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
+
+impl PassStats {
+    /// Stats file signature
+    const MAGIC: &'static [u8; 8] = b"MEADPASS";
+
+    /// Wrap statistics written by `encoder`
+    ///
+    /// The stats file stores the name with a one-byte length, so names
+    /// longer than 255 bytes are rejected.
+    pub fn new(encoder: impl Into<String>, data: Vec<u8>) -> Result<Self> {
+        let encoder = encoder.into();
+        if encoder.len() > usize::from(u8::MAX) {
+            return Err(Error::InvalidInput(format!(
+                "Encoder name for first pass stats is {} bytes, at most 255 are supported",
+                encoder.len()
+            )));
+        }
+        Ok(Self { encoder, data })
+    }
+
+    /// Name of the encoder that wrote the statistics
+    pub fn encoder(&self) -> &str {
+        &self.encoder
+    }
+
+    /// Statistics for `encoder`, or an error if another encoder wrote them
+    pub fn data_for(&self, encoder: &str) -> Result<&[u8]> {
+        if self.encoder != encoder {
+            return Err(Error::InvalidInput(format!(
+                "First pass stats were written by {}, not {}",
+                self.encoder, encoder
+            )));
+        }
+        Ok(&self.data)
+    }
+
+    /// Serialize into the stats file format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes =
+            Vec::with_capacity(Self::MAGIC.len() + 1 + self.encoder.len() + self.data.len());
+        bytes.extend_from_slice(Self::MAGIC);
+        // Checked in new
+        bytes.push(self.encoder.len() as u8);
+        bytes.extend_from_slice(self.encoder.as_bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    /// Parse the stats file format
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid = || Error::InvalidInput("Not a first pass stats file".to_string());
+        let rest = bytes
+            .strip_prefix(Self::MAGIC.as_slice())
+            .ok_or_else(invalid)?;
+        let (&len, rest) = rest.split_first().ok_or_else(invalid)?;
+        if rest.len() < usize::from(len) {
+            return Err(invalid());
+        }
+        let (encoder, data) = rest.split_at(usize::from(len));
+        let encoder = std::str::from_utf8(encoder).map_err(|_| invalid())?;
+        Self::new(encoder, data.to_vec())
+    }
+
+    /// Read a stats file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Write a stats file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+}
+
+/// Parse a bitrate such as `2500`, `2500k` or `2.5M` into kbps
+///
+/// Plain numbers are kilobits per second.
+///
+/// # Example
+/// ```
+/// use mead_core::codec::rate::parse_bitrate;
+///
+/// assert_eq!(parse_bitrate("2500").unwrap(), 2500);
+/// assert_eq!(parse_bitrate("2.5M").unwrap(), 2500);
+/// assert_eq!(parse_bitrate("800k").unwrap(), 800);
+/// ```
+pub fn parse_bitrate(s: &str) -> Result<u32> {
+    let s = s.trim();
+    let (number, scale) = match s.char_indices().last() {
+        Some((index, 'k' | 'K')) => (&s[..index], 1.0),
+        Some((index, 'm' | 'M')) => (&s[..index], 1000.0),
+        _ => (s, 1.0),
+    };
+    let kbps = number
+        .parse::<f64>()
+        .ok()
+        .map(|value| (value * scale).round())
+        .filter(|kbps| kbps.is_finite() && *kbps >= 1.0 && *kbps <= f64::from(u32::MAX))
+        .ok_or_else(|| Error::InvalidInput(format!("Invalid bitrate: {}", s)))?;
+    Ok(kbps as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bitrate() {
+        assert_eq!(parse_bitrate("6000").unwrap(), 6000);
+        assert_eq!(parse_bitrate("6000k").unwrap(), 6000);
+        assert_eq!(parse_bitrate("0.75M").unwrap(), 750);
+        for invalid in ["", "k", "fast", "-5", "0", "1G"] {
+            assert!(parse_bitrate(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(RateControl::Crf(30).validate().is_ok());
+        assert!(RateControl::Crf(64).validate().is_err());
+
+        let vbr = RateControl::Vbr {
+            bitrate: 4000,
+            max_bitrate: Some(6000),
+            buffer_size: Some(8000),
+        };
+        assert!(vbr.validate().is_ok());
+        assert_eq!(vbr.buffer_ms(), Some(2000));
+        assert_eq!(
+            vbr.to_string(),
+            "VBR 4000 kbps, max 6000 kbps, 8000 kbit buffer"
+        );
+        let capped_below = RateControl::Vbr {
+            bitrate: 4000,
+            max_bitrate: Some(3000),
+            buffer_size: None,
+        };
+        assert!(capped_below.validate().is_err());
+
+        assert!(
+            RateControl::Cbr {
+                bitrate: 0,
+                buffer_size: None
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            RateControl::Cbr {
+                bitrate: 2000,
+                buffer_size: Some(0)
+            }
+            .validate()
+            .is_err()
+        );
+        assert_eq!(
+            RateControl::Cbr {
+                bitrate: 2000,
+                buffer_size: None
+            }
+            .buffer_ms(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_pass() {
+        let vbr = RateControl::Vbr {
+            bitrate: 2000,
+            max_bitrate: None,
+            buffer_size: None,
+        };
+        assert!(Pass::First.validate(&vbr).is_ok());
+        assert!(Pass::First.validate(&RateControl::Crf(30)).is_err());
+        assert!(Pass::Single.validate(&RateControl::Crf(30)).is_ok());
+
+        let stats = PassStats::new("rav1e", vec![1, 2, 3]).unwrap();
+        let parsed = PassStats::from_bytes(&stats.to_bytes()).unwrap();
+        assert_eq!(parsed, stats);
+        assert_eq!(parsed.data_for("rav1e").unwrap(), &[1, 2, 3]);
+        assert!(parsed.data_for("svt-av1").is_err());
+        assert!(PassStats::from_bytes(b"MEADPASS").is_err());
+        assert!(PassStats::from_bytes(b"YUV4MPEG2 W64").is_err());
+
+        // The name length must fit the stats file's one-byte prefix
+        assert!(PassStats::new("x".repeat(255), Vec::new()).is_ok());
+        assert!(PassStats::new("x".repeat(256), Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,381 @@
+//! Encoder selection by name
+//!
+//! [`EncoderOptions`] describes an encode independent of the backend, and
+//! an [`EncoderRegistry`] turns it into a [`VideoEncoder`] for a backend
+//! chosen by name. Each backend is an [`EncoderFactory`] that reports what
+//! it can do through [`EncoderCapabilities`]; the registry checks options
+//! against them before building anything. rav1e is built in, and backends
+//! living in other crates (such as SVT-AV1, which needs `unsafe`) register
+//! their own factory.
+//!
+//! # Example
+//! ```no_run
+//! use mead_core::codec::rate::RateControl;
+//! use mead_core::codec::registry::{EncoderOptions, EncoderRegistry};
+//! use mead_core::PixelFormat;
+//!
+//! let registry = EncoderRegistry::default();
+//! let factory = registry.get("rav1e").unwrap();
+//! let input = PixelFormat::Yuv422p10;
+//! let options = EncoderOptions {
+//!     width: 1920,
+//!     height: 1080,
+//!     framerate: (24000, 1001),
+//!     pixel_format: factory.capabilities().pixel_format_for(input),
+//!     rate_control: Some(RateControl::Crf(28)),
+//!     ..Default::default()
+//! };
+//! let encoder = registry.create("rav1e", &options)?;
+//! # Ok::<(), mead_core::Error>(())
+//! ```
+
+use super::VideoEncoder;
+use super::av1::{FilmGrain, Rav1eFactory};
+use super::preset::{Preset, Tune};
+use super::rate::{Pass, RateControl};
+use crate::{Colorimetry, Error, PixelFormat, PlaneLayout, Result};
+use std::fmt;
+
+/// Encode settings shared by every backend
+#[derive(Debug, Clone)]
+pub struct EncoderOptions {
+    /// Frame width in pixels
+    pub width: u32,
+    /// Frame height in pixels
+    pub height: u32,
+    /// Frame rate as (numerator, denominator)
+    pub framerate: (u64, u64),
+    /// Format of the frames sent to the encoder
+    pub pixel_format: PixelFormat,
+    /// Color description and HDR metadata written to the sequence header
+    pub colorimetry: Colorimetry,
+    /// Speed against compression
+    pub preset: Preset,
+    /// Content to tune for
+    pub tune: Option<Tune>,
+    /// Constant quality or target bitrate (None = the preset's default)
+    pub rate_control: Option<RateControl>,
+    /// Single pass, or which pass of a two-pass encode
+    pub pass: Pass,
+    /// Most frames between keyframes (None = backend default)
+    pub max_keyframe_interval: Option<u64>,
+    /// Fewest frames between keyframes, for backends that place them at
+    /// scene changes (None = backend default)
+    pub min_keyframe_interval: Option<u64>,
+    /// Number of threads (0 = auto-detect from CPU cores)
+    pub threads: usize,
+    /// Film grain the decoder adds back after decoding
+    pub film_grain: Option<FilmGrain>,
+}
+
+impl Default for EncoderOptions {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            framerate: (30, 1),
+            pixel_format: PixelFormat::Yuv420p,
+            colorimetry: Colorimetry::default(),
+            preset: Preset::default(),
+            tune: None,
+            rate_control: None,
+            pass: Pass::Single,
+            max_keyframe_interval: None,
+            min_keyframe_interval: None,
+            threads: 0,
+            film_grain: None,
+        }
+    }
+}
+
+/// What an encoder backend supports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncoderCapabilities {
+    /// Pixel formats frames can be sent in
+    pub pixel_formats: &'static [PixelFormat],
+    /// Bit depths the backend encodes
+    pub bit_depths: &'static [u8],
+    /// Whether two-pass encoding is supported
+    pub two_pass: bool,
+    /// Whether film grain synthesis parameters can be signalled
+    pub film_grain: bool,
+    /// Whether VBR peak bitrates are honored
+    pub max_bitrate: bool,
+    /// Layout of the frames the backend reads most efficiently
+    pub plane_layout: PlaneLayout,
+}
+
+impl EncoderCapabilities {
+    /// Pixel format to encode `input` as, the closest one the backend accepts
+    ///
+    /// Chroma subsampling is kept where possible, then bit depth, which is
+    /// raised rather than lowered. RGB is encoded as 4:2:0.
+    pub fn pixel_format_for(&self, input: PixelFormat) -> PixelFormat {
+        if self.pixel_formats.contains(&input) {
+            return input;
+        }
+        let shift = if input.is_yuv() {
+            input.chroma_shift()
+        } else {
+            (1, 1)
+        };
+        let depth = input.bit_depth();
+        self.pixel_formats
+            .iter()
+            .copied()
+            .min_by_key(|format| {
+                let depth_cost = match format.bit_depth() {
+                    d if d >= depth => d - depth,
+                    d => 16 + depth - d,
+                };
+                (format.chroma_shift() != shift, depth_cost)
+            })
+            .unwrap_or(input)
+    }
+
+    /// Check `options` only asks for what the backend supports
+    pub fn check(&self, name: &str, options: &EncoderOptions) -> Result<()> {
+        if !self.pixel_formats.contains(&options.pixel_format) {
+            return Err(Error::UnsupportedFormat(format!(
+                "{} cannot encode {:?}",
+                name, options.pixel_format
+            )));
+        }
+        if !self.bit_depths.contains(&options.pixel_format.bit_depth()) {
+            return Err(Error::UnsupportedFormat(format!(
+                "{} cannot encode {}-bit video",
+                name,
+                options.pixel_format.bit_depth()
+            )));
+        }
+        if matches!(
+            options.rate_control,
+            Some(RateControl::Vbr {
+                max_bitrate: Some(_),
+                ..
+            })
+        ) && !self.max_bitrate
+        {
+            return Err(Error::UnsupportedFormat(format!(
+                "{} cannot cap the VBR bitrate",
+                name
+            )));
+        }
+        if options.pass.is_two_pass() && !self.two_pass {
+            return Err(Error::UnsupportedFormat(format!(
+                "{} has no two-pass mode",
+                name
+            )));
+        }
+        if options.film_grain.is_some() && !self.film_grain {
+            return Err(Error::UnsupportedFormat(format!(
+                "{} has no film grain synthesis",
+                name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Builds one backend's encoders
+pub trait EncoderFactory: Send + Sync {
+    /// Name the backend is selected by
+    fn name(&self) -> &'static str;
+
+    /// Other names accepted for the backend
+    fn aliases(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// What the backend supports
+    fn capabilities(&self) -> EncoderCapabilities;
+
+    /// Backend configuration `options` resolve to, for display
+    fn settings(&self, options: &EncoderOptions) -> String;
+
+    /// Build an encoder; [`EncoderRegistry::create`] has already checked
+    /// the options against the capabilities
+    fn create(&self, options: &EncoderOptions) -> Result<Box<dyn VideoEncoder>>;
+}
+
+/// Encoder backends by name
+///
+/// The default registry holds rav1e; [`EncoderRegistry::new`] starts empty.
+pub struct EncoderRegistry {
+    factories: Vec<Box<dyn EncoderFactory>>,
+}
+
+impl fmt::Debug for EncoderRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+impl Default for EncoderRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register(Rav1eFactory);
+        registry
+    }
+}
+
+impl EncoderRegistry {
+    /// Create a registry without any backends
+    pub fn new() -> Self {
+        Self {
+            factories: Vec::new(),
+        }
+    }
+
+    /// Add a backend, replacing any with the same name
+    ///
+    /// The first backend registered is the [default](Self::default_factory).
+    pub fn register(&mut self, factory: impl EncoderFactory + 'static) {
+        match self
+            .factories
+            .iter()
+            .position(|existing| existing.name() == factory.name())
+        {
+            Some(index) => self.factories[index] = Box::new(factory),
+            None => self.factories.push(Box::new(factory)),
+        }
+    }
+
+    /// Backend registered as `name` or one of its aliases, ignoring case
+    pub fn get(&self, name: &str) -> Option<&dyn EncoderFactory> {
+        self.factories
+            .iter()
+            .find(|factory| {
+                std::iter::once(factory.name())
+                    .chain(factory.aliases().iter().copied())
+                    .any(|candidate| candidate.eq_ignore_ascii_case(name))
+            })
+            .map(|factory| factory.as_ref())
+    }
+
+    /// The first backend registered
+    pub fn default_factory(&self) -> Option<&dyn EncoderFactory> {
+        self.factories.first().map(|factory| factory.as_ref())
+    }
+
+    /// Names of the registered backends, in registration order
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.factories.iter().map(|factory| factory.name())
+    }
+
+    /// Build an encoder with the backend registered as `name`
+    pub fn create(&self, name: &str, options: &EncoderOptions) -> Result<Box<dyn VideoEncoder>> {
+        let factory = self.get(name).ok_or_else(|| {
+            Error::UnsupportedFormat(format!(
+                "Unknown encoder {}, expected one of: {}",
+                name,
+                self.names().collect::<Vec<_>>().join(", ")
+            ))
+        })?;
+        if options.width == 0 || options.height == 0 {
+            return Err(Error::InvalidInput(
+                "Width and height must be set".to_string(),
+            ));
+        }
+        factory.capabilities().check(factory.name(), options)?;
+        factory.create(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry() {
+        let registry = EncoderRegistry::default();
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["rav1e"]);
+        assert_eq!(registry.get("RAV1E").unwrap().name(), "rav1e");
+        assert!(registry.get("x264").is_none());
+        assert!(EncoderRegistry::new().default_factory().is_none());
+
+        let options = EncoderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        assert!(registry.create("rav1e", &options).is_ok());
+        assert!(registry.create("x264", &options).is_err());
+        assert!(
+            registry
+                .create("rav1e", &EncoderOptions::default())
+                .is_err()
+        );
+        let rgb = EncoderOptions {
+            pixel_format: PixelFormat::Rgb24,
+            ..options.clone()
+        };
+        assert!(registry.create("rav1e", &rgb).is_err());
+        let capped = EncoderOptions {
+            rate_control: Some(RateControl::Vbr {
+                bitrate: 2000,
+                max_bitrate: Some(3000),
+                buffer_size: None,
+            }),
+            ..options
+        };
+        assert!(registry.create("rav1e", &capped).is_err());
+    }
+
+    #[test]
+    fn test_check_bit_depth() {
+        const FORMATS: &[PixelFormat] = &[PixelFormat::Yuv420p, PixelFormat::Yuv420p10];
+        let capabilities = EncoderCapabilities {
+            pixel_formats: FORMATS,
+            bit_depths: &[8],
+            two_pass: false,
+            film_grain: false,
+            max_bitrate: false,
+            plane_layout: PlaneLayout::default(),
+        };
+        let options = EncoderOptions {
+            width: 64,
+            height: 64,
+            ..Default::default()
+        };
+        assert!(capabilities.check("test", &options).is_ok());
+        let deep = EncoderOptions {
+            pixel_format: PixelFormat::Yuv420p10,
+            ..options
+        };
+        assert!(capabilities.check("test", &deep).is_err());
+    }
+
+    #[test]
+    fn test_pixel_format_for() {
+        const FORMATS: &[PixelFormat] = &[PixelFormat::Yuv420p, PixelFormat::Yuv420p10];
+        let capabilities = EncoderCapabilities {
+            pixel_formats: FORMATS,
+            bit_depths: &[8, 10],
+            two_pass: false,
+            film_grain: false,
+            max_bitrate: false,
+            plane_layout: PlaneLayout::default(),
+        };
+        assert_eq!(
+            capabilities.pixel_format_for(PixelFormat::Yuv420p10),
+            PixelFormat::Yuv420p10
+        );
+        assert_eq!(
+            capabilities.pixel_format_for(PixelFormat::Yuv444p12),
+            PixelFormat::Yuv420p10
+        );
+        assert_eq!(
+            capabilities.pixel_format_for(PixelFormat::Yuv422p),
+            PixelFormat::Yuv420p
+        );
+        assert_eq!(
+            capabilities.pixel_format_for(PixelFormat::Rgb24),
+            PixelFormat::Yuv420p
+        );
+        assert_eq!(
+            capabilities.pixel_format_for(PixelFormat::P010),
+            PixelFormat::Yuv420p10
+        );
+    }
+}
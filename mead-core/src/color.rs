@@ -0,0 +1,571 @@
+//! Colorimetry and HDR metadata
+//!
+//! Code points follow ITU-T H.273, as used by the AV1 sequence header, the
+//! MP4 `colr` box and the Matroska `Colour` element. Mastering display and
+//! content light values use the AV1 metadata OBU units.
+
+use crate::{Error, Result};
+use std::str::FromStr;
+
+/// Color primaries (H.273 `ColourPrimaries`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorPrimaries {
+    /// BT.709 / sRGB
+    Bt709,
+    /// Unspecified
+    #[default]
+    Unspecified,
+    /// BT.470 System M
+    Bt470M,
+    /// BT.470 System B, G (PAL)
+    Bt470Bg,
+    /// BT.601 / SMPTE 170M (NTSC)
+    Bt601,
+    /// SMPTE 240M
+    Smpte240,
+    /// Generic film
+    GenericFilm,
+    /// BT.2020 / BT.2100
+    Bt2020,
+    /// SMPTE ST 428 (CIE XYZ)
+    Xyz,
+    /// SMPTE RP 431-2 (DCI-P3)
+    Smpte431,
+    /// SMPTE EG 432-1 (Display P3)
+    Smpte432,
+    /// EBU Tech. 3213-E
+    Ebu3213,
+}
+
+impl ColorPrimaries {
+    /// Code point and name of each variant; aliases follow the primary name
+    const TABLE: &'static [(Self, u8, &'static str)] = &[
+        (Self::Bt709, 1, "bt709"),
+        (Self::Unspecified, 2, "unspecified"),
+        (Self::Bt470M, 4, "bt470m"),
+        (Self::Bt470Bg, 5, "bt470bg"),
+        (Self::Bt601, 6, "smpte170m"),
+        (Self::Bt601, 6, "bt601"),
+        (Self::Smpte240, 7, "smpte240m"),
+        (Self::GenericFilm, 8, "film"),
+        (Self::Bt2020, 9, "bt2020"),
+        (Self::Xyz, 10, "smpte428"),
+        (Self::Smpte431, 11, "smpte431"),
+        (Self::Smpte432, 12, "smpte432"),
+        (Self::Smpte432, 12, "p3"),
+        (Self::Ebu3213, 22, "ebu3213"),
+    ];
+
+    /// Map an H.273 code point, treating unknown values as unspecified
+    pub fn from_code(code: u8) -> Self {
+        lookup_code(Self::TABLE, code).unwrap_or_default()
+    }
+
+    /// H.273 code point
+    pub fn code(self) -> u8 {
+        lookup_value(Self::TABLE, self).0
+    }
+
+    /// Canonical name, as accepted by `FromStr`
+    pub fn name(self) -> &'static str {
+        lookup_value(Self::TABLE, self).1
+    }
+}
+
+impl FromStr for ColorPrimaries {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        lookup_name(Self::TABLE, s, "color primaries")
+    }
+}
+
+/// Transfer characteristics (H.273 `TransferCharacteristics`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TransferCharacteristics {
+    /// BT.709
+    Bt709,
+    /// Unspecified
+    #[default]
+    Unspecified,
+    /// BT.470 System M (gamma 2.2)
+    Bt470M,
+    /// BT.470 System B, G (gamma 2.8)
+    Bt470Bg,
+    /// BT.601 / SMPTE 170M
+    Bt601,
+    /// SMPTE 240M
+    Smpte240,
+    /// Linear
+    Linear,
+    /// Logarithmic, 100:1 range
+    Log100,
+    /// Logarithmic, 316:1 range
+    Log100Sqrt10,
+    /// IEC 61966-2-4 (xvYCC)
+    Iec61966,
+    /// BT.1361 extended gamut
+    Bt1361,
+    /// sRGB / sYCC
+    Srgb,
+    /// BT.2020 10-bit
+    Bt2020Ten,
+    /// BT.2020 12-bit
+    Bt2020Twelve,
+    /// SMPTE ST 2084 perceptual quantizer (HDR10)
+    Smpte2084,
+    /// SMPTE ST 428
+    Smpte428,
+    /// ARIB STD-B67 hybrid log-gamma
+    Hlg,
+}
+
+impl TransferCharacteristics {
+    /// Code point and name of each variant; aliases follow the primary name
+    const TABLE: &'static [(Self, u8, &'static str)] = &[
+        (Self::Bt709, 1, "bt709"),
+        (Self::Unspecified, 2, "unspecified"),
+        (Self::Bt470M, 4, "bt470m"),
+        (Self::Bt470Bg, 5, "bt470bg"),
+        (Self::Bt601, 6, "smpte170m"),
+        (Self::Bt601, 6, "bt601"),
+        (Self::Smpte240, 7, "smpte240m"),
+        (Self::Linear, 8, "linear"),
+        (Self::Log100, 9, "log100"),
+        (Self::Log100Sqrt10, 10, "log316"),
+        (Self::Iec61966, 11, "iec61966-2-4"),
+        (Self::Bt1361, 12, "bt1361e"),
+        (Self::Srgb, 13, "iec61966-2-1"),
+        (Self::Srgb, 13, "srgb"),
+        (Self::Bt2020Ten, 14, "bt2020-10"),
+        (Self::Bt2020Twelve, 15, "bt2020-12"),
+        (Self::Smpte2084, 16, "smpte2084"),
+        (Self::Smpte2084, 16, "pq"),
+        (Self::Smpte428, 17, "smpte428"),
+        (Self::Hlg, 18, "arib-std-b67"),
+        (Self::Hlg, 18, "hlg"),
+    ];
+
+    /// Map an H.273 code point, treating unknown values as unspecified
+    pub fn from_code(code: u8) -> Self {
+        lookup_code(Self::TABLE, code).unwrap_or_default()
+    }
+
+    /// H.273 code point
+    pub fn code(self) -> u8 {
+        lookup_value(Self::TABLE, self).0
+    }
+
+    /// Canonical name, as accepted by `FromStr`
+    pub fn name(self) -> &'static str {
+        lookup_value(Self::TABLE, self).1
+    }
+
+    /// Returns true for the HDR transfer functions (PQ and HLG)
+    pub fn is_hdr(self) -> bool {
+        matches!(self, Self::Smpte2084 | Self::Hlg)
+    }
+}
+
+impl FromStr for TransferCharacteristics {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        lookup_name(Self::TABLE, s, "transfer characteristics")
+    }
+}
+
+/// Matrix coefficients (H.273 `MatrixCoefficients`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatrixCoefficients {
+    /// Identity (GBR)
+    Identity,
+    /// BT.709
+    Bt709,
+    /// Unspecified
+    #[default]
+    Unspecified,
+    /// US FCC 73.628
+    Fcc,
+    /// BT.470 System B, G
+    Bt470Bg,
+    /// BT.601 / SMPTE 170M
+    Bt601,
+    /// SMPTE 240M
+    Smpte240,
+    /// YCgCo
+    YCgCo,
+    /// BT.2020 non-constant luminance
+    Bt2020Ncl,
+    /// BT.2020 constant luminance
+    Bt2020Cl,
+    /// SMPTE ST 2085
+    Smpte2085,
+    /// Chromaticity-derived non-constant luminance
+    ChromatNcl,
+    /// Chromaticity-derived constant luminance
+    ChromatCl,
+    /// BT.2100 ICtCp
+    ICtCp,
+}
+
+impl MatrixCoefficients {
+    /// Code point and name of each variant; aliases follow the primary name
+    const TABLE: &'static [(Self, u8, &'static str)] = &[
+        (Self::Identity, 0, "gbr"),
+        (Self::Identity, 0, "identity"),
+        (Self::Bt709, 1, "bt709"),
+        (Self::Unspecified, 2, "unspecified"),
+        (Self::Fcc, 4, "fcc"),
+        (Self::Bt470Bg, 5, "bt470bg"),
+        (Self::Bt601, 6, "smpte170m"),
+        (Self::Bt601, 6, "bt601"),
+        (Self::Smpte240, 7, "smpte240m"),
+        (Self::YCgCo, 8, "ycgco"),
+        (Self::Bt2020Ncl, 9, "bt2020nc"),
+        (Self::Bt2020Cl, 10, "bt2020c"),
+        (Self::Smpte2085, 11, "smpte2085"),
+        (Self::ChromatNcl, 12, "chroma-derived-nc"),
+        (Self::ChromatCl, 13, "chroma-derived-c"),
+        (Self::ICtCp, 14, "ictcp"),
+    ];
+
+    /// Map an H.273 code point, treating unknown values as unspecified
+    pub fn from_code(code: u8) -> Self {
+        lookup_code(Self::TABLE, code).unwrap_or_default()
+    }
+
+    /// H.273 code point
+    pub fn code(self) -> u8 {
+        lookup_value(Self::TABLE, self).0
+    }
+
+    /// Canonical name, as accepted by `FromStr`
+    pub fn name(self) -> &'static str {
+        lookup_value(Self::TABLE, self).1
+    }
+}
+
+impl FromStr for MatrixCoefficients {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        lookup_name(Self::TABLE, s, "matrix coefficients")
+    }
+}
+
+fn lookup_code<T: Copy>(table: &[(T, u8, &'static str)], code: u8) -> Option<T> {
+    table
+        .iter()
+        .find(|entry| entry.1 == code)
+        .map(|entry| entry.0)
+}
+
+fn lookup_value<T: Copy + PartialEq>(
+    table: &[(T, u8, &'static str)],
+    value: T,
+) -> (u8, &'static str) {
+    // Every variant has an entry, so the fallback is never used
+    table
+        .iter()
+        .find(|entry| entry.0 == value)
+        .map_or((2, "unspecified"), |entry| (entry.1, entry.2))
+}
+
+fn lookup_name<T: Copy>(table: &[(T, u8, &'static str)], name: &str, what: &str) -> Result<T> {
+    table
+        .iter()
+        .find(|entry| entry.2.eq_ignore_ascii_case(name))
+        .map(|entry| entry.0)
+        .ok_or_else(|| Error::InvalidInput(format!("Unknown {}: {}", what, name)))
+}
+
+/// Sample value range
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorRange {
+    /// Limited (TV / studio) range, e.g. 16-235 for 8-bit luma
+    #[default]
+    Limited,
+    /// Full (PC / JPEG) range
+    Full,
+}
+
+/// Parses `limited` (or `tv`) and `full` (or `pc`)
+impl FromStr for ColorRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "limited" | "tv" => Ok(Self::Limited),
+            "full" | "pc" => Ok(Self::Full),
+            _ => Err(Error::InvalidInput(format!("Unknown color range: {}", s))),
+        }
+    }
+}
+
+/// Position of 4:2:0 chroma samples relative to luma (AV1 `chroma_sample_position`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChromaSamplePosition {
+    /// Unknown, or centered between luma samples (JPEG/MPEG-1)
+    #[default]
+    Unknown,
+    /// Horizontally co-sited with luma, vertically centered (MPEG-2 "left")
+    Vertical,
+    /// Co-sited with the top-left luma sample (BT.2020 "top-left")
+    Colocated,
+}
+
+impl ChromaSamplePosition {
+    /// Map an AV1 code point, treating unknown values as `Unknown`
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            1 => Self::Vertical,
+            2 => Self::Colocated,
+            _ => Self::Unknown,
+        }
+    }
+
+    /// AV1 code point
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Unknown => 0,
+            Self::Vertical => 1,
+            Self::Colocated => 2,
+        }
+    }
+}
+
+/// Mastering display color volume (SMPTE ST 2086)
+///
+/// Stored in AV1 metadata units: chromaticity coordinates as 0.16 fixed
+/// point, maximum luminance as 24.8 and minimum luminance as 18.14 fixed
+/// point cd/m².
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MasteringDisplay {
+    /// Red, green and blue primaries as (x, y)
+    pub primaries: [(u16, u16); 3],
+    /// White point as (x, y)
+    pub white_point: (u16, u16),
+    /// Maximum luminance (24.8 fixed point cd/m²)
+    pub max_luminance: u32,
+    /// Minimum luminance (18.14 fixed point cd/m²)
+    pub min_luminance: u32,
+}
+
+impl MasteringDisplay {
+    /// Red, green and blue primaries as CIE 1931 (x, y) coordinates
+    pub fn primaries_xy(&self) -> [(f64, f64); 3] {
+        self.primaries
+            .map(|(x, y)| (f64::from(x) / 65536.0, f64::from(y) / 65536.0))
+    }
+
+    /// White point as CIE 1931 (x, y) coordinates
+    pub fn white_point_xy(&self) -> (f64, f64) {
+        let (x, y) = self.white_point;
+        (f64::from(x) / 65536.0, f64::from(y) / 65536.0)
+    }
+
+    /// Maximum luminance in cd/m²
+    pub fn max_luminance_nits(&self) -> f64 {
+        f64::from(self.max_luminance) / 256.0
+    }
+
+    /// Minimum luminance in cd/m²
+    pub fn min_luminance_nits(&self) -> f64 {
+        f64::from(self.min_luminance) / 16384.0
+    }
+}
+
+/// Parses `G(x,y)B(x,y)R(x,y)WP(x,y)L(max,min)`, the syntax used by SVT-AV1
+/// and x265, with chromaticity coordinates and luminance in cd/m² as decimals
+///
+/// # Example
+/// ```
+/// use mead_core::color::MasteringDisplay;
+///
+/// let display: MasteringDisplay =
+///     "G(0.265,0.69)B(0.15,0.06)R(0.68,0.32)WP(0.3127,0.329)L(1000,0.0001)".parse()?;
+/// assert_eq!(display.max_luminance_nits(), 1000.0);
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+impl FromStr for MasteringDisplay {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidInput(format!("Invalid mastering display: {}", s));
+
+        // Collect "NAME(a,b)" groups in any order
+        let mut groups = std::collections::HashMap::new();
+        for group in s.split(')').map(str::trim).filter(|g| !g.is_empty()) {
+            let (name, values) = group.split_once('(').ok_or_else(invalid)?;
+            let (a, b) = values.split_once(',').ok_or_else(invalid)?;
+            let a: f64 = a.trim().parse().map_err(|_| invalid())?;
+            let b: f64 = b.trim().parse().map_err(|_| invalid())?;
+            groups.insert(name.trim().to_ascii_uppercase(), (a, b));
+        }
+
+        let mut get = |name: &str| groups.remove(name).ok_or_else(invalid);
+        let chromaticity = |(x, y): (f64, f64)| {
+            if (0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y) {
+                Ok((
+                    (x * 65536.0).round().min(65535.0) as u16,
+                    (y * 65536.0).round().min(65535.0) as u16,
+                ))
+            } else {
+                Err(invalid())
+            }
+        };
+
+        let green = chromaticity(get("G")?)?;
+        let blue = chromaticity(get("B")?)?;
+        let red = chromaticity(get("R")?)?;
+        let white_point = chromaticity(get("WP")?)?;
+        let (max, min) = get("L")?;
+        if !(0.0..=16_777_215.0).contains(&max) || !(0.0..=max).contains(&min) {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            primaries: [red, green, blue],
+            white_point,
+            max_luminance: (max * 256.0).round() as u32,
+            min_luminance: (min * 16384.0).round() as u32,
+        })
+    }
+}
+
+/// Content light level (CTA-861.3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ContentLightLevel {
+    /// Maximum content light level in cd/m² (MaxCLL)
+    pub max_cll: u16,
+    /// Maximum frame-average light level in cd/m² (MaxFALL)
+    pub max_fall: u16,
+}
+
+/// Parses `max_cll,max_fall`, e.g. `1000,400`
+impl FromStr for ContentLightLevel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidInput(format!("Invalid content light level: {}", s));
+        let (max_cll, max_fall) = s.split_once(',').ok_or_else(invalid)?;
+        Ok(Self {
+            max_cll: max_cll.trim().parse().map_err(|_| invalid())?,
+            max_fall: max_fall.trim().parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Color description and HDR metadata of a video stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Colorimetry {
+    /// Color primaries
+    pub primaries: ColorPrimaries,
+    /// Transfer characteristics
+    pub transfer: TransferCharacteristics,
+    /// Matrix coefficients
+    pub matrix: MatrixCoefficients,
+    /// Sample value range
+    pub range: ColorRange,
+    /// 4:2:0 chroma sample position
+    pub chroma_position: ChromaSamplePosition,
+    /// Mastering display color volume (HDR)
+    pub mastering_display: Option<MasteringDisplay>,
+    /// Content light level (HDR)
+    pub content_light: Option<ContentLightLevel>,
+}
+
+impl Colorimetry {
+    /// Returns true if any of primaries, transfer or matrix is specified
+    pub fn has_color_description(&self) -> bool {
+        self.primaries != ColorPrimaries::Unspecified
+            || self.transfer != TransferCharacteristics::Unspecified
+            || self.matrix != MatrixCoefficients::Unspecified
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_points() {
+        assert_eq!(ColorPrimaries::from_code(9), ColorPrimaries::Bt2020);
+        assert_eq!(ColorPrimaries::from_code(3), ColorPrimaries::Unspecified);
+        assert_eq!(ColorPrimaries::Ebu3213.code(), 22);
+        assert_eq!(
+            TransferCharacteristics::from_code(16),
+            TransferCharacteristics::Smpte2084
+        );
+        assert_eq!(TransferCharacteristics::Hlg.code(), 18);
+        assert_eq!(MatrixCoefficients::Identity.code(), 0);
+        assert_eq!(
+            MatrixCoefficients::from_code(9),
+            MatrixCoefficients::Bt2020Ncl
+        );
+        assert_eq!(
+            ChromaSamplePosition::from_code(2),
+            ChromaSamplePosition::Colocated
+        );
+    }
+
+    #[test]
+    fn test_names() {
+        assert_eq!(
+            "BT2020".parse::<ColorPrimaries>().unwrap(),
+            ColorPrimaries::Bt2020
+        );
+        assert_eq!(
+            "pq".parse::<TransferCharacteristics>().unwrap(),
+            TransferCharacteristics::Smpte2084
+        );
+        assert_eq!(TransferCharacteristics::Smpte2084.name(), "smpte2084");
+        assert_eq!(
+            "bt2020nc".parse::<MatrixCoefficients>().unwrap(),
+            MatrixCoefficients::Bt2020Ncl
+        );
+        assert!("bt2021".parse::<ColorPrimaries>().is_err());
+        assert_eq!("pc".parse::<ColorRange>().unwrap(), ColorRange::Full);
+    }
+
+    #[test]
+    fn test_mastering_display() {
+        let display: MasteringDisplay =
+            "G(0.265,0.690)B(0.150,0.060)R(0.680,0.320)WP(0.3127,0.3290)L(1000,0.0001)"
+                .parse()
+                .unwrap();
+
+        // Stored in R, G, B order in 0.16 fixed point
+        assert_eq!(display.primaries[0], (44564, 20972));
+        assert_eq!(display.primaries[1], (17367, 45220));
+        assert_eq!(display.white_point, (20493, 21561));
+        assert_eq!(display.max_luminance, 256_000);
+        assert_eq!(display.min_luminance, 2);
+        assert!((display.primaries_xy()[2].0 - 0.150).abs() < 1e-4);
+
+        assert!(
+            "G(0.265,0.690)B(0.150,0.060)"
+                .parse::<MasteringDisplay>()
+                .is_err()
+        );
+        assert!(
+            "G(2,0)B(0,0)R(0,0)WP(0,0)L(1,0)"
+                .parse::<MasteringDisplay>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_content_light() {
+        let level: ContentLightLevel = "1000, 400".parse().unwrap();
+        assert_eq!(
+            level,
+            ContentLightLevel {
+                max_cll: 1000,
+                max_fall: 400
+            }
+        );
+        assert!("1000".parse::<ContentLightLevel>().is_err());
+    }
+}
@@ -8,36 +8,41 @@
 //! - Frame header (12 bytes) + frame data
 //! - Repeat for each frame
 
-use crate::{Error, Result};
-use super::{Muxer, Packet};
-use std::io::Write;
+use super::{Demuxer, Metadata, Muxer, Packet};
+use crate::codec::av1::obu::Av1Parser;
+use crate::{Error, MediaSource, Result};
+use std::io::{BufReader, Read, Write};
+
+/// Upper bound on a single IVF frame (guards against corrupt size fields)
+const MAX_FRAME_SIZE: u32 = 256 * 1024 * 1024;
 
 /// IVF file header (32 bytes)
 #[derive(Debug)]
 struct IvfHeader {
-    signature: [u8; 4],      // "DKIF"
-    version: u16,            // 0
-    header_size: u16,        // 32
-    fourcc: [u8; 4],         // "AV01" for AV1
+    signature: [u8; 4], // "DKIF"
+    version: u16,       // 0
+    header_size: u16,   // 32
+    fourcc: [u8; 4],    // "AV01" for AV1
     width: u16,
     height: u16,
-    timebase_den: u32,       // Frame rate denominator
-    timebase_num: u32,       // Frame rate numerator
-    frame_count: u32,        // 0 initially, updated at end
-    unused: u32,             // 0
+    timebase_den: u32, // Frame rate denominator
+    timebase_num: u32, // Frame rate numerator
+    frame_count: u32,  // 0 initially, updated at end
+    unused: u32,       // 0
 }
 
 impl IvfHeader {
     fn new(width: u16, height: u16, fps_num: u32, fps_den: u32) -> Self {
+        // Timebase is the inverse of the frame rate (one tick per frame)
         Self {
             signature: *b"DKIF",
             version: 0,
             header_size: 32,
-            fourcc: *b"AV01",  // AV1 codec
+            fourcc: *b"AV01", // AV1 codec
             width,
             height,
-            timebase_den: fps_den,
-            timebase_num: fps_num,
+            timebase_den: fps_num,
+            timebase_num: fps_den,
             frame_count: 0,
             unused: 0,
         }
@@ -56,6 +61,66 @@ impl IvfHeader {
         writer.write_all(&self.unused.to_le_bytes())?;
         Ok(())
     }
+
+    fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut buf = [0u8; 32];
+        reader.read_exact(&mut buf).map_err(|e| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => {
+                Error::ContainerParse("Truncated IVF file header".to_string())
+            }
+            _ => Error::Io(e),
+        })?;
+
+        let u16_at = |i: usize| u16::from_le_bytes([buf[i], buf[i + 1]]);
+        let u32_at = |i: usize| u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+
+        let header = Self {
+            signature: [buf[0], buf[1], buf[2], buf[3]],
+            version: u16_at(4),
+            header_size: u16_at(6),
+            fourcc: [buf[8], buf[9], buf[10], buf[11]],
+            width: u16_at(12),
+            height: u16_at(14),
+            timebase_den: u32_at(16),
+            timebase_num: u32_at(20),
+            frame_count: u32_at(24),
+            unused: u32_at(28),
+        };
+
+        if &header.signature != b"DKIF" {
+            return Err(Error::ContainerParse(format!(
+                "Invalid IVF signature: {:?}",
+                header.signature
+            )));
+        }
+
+        if header.version != 0 {
+            return Err(Error::UnsupportedFormat(format!(
+                "IVF version {}",
+                header.version
+            )));
+        }
+
+        if header.header_size < 32 {
+            return Err(Error::ContainerParse(format!(
+                "Invalid IVF header size: {}",
+                header.header_size
+            )));
+        }
+
+        // Skip any extension bytes beyond the standard 32-byte header
+        let extra = u64::from(header.header_size - 32);
+        if extra > 0 {
+            let skipped = std::io::copy(&mut reader.take(extra), &mut std::io::sink())?;
+            if skipped != extra {
+                return Err(Error::ContainerParse(
+                    "Truncated IVF file header".to_string(),
+                ));
+            }
+        }
+
+        Ok(header)
+    }
 }
 
 /// IVF frame header (12 bytes)
@@ -71,6 +136,47 @@ impl IvfFrameHeader {
         writer.write_all(&self.timestamp.to_le_bytes())?;
         Ok(())
     }
+
+    /// Read a frame header, returning `None` on a clean end of stream
+    fn read<R: Read>(reader: &mut R) -> Result<Option<Self>> {
+        let mut buf = [0u8; 12];
+        let mut filled = 0;
+        while filled < buf.len() {
+            match reader.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::Io(e)),
+            }
+        }
+
+        if filled == 0 {
+            return Ok(None);
+        }
+        if filled < buf.len() {
+            return Err(Error::ContainerParse(format!(
+                "Truncated IVF frame header ({} of 12 bytes)",
+                filled
+            )));
+        }
+
+        let frame_size = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+        let timestamp = u64::from_le_bytes([
+            buf[4], buf[5], buf[6], buf[7], buf[8], buf[9], buf[10], buf[11],
+        ]);
+
+        if frame_size > MAX_FRAME_SIZE {
+            return Err(Error::ContainerParse(format!(
+                "IVF frame size {} exceeds maximum of {} bytes",
+                frame_size, MAX_FRAME_SIZE
+            )));
+        }
+
+        Ok(Some(Self {
+            frame_size,
+            timestamp,
+        }))
+    }
 }
 
 /// IVF muxer for writing AV1 video
@@ -120,7 +226,10 @@ impl<W: Write> IvfMuxer<W> {
     pub fn new(mut writer: W, width: u16, height: u16, fps_num: u32, fps_den: u32) -> Result<Self> {
         tracing::info!(
             "Creating IVF muxer: {}x{} @ {}/{} fps",
-            width, height, fps_num, fps_den
+            width,
+            height,
+            fps_num,
+            fps_den
         );
 
         let header = IvfHeader::new(width, height, fps_num, fps_den);
@@ -187,9 +296,180 @@ impl<W: Write> Muxer for IvfMuxer<W> {
     }
 }
 
+/// IVF demuxer for reading AV1/VP8/VP9 streams
+///
+/// Reads sequentially and never seeks, so it works equally well with
+/// files and non-seekable sources such as stdin.
+///
+/// # Example
+/// ```no_run
+/// use mead_core::container::ivf::IvfDemuxer;
+/// use mead_core::container::Demuxer;
+/// use std::fs::File;
+///
+/// let file = File::open("input.ivf")?;
+/// let mut demuxer = IvfDemuxer::new(file)?;
+///
+/// println!("Video: {}x{}", demuxer.width(), demuxer.height());
+///
+/// while let Some(packet) = demuxer.read_packet()? {
+///     // Decode packet
+/// }
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+pub struct IvfDemuxer<R: MediaSource> {
+    reader: BufReader<R>,
+    header: IvfHeader,
+    metadata: Metadata,
+    frames_read: u64,
+    /// Finds keyframes in AV1 streams
+    parser: Option<Av1Parser>,
+}
+
+impl<R: MediaSource> IvfDemuxer<R> {
+    /// Create a new IVF demuxer, parsing the 32-byte file header
+    pub fn new(source: R) -> Result<Self> {
+        let mut reader = BufReader::new(source);
+        let header = IvfHeader::read(&mut reader)?;
+
+        // The header frame count is optional (streaming writers leave it 0)
+        // and crafted timebases may not fit the arithmetic
+        let duration_ms = u64::from(header.frame_count)
+            .checked_mul(u64::from(header.timebase_num))
+            .and_then(|ticks| ticks.checked_mul(1000))
+            .and_then(|ticks| ticks.checked_div(u64::from(header.timebase_den)))
+            .filter(|_| header.frame_count > 0);
+
+        let metadata = Metadata {
+            duration_ms,
+            stream_count: 1,
+            format: "IVF".to_string(),
+        };
+
+        tracing::info!(
+            "IVF opened: {} {}x{}, timebase {}/{}, {} frames",
+            String::from_utf8_lossy(&header.fourcc),
+            header.width,
+            header.height,
+            header.timebase_num,
+            header.timebase_den,
+            header.frame_count
+        );
+
+        let parser = (&header.fourcc == b"AV01").then(Av1Parser::new);
+
+        Ok(Self {
+            reader,
+            header,
+            metadata,
+            frames_read: 0,
+            parser,
+        })
+    }
+
+    /// Get codec fourcc (e.g. `AV01`, `VP90`)
+    pub fn fourcc(&self) -> [u8; 4] {
+        self.header.fourcc
+    }
+
+    /// Get video width
+    pub fn width(&self) -> u16 {
+        self.header.width
+    }
+
+    /// Get video height
+    pub fn height(&self) -> u16 {
+        self.header.height
+    }
+
+    /// Get timebase as (numerator, denominator) in seconds per tick
+    pub fn timebase(&self) -> (u32, u32) {
+        (self.header.timebase_num, self.header.timebase_den)
+    }
+
+    /// Get frame count declared in the header (0 if unknown)
+    pub fn frame_count(&self) -> u32 {
+        self.header.frame_count
+    }
+
+    /// Get number of frames read so far
+    pub fn frames_read(&self) -> u64 {
+        self.frames_read
+    }
+
+    /// Get the AV1 parser, which holds the latest sequence header
+    ///
+    /// Returns `None` for other codecs.
+    pub fn av1_parser(&self) -> Option<&Av1Parser> {
+        self.parser.as_ref()
+    }
+}
+
+impl<R: MediaSource> std::fmt::Debug for IvfDemuxer<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IvfDemuxer")
+            .field("header", &self.header)
+            .field("metadata", &self.metadata)
+            .field("frames_read", &self.frames_read)
+            .finish()
+    }
+}
+
+impl<R: MediaSource> Demuxer for IvfDemuxer<R> {
+    fn read_packet(&mut self) -> Result<Option<Packet>> {
+        let frame_header = match IvfFrameHeader::read(&mut self.reader)? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+
+        // Read through `take` so a corrupt size can't force a huge allocation
+        let size = u64::from(frame_header.frame_size);
+        let mut data = Vec::new();
+        (&mut self.reader).take(size).read_to_end(&mut data)?;
+        if data.len() as u64 != size {
+            return Err(Error::ContainerParse(format!(
+                "Truncated IVF frame {}: expected {} bytes, got {}",
+                self.frames_read,
+                size,
+                data.len()
+            )));
+        }
+
+        // IVF carries no sync flags, so AV1 frame headers are parsed; for
+        // other codecs only the first frame is known to be a keyframe
+        let is_keyframe = match &mut self.parser {
+            Some(parser) => parser
+                .parse_temporal_unit(&data)
+                .map_err(|e| {
+                    Error::ContainerParse(format!("IVF frame {}: {}", self.frames_read, e))
+                })?
+                .is_keyframe(),
+            None => self.frames_read == 0,
+        };
+        self.frames_read += 1;
+
+        if self.frames_read % 100 == 0 {
+            tracing::debug!("Read {} frames from IVF", self.frames_read);
+        }
+
+        Ok(Some(Packet {
+            stream_index: 0,
+            data,
+            pts: Some(frame_header.timestamp as i64),
+            dts: None,
+            is_keyframe,
+        }))
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::codec::av1::obu::test_data::{av1_packet, temporal_unit};
     use std::io::Cursor;
 
     #[test]
@@ -259,7 +539,7 @@ mod tests {
         let mut muxer = IvfMuxer::new(cursor, 1920, 1080, 30, 1).unwrap();
 
         let packet = Packet {
-            stream_index: 1,  // IVF only supports stream 0
+            stream_index: 1, // IVF only supports stream 0
             data: vec![1, 2, 3],
             pts: Some(0),
             dts: None,
@@ -268,4 +548,117 @@ mod tests {
 
         assert!(muxer.write_packet(packet).is_err());
     }
+
+    fn mux_test_stream(frames: usize) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut muxer = IvfMuxer::new(&mut buf, 320, 240, 30, 1).unwrap();
+        for i in 0..frames {
+            muxer.write_packet(av1_packet(i as i64, i == 0)).unwrap();
+        }
+        muxer.finalize().unwrap();
+        buf
+    }
+
+    #[test]
+    fn test_ivf_header_timebase() {
+        let header = IvfHeader::new(1920, 1080, 30000, 1001);
+        let mut buf = Vec::new();
+        header.write(&mut buf).unwrap();
+        // Offset 16 holds the rate (timebase denominator), 20 the scale
+        assert_eq!(&buf[16..20], &30000u32.to_le_bytes());
+        assert_eq!(&buf[20..24], &1001u32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_ivf_demuxer_round_trip() {
+        let data = mux_test_stream(5);
+        let mut demuxer = IvfDemuxer::new(Cursor::new(data)).unwrap();
+
+        assert_eq!(&demuxer.fourcc(), b"AV01");
+        assert_eq!((demuxer.width(), demuxer.height()), (320, 240));
+        assert_eq!(demuxer.timebase(), (1, 30));
+        assert_eq!(demuxer.metadata().format, "IVF");
+
+        for i in 0..5 {
+            let packet = demuxer.read_packet().unwrap().unwrap();
+            assert_eq!(packet.pts, Some(i as i64));
+            assert_eq!(packet.data, temporal_unit(i as u8, i == 0));
+            assert_eq!(packet.is_keyframe, i == 0);
+        }
+        assert!(demuxer.read_packet().unwrap().is_none());
+        assert_eq!(demuxer.frames_read(), 5);
+    }
+
+    #[test]
+    fn test_ivf_demuxer_non_seekable() {
+        let data = mux_test_stream(3);
+        let source = crate::io::ReadOnlySource::new(Cursor::new(data));
+        let mut demuxer = IvfDemuxer::new(source).unwrap();
+
+        let mut count = 0;
+        while demuxer.read_packet().unwrap().is_some() {
+            count += 1;
+        }
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_ivf_demuxer_bad_signature() {
+        let mut data = mux_test_stream(1);
+        data[0..4].copy_from_slice(b"RIFF");
+        let result = IvfDemuxer::new(Cursor::new(data));
+        assert!(matches!(result, Err(Error::ContainerParse(_))));
+    }
+
+    #[test]
+    fn test_ivf_demuxer_truncated_header() {
+        let data = mux_test_stream(1);
+        let result = IvfDemuxer::new(Cursor::new(data[..20].to_vec()));
+        assert!(matches!(result, Err(Error::ContainerParse(_))));
+    }
+
+    #[test]
+    fn test_ivf_demuxer_truncated_frame_header() {
+        let mut data = mux_test_stream(1);
+        data.extend_from_slice(&[1, 2, 3]); // partial second frame header
+        let mut demuxer = IvfDemuxer::new(Cursor::new(data)).unwrap();
+
+        assert!(demuxer.read_packet().unwrap().is_some());
+        assert!(matches!(
+            demuxer.read_packet(),
+            Err(Error::ContainerParse(_))
+        ));
+    }
+
+    #[test]
+    fn test_ivf_demuxer_truncated_frame_data() {
+        let data = mux_test_stream(1);
+        let truncated = data[..data.len() - 4].to_vec();
+        let mut demuxer = IvfDemuxer::new(Cursor::new(truncated)).unwrap();
+        assert!(matches!(
+            demuxer.read_packet(),
+            Err(Error::ContainerParse(_))
+        ));
+    }
+
+    #[test]
+    fn test_ivf_demuxer_huge_duration() {
+        // Timebase numerator and frame count whose product overflows
+        let mut data = mux_test_stream(1);
+        data[20..28].copy_from_slice(&[0xFF; 8]);
+        let demuxer = IvfDemuxer::new(Cursor::new(data)).unwrap();
+        assert_eq!(demuxer.metadata().duration_ms, None);
+    }
+
+    #[test]
+    fn test_ivf_demuxer_oversized_frame() {
+        let mut data = mux_test_stream(0);
+        data.extend_from_slice(&u32::MAX.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        let mut demuxer = IvfDemuxer::new(Cursor::new(data)).unwrap();
+        assert!(matches!(
+            demuxer.read_packet(),
+            Err(Error::ContainerParse(_))
+        ));
+    }
 }
@@ -0,0 +1,1762 @@
+//! Matroska/WebM container support
+//!
+//! Matroska is built on EBML, a binary XML-like format of nested elements.
+//! WebM is the Matroska subset restricted to royalty-free codecs (AV1, VP8/VP9,
+//! Opus, Vorbis) and is what browsers play.
+//!
+//! The muxer writes AV1 (`V_AV1`) and Opus (`A_OPUS`) tracks as SimpleBlocks.
+//! In the default mode it adds a SeekHead and Cues for seekability; live mode
+//! writes unknown-size Segment/Cluster elements and never seeks, so it can
+//! stream to stdout.
+//!
+//! The demuxer reads SimpleBlocks and BlockGroups from all tracks, including
+//! laced blocks and unknown-size (live) files, and never seeks.
+
+use super::mp4::prepare_av1_sample;
+use super::{Demuxer, Metadata, Muxer, Packet};
+use crate::codec::av1::obu::Av1Parser;
+use crate::codec::opus;
+use crate::color::{ChromaSamplePosition, ColorRange};
+use crate::{Colorimetry, Error, MediaSource, Result};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+
+// EBML header
+const ID_EBML: u32 = 0x1A45_DFA3;
+const ID_EBML_VERSION: u32 = 0x4286;
+const ID_EBML_READ_VERSION: u32 = 0x42F7;
+const ID_EBML_MAX_ID_LENGTH: u32 = 0x42F2;
+const ID_EBML_MAX_SIZE_LENGTH: u32 = 0x42F3;
+const ID_DOC_TYPE: u32 = 0x4282;
+const ID_DOC_TYPE_VERSION: u32 = 0x4287;
+const ID_DOC_TYPE_READ_VERSION: u32 = 0x4285;
+const ID_VOID: u32 = 0xEC;
+
+// Segment and top-level elements
+const ID_SEGMENT: u32 = 0x1853_8067;
+const ID_SEEK_HEAD: u32 = 0x114D_9B74;
+const ID_SEEK: u32 = 0x4DBB;
+const ID_SEEK_ID: u32 = 0x53AB;
+const ID_SEEK_POSITION: u32 = 0x53AC;
+const ID_INFO: u32 = 0x1549_A966;
+const ID_TIMESTAMP_SCALE: u32 = 0x2A_D7B1;
+const ID_DURATION: u32 = 0x4489;
+const ID_MUXING_APP: u32 = 0x4D80;
+const ID_WRITING_APP: u32 = 0x5741;
+const ID_TRACKS: u32 = 0x1654_AE6B;
+const ID_CLUSTER: u32 = 0x1F43_B675;
+const ID_CUES: u32 = 0x1C53_BB6B;
+
+// Tracks
+const ID_TRACK_ENTRY: u32 = 0xAE;
+const ID_TRACK_NUMBER: u32 = 0xD7;
+const ID_TRACK_UID: u32 = 0x73C5;
+const ID_TRACK_TYPE: u32 = 0x83;
+const ID_FLAG_LACING: u32 = 0x9C;
+const ID_CODEC_ID: u32 = 0x86;
+const ID_CODEC_PRIVATE: u32 = 0x63A2;
+const ID_CODEC_DELAY: u32 = 0x56AA;
+const ID_SEEK_PRE_ROLL: u32 = 0x56BB;
+const ID_DEFAULT_DURATION: u32 = 0x23_E383;
+const ID_VIDEO: u32 = 0xE0;
+const ID_PIXEL_WIDTH: u32 = 0xB0;
+const ID_PIXEL_HEIGHT: u32 = 0xBA;
+const ID_COLOUR: u32 = 0x55B0;
+const ID_MATRIX_COEFFICIENTS: u32 = 0x55B1;
+const ID_CHROMA_SITING_HORZ: u32 = 0x55B7;
+const ID_CHROMA_SITING_VERT: u32 = 0x55B8;
+const ID_RANGE: u32 = 0x55B9;
+const ID_TRANSFER_CHARACTERISTICS: u32 = 0x55BA;
+const ID_PRIMARIES: u32 = 0x55BB;
+const ID_MAX_CLL: u32 = 0x55BC;
+const ID_MAX_FALL: u32 = 0x55BD;
+const ID_MASTERING_METADATA: u32 = 0x55D0;
+/// PrimaryRChromaticityX; the R, G, B and white point x/y IDs follow in order
+const ID_PRIMARY_R_CHROMATICITY_X: u32 = 0x55D1;
+const ID_LUMINANCE_MAX: u32 = 0x55D9;
+const ID_LUMINANCE_MIN: u32 = 0x55DA;
+const ID_AUDIO: u32 = 0xE1;
+const ID_SAMPLING_FREQUENCY: u32 = 0xB5;
+const ID_CHANNELS: u32 = 0x9F;
+
+// Clusters
+const ID_TIMESTAMP: u32 = 0xE7;
+const ID_SIMPLE_BLOCK: u32 = 0xA3;
+const ID_BLOCK_GROUP: u32 = 0xA0;
+const ID_BLOCK: u32 = 0xA1;
+const ID_BLOCK_DURATION: u32 = 0x9B;
+const ID_REFERENCE_BLOCK: u32 = 0xFB;
+
+// Cues
+const ID_CUE_POINT: u32 = 0xBB;
+const ID_CUE_TIME: u32 = 0xB3;
+const ID_CUE_TRACK_POSITIONS: u32 = 0xB7;
+const ID_CUE_TRACK: u32 = 0xF7;
+const ID_CUE_CLUSTER_POSITION: u32 = 0xF1;
+const ID_CUE_RELATIVE_POSITION: u32 = 0xF0;
+
+/// Matroska track types
+const TRACK_TYPE_VIDEO: u64 = 1;
+const TRACK_TYPE_AUDIO: u64 = 2;
+
+/// Timestamp scale written by the muxer: 1 ms per tick
+const TIMESTAMP_SCALE_NS: u64 = 1_000_000;
+
+/// 8-byte "unknown size" marker
+const UNKNOWN_SIZE: [u8; 8] = [0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+
+/// Bytes reserved after the Segment header for the SeekHead
+const SEEK_HEAD_RESERVED: usize = 128;
+
+/// Audio-only streams start a new cluster at this interval (ms)
+const AUDIO_CLUSTER_MS: i64 = 5000;
+
+/// Packets buffered while waiting for the AV1 sequence header
+const MAX_PENDING_PACKETS: usize = 256;
+
+/// Upper bound on a single element read into memory (guards corrupt sizes)
+const MAX_ELEMENT_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Track description for [`MkvMuxer`]
+#[derive(Debug, Clone)]
+pub enum MkvTrackConfig {
+    /// AV1 video track
+    ///
+    /// Packet timestamps are in frame units (timebase `fps_den/fps_num`),
+    /// the same convention as [`IvfMuxer`](super::ivf::IvfMuxer).
+    Av1 {
+        /// Width in pixels
+        width: u32,
+        /// Height in pixels
+        height: u32,
+        /// Frame rate numerator
+        fps_num: u32,
+        /// Frame rate denominator
+        fps_den: u32,
+    },
+    /// Opus audio track
+    ///
+    /// Packet timestamps are in 48 kHz samples.
+    Opus {
+        /// Channel count (1 or 2)
+        channels: u8,
+        /// Sample rate of the original input (informational)
+        input_sample_rate: u32,
+        /// Samples to discard from the start of the decoded stream
+        pre_skip: u16,
+    },
+}
+
+impl MkvTrackConfig {
+    fn is_video(&self) -> bool {
+        matches!(self, Self::Av1 { .. })
+    }
+}
+
+/// EBML DocType written by [`MkvMuxer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DocType {
+    /// WebM (browser-playable subset)
+    #[default]
+    WebM,
+    /// Full Matroska
+    Matroska,
+}
+
+impl DocType {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::WebM => "webm",
+            Self::Matroska => "matroska",
+        }
+    }
+}
+
+/// Matroska/WebM muxer configuration
+#[derive(Debug, Clone, Default)]
+pub struct MkvMuxerConfig {
+    /// Tracks, indexed by `Packet::stream_index`
+    pub tracks: Vec<MkvTrackConfig>,
+    /// EBML DocType
+    pub doc_type: DocType,
+    /// Live-streaming mode: unknown-size Segment and Clusters, no Cues,
+    /// no seeking (suitable for stdout)
+    pub live: bool,
+}
+
+/// Cluster currently being written
+#[derive(Debug, Clone, Copy)]
+struct ClusterState {
+    /// Offset of the Cluster element ID
+    start: u64,
+    /// Offset of the first byte after the Cluster size field
+    data_start: u64,
+    /// Cluster timestamp (ms)
+    timestamp: i64,
+    block_count: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CuePoint {
+    time: u64,
+    track: u64,
+    /// Cluster offset relative to the Segment payload
+    cluster_position: u64,
+    /// Block offset relative to the Cluster payload
+    relative_position: u64,
+}
+
+/// Matroska/WebM muxer for AV1 video and Opus audio
+///
+/// Writes SimpleBlocks grouped into Clusters that start at video keyframes.
+/// The header is written once the AV1 sequence header has been seen (it is
+/// needed for the `V_AV1` CodecPrivate), so the first few packets may be
+/// buffered.
+///
+/// The default mode patches sizes, Duration and the SeekHead on `finalize`
+/// and therefore needs a seekable writer. Live mode never seeks; wrap
+/// non-seekable writers in [`WriteOnlySink`](crate::io::WriteOnlySink).
+///
+/// # Example
+/// ```no_run
+/// use mead_core::container::mkv::{MkvMuxer, MkvMuxerConfig, MkvTrackConfig};
+/// use mead_core::container::{Muxer, Packet};
+/// use mead_core::io::WriteOnlySink;
+///
+/// let config = MkvMuxerConfig {
+///     tracks: vec![MkvTrackConfig::Av1 { width: 1920, height: 1080, fps_num: 30, fps_den: 1 }],
+///     live: true,
+///     ..Default::default()
+/// };
+/// let mut muxer = MkvMuxer::new(WriteOnlySink::new(std::io::stdout().lock()), config)?;
+///
+/// let packet = Packet {
+///     stream_index: 0,
+///     data: vec![/* AV1 temporal unit */],
+///     pts: Some(0),
+///     dts: None,
+///     is_keyframe: true,
+/// };
+/// muxer.write_packet(packet)?;
+///
+/// muxer.finalize()?;
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+pub struct MkvMuxer<W: Write + Seek> {
+    writer: W,
+    config: MkvMuxerConfig,
+    /// av1C records, filled in as sequence headers are seen
+    codec_private: Vec<Option<Vec<u8>>>,
+    /// Per-track AV1 parsers, used to find keyframes
+    parsers: Vec<Av1Parser>,
+    /// Packets held back until every AV1 track has a sequence header
+    pending: Vec<Packet>,
+    header_written: bool,
+    position: u64,
+    segment_size_pos: u64,
+    segment_data_start: u64,
+    seek_head_pos: u64,
+    info_pos: u64,
+    tracks_pos: u64,
+    duration_pos: u64,
+    cluster: Option<ClusterState>,
+    cues: Vec<CuePoint>,
+    /// Next timestamp per track, used when a packet carries no pts
+    next_pts: Vec<i64>,
+    /// Largest block end time seen (ms)
+    end_time_ms: i64,
+}
+
+impl<W: Write + Seek> MkvMuxer<W> {
+    /// Create a new Matroska/WebM muxer
+    pub fn new(mut writer: W, config: MkvMuxerConfig) -> Result<Self> {
+        if config.tracks.is_empty() {
+            return Err(Error::InvalidInput(
+                "Matroska muxer requires at least one track".to_string(),
+            ));
+        }
+
+        for track in &config.tracks {
+            match *track {
+                MkvTrackConfig::Av1 {
+                    width,
+                    height,
+                    fps_num,
+                    fps_den,
+                } => {
+                    if width == 0 || height == 0 {
+                        return Err(Error::InvalidInput(format!(
+                            "Invalid AV1 track dimensions {}x{}",
+                            width, height
+                        )));
+                    }
+                    if fps_num == 0 || fps_den == 0 {
+                        return Err(Error::InvalidInput(format!(
+                            "Invalid AV1 track frame rate {}/{}",
+                            fps_num, fps_den
+                        )));
+                    }
+                }
+                MkvTrackConfig::Opus { channels, .. } => {
+                    // Channel mapping family 0 only covers mono and stereo
+                    if !(1..=2).contains(&channels) {
+                        return Err(Error::UnsupportedFormat(format!(
+                            "Opus in Matroska with {} channels",
+                            channels
+                        )));
+                    }
+                }
+            }
+        }
+
+        tracing::info!(
+            "Creating {} muxer: {} tracks, live: {}",
+            config.doc_type.as_str(),
+            config.tracks.len(),
+            config.live
+        );
+
+        let position = writer.stream_position()?;
+        let track_count = config.tracks.len();
+
+        Ok(Self {
+            writer,
+            config,
+            codec_private: vec![None; track_count],
+            parsers: vec![Av1Parser::new(); track_count],
+            pending: Vec::new(),
+            header_written: false,
+            position,
+            segment_size_pos: 0,
+            segment_data_start: 0,
+            seek_head_pos: 0,
+            info_pos: 0,
+            tracks_pos: 0,
+            duration_pos: 0,
+            cluster: None,
+            cues: Vec::new(),
+            next_pts: vec![0; track_count],
+            end_time_ms: 0,
+        })
+    }
+
+    /// Get number of cue points recorded so far
+    pub fn cue_count(&self) -> usize {
+        self.cues.len()
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Write EBML header, Segment header, Info and Tracks
+    fn write_header(&mut self) -> Result<()> {
+        for (index, track) in self.config.tracks.iter().enumerate() {
+            if track.is_video() && self.codec_private[index].is_none() {
+                return Err(Error::InvalidInput(format!(
+                    "No AV1 sequence header found in stream {}",
+                    index
+                )));
+            }
+        }
+
+        let mut buf = Vec::new();
+        write_master(&mut buf, ID_EBML, |b| {
+            write_uint(b, ID_EBML_VERSION, 1);
+            write_uint(b, ID_EBML_READ_VERSION, 1);
+            write_uint(b, ID_EBML_MAX_ID_LENGTH, 4);
+            write_uint(b, ID_EBML_MAX_SIZE_LENGTH, 8);
+            write_string(b, ID_DOC_TYPE, self.config.doc_type.as_str());
+            write_uint(b, ID_DOC_TYPE_VERSION, 4);
+            write_uint(b, ID_DOC_TYPE_READ_VERSION, 2);
+        });
+
+        // Segment size is patched on finalize (left unknown in live mode)
+        write_id(&mut buf, ID_SEGMENT);
+        let segment_size_pos = self.position + buf.len() as u64;
+        buf.extend_from_slice(&UNKNOWN_SIZE);
+        let segment_data_start = self.position + buf.len() as u64;
+
+        let seek_head_pos = segment_data_start;
+        if !self.config.live {
+            write_void(&mut buf, SEEK_HEAD_RESERVED);
+        }
+
+        let info_pos = self.position + buf.len() as u64;
+        let mut duration_offset = 0;
+        write_master(&mut buf, ID_INFO, |b| {
+            write_uint(b, ID_TIMESTAMP_SCALE, TIMESTAMP_SCALE_NS);
+            if !self.config.live {
+                write_id(b, ID_DURATION);
+                write_size(b, 8);
+                duration_offset = b.len();
+                b.extend_from_slice(&0f64.to_be_bytes());
+            }
+            write_string(
+                b,
+                ID_MUXING_APP,
+                concat!("mead-core ", env!("CARGO_PKG_VERSION")),
+            );
+            write_string(b, ID_WRITING_APP, "mead");
+        });
+        // write_master buffers its body, so the offset is relative to it
+        let info_header_len = id_len(ID_INFO) + 8;
+        let duration_pos = info_pos + (info_header_len + duration_offset) as u64;
+
+        let tracks_pos = self.position + buf.len() as u64;
+        write_master(&mut buf, ID_TRACKS, |b| {
+            for (index, track) in self.config.tracks.iter().enumerate() {
+                let number = index as u64 + 1;
+                write_master(b, ID_TRACK_ENTRY, |b| {
+                    write_uint(b, ID_TRACK_NUMBER, number);
+                    write_uint(b, ID_TRACK_UID, number);
+                    write_uint(b, ID_FLAG_LACING, 0);
+                    match *track {
+                        MkvTrackConfig::Av1 {
+                            width,
+                            height,
+                            fps_num,
+                            fps_den,
+                        } => {
+                            write_uint(b, ID_TRACK_TYPE, TRACK_TYPE_VIDEO);
+                            write_string(b, ID_CODEC_ID, "V_AV1");
+                            if let Some(av1c) = &self.codec_private[index] {
+                                write_binary(b, ID_CODEC_PRIVATE, av1c);
+                            }
+                            let frame_ns =
+                                1_000_000_000u64 * u64::from(fps_den) / u64::from(fps_num);
+                            write_uint(b, ID_DEFAULT_DURATION, frame_ns);
+                            write_master(b, ID_VIDEO, |b| {
+                                write_uint(b, ID_PIXEL_WIDTH, u64::from(width));
+                                write_uint(b, ID_PIXEL_HEIGHT, u64::from(height));
+                                if let Some(colorimetry) = self.parsers[index].colorimetry() {
+                                    write_colour(b, &colorimetry);
+                                }
+                            });
+                        }
+                        MkvTrackConfig::Opus {
+                            channels,
+                            input_sample_rate,
+                            pre_skip,
+                        } => {
+                            write_uint(b, ID_TRACK_TYPE, TRACK_TYPE_AUDIO);
+                            write_string(b, ID_CODEC_ID, "A_OPUS");
+                            let head = opus::OpusHead::new(channels, pre_skip, input_sample_rate);
+                            write_binary(b, ID_CODEC_PRIVATE, &head.to_bytes());
+                            write_uint(
+                                b,
+                                ID_CODEC_DELAY,
+                                u64::from(pre_skip) * 1_000_000_000 / 48000,
+                            );
+                            write_uint(b, ID_SEEK_PRE_ROLL, 80_000_000);
+                            write_master(b, ID_AUDIO, |b| {
+                                write_float(b, ID_SAMPLING_FREQUENCY, 48000.0);
+                                write_uint(b, ID_CHANNELS, u64::from(channels));
+                            });
+                        }
+                    }
+                });
+            }
+        });
+
+        self.write_bytes(&buf)?;
+
+        self.segment_size_pos = segment_size_pos;
+        self.segment_data_start = segment_data_start;
+        self.seek_head_pos = seek_head_pos;
+        self.info_pos = info_pos;
+        self.tracks_pos = tracks_pos;
+        self.duration_pos = duration_pos;
+        self.header_written = true;
+
+        Ok(())
+    }
+
+    /// Patch the size of the open cluster and close it
+    fn close_cluster(&mut self) -> Result<()> {
+        if let Some(cluster) = self.cluster.take() {
+            if !self.config.live {
+                let size = self.position - cluster.data_start;
+                self.writer.seek(SeekFrom::Start(cluster.data_start - 8))?;
+                self.writer.write_all(&size_vint8(size))?;
+                self.writer.seek(SeekFrom::Start(self.position))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn open_cluster(&mut self, timestamp: i64) -> Result<()> {
+        self.close_cluster()?;
+
+        let start = self.position;
+        let mut buf = Vec::new();
+        write_id(&mut buf, ID_CLUSTER);
+        buf.extend_from_slice(&UNKNOWN_SIZE);
+        let data_start = start + buf.len() as u64;
+        write_uint(&mut buf, ID_TIMESTAMP, timestamp.max(0) as u64);
+        self.write_bytes(&buf)?;
+
+        self.cluster = Some(ClusterState {
+            start,
+            data_start,
+            timestamp: timestamp.max(0),
+            block_count: 0,
+        });
+        Ok(())
+    }
+
+    /// Convert a packet's pts to milliseconds and return (timestamp, duration)
+    fn packet_time(&mut self, stream_index: usize, packet: &Packet) -> Result<(i64, i64)> {
+        match self.config.tracks[stream_index] {
+            MkvTrackConfig::Av1 {
+                fps_num, fps_den, ..
+            } => {
+                let pts = packet.pts.unwrap_or(self.next_pts[stream_index]);
+                self.next_pts[stream_index] = pts + 1;
+                let to_ms = |frames: i64| frames * 1000 * i64::from(fps_den) / i64::from(fps_num);
+                Ok((to_ms(pts), to_ms(pts + 1) - to_ms(pts)))
+            }
+            MkvTrackConfig::Opus { .. } => {
+                let samples = opus::packet_duration(&packet.data).ok_or_else(|| {
+                    Error::InvalidInput(format!("Malformed Opus packet in stream {}", stream_index))
+                })?;
+                let pts = packet.pts.unwrap_or(self.next_pts[stream_index]);
+                self.next_pts[stream_index] = pts + i64::from(samples);
+                Ok((pts * 1000 / 48000, i64::from(samples) * 1000 / 48000))
+            }
+        }
+    }
+
+    fn write_block(&mut self, packet: Packet) -> Result<()> {
+        let stream_index = packet.stream_index;
+        let is_video = self.config.tracks[stream_index].is_video();
+        let (timestamp, duration) = self.packet_time(stream_index, &packet)?;
+        let has_video = self.config.tracks.iter().any(MkvTrackConfig::is_video);
+
+        let needs_cluster = match self.cluster {
+            None => true,
+            Some(cluster) => {
+                let relative = timestamp - cluster.timestamp;
+                relative < i64::from(i16::MIN)
+                    || relative > i64::from(i16::MAX)
+                    || (is_video && packet.is_keyframe && cluster.block_count > 0)
+                    || (!has_video && relative >= AUDIO_CLUSTER_MS)
+            }
+        };
+        if needs_cluster {
+            self.open_cluster(timestamp)?;
+        }
+
+        // A new cluster was opened above unless the offset fits in an i16
+        let cluster = self.cluster.as_mut().expect("cluster opened above");
+        let relative = timestamp - cluster.timestamp;
+
+        let cue_worthy = if has_video {
+            is_video && packet.is_keyframe
+        } else {
+            cluster.block_count == 0
+        };
+        if cue_worthy && !self.config.live {
+            self.cues.push(CuePoint {
+                time: timestamp.max(0) as u64,
+                track: stream_index as u64 + 1,
+                cluster_position: cluster.start - self.segment_data_start,
+                relative_position: self.position - cluster.data_start,
+            });
+        }
+        cluster.block_count += 1;
+
+        // Audio blocks are always independently decodable
+        let keyframe = packet.is_keyframe || !is_video;
+
+        // Track number, relative timestamp and flags
+        let mut header = Vec::with_capacity(12);
+        write_size(&mut header, stream_index as u64 + 1);
+        header.extend_from_slice(&(relative as i16).to_be_bytes());
+        header.push(if keyframe { 0x80 } else { 0x00 });
+
+        let mut buf = Vec::with_capacity(header.len() + 12);
+        write_id(&mut buf, ID_SIMPLE_BLOCK);
+        write_size(&mut buf, (packet.data.len() + header.len()) as u64);
+        buf.extend_from_slice(&header);
+        self.write_bytes(&buf)?;
+        self.write_bytes(&packet.data)?;
+
+        self.end_time_ms = self.end_time_ms.max(timestamp + duration);
+
+        Ok(())
+    }
+
+    fn write_cues(&mut self) -> Result<Option<u64>> {
+        if self.cues.is_empty() {
+            return Ok(None);
+        }
+
+        let cues_pos = self.position;
+        let mut buf = Vec::new();
+        write_master(&mut buf, ID_CUES, |b| {
+            for cue in &self.cues {
+                write_master(b, ID_CUE_POINT, |b| {
+                    write_uint(b, ID_CUE_TIME, cue.time);
+                    write_master(b, ID_CUE_TRACK_POSITIONS, |b| {
+                        write_uint(b, ID_CUE_TRACK, cue.track);
+                        write_uint(b, ID_CUE_CLUSTER_POSITION, cue.cluster_position);
+                        write_uint(b, ID_CUE_RELATIVE_POSITION, cue.relative_position);
+                    });
+                });
+            }
+        });
+        self.write_bytes(&buf)?;
+
+        Ok(Some(cues_pos))
+    }
+
+    fn write_seek_head(&mut self, cues_pos: Option<u64>) -> Result<()> {
+        let mut entries = vec![(ID_INFO, self.info_pos), (ID_TRACKS, self.tracks_pos)];
+        if let Some(pos) = cues_pos {
+            entries.push((ID_CUES, pos));
+        }
+
+        let mut buf = Vec::new();
+        write_master(&mut buf, ID_SEEK_HEAD, |b| {
+            for (id, pos) in &entries {
+                write_master(b, ID_SEEK, |b| {
+                    let mut id_bytes = Vec::new();
+                    write_id(&mut id_bytes, *id);
+                    write_binary(b, ID_SEEK_ID, &id_bytes);
+                    write_uint(b, ID_SEEK_POSITION, pos - self.segment_data_start);
+                });
+            }
+        });
+        let padding = SEEK_HEAD_RESERVED - buf.len();
+        write_void(&mut buf, padding);
+
+        self.writer.seek(SeekFrom::Start(self.seek_head_pos))?;
+        self.writer.write_all(&buf)?;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek> std::fmt::Debug for MkvMuxer<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MkvMuxer")
+            .field("config", &self.config)
+            .field("header_written", &self.header_written)
+            .field("position", &self.position)
+            .field("cues", &self.cues.len())
+            .finish()
+    }
+}
+
+impl<W: Write + Seek> Muxer for MkvMuxer<W> {
+    fn write_packet(&mut self, packet: Packet) -> Result<()> {
+        let stream_index = packet.stream_index;
+        let track = self.config.tracks.get(stream_index).ok_or_else(|| {
+            Error::InvalidInput(format!(
+                "Matroska muxer has no stream index {}",
+                stream_index
+            ))
+        })?;
+
+        let packet = if track.is_video() {
+            let parser = &mut self.parsers[stream_index];
+            let is_keyframe = parser.parse_temporal_unit(&packet.data)?.is_keyframe();
+            if self.codec_private[stream_index].is_none() {
+                self.codec_private[stream_index] = parser.av1c();
+            }
+            let data = prepare_av1_sample(packet.data)?;
+            Packet {
+                data,
+                is_keyframe,
+                ..packet
+            }
+        } else {
+            packet
+        };
+
+        if !self.header_written {
+            self.pending.push(packet);
+
+            let ready = self
+                .config
+                .tracks
+                .iter()
+                .zip(&self.codec_private)
+                .all(|(track, private)| !track.is_video() || private.is_some());
+
+            if ready {
+                self.write_header()?;
+                for packet in std::mem::take(&mut self.pending) {
+                    self.write_block(packet)?;
+                }
+            } else if self.pending.len() > MAX_PENDING_PACKETS {
+                return Err(Error::InvalidInput(format!(
+                    "No AV1 sequence header within the first {} packets",
+                    MAX_PENDING_PACKETS
+                )));
+            }
+            return Ok(());
+        }
+
+        self.write_block(packet)
+    }
+
+    fn finalize(mut self) -> Result<()> {
+        if !self.header_written {
+            self.write_header()?;
+            for packet in std::mem::take(&mut self.pending) {
+                self.write_block(packet)?;
+            }
+        }
+
+        self.close_cluster()?;
+
+        if self.config.live {
+            tracing::info!("Finalizing live {} stream", self.config.doc_type.as_str());
+            self.writer.flush()?;
+            return Ok(());
+        }
+
+        let cues_pos = self.write_cues()?;
+        let end = self.position;
+
+        tracing::info!(
+            "Finalizing {} file: {} ms, {} cue points",
+            self.config.doc_type.as_str(),
+            self.end_time_ms,
+            self.cues.len()
+        );
+
+        self.writer.seek(SeekFrom::Start(self.segment_size_pos))?;
+        self.writer
+            .write_all(&size_vint8(end - self.segment_data_start))?;
+
+        self.writer.seek(SeekFrom::Start(self.duration_pos))?;
+        self.writer
+            .write_all(&(self.end_time_ms as f64).to_be_bytes())?;
+
+        self.write_seek_head(cues_pos)?;
+
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn id_len(id: u32) -> usize {
+    match id {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x1_0000..=0xFF_FFFF => 3,
+        _ => 4,
+    }
+}
+
+/// Element IDs already include their length marker bits
+fn write_id(buf: &mut Vec<u8>, id: u32) {
+    let bytes = id.to_be_bytes();
+    buf.extend_from_slice(&bytes[4 - id_len(id)..]);
+}
+
+/// Write a size as the shortest EBML variable-length integer
+fn write_size(buf: &mut Vec<u8>, size: u64) {
+    // All-ones values are reserved for "unknown", hence the strict bound
+    let len = (1..=8)
+        .find(|&len| size < (1u64 << (7 * len)) - 1)
+        .unwrap_or(8);
+    let marked = size | (1u64 << (7 * len));
+    buf.extend_from_slice(&marked.to_be_bytes()[8 - len..]);
+}
+
+/// Encode a size as a fixed 8-byte vint (for fields patched in place)
+fn size_vint8(size: u64) -> [u8; 8] {
+    (size | (1u64 << 56)).to_be_bytes()
+}
+
+/// Write a Colour element, unless the stream signals nothing beyond the defaults
+fn write_colour(buf: &mut Vec<u8>, colorimetry: &Colorimetry) {
+    if *colorimetry == Colorimetry::default() {
+        return;
+    }
+
+    write_master(buf, ID_COLOUR, |b| {
+        write_uint(
+            b,
+            ID_MATRIX_COEFFICIENTS,
+            u64::from(colorimetry.matrix.code()),
+        );
+        let (horz, vert) = match colorimetry.chroma_position {
+            ChromaSamplePosition::Unknown => (0, 0),
+            ChromaSamplePosition::Vertical => (1, 2),
+            ChromaSamplePosition::Colocated => (1, 1),
+        };
+        if colorimetry.chroma_position != ChromaSamplePosition::Unknown {
+            write_uint(b, ID_CHROMA_SITING_HORZ, horz);
+            write_uint(b, ID_CHROMA_SITING_VERT, vert);
+        }
+        let range = match colorimetry.range {
+            ColorRange::Limited => 1,
+            ColorRange::Full => 2,
+        };
+        write_uint(b, ID_RANGE, range);
+        write_uint(
+            b,
+            ID_TRANSFER_CHARACTERISTICS,
+            u64::from(colorimetry.transfer.code()),
+        );
+        write_uint(b, ID_PRIMARIES, u64::from(colorimetry.primaries.code()));
+
+        if let Some(level) = &colorimetry.content_light {
+            write_uint(b, ID_MAX_CLL, u64::from(level.max_cll));
+            write_uint(b, ID_MAX_FALL, u64::from(level.max_fall));
+        }
+
+        if let Some(display) = &colorimetry.mastering_display {
+            write_master(b, ID_MASTERING_METADATA, |b| {
+                let [red, green, blue] = display.primaries_xy();
+                let points = [red, green, blue, display.white_point_xy()];
+                for (i, (x, y)) in points.into_iter().enumerate() {
+                    let id = ID_PRIMARY_R_CHROMATICITY_X + 2 * i as u32;
+                    write_float(b, id, x);
+                    write_float(b, id + 1, y);
+                }
+                write_float(b, ID_LUMINANCE_MAX, display.max_luminance_nits());
+                write_float(b, ID_LUMINANCE_MIN, display.min_luminance_nits());
+            });
+        }
+    });
+}
+
+fn write_master(buf: &mut Vec<u8>, id: u32, body: impl FnOnce(&mut Vec<u8>)) {
+    let mut inner = Vec::new();
+    body(&mut inner);
+    write_id(buf, id);
+    // Fixed 8-byte sizes keep header offsets predictable for patching
+    buf.extend_from_slice(&size_vint8(inner.len() as u64));
+    buf.extend_from_slice(&inner);
+}
+
+fn write_uint(buf: &mut Vec<u8>, id: u32, value: u64) {
+    let len = ((64 - value.leading_zeros() as usize).div_ceil(8)).max(1);
+    write_id(buf, id);
+    write_size(buf, len as u64);
+    buf.extend_from_slice(&value.to_be_bytes()[8 - len..]);
+}
+
+fn write_float(buf: &mut Vec<u8>, id: u32, value: f64) {
+    write_id(buf, id);
+    write_size(buf, 8);
+    buf.extend_from_slice(&value.to_be_bytes());
+}
+
+fn write_string(buf: &mut Vec<u8>, id: u32, value: &str) {
+    write_binary(buf, id, value.as_bytes());
+}
+
+fn write_binary(buf: &mut Vec<u8>, id: u32, value: &[u8]) {
+    write_id(buf, id);
+    write_size(buf, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+/// Write a Void element occupying exactly `total` bytes (at least 9)
+fn write_void(buf: &mut Vec<u8>, total: usize) {
+    write_id(buf, ID_VOID);
+    let payload = total - 9;
+    buf.extend_from_slice(&size_vint8(payload as u64));
+    buf.extend(std::iter::repeat_n(0u8, payload));
+}
+
+/// Track description parsed by [`MkvDemuxer`]
+#[derive(Debug, Clone, Default)]
+pub struct MkvTrack {
+    /// Matroska track number (as used in blocks)
+    pub number: u64,
+    /// Track type (1 = video, 2 = audio, 17 = subtitle, ...)
+    pub track_type: u64,
+    /// Codec ID (e.g. `V_AV1`, `A_OPUS`)
+    pub codec_id: String,
+    /// Codec-specific setup data (av1C, OpusHead, ...)
+    pub codec_private: Vec<u8>,
+    /// Nominal frame duration in nanoseconds
+    pub default_duration_ns: Option<u64>,
+    /// Codec delay in nanoseconds (Opus pre-skip)
+    pub codec_delay_ns: u64,
+    /// Video width in pixels
+    pub width: Option<u32>,
+    /// Video height in pixels
+    pub height: Option<u32>,
+    /// Audio sampling frequency in Hz
+    pub sample_rate: Option<f64>,
+    /// Audio channel count
+    pub channels: Option<u32>,
+}
+
+impl MkvTrack {
+    /// Returns true for video tracks
+    pub fn is_video(&self) -> bool {
+        self.track_type == TRACK_TYPE_VIDEO
+    }
+
+    /// Returns true for audio tracks
+    pub fn is_audio(&self) -> bool {
+        self.track_type == TRACK_TYPE_AUDIO
+    }
+}
+
+/// An EBML element header
+#[derive(Debug, Clone, Copy)]
+struct ElementHeader {
+    id: u32,
+    /// Payload size, `None` for unknown-size elements
+    size: Option<u64>,
+}
+
+/// Matroska/WebM demuxer
+///
+/// Reads sequentially and never seeks, so it works with files, stdin and
+/// live streams with unknown-size Segments and Clusters. Packets are
+/// returned for all tracks in file order; `stream_index` is the track's
+/// position in [`tracks`](Self::tracks) and timestamps are in units of
+/// [`timestamp_scale`](Self::timestamp_scale) nanoseconds.
+///
+/// # Example
+/// ```no_run
+/// use mead_core::container::mkv::MkvDemuxer;
+/// use mead_core::container::Demuxer;
+/// use std::fs::File;
+///
+/// let file = File::open("input.webm")?;
+/// let mut demuxer = MkvDemuxer::new(file)?;
+///
+/// for track in demuxer.tracks() {
+///     println!("Track {}: {}", track.number, track.codec_id);
+/// }
+///
+/// while let Some(packet) = demuxer.read_packet()? {
+///     // Decode packet
+/// }
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+pub struct MkvDemuxer<R: MediaSource> {
+    reader: BufReader<R>,
+    metadata: Metadata,
+    tracks: Vec<MkvTrack>,
+    timestamp_scale: u64,
+    cluster_timestamp: i64,
+    /// Frames from a laced block not yet returned
+    queued: std::collections::VecDeque<Packet>,
+}
+
+impl<R: MediaSource> MkvDemuxer<R> {
+    /// Create a new Matroska/WebM demuxer, parsing headers up to the first Cluster
+    pub fn new(source: R) -> Result<Self> {
+        let mut reader = BufReader::new(source);
+
+        let header = read_element_header(&mut reader)?
+            .ok_or_else(|| Error::ContainerParse("Empty Matroska file".to_string()))?;
+        if header.id != ID_EBML {
+            return Err(Error::ContainerParse("Missing EBML header".to_string()));
+        }
+        let ebml = read_payload(&mut reader, header)?;
+        let mut doc_type = String::from("matroska");
+        for (id, value) in children(&ebml)? {
+            if id == ID_DOC_TYPE {
+                doc_type = String::from_utf8_lossy(value)
+                    .trim_end_matches('\0')
+                    .to_string();
+            }
+        }
+        let format = match doc_type.as_str() {
+            "webm" => "WebM",
+            "matroska" => "Matroska",
+            other => {
+                return Err(Error::UnsupportedFormat(format!("EBML DocType {}", other)));
+            }
+        };
+
+        let segment = read_element_header(&mut reader)?
+            .ok_or_else(|| Error::ContainerParse("Missing Matroska Segment".to_string()))?;
+        if segment.id != ID_SEGMENT {
+            return Err(Error::ContainerParse(
+                "Missing Matroska Segment".to_string(),
+            ));
+        }
+
+        let mut demuxer = Self {
+            reader,
+            metadata: Metadata {
+                duration_ms: None,
+                stream_count: 0,
+                format: format.to_string(),
+            },
+            tracks: Vec::new(),
+            timestamp_scale: TIMESTAMP_SCALE_NS,
+            cluster_timestamp: 0,
+            queued: std::collections::VecDeque::new(),
+        };
+
+        // Parse top-level elements until the first Cluster
+        let mut duration = None;
+        loop {
+            let element = read_element_header(&mut demuxer.reader)?.ok_or_else(|| {
+                Error::ContainerParse("Matroska file has no Clusters".to_string())
+            })?;
+
+            match element.id {
+                ID_INFO => {
+                    let info = read_payload(&mut demuxer.reader, element)?;
+                    for (id, value) in children(&info)? {
+                        match id {
+                            ID_TIMESTAMP_SCALE => demuxer.timestamp_scale = parse_uint(value)?,
+                            ID_DURATION => duration = Some(parse_float(value)?),
+                            _ => {}
+                        }
+                    }
+                }
+                ID_TRACKS => {
+                    let tracks = read_payload(&mut demuxer.reader, element)?;
+                    demuxer.tracks = parse_tracks(&tracks)?;
+                }
+                ID_CLUSTER => break,
+                _ => skip_payload(&mut demuxer.reader, element)?,
+            }
+        }
+
+        if demuxer.tracks.is_empty() {
+            return Err(Error::ContainerParse(
+                "Matroska file has no tracks".to_string(),
+            ));
+        }
+
+        demuxer.metadata.stream_count = demuxer.tracks.len();
+        demuxer.metadata.duration_ms =
+            duration.map(|d| (d * demuxer.timestamp_scale as f64 / 1_000_000.0) as u64);
+
+        tracing::info!(
+            "{} opened: {} tracks, duration: {:?}ms",
+            format,
+            demuxer.tracks.len(),
+            demuxer.metadata.duration_ms
+        );
+
+        Ok(demuxer)
+    }
+
+    /// Get parsed track descriptions
+    pub fn tracks(&self) -> &[MkvTrack] {
+        &self.tracks
+    }
+
+    /// Nanoseconds per timestamp tick
+    pub fn timestamp_scale(&self) -> u64 {
+        self.timestamp_scale
+    }
+
+    /// Turn a (Simple)Block payload into one packet per (laced) frame
+    fn parse_block(&mut self, data: &[u8], keyframe: Option<bool>) -> Result<()> {
+        let (track_number, len) = read_vint(data)?
+            .ok_or_else(|| Error::ContainerParse("Block with unknown track number".to_string()))?;
+        let rest = &data[len..];
+        if rest.len() < 3 {
+            return Err(Error::ContainerParse(
+                "Truncated Matroska block header".to_string(),
+            ));
+        }
+
+        let relative = i64::from(i16::from_be_bytes([rest[0], rest[1]]));
+        let flags = rest[2];
+        let payload = &rest[3..];
+
+        let (stream_index, track) = match self
+            .tracks
+            .iter()
+            .enumerate()
+            .find(|(_, t)| t.number == track_number)
+        {
+            Some(found) => found,
+            None => {
+                tracing::debug!("Skipping block for unknown track {}", track_number);
+                return Ok(());
+            }
+        };
+
+        // SimpleBlocks carry the flag; BlockGroups infer it from ReferenceBlock
+        let is_keyframe = keyframe.unwrap_or(flags & 0x80 != 0);
+        let frames = split_lacing(payload, (flags >> 1) & 0x03)?;
+
+        let pts = self.cluster_timestamp + relative;
+        // Laced frames after the first are spaced by the track's default duration
+        let step = track
+            .default_duration_ns
+            .map(|ns| (ns / self.timestamp_scale.max(1)) as i64);
+
+        for (i, frame) in frames.into_iter().enumerate() {
+            let frame_pts = match (i, step) {
+                (0, _) => Some(pts),
+                (_, Some(step)) => Some(pts + step * i as i64),
+                (_, None) => None,
+            };
+            self.queued.push_back(Packet {
+                stream_index,
+                data: frame.to_vec(),
+                pts: frame_pts,
+                dts: None,
+                is_keyframe,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: MediaSource> std::fmt::Debug for MkvDemuxer<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MkvDemuxer")
+            .field("metadata", &self.metadata)
+            .field("tracks", &self.tracks)
+            .field("timestamp_scale", &self.timestamp_scale)
+            .finish()
+    }
+}
+
+impl<R: MediaSource> Demuxer for MkvDemuxer<R> {
+    fn read_packet(&mut self) -> Result<Option<Packet>> {
+        loop {
+            if let Some(packet) = self.queued.pop_front() {
+                return Ok(Some(packet));
+            }
+
+            let element = match read_element_header(&mut self.reader)? {
+                Some(element) => element,
+                None => return Ok(None),
+            };
+
+            match element.id {
+                // Descend into masters; works for known and unknown sizes alike
+                ID_SEGMENT | ID_CLUSTER => {}
+                ID_TIMESTAMP => {
+                    let value = read_payload(&mut self.reader, element)?;
+                    self.cluster_timestamp = parse_uint(&value)? as i64;
+                }
+                ID_SIMPLE_BLOCK => {
+                    let block = read_payload(&mut self.reader, element)?;
+                    self.parse_block(&block, None)?;
+                }
+                ID_BLOCK_GROUP => {
+                    let group = read_payload(&mut self.reader, element)?;
+                    let mut block = None;
+                    let mut has_reference = false;
+                    for (id, value) in children(&group)? {
+                        match id {
+                            ID_BLOCK => block = Some(value),
+                            ID_REFERENCE_BLOCK => has_reference = true,
+                            ID_BLOCK_DURATION => {}
+                            _ => {}
+                        }
+                    }
+                    if let Some(block) = block {
+                        self.parse_block(block, Some(!has_reference))?;
+                    }
+                }
+                _ => skip_payload(&mut self.reader, element)?,
+            }
+        }
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+fn parse_tracks(data: &[u8]) -> Result<Vec<MkvTrack>> {
+    let mut tracks = Vec::new();
+
+    for (id, entry) in children(data)? {
+        if id != ID_TRACK_ENTRY {
+            continue;
+        }
+
+        let mut track = MkvTrack::default();
+        for (id, value) in children(entry)? {
+            match id {
+                ID_TRACK_NUMBER => track.number = parse_uint(value)?,
+                ID_TRACK_TYPE => track.track_type = parse_uint(value)?,
+                ID_CODEC_ID => {
+                    track.codec_id = String::from_utf8_lossy(value)
+                        .trim_end_matches('\0')
+                        .to_string()
+                }
+                ID_CODEC_PRIVATE => track.codec_private = value.to_vec(),
+                ID_DEFAULT_DURATION => track.default_duration_ns = Some(parse_uint(value)?),
+                ID_CODEC_DELAY => track.codec_delay_ns = parse_uint(value)?,
+                ID_VIDEO => {
+                    for (id, value) in children(value)? {
+                        match id {
+                            ID_PIXEL_WIDTH => track.width = Some(parse_uint(value)? as u32),
+                            ID_PIXEL_HEIGHT => track.height = Some(parse_uint(value)? as u32),
+                            _ => {}
+                        }
+                    }
+                }
+                ID_AUDIO => {
+                    for (id, value) in children(value)? {
+                        match id {
+                            ID_SAMPLING_FREQUENCY => track.sample_rate = Some(parse_float(value)?),
+                            ID_CHANNELS => track.channels = Some(parse_uint(value)? as u32),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if track.number == 0 {
+            return Err(Error::ContainerParse(
+                "Matroska track without TrackNumber".to_string(),
+            ));
+        }
+        tracks.push(track);
+    }
+
+    Ok(tracks)
+}
+
+/// Split a block payload into frames according to its lacing mode
+fn split_lacing(payload: &[u8], lacing: u8) -> Result<Vec<&[u8]>> {
+    if lacing == 0 {
+        return Ok(vec![payload]);
+    }
+
+    let truncated = || Error::ContainerParse("Truncated Matroska lacing header".to_string());
+    let count = usize::from(*payload.first().ok_or_else(truncated)?) + 1;
+    let mut pos = 1;
+    let mut sizes = Vec::with_capacity(count);
+
+    match lacing {
+        // Xiph lacing: sizes as runs of 255
+        1 => {
+            for _ in 0..count - 1 {
+                let mut size = 0usize;
+                loop {
+                    let byte = *payload.get(pos).ok_or_else(truncated)?;
+                    pos += 1;
+                    size += usize::from(byte);
+                    if byte != 255 {
+                        break;
+                    }
+                }
+                sizes.push(size);
+            }
+        }
+        // Fixed-size lacing: equal split
+        2 => {
+            let total = payload.len() - pos;
+            if total % count != 0 {
+                return Err(Error::ContainerParse(
+                    "Uneven fixed-size lacing".to_string(),
+                ));
+            }
+            sizes.extend(std::iter::repeat_n(total / count, count - 1));
+        }
+        // A single frame has no sizes to read
+        3 if count == 1 => {}
+        // EBML lacing: first size as vint, then signed differences
+        3 => {
+            let (first, len) = read_vint(&payload[pos..])?.ok_or_else(truncated)?;
+            pos += len;
+            let mut size = first as i64;
+            sizes.push(first as usize);
+            for _ in 1..count - 1 {
+                let (raw, len) = read_vint(&payload[pos..])?.ok_or_else(truncated)?;
+                let bias = (1i64 << (7 * len - 1)) - 1;
+                pos += len;
+                size += raw as i64 - bias;
+                if size < 0 {
+                    return Err(Error::ContainerParse("Negative EBML lace size".to_string()));
+                }
+                sizes.push(size as usize);
+            }
+        }
+        _ => {
+            return Err(Error::ContainerParse(format!(
+                "Invalid Matroska lacing {}",
+                lacing
+            )));
+        }
+    }
+
+    let mut frames = Vec::with_capacity(count);
+    for size in sizes {
+        let end = pos
+            .checked_add(size)
+            .filter(|&end| end <= payload.len())
+            .ok_or_else(truncated)?;
+        frames.push(&payload[pos..end]);
+        pos = end;
+    }
+    frames.push(&payload[pos..]);
+
+    Ok(frames)
+}
+
+/// Decode a vint from a byte slice, returning (value, length) or `None` if unknown-size
+fn read_vint(data: &[u8]) -> Result<Option<(u64, usize)>> {
+    let first = *data
+        .first()
+        .ok_or_else(|| Error::ContainerParse("Truncated EBML vint".to_string()))?;
+    if first == 0 {
+        return Err(Error::ContainerParse("Invalid EBML vint".to_string()));
+    }
+    let len = first.leading_zeros() as usize + 1;
+    let bytes = data
+        .get(..len)
+        .ok_or_else(|| Error::ContainerParse("Truncated EBML vint".to_string()))?;
+
+    let mut value = u64::from(first) & ((1u64 << (8 - len)) - 1);
+    for &byte in &bytes[1..] {
+        value = (value << 8) | u64::from(byte);
+    }
+
+    let all_ones = (1u64 << (7 * len)) - 1;
+    Ok((value != all_ones).then_some((value, len)))
+}
+
+/// Iterate over the child elements of a master element's payload
+fn children(mut data: &[u8]) -> Result<Vec<(u32, &[u8])>> {
+    let mut elements = Vec::new();
+
+    while !data.is_empty() {
+        let id_len = data[0].leading_zeros() as usize + 1;
+        if id_len > 4 || data.len() < id_len {
+            return Err(Error::ContainerParse("Invalid EBML element ID".to_string()));
+        }
+        let id = data[..id_len]
+            .iter()
+            .fold(0u32, |acc, &b| (acc << 8) | u32::from(b));
+        data = &data[id_len..];
+
+        let (size, size_len) = read_vint(data)?
+            .ok_or_else(|| Error::ContainerParse("Unknown-size child element".to_string()))?;
+        let end = size_len
+            .checked_add(size as usize)
+            .filter(|&end| end <= data.len())
+            .ok_or_else(|| Error::ContainerParse("Truncated EBML element".to_string()))?;
+
+        elements.push((id, &data[size_len..end]));
+        data = &data[end..];
+    }
+
+    Ok(elements)
+}
+
+/// Read an element header from a stream, returning `None` on a clean EOF
+fn read_element_header<R: Read>(reader: &mut R) -> Result<Option<ElementHeader>> {
+    let mut first = [0u8; 1];
+    loop {
+        match reader.read(&mut first) {
+            Ok(0) => return Ok(None),
+            Ok(_) => break,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Error::Io(e)),
+        }
+    }
+
+    let truncated = |e: std::io::Error| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => {
+            Error::ContainerParse("Truncated EBML element header".to_string())
+        }
+        _ => Error::Io(e),
+    };
+
+    let id_len = first[0].leading_zeros() as usize + 1;
+    if id_len > 4 {
+        return Err(Error::ContainerParse(format!(
+            "Invalid EBML element ID byte 0x{:02x}",
+            first[0]
+        )));
+    }
+    let mut id_bytes = [0u8; 4];
+    id_bytes[0] = first[0];
+    reader
+        .read_exact(&mut id_bytes[1..id_len])
+        .map_err(truncated)?;
+    let id = id_bytes[..id_len]
+        .iter()
+        .fold(0u32, |acc, &b| (acc << 8) | u32::from(b));
+
+    let mut size_bytes = [0u8; 8];
+    reader.read_exact(&mut size_bytes[..1]).map_err(truncated)?;
+    if size_bytes[0] == 0 {
+        return Err(Error::ContainerParse(
+            "Invalid EBML element size".to_string(),
+        ));
+    }
+    let size_len = size_bytes[0].leading_zeros() as usize + 1;
+    reader
+        .read_exact(&mut size_bytes[1..size_len])
+        .map_err(truncated)?;
+    let size = read_vint(&size_bytes[..size_len])?.map(|(value, _)| value);
+
+    Ok(Some(ElementHeader { id, size }))
+}
+
+/// Read an element's payload into memory
+fn read_payload<R: Read>(reader: &mut R, header: ElementHeader) -> Result<Vec<u8>> {
+    let size = header
+        .size
+        .ok_or_else(|| Error::ContainerParse(format!("Unknown-size element 0x{:x}", header.id)))?;
+    if size > MAX_ELEMENT_SIZE {
+        return Err(Error::ContainerParse(format!(
+            "EBML element 0x{:x} of {} bytes exceeds maximum",
+            header.id, size
+        )));
+    }
+
+    // Read through `take` so a corrupt size can't force a huge allocation
+    let mut data = Vec::new();
+    reader.take(size).read_to_end(&mut data)?;
+    if data.len() as u64 != size {
+        return Err(Error::ContainerParse(format!(
+            "Truncated EBML element 0x{:x}: expected {} bytes, got {}",
+            header.id,
+            size,
+            data.len()
+        )));
+    }
+    Ok(data)
+}
+
+fn skip_payload<R: Read>(reader: &mut R, header: ElementHeader) -> Result<()> {
+    let size = header.size.ok_or_else(|| {
+        Error::ContainerParse(format!(
+            "Cannot skip unknown-size element 0x{:x}",
+            header.id
+        ))
+    })?;
+    let skipped = std::io::copy(&mut reader.take(size), &mut std::io::sink())?;
+    if skipped != size {
+        return Err(Error::ContainerParse(format!(
+            "Truncated EBML element 0x{:x}",
+            header.id
+        )));
+    }
+    Ok(())
+}
+
+fn parse_uint(data: &[u8]) -> Result<u64> {
+    if data.len() > 8 {
+        return Err(Error::ContainerParse(
+            "EBML unsigned integer longer than 8 bytes".to_string(),
+        ));
+    }
+    Ok(data.iter().fold(0u64, |acc, &b| (acc << 8) | u64::from(b)))
+}
+
+fn parse_float(data: &[u8]) -> Result<f64> {
+    match data.len() {
+        0 => Ok(0.0),
+        4 => Ok(f64::from(f32::from_be_bytes([
+            data[0], data[1], data[2], data[3],
+        ]))),
+        8 => {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(data);
+            Ok(f64::from_be_bytes(bytes))
+        }
+        len => Err(Error::ContainerParse(format!(
+            "Invalid EBML float length {}",
+            len
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::av1::obu::test_data::av1_packet;
+    use crate::io::{ReadOnlySource, WriteOnlySink};
+    use std::io::Cursor;
+
+    fn opus_packet(index: i64) -> Packet {
+        Packet {
+            stream_index: 1,
+            data: vec![0xFC, index as u8], // CELT 20 ms
+            pts: Some(index * 960),
+            dts: None,
+            is_keyframe: true,
+        }
+    }
+
+    fn test_config(live: bool) -> MkvMuxerConfig {
+        MkvMuxerConfig {
+            tracks: vec![
+                MkvTrackConfig::Av1 {
+                    width: 64,
+                    height: 64,
+                    fps_num: 25,
+                    fps_den: 1,
+                },
+                MkvTrackConfig::Opus {
+                    channels: 2,
+                    input_sample_rate: 48000,
+                    pre_skip: 312,
+                },
+            ],
+            live,
+            ..Default::default()
+        }
+    }
+
+    fn write_packets<W: Write + Seek>(muxer: &mut MkvMuxer<W>) {
+        for i in 0..6 {
+            // Opus first, so it is buffered until the sequence header arrives
+            muxer.write_packet(opus_packet(i * 2)).unwrap();
+            muxer.write_packet(opus_packet(i * 2 + 1)).unwrap();
+            muxer.write_packet(av1_packet(i, i % 3 == 0)).unwrap();
+        }
+    }
+
+    fn read_all(data: Vec<u8>) -> (Vec<MkvTrack>, Vec<Packet>) {
+        let mut demuxer = MkvDemuxer::new(ReadOnlySource::new(Cursor::new(data))).unwrap();
+        let tracks = demuxer.tracks().to_vec();
+        let mut packets = Vec::new();
+        while let Some(packet) = demuxer.read_packet().unwrap() {
+            packets.push(packet);
+        }
+        (tracks, packets)
+    }
+
+    #[test]
+    fn test_vint_round_trip() {
+        for value in [0u64, 1, 126, 127, 16382, 16383, 1 << 20, (1 << 56) - 2] {
+            let mut buf = Vec::new();
+            write_size(&mut buf, value);
+            assert_eq!(
+                read_vint(&buf).unwrap(),
+                Some((value, buf.len())),
+                "value {}",
+                value
+            );
+        }
+        assert_eq!(read_vint(&UNKNOWN_SIZE).unwrap(), None);
+        assert_eq!(read_vint(&size_vint8(42)).unwrap(), Some((42, 8)));
+    }
+
+    #[test]
+    fn test_mkv_round_trip() {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut muxer = MkvMuxer::new(&mut cursor, test_config(false)).unwrap();
+        write_packets(&mut muxer);
+        assert_eq!(muxer.cue_count(), 2);
+        muxer.finalize().unwrap();
+
+        let data = cursor.into_inner();
+        let demuxer = MkvDemuxer::new(Cursor::new(data.clone())).unwrap();
+        assert_eq!(demuxer.metadata().format, "WebM");
+        assert_eq!(demuxer.metadata().duration_ms, Some(240));
+
+        let (tracks, packets) = read_all(data);
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].codec_id, "V_AV1");
+        assert_eq!(&tracks[0].codec_private[..4], &[0x81, 0x1f, 0x0c, 0x00]);
+        assert_eq!((tracks[0].width, tracks[0].height), (Some(64), Some(64)));
+        assert_eq!(tracks[0].default_duration_ns, Some(40_000_000));
+        assert_eq!(tracks[1].codec_id, "A_OPUS");
+        assert_eq!(&tracks[1].codec_private[..8], b"OpusHead");
+        assert_eq!(tracks[1].channels, Some(2));
+
+        let video: Vec<&Packet> = packets.iter().filter(|p| p.stream_index == 0).collect();
+        let audio: Vec<&Packet> = packets.iter().filter(|p| p.stream_index == 1).collect();
+        assert_eq!(video.len(), 6);
+        assert_eq!(audio.len(), 12);
+
+        for (i, packet) in video.iter().enumerate() {
+            assert_eq!(packet.pts, Some(i as i64 * 40));
+            assert_eq!(packet.is_keyframe, i % 3 == 0);
+            // Temporal delimiters are stripped, the sequence header is kept
+            assert_ne!(packet.data[0], 0x12);
+            let frame_header = if i % 3 == 0 { 0x10 } else { 0x30 };
+            assert_eq!(
+                &packet.data[packet.data.len() - 5..],
+                &[0x32, 0x03, frame_header, i as u8, 0xAA]
+            );
+        }
+        for (i, packet) in audio.iter().enumerate() {
+            assert_eq!(packet.pts, Some(i as i64 * 20));
+            assert_eq!(packet.data, vec![0xFC, i as u8]);
+        }
+    }
+
+    #[test]
+    fn test_mkv_many_tracks() {
+        // Track numbers from 127 up take two bytes in a block header
+        let config = MkvMuxerConfig {
+            tracks: vec![
+                MkvTrackConfig::Opus {
+                    channels: 1,
+                    input_sample_rate: 48000,
+                    pre_skip: 0
+                };
+                300
+            ],
+            ..Default::default()
+        };
+        let mut output = Cursor::new(Vec::new());
+        let mut muxer = MkvMuxer::new(&mut output, config).unwrap();
+        for stream_index in [0, 126, 127, 299] {
+            muxer
+                .write_packet(Packet {
+                    stream_index,
+                    ..opus_packet(0)
+                })
+                .unwrap();
+        }
+        muxer.finalize().unwrap();
+
+        let (tracks, packets) = read_all(output.into_inner());
+        assert_eq!(tracks.len(), 300);
+        let indices: Vec<_> = packets.iter().map(|packet| packet.stream_index).collect();
+        assert_eq!(indices, [0, 126, 127, 299]);
+    }
+
+    #[test]
+    fn test_mkv_live_mode() {
+        let mut sink = WriteOnlySink::new(Vec::new());
+        let mut muxer = MkvMuxer::new(&mut sink, test_config(true)).unwrap();
+        write_packets(&mut muxer);
+        assert_eq!(muxer.cue_count(), 0);
+        muxer.finalize().unwrap();
+        let data = sink.into_inner();
+
+        // Segment and Clusters keep their unknown sizes
+        let segment = data
+            .windows(4)
+            .position(|w| w == [0x18, 0x53, 0x80, 0x67])
+            .unwrap();
+        assert_eq!(&data[segment + 4..segment + 12], &UNKNOWN_SIZE);
+        assert!(
+            !data.windows(4).any(|w| w == [0x1C, 0x53, 0xBB, 0x6B]),
+            "no Cues"
+        );
+
+        let (_, packets) = read_all(data);
+        assert_eq!(packets.len(), 18);
+    }
+
+    #[test]
+    fn test_mkv_matroska_doc_type() {
+        let config = MkvMuxerConfig {
+            doc_type: DocType::Matroska,
+            ..test_config(false)
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        let mut muxer = MkvMuxer::new(&mut cursor, config).unwrap();
+        muxer.write_packet(av1_packet(0, true)).unwrap();
+        muxer.finalize().unwrap();
+
+        let demuxer = MkvDemuxer::new(Cursor::new(cursor.into_inner())).unwrap();
+        assert_eq!(demuxer.metadata().format, "Matroska");
+    }
+
+    #[test]
+    fn test_mkv_colour() {
+        let config = MkvMuxerConfig {
+            tracks: vec![MkvTrackConfig::Av1 {
+                width: 64,
+                height: 64,
+                fps_num: 25,
+                fps_den: 1,
+            }],
+            ..Default::default()
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        let mut muxer = MkvMuxer::new(&mut cursor, config).unwrap();
+
+        // HDR_CLL metadata OBU (MaxCLL 1000, MaxFALL 400) in the first temporal unit
+        let mut packet = av1_packet(0, true);
+        packet
+            .data
+            .extend_from_slice(&[0x2a, 0x05, 0x01, 0x03, 0xe8, 0x01, 0x90]);
+        muxer.write_packet(packet).unwrap();
+        muxer.finalize().unwrap();
+
+        let data = cursor.into_inner();
+        let contains = |needle: &[u8]| data.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(&[0x55, 0xB0]));
+        assert!(contains(&[
+            0x55, 0xBC, 0x82, 0x03, 0xE8, 0x55, 0xBD, 0x82, 0x01, 0x90
+        ]));
+        // Limited range, unspecified transfer and primaries
+        assert!(contains(&[
+            0x55, 0xB9, 0x81, 0x01, 0x55, 0xBA, 0x81, 0x02, 0x55, 0xBB, 0x81, 0x02
+        ]));
+
+        let (_, packets) = read_all(data);
+        assert_eq!(packets.len(), 1);
+    }
+
+    #[test]
+    fn test_mkv_muxer_requires_sequence_header() {
+        let mut muxer = MkvMuxer::new(Cursor::new(Vec::new()), test_config(false)).unwrap();
+        muxer.write_packet(av1_packet(0, false)).unwrap();
+        assert!(muxer.finalize().is_err());
+    }
+
+    #[test]
+    fn test_split_lacing() {
+        // Xiph: 3 frames of sizes 2, 300, rest
+        let mut xiph = vec![2, 2, 255, 45];
+        xiph.extend(std::iter::repeat_n(1u8, 2 + 300 + 4));
+        let frames = split_lacing(&xiph, 1).unwrap();
+        assert_eq!(
+            frames.iter().map(|f| f.len()).collect::<Vec<_>>(),
+            vec![2, 300, 4]
+        );
+
+        // Fixed: 2 frames of 3 bytes
+        let frames = split_lacing(&[1, 1, 2, 3, 4, 5, 6], 2).unwrap();
+        assert_eq!(frames, vec![&[1, 2, 3][..], &[4, 5, 6][..]]);
+
+        // EBML: 3 frames, sizes 2, 3 (delta +1), rest
+        let frames = split_lacing(&[2, 0x82, 0xC0, 1, 1, 2, 2, 2, 3], 3).unwrap();
+        assert_eq!(
+            frames.iter().map(|f| f.len()).collect::<Vec<_>>(),
+            vec![2, 3, 1]
+        );
+
+        // EBML with a single frame carries no sizes
+        let frames = split_lacing(&[0, 5, 6, 7], 3).unwrap();
+        assert_eq!(frames, vec![&[5, 6, 7][..]]);
+
+        assert!(split_lacing(&[3, 10], 1).is_err(), "truncated lacing");
+        assert!(split_lacing(&[0, 1], 4).is_err(), "unknown lacing");
+    }
+
+    #[test]
+    fn test_mkv_demuxer_rejects_garbage() {
+        assert!(MkvDemuxer::new(Cursor::new(vec![0u8; 64])).is_err());
+        assert!(MkvDemuxer::new(Cursor::new(b"DKIF".to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_mkv_demuxer_truncated_block() {
+        let mut cursor = Cursor::new(Vec::new());
+        let mut muxer = MkvMuxer::new(&mut cursor, test_config(true)).unwrap();
+        write_packets(&mut muxer);
+        muxer.finalize().unwrap();
+
+        let mut data = cursor.into_inner();
+        data.truncate(data.len() - 3);
+        let mut demuxer = MkvDemuxer::new(Cursor::new(data)).unwrap();
+        let result = loop {
+            match demuxer.read_packet() {
+                Ok(Some(_)) => continue,
+                other => break other,
+            }
+        };
+        assert!(matches!(result, Err(Error::ContainerParse(_))));
+    }
+}
@@ -1,7 +1,10 @@
-//! Container format handlers (MP4, IVF, WebM, MKV, Y4M)
+//! Container format handlers (MP4, IVF, WebM, MKV, Ogg, OBU, Y4M)
 
-pub mod mp4;
 pub mod ivf;
+pub mod mkv;
+pub mod mp4;
+pub mod obu;
+pub mod ogg;
 pub mod y4m;
 
 use crate::Result;
@@ -1,11 +1,18 @@
-//! MP4 container support using mp4 crate
+//! MP4 container support
 //!
-//! Uses buffered reading with the `mp4` crate for efficient large file handling.
-//! Does NOT load entire file into memory.
+//! Demuxing uses buffered reading with the `mp4` crate for efficient large file
+//! handling and does NOT load the entire file into memory.
+//!
+//! Muxing writes ISOBMFF boxes directly, since the `mp4` crate has no AV1 or
+//! Opus sample entries. Sample data is streamed to `mdat` as it arrives; only
+//! the per-sample tables are kept in memory until `finalize`.
 
-use crate::{Error, MediaSource, Result};
-use super::{Demuxer, Metadata, Packet};
-use std::io::BufReader;
+use super::{Demuxer, Metadata, Muxer, Packet};
+use crate::codec::av1::obu::{Av1Parser, Obu, ObuType, parse_obus};
+use crate::codec::opus;
+use crate::color::ColorRange;
+use crate::{Colorimetry, Error, MediaSource, Result};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
 
 /// MP4 demuxer using streaming with `mp4` crate
 ///
@@ -26,7 +33,9 @@ impl<R: MediaSource> Mp4Demuxer<R> {
     pub fn new(source: R) -> Result<Self> {
         // Get file size for mp4 crate API
         let size = source.len().ok_or_else(|| {
-            Error::InvalidInput("Cannot determine source length - required for MP4 parsing".to_string())
+            Error::InvalidInput(
+                "Cannot determine source length - required for MP4 parsing".to_string(),
+            )
         })?;
 
         tracing::info!("Opening MP4 file ({} bytes) with streaming support", size);
@@ -74,10 +83,7 @@ impl<R: MediaSource> Mp4Demuxer<R> {
     /// Select a track for reading
     pub fn select_track(&mut self, track_id: u32) -> Result<()> {
         if !self.reader.tracks().contains_key(&track_id) {
-            return Err(Error::InvalidInput(format!(
-                "Track {} not found",
-                track_id
-            )));
+            return Err(Error::InvalidInput(format!("Track {} not found", track_id)));
         }
         self.current_track = Some(track_id);
         self.current_sample = 0;
@@ -86,7 +92,9 @@ impl<R: MediaSource> Mp4Demuxer<R> {
 
     /// Get video tracks
     pub fn video_tracks(&self) -> Vec<(u32, &mp4::Mp4Track)> {
-        self.reader.tracks().iter()
+        self.reader
+            .tracks()
+            .iter()
             .filter(|(_, track)| matches!(track.track_type(), Ok(mp4::TrackType::Video)))
             .map(|(id, track)| (*id, track))
             .collect()
@@ -94,7 +102,9 @@ impl<R: MediaSource> Mp4Demuxer<R> {
 
     /// Get audio tracks
     pub fn audio_tracks(&self) -> Vec<(u32, &mp4::Mp4Track)> {
-        self.reader.tracks().iter()
+        self.reader
+            .tracks()
+            .iter()
             .filter(|(_, track)| matches!(track.track_type(), Ok(mp4::TrackType::Audio)))
             .map(|(id, track)| (*id, track))
             .collect()
@@ -176,9 +186,883 @@ impl<R: MediaSource> Demuxer for Mp4Demuxer<R> {
     }
 }
 
+/// Movie timescale used for mvhd/tkhd/elst durations (milliseconds)
+const MOVIE_TIMESCALE: u32 = 1000;
+
+/// Size of the 64-bit `mdat` box header (size=1 + largesize)
+const MDAT_HEADER_SIZE: u64 = 16;
+
+/// Track description for [`Mp4Muxer`]
+#[derive(Debug, Clone)]
+pub enum Mp4TrackConfig {
+    /// AV1 video track
+    ///
+    /// Packet timestamps are in frame units (timebase `fps_den/fps_num`),
+    /// the same convention as [`IvfMuxer`](super::ivf::IvfMuxer).
+    Av1 {
+        /// Width in pixels
+        width: u32,
+        /// Height in pixels
+        height: u32,
+        /// Frame rate numerator
+        fps_num: u32,
+        /// Frame rate denominator
+        fps_den: u32,
+    },
+    /// Opus audio track
+    ///
+    /// Packet timestamps are in 48 kHz samples.
+    Opus {
+        /// Channel count (1 or 2)
+        channels: u8,
+        /// Sample rate of the original input (informational)
+        input_sample_rate: u32,
+        /// Samples to discard from the start of the decoded stream
+        pre_skip: u16,
+    },
+}
+
+impl Mp4TrackConfig {
+    fn timescale(&self) -> u32 {
+        match self {
+            Self::Av1 { fps_num, .. } => *fps_num,
+            Self::Opus { .. } => 48000,
+        }
+    }
+
+    fn is_video(&self) -> bool {
+        matches!(self, Self::Av1 { .. })
+    }
+}
+
+/// MP4 muxer configuration
+#[derive(Debug, Clone, Default)]
+pub struct Mp4MuxerConfig {
+    /// Tracks, indexed by `Packet::stream_index`
+    pub tracks: Vec<Mp4TrackConfig>,
+}
+
+/// Per-sample bookkeeping for the sample tables
+#[derive(Debug, Clone, Copy)]
+struct SampleInfo {
+    size: u32,
+    /// Decode time in media timescale units
+    dts: i64,
+    /// Composition offset (pts - dts) in media timescale units
+    cts_offset: i64,
+    /// Explicit duration (Opus, from the TOC byte)
+    duration: Option<u32>,
+    is_sync: bool,
+}
+
+/// A run of consecutive samples from one track in `mdat`
+#[derive(Debug, Clone, Copy)]
+struct Chunk {
+    offset: u64,
+    sample_count: u32,
+}
+
+#[derive(Debug)]
+struct TrackState {
+    config: Mp4TrackConfig,
+    samples: Vec<SampleInfo>,
+    chunks: Vec<Chunk>,
+    /// Tracks AV1 sequence headers and frame types
+    parser: Av1Parser,
+}
+
+impl TrackState {
+    fn durations(&self) -> Vec<u32> {
+        let default = match self.config {
+            Mp4TrackConfig::Av1 { fps_den, .. } => fps_den,
+            Mp4TrackConfig::Opus { .. } => 960,
+        };
+
+        let mut durations: Vec<u32> = self
+            .samples
+            .windows(2)
+            .map(|w| w[0].duration.unwrap_or((w[1].dts - w[0].dts) as u32))
+            .collect();
+
+        if let Some(last) = self.samples.last() {
+            let fallback = durations.last().copied().unwrap_or(default);
+            durations.push(last.duration.unwrap_or(fallback));
+        }
+
+        durations
+    }
+
+    fn next_dts(&self) -> i64 {
+        self.samples
+            .last()
+            .map(|s| s.dts + i64::from(s.duration.unwrap_or(0)))
+            .unwrap_or(0)
+    }
+}
+
+/// MP4 muxer for AV1 video and Opus audio
+///
+/// Writes `av01` sample entries with an `av1C` record built from the stream's
+/// sequence header OBU, plus `colr`, `mdcv` and `clli` boxes when the stream
+/// signals a color description or HDR metadata, and `Opus` sample entries
+/// with a `dOps` record.
+/// Sample data is written as it arrives; `moov` is written on `finalize`,
+/// optionally moved in front of `mdat` (faststart) for progressive playback.
+///
+/// [`Mp4Muxer::new`] writes `moov` after `mdat` and only needs to write and
+/// seek. Faststart ([`Mp4Muxer::new_faststart`]) rewrites the file in place,
+/// so the writer must support reading as well (e.g. a `File` opened
+/// read-write).
+///
+/// # Example
+/// ```no_run
+/// use mead_core::container::mp4::{Mp4Muxer, Mp4MuxerConfig, Mp4TrackConfig};
+/// use mead_core::container::{Muxer, Packet};
+/// use std::fs::OpenOptions;
+///
+/// let file = OpenOptions::new()
+///     .read(true)
+///     .write(true)
+///     .create(true)
+///     .truncate(true)
+///     .open("output.mp4")?;
+///
+/// let config = Mp4MuxerConfig {
+///     tracks: vec![Mp4TrackConfig::Av1 { width: 1920, height: 1080, fps_num: 30, fps_den: 1 }],
+/// };
+/// let mut muxer = Mp4Muxer::new_faststart(file, config)?;
+///
+/// let packet = Packet {
+///     stream_index: 0,
+///     data: vec![/* AV1 temporal unit */],
+///     pts: Some(0),
+///     dts: None,
+///     is_keyframe: true,
+/// };
+/// muxer.write_packet(packet)?;
+///
+/// muxer.finalize()?;
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+pub struct Mp4Muxer<W: Write + Seek> {
+    writer: W,
+    tracks: Vec<TrackState>,
+    /// Moves `mdat` forward to make room for `moov` (faststart only)
+    shift: Option<ShiftFn<W>>,
+    /// File offset of the `mdat` box header
+    mdat_start: u64,
+    /// Current write position (end of `mdat`)
+    position: u64,
+    /// Track that wrote the previous sample (for chunk grouping)
+    last_track: Option<usize>,
+}
+
+/// Moves the byte range `[start, end)` of a writer forward by a number of bytes
+type ShiftFn<W> = fn(&mut W, u64, u64, u64) -> Result<()>;
+
+impl<W: Read + Write + Seek> Mp4Muxer<W> {
+    /// Create a new MP4 muxer that moves `moov` in front of `mdat` on
+    /// finalize, so playback can start before the whole file is downloaded
+    pub fn new_faststart(writer: W, config: Mp4MuxerConfig) -> Result<Self> {
+        Self::with_shift(writer, config, Some(shift_forward::<W>))
+    }
+}
+
+impl<W: Write + Seek> Mp4Muxer<W> {
+    /// Create a new MP4 muxer and write the `ftyp` and `mdat` headers
+    pub fn new(writer: W, config: Mp4MuxerConfig) -> Result<Self> {
+        Self::with_shift(writer, config, None)
+    }
+
+    fn with_shift(
+        mut writer: W,
+        config: Mp4MuxerConfig,
+        shift: Option<ShiftFn<W>>,
+    ) -> Result<Self> {
+        if config.tracks.is_empty() {
+            return Err(Error::InvalidInput(
+                "MP4 muxer requires at least one track".to_string(),
+            ));
+        }
+
+        for track in &config.tracks {
+            match *track {
+                Mp4TrackConfig::Av1 {
+                    width,
+                    height,
+                    fps_num,
+                    fps_den,
+                } => {
+                    if width == 0 || height == 0 || width > 0xFFFF || height > 0xFFFF {
+                        return Err(Error::InvalidInput(format!(
+                            "Invalid AV1 track dimensions {}x{}",
+                            width, height
+                        )));
+                    }
+                    if fps_num == 0 || fps_den == 0 {
+                        return Err(Error::InvalidInput(format!(
+                            "Invalid AV1 track frame rate {}/{}",
+                            fps_num, fps_den
+                        )));
+                    }
+                }
+                Mp4TrackConfig::Opus { channels, .. } => {
+                    // Channel mapping family 0 only covers mono and stereo
+                    if !(1..=2).contains(&channels) {
+                        return Err(Error::UnsupportedFormat(format!(
+                            "Opus in MP4 with {} channels",
+                            channels
+                        )));
+                    }
+                }
+            }
+        }
+
+        let has_av1 = config.tracks.iter().any(Mp4TrackConfig::is_video);
+
+        tracing::info!(
+            "Creating MP4 muxer: {} tracks, faststart: {}",
+            config.tracks.len(),
+            shift.is_some()
+        );
+
+        let start = writer.stream_position()?;
+
+        let mut ftyp = Vec::new();
+        write_box(&mut ftyp, b"ftyp", |b| {
+            b.extend_from_slice(b"isom");
+            b.extend_from_slice(&0x200u32.to_be_bytes());
+            b.extend_from_slice(b"isom");
+            b.extend_from_slice(b"iso6");
+            b.extend_from_slice(b"mp41");
+            if has_av1 {
+                b.extend_from_slice(b"av01");
+            }
+        });
+        writer.write_all(&ftyp)?;
+
+        let mdat_start = start + ftyp.len() as u64;
+        write_mdat_header(&mut writer, 0)?;
+
+        let tracks = config
+            .tracks
+            .into_iter()
+            .map(|config| TrackState {
+                config,
+                samples: Vec::new(),
+                chunks: Vec::new(),
+                parser: Av1Parser::new(),
+            })
+            .collect();
+
+        Ok(Self {
+            writer,
+            tracks,
+            shift,
+            mdat_start,
+            position: mdat_start + MDAT_HEADER_SIZE,
+            last_track: None,
+        })
+    }
+
+    /// Get number of samples written to a track
+    pub fn sample_count(&self, stream_index: usize) -> Option<usize> {
+        self.tracks.get(stream_index).map(|t| t.samples.len())
+    }
+
+    /// Build the `moov` box, adding `shift` to every chunk offset
+    fn build_moov(&self, shift: u64, av1c: &[Option<Vec<u8>>]) -> Vec<u8> {
+        let durations: Vec<Vec<u32>> = self.tracks.iter().map(TrackState::durations).collect();
+        let media_durations: Vec<u64> = durations
+            .iter()
+            .map(|d| d.iter().map(|&x| u64::from(x)).sum())
+            .collect();
+        // Track durations cover what the edit list presents
+        let movie_durations: Vec<u64> = self
+            .tracks
+            .iter()
+            .zip(&media_durations)
+            .map(|(t, &d)| {
+                let presented = d.saturating_sub(edit_media_time(t).max(0) as u64);
+                presented * u64::from(MOVIE_TIMESCALE) / u64::from(t.config.timescale())
+            })
+            .collect();
+
+        let mut buf = Vec::new();
+        write_box(&mut buf, b"moov", |b| {
+            write_full_box(b, b"mvhd", 0, 0, |b| {
+                b.extend_from_slice(&0u32.to_be_bytes()); // creation_time
+                b.extend_from_slice(&0u32.to_be_bytes()); // modification_time
+                b.extend_from_slice(&MOVIE_TIMESCALE.to_be_bytes());
+                let duration = movie_durations.iter().copied().max().unwrap_or(0);
+                b.extend_from_slice(&(duration.min(u64::from(u32::MAX)) as u32).to_be_bytes());
+                b.extend_from_slice(&0x0001_0000u32.to_be_bytes()); // rate 1.0
+                b.extend_from_slice(&0x0100u16.to_be_bytes()); // volume 1.0
+                b.extend_from_slice(&[0u8; 10]); // reserved
+                write_matrix(b);
+                b.extend_from_slice(&[0u8; 24]); // pre_defined
+                b.extend_from_slice(&(self.tracks.len() as u32 + 1).to_be_bytes()); // next_track_ID
+            });
+
+            for (index, track) in self.tracks.iter().enumerate() {
+                let track_id = index as u32 + 1;
+                let av1c = av1c[index].as_deref();
+                write_box(b, b"trak", |b| {
+                    write_tkhd(b, track_id, &track.config, movie_durations[index]);
+                    write_edts(b, track, movie_durations[index]);
+                    write_box(b, b"mdia", |b| {
+                        write_mdhd(b, track.config.timescale(), media_durations[index]);
+                        write_hdlr(b, &track.config);
+                        write_box(b, b"minf", |b| {
+                            if track.config.is_video() {
+                                write_full_box(b, b"vmhd", 0, 1, |b| {
+                                    b.extend_from_slice(&[0u8; 8]); // graphicsmode + opcolor
+                                });
+                            } else {
+                                write_full_box(b, b"smhd", 0, 0, |b| {
+                                    b.extend_from_slice(&[0u8; 4]); // balance + reserved
+                                });
+                            }
+                            write_box(b, b"dinf", |b| {
+                                write_full_box(b, b"dref", 0, 0, |b| {
+                                    b.extend_from_slice(&1u32.to_be_bytes());
+                                    write_full_box(b, b"url ", 0, 1, |_| {}); // self-contained
+                                });
+                            });
+                            write_stbl(b, track, &durations[index], shift, av1c);
+                        });
+                    });
+                });
+            }
+        });
+
+        buf
+    }
+}
+
+impl<W: Write + Seek> std::fmt::Debug for Mp4Muxer<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Mp4Muxer")
+            .field(
+                "tracks",
+                &self.tracks.iter().map(|t| &t.config).collect::<Vec<_>>(),
+            )
+            .field("faststart", &self.shift.is_some())
+            .field("position", &self.position)
+            .finish()
+    }
+}
+
+impl<W: Write + Seek> Muxer for Mp4Muxer<W> {
+    fn write_packet(&mut self, packet: Packet) -> Result<()> {
+        let stream_index = packet.stream_index;
+        let track = self.tracks.get_mut(stream_index).ok_or_else(|| {
+            Error::InvalidInput(format!("MP4 muxer has no stream index {}", stream_index))
+        })?;
+
+        let sample_index = track.samples.len() as i64;
+
+        let (data, dts, cts_offset, duration, is_sync) = match track.config {
+            Mp4TrackConfig::Av1 { fps_den, .. } => {
+                let is_sync = track
+                    .parser
+                    .parse_temporal_unit(&packet.data)?
+                    .is_keyframe();
+                let data = prepare_av1_sample(packet.data)?;
+
+                let scale = i64::from(fps_den);
+                let pts = packet.pts.unwrap_or(sample_index);
+                let dts = packet.dts.or(packet.pts).unwrap_or(sample_index);
+                (data, dts * scale, (pts - dts) * scale, None, is_sync)
+            }
+            Mp4TrackConfig::Opus { .. } => {
+                let duration = opus::packet_duration(&packet.data).ok_or_else(|| {
+                    Error::InvalidInput(format!("Malformed Opus packet in stream {}", stream_index))
+                })?;
+                let dts = packet.pts.unwrap_or_else(|| track.next_dts());
+                (packet.data, dts, 0, Some(duration), true)
+            }
+        };
+
+        if let Some(last) = track.samples.last() {
+            if dts <= last.dts {
+                return Err(Error::InvalidInput(format!(
+                    "Non-monotonic timestamp {} after {} in stream {}",
+                    dts, last.dts, stream_index
+                )));
+            }
+        }
+
+        let size = u32::try_from(data.len()).map_err(|_| {
+            Error::InvalidInput(format!("MP4 sample of {} bytes is too large", data.len()))
+        })?;
+
+        // Consecutive samples from the same track share a chunk
+        match track.chunks.last_mut() {
+            Some(chunk) if self.last_track == Some(stream_index) => chunk.sample_count += 1,
+            _ => track.chunks.push(Chunk {
+                offset: self.position,
+                sample_count: 1,
+            }),
+        }
+
+        track.samples.push(SampleInfo {
+            size,
+            dts,
+            cts_offset,
+            duration,
+            is_sync,
+        });
+
+        self.writer.write_all(&data)?;
+        self.position += u64::from(size);
+        self.last_track = Some(stream_index);
+
+        Ok(())
+    }
+
+    fn finalize(mut self) -> Result<()> {
+        let av1c = self
+            .tracks
+            .iter()
+            .enumerate()
+            .map(
+                |(index, track)| match (&track.config, track.parser.av1c()) {
+                    (Mp4TrackConfig::Av1 { .. }, Some(av1c)) => Ok(Some(av1c)),
+                    (Mp4TrackConfig::Av1 { .. }, None) => Err(Error::InvalidInput(format!(
+                        "No AV1 sequence header found in stream {}",
+                        index
+                    ))),
+                    _ => Ok(None),
+                },
+            )
+            .collect::<Result<Vec<_>>>()?;
+
+        let mdat_end = self.position;
+        let mdat_size = mdat_end - self.mdat_start;
+
+        if let Some(shift_forward) = self.shift {
+            // Offsets may switch from stco to co64 once shifted, so size twice
+            let estimate = self.build_moov(0, &av1c).len() as u64;
+            let mut moov = self.build_moov(estimate, &av1c);
+            if moov.len() as u64 != estimate {
+                moov = self.build_moov(moov.len() as u64, &av1c);
+            }
+            let shift = moov.len() as u64;
+
+            tracing::info!("Finalizing MP4 with faststart (moov: {} bytes)", shift);
+
+            shift_forward(&mut self.writer, self.mdat_start, mdat_end, shift)?;
+            self.writer.seek(SeekFrom::Start(self.mdat_start))?;
+            self.writer.write_all(&moov)?;
+            write_mdat_header(&mut self.writer, mdat_size)?;
+            self.writer.seek(SeekFrom::Start(mdat_end + shift))?;
+        } else {
+            let moov = self.build_moov(0, &av1c);
+
+            tracing::info!("Finalizing MP4 (moov: {} bytes)", moov.len());
+
+            self.writer.seek(SeekFrom::Start(self.mdat_start))?;
+            write_mdat_header(&mut self.writer, mdat_size)?;
+            self.writer.seek(SeekFrom::Start(mdat_end))?;
+            self.writer.write_all(&moov)?;
+        }
+
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Move the byte range `[start, end)` forward by `shift` bytes
+///
+/// Copies from the end backwards so the source is never overwritten
+/// before it has been read.
+fn shift_forward<W: Read + Write + Seek>(
+    writer: &mut W,
+    start: u64,
+    end: u64,
+    shift: u64,
+) -> Result<()> {
+    const BLOCK: u64 = 1 << 20;
+    let mut buf = vec![0u8; BLOCK as usize];
+    let mut remaining_end = end;
+
+    while remaining_end > start {
+        let len = (remaining_end - start).min(BLOCK);
+        let from = remaining_end - len;
+        let block = &mut buf[..len as usize];
+
+        writer.seek(SeekFrom::Start(from))?;
+        writer.read_exact(block)?;
+        writer.seek(SeekFrom::Start(from + shift))?;
+        writer.write_all(block)?;
+
+        remaining_end = from;
+    }
+
+    Ok(())
+}
+
+fn write_mdat_header<W: Write>(writer: &mut W, size: u64) -> Result<()> {
+    writer.write_all(&1u32.to_be_bytes())?; // size=1: 64-bit largesize follows
+    writer.write_all(b"mdat")?;
+    writer.write_all(&size.to_be_bytes())?;
+    Ok(())
+}
+
+fn write_box(buf: &mut Vec<u8>, fourcc: &[u8; 4], body: impl FnOnce(&mut Vec<u8>)) {
+    let start = buf.len();
+    buf.extend_from_slice(&[0u8; 4]);
+    buf.extend_from_slice(fourcc);
+    body(buf);
+    let size = (buf.len() - start) as u32;
+    buf[start..start + 4].copy_from_slice(&size.to_be_bytes());
+}
+
+fn write_full_box(
+    buf: &mut Vec<u8>,
+    fourcc: &[u8; 4],
+    version: u8,
+    flags: u32,
+    body: impl FnOnce(&mut Vec<u8>),
+) {
+    write_box(buf, fourcc, |b| {
+        b.extend_from_slice(&((u32::from(version) << 24) | (flags & 0x00FF_FFFF)).to_be_bytes());
+        body(b);
+    });
+}
+
+fn write_matrix(buf: &mut Vec<u8>) {
+    for value in [0x0001_0000u32, 0, 0, 0, 0x0001_0000, 0, 0, 0, 0x4000_0000] {
+        buf.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn write_tkhd(buf: &mut Vec<u8>, track_id: u32, config: &Mp4TrackConfig, duration: u64) {
+    // flags: track_enabled | track_in_movie
+    write_full_box(buf, b"tkhd", 0, 0x3, |b| {
+        b.extend_from_slice(&0u32.to_be_bytes()); // creation_time
+        b.extend_from_slice(&0u32.to_be_bytes()); // modification_time
+        b.extend_from_slice(&track_id.to_be_bytes());
+        b.extend_from_slice(&0u32.to_be_bytes()); // reserved
+        b.extend_from_slice(&(duration.min(u64::from(u32::MAX)) as u32).to_be_bytes());
+        b.extend_from_slice(&[0u8; 8]); // reserved
+        b.extend_from_slice(&0u16.to_be_bytes()); // layer
+        let (alternate_group, volume, width, height) = match *config {
+            Mp4TrackConfig::Av1 { width, height, .. } => (0u16, 0u16, width, height),
+            Mp4TrackConfig::Opus { .. } => (1, 0x0100, 0, 0),
+        };
+        b.extend_from_slice(&alternate_group.to_be_bytes());
+        b.extend_from_slice(&volume.to_be_bytes());
+        b.extend_from_slice(&0u16.to_be_bytes()); // reserved
+        write_matrix(b);
+        b.extend_from_slice(&(width << 16).to_be_bytes());
+        b.extend_from_slice(&(height << 16).to_be_bytes());
+    });
+}
+
+/// Media time presentation starts from: the Opus pre-skip or the first video pts
+fn edit_media_time(track: &TrackState) -> i64 {
+    match track.config {
+        Mp4TrackConfig::Opus { pre_skip, .. } => i64::from(pre_skip),
+        Mp4TrackConfig::Av1 { .. } => track
+            .samples
+            .first()
+            .map(|s| s.dts + s.cts_offset)
+            .unwrap_or(0),
+    }
+}
+
+/// Write an edit list trimming Opus pre-skip or a non-zero initial video pts
+///
+/// `segment_duration` is the presented duration in the movie timescale.
+fn write_edts(buf: &mut Vec<u8>, track: &TrackState, segment_duration: u64) {
+    let media_time = edit_media_time(track);
+    if media_time <= 0 {
+        return;
+    }
+
+    write_box(buf, b"edts", |b| {
+        write_full_box(b, b"elst", 0, 0, |b| {
+            b.extend_from_slice(&1u32.to_be_bytes());
+            b.extend_from_slice(&(segment_duration.min(u64::from(u32::MAX)) as u32).to_be_bytes());
+            b.extend_from_slice(&(media_time as i32).to_be_bytes());
+            b.extend_from_slice(&0x0001_0000u32.to_be_bytes()); // media_rate 1.0
+        });
+    });
+}
+
+fn write_mdhd(buf: &mut Vec<u8>, timescale: u32, duration: u64) {
+    match u32::try_from(duration) {
+        Ok(duration) => write_full_box(buf, b"mdhd", 0, 0, |b| {
+            b.extend_from_slice(&0u32.to_be_bytes()); // creation_time
+            b.extend_from_slice(&0u32.to_be_bytes()); // modification_time
+            b.extend_from_slice(&timescale.to_be_bytes());
+            b.extend_from_slice(&duration.to_be_bytes());
+            b.extend_from_slice(&0x55C4u16.to_be_bytes()); // language: "und"
+            b.extend_from_slice(&0u16.to_be_bytes()); // pre_defined
+        }),
+        Err(_) => write_full_box(buf, b"mdhd", 1, 0, |b| {
+            b.extend_from_slice(&0u64.to_be_bytes());
+            b.extend_from_slice(&0u64.to_be_bytes());
+            b.extend_from_slice(&timescale.to_be_bytes());
+            b.extend_from_slice(&duration.to_be_bytes());
+            b.extend_from_slice(&0x55C4u16.to_be_bytes());
+            b.extend_from_slice(&0u16.to_be_bytes());
+        }),
+    }
+}
+
+fn write_hdlr(buf: &mut Vec<u8>, config: &Mp4TrackConfig) {
+    let (handler, name): (&[u8; 4], &[u8]) = if config.is_video() {
+        (b"vide", b"VideoHandler\0")
+    } else {
+        (b"soun", b"SoundHandler\0")
+    };
+
+    write_full_box(buf, b"hdlr", 0, 0, |b| {
+        b.extend_from_slice(&0u32.to_be_bytes()); // pre_defined
+        b.extend_from_slice(handler);
+        b.extend_from_slice(&[0u8; 12]); // reserved
+        b.extend_from_slice(name);
+    });
+}
+
+fn write_stbl(
+    buf: &mut Vec<u8>,
+    track: &TrackState,
+    durations: &[u32],
+    shift: u64,
+    av1c: Option<&[u8]>,
+) {
+    write_box(buf, b"stbl", |b| {
+        write_full_box(b, b"stsd", 0, 0, |b| {
+            b.extend_from_slice(&1u32.to_be_bytes());
+            write_sample_entry(b, &track.config, av1c, track.parser.colorimetry().as_ref());
+        });
+
+        // Decode-time deltas, run-length encoded
+        let mut stts: Vec<(u32, u32)> = Vec::new();
+        for &duration in durations {
+            match stts.last_mut() {
+                Some((count, delta)) if *delta == duration => *count += 1,
+                _ => stts.push((1, duration)),
+            }
+        }
+        write_full_box(b, b"stts", 0, 0, |b| {
+            b.extend_from_slice(&(stts.len() as u32).to_be_bytes());
+            for (count, delta) in &stts {
+                b.extend_from_slice(&count.to_be_bytes());
+                b.extend_from_slice(&delta.to_be_bytes());
+            }
+        });
+
+        // Composition offsets, only when pts differs from dts
+        if track.samples.iter().any(|s| s.cts_offset != 0) {
+            let mut ctts: Vec<(u32, i64)> = Vec::new();
+            for sample in &track.samples {
+                match ctts.last_mut() {
+                    Some((count, offset)) if *offset == sample.cts_offset => *count += 1,
+                    _ => ctts.push((1, sample.cts_offset)),
+                }
+            }
+            let version = u8::from(ctts.iter().any(|&(_, offset)| offset < 0));
+            write_full_box(b, b"ctts", version, 0, |b| {
+                b.extend_from_slice(&(ctts.len() as u32).to_be_bytes());
+                for (count, offset) in &ctts {
+                    b.extend_from_slice(&count.to_be_bytes());
+                    b.extend_from_slice(&(*offset as i32).to_be_bytes());
+                }
+            });
+        }
+
+        // Sync samples, omitted when every sample is a sync sample
+        if track.samples.iter().any(|s| !s.is_sync) {
+            let sync: Vec<u32> = track
+                .samples
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.is_sync)
+                .map(|(i, _)| i as u32 + 1)
+                .collect();
+            write_full_box(b, b"stss", 0, 0, |b| {
+                b.extend_from_slice(&(sync.len() as u32).to_be_bytes());
+                for number in &sync {
+                    b.extend_from_slice(&number.to_be_bytes());
+                }
+            });
+        }
+
+        // Sample-to-chunk, one entry per change in samples-per-chunk
+        let mut stsc: Vec<(u32, u32)> = Vec::new();
+        for (index, chunk) in track.chunks.iter().enumerate() {
+            if stsc.last().map(|&(_, count)| count) != Some(chunk.sample_count) {
+                stsc.push((index as u32 + 1, chunk.sample_count));
+            }
+        }
+        write_full_box(b, b"stsc", 0, 0, |b| {
+            b.extend_from_slice(&(stsc.len() as u32).to_be_bytes());
+            for (first_chunk, samples_per_chunk) in &stsc {
+                b.extend_from_slice(&first_chunk.to_be_bytes());
+                b.extend_from_slice(&samples_per_chunk.to_be_bytes());
+                b.extend_from_slice(&1u32.to_be_bytes()); // sample_description_index
+            }
+        });
+
+        write_full_box(b, b"stsz", 0, 0, |b| {
+            b.extend_from_slice(&0u32.to_be_bytes()); // sample_size: sizes follow
+            b.extend_from_slice(&(track.samples.len() as u32).to_be_bytes());
+            for sample in &track.samples {
+                b.extend_from_slice(&sample.size.to_be_bytes());
+            }
+        });
+
+        let offsets: Vec<u64> = track.chunks.iter().map(|c| c.offset + shift).collect();
+        if offsets.iter().all(|&o| o <= u64::from(u32::MAX)) {
+            write_full_box(b, b"stco", 0, 0, |b| {
+                b.extend_from_slice(&(offsets.len() as u32).to_be_bytes());
+                for offset in &offsets {
+                    b.extend_from_slice(&(*offset as u32).to_be_bytes());
+                }
+            });
+        } else {
+            write_full_box(b, b"co64", 0, 0, |b| {
+                b.extend_from_slice(&(offsets.len() as u32).to_be_bytes());
+                for offset in &offsets {
+                    b.extend_from_slice(&offset.to_be_bytes());
+                }
+            });
+        }
+    });
+}
+
+fn write_sample_entry(
+    buf: &mut Vec<u8>,
+    config: &Mp4TrackConfig,
+    av1c: Option<&[u8]>,
+    colorimetry: Option<&Colorimetry>,
+) {
+    match *config {
+        Mp4TrackConfig::Av1 { width, height, .. } => {
+            write_box(buf, b"av01", |b| {
+                b.extend_from_slice(&[0u8; 6]); // reserved
+                b.extend_from_slice(&1u16.to_be_bytes()); // data_reference_index
+                b.extend_from_slice(&[0u8; 16]); // pre_defined + reserved
+                b.extend_from_slice(&(width as u16).to_be_bytes());
+                b.extend_from_slice(&(height as u16).to_be_bytes());
+                b.extend_from_slice(&0x0048_0000u32.to_be_bytes()); // 72 dpi
+                b.extend_from_slice(&0x0048_0000u32.to_be_bytes());
+                b.extend_from_slice(&0u32.to_be_bytes()); // reserved
+                b.extend_from_slice(&1u16.to_be_bytes()); // frame_count
+                let mut compressor = [0u8; 32];
+                let name = b"AOM Coding";
+                compressor[0] = name.len() as u8;
+                compressor[1..=name.len()].copy_from_slice(name);
+                b.extend_from_slice(&compressor);
+                b.extend_from_slice(&0x0018u16.to_be_bytes()); // depth
+                b.extend_from_slice(&(-1i16).to_be_bytes()); // pre_defined
+                if let Some(av1c) = av1c {
+                    write_box(b, b"av1C", |b| b.extend_from_slice(av1c));
+                }
+                if let Some(colorimetry) = colorimetry {
+                    write_color_boxes(b, colorimetry);
+                }
+            });
+        }
+        Mp4TrackConfig::Opus {
+            channels,
+            input_sample_rate,
+            pre_skip,
+        } => {
+            write_box(buf, b"Opus", |b| {
+                b.extend_from_slice(&[0u8; 6]); // reserved
+                b.extend_from_slice(&1u16.to_be_bytes()); // data_reference_index
+                b.extend_from_slice(&[0u8; 8]); // reserved
+                b.extend_from_slice(&u16::from(channels).to_be_bytes());
+                b.extend_from_slice(&16u16.to_be_bytes()); // samplesize
+                b.extend_from_slice(&[0u8; 4]); // pre_defined + reserved
+                b.extend_from_slice(&(48000u32 << 16).to_be_bytes());
+                write_box(b, b"dOps", |b| {
+                    b.push(0); // version
+                    b.push(channels);
+                    b.extend_from_slice(&pre_skip.to_be_bytes());
+                    b.extend_from_slice(&input_sample_rate.to_be_bytes());
+                    b.extend_from_slice(&0i16.to_be_bytes()); // output gain
+                    b.push(0); // channel mapping family
+                });
+            });
+        }
+    }
+}
+
+/// Write `colr` (nclx), `mdcv` and `clli` boxes for a visual sample entry
+fn write_color_boxes(buf: &mut Vec<u8>, colorimetry: &Colorimetry) {
+    if colorimetry.has_color_description() || colorimetry.range == ColorRange::Full {
+        write_box(buf, b"colr", |b| {
+            b.extend_from_slice(b"nclx");
+            b.extend_from_slice(&u16::from(colorimetry.primaries.code()).to_be_bytes());
+            b.extend_from_slice(&u16::from(colorimetry.transfer.code()).to_be_bytes());
+            b.extend_from_slice(&u16::from(colorimetry.matrix.code()).to_be_bytes());
+            b.push(if colorimetry.range == ColorRange::Full {
+                0x80
+            } else {
+                0
+            });
+        });
+    }
+
+    // Same layout as the HEVC SEI: G, B, R primaries in 0.00002 units and
+    // luminance in 0.0001 cd/m² units
+    if let Some(display) = &colorimetry.mastering_display {
+        write_box(buf, b"mdcv", |b| {
+            let [red, green, blue] = display.primaries_xy();
+            for (x, y) in [green, blue, red, display.white_point_xy()] {
+                b.extend_from_slice(&((x * 50000.0).round() as u16).to_be_bytes());
+                b.extend_from_slice(&((y * 50000.0).round() as u16).to_be_bytes());
+            }
+            b.extend_from_slice(
+                &((display.max_luminance_nits() * 10000.0).round() as u32).to_be_bytes(),
+            );
+            b.extend_from_slice(
+                &((display.min_luminance_nits() * 10000.0).round() as u32).to_be_bytes(),
+            );
+        });
+    }
+
+    if let Some(level) = &colorimetry.content_light {
+        write_box(buf, b"clli", |b| {
+            b.extend_from_slice(&level.max_cll.to_be_bytes());
+            b.extend_from_slice(&level.max_fall.to_be_bytes());
+        });
+    }
+}
+
+/// Strip temporal delimiter and padding OBUs from an AV1 temporal unit
+///
+/// Both ISOBMFF and Matroska store temporal units without them.
+pub(super) fn prepare_av1_sample(data: Vec<u8>) -> Result<Vec<u8>> {
+    let obus = parse_obus(&data)?;
+    let is_stripped = |obu: &Obu<'_>| {
+        matches!(
+            obu.obu_type(),
+            ObuType::TemporalDelimiter | ObuType::Padding
+        )
+    };
+
+    if !obus.iter().any(is_stripped) {
+        return Ok(data);
+    }
+    Ok(obus
+        .iter()
+        .filter(|obu| !is_stripped(obu))
+        .flat_map(|obu| obu.data.iter().copied())
+        .collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::codec::av1::obu::test_data::{TD_AND_SEQUENCE_HEADER, av1_packet};
     use std::io::Cursor;
 
     #[test]
@@ -256,6 +1140,248 @@ mod tests {
         }
     }
 
+    fn mux_test_file(faststart: bool) -> Vec<u8> {
+        let config = Mp4MuxerConfig {
+            tracks: vec![
+                Mp4TrackConfig::Av1 {
+                    width: 64,
+                    height: 64,
+                    fps_num: 30,
+                    fps_den: 1,
+                },
+                Mp4TrackConfig::Opus {
+                    channels: 2,
+                    input_sample_rate: 48000,
+                    pre_skip: 312,
+                },
+            ],
+        };
+
+        let mut cursor = Cursor::new(Vec::new());
+        if faststart {
+            write_test_packets(Mp4Muxer::new_faststart(&mut cursor, config).unwrap());
+        } else {
+            // BufWriter cannot read, which only faststart needs
+            write_test_packets(
+                Mp4Muxer::new(std::io::BufWriter::new(&mut cursor), config).unwrap(),
+            );
+        }
+        cursor.into_inner()
+    }
+
+    fn write_test_packets<W: Write + Seek>(mut muxer: Mp4Muxer<W>) {
+        for i in 0..4 {
+            // Sync samples come from the AV1 frame headers, not the packet flag
+            let packet = Packet {
+                is_keyframe: true,
+                ..av1_packet(i, i == 0)
+            };
+            muxer.write_packet(packet).unwrap();
+            muxer
+                .write_packet(Packet {
+                    stream_index: 1,
+                    data: vec![0xFC, i as u8], // CELT 20 ms
+                    pts: None,
+                    dts: None,
+                    is_keyframe: true,
+                })
+                .unwrap();
+        }
+        muxer.finalize().unwrap();
+    }
+
+    /// Top-level boxes as (type, offset, size)
+    fn top_level_boxes(data: &[u8]) -> Vec<([u8; 4], usize, usize)> {
+        let mut boxes = Vec::new();
+        let mut pos = 0;
+        while pos + 8 <= data.len() {
+            let mut size = u32::from_be_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            if size == 1 {
+                size = u64::from_be_bytes(data[pos + 8..pos + 16].try_into().unwrap()) as usize;
+            }
+            boxes.push((data[pos + 4..pos + 8].try_into().unwrap(), pos, size));
+            pos += size;
+        }
+        assert_eq!(pos, data.len(), "boxes must exactly cover the file");
+        boxes
+    }
+
+    /// First chunk offset of each track, in trak order
+    fn chunk_offsets(data: &[u8]) -> Vec<u32> {
+        data.windows(4)
+            .enumerate()
+            .filter(|(_, w)| *w == b"stco")
+            .map(|(i, _)| u32::from_be_bytes(data[i + 12..i + 16].try_into().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_mp4_muxer_faststart_layout() {
+        let data = mux_test_file(true);
+        let boxes = top_level_boxes(&data);
+        let order: Vec<&[u8; 4]> = boxes.iter().map(|(t, _, _)| t).collect();
+        assert_eq!(order, vec![b"ftyp", b"moov", b"mdat"]);
+
+        // First video sample: sequence header + frame, temporal delimiter stripped
+        let offsets = chunk_offsets(&data);
+        assert_eq!(offsets.len(), 2);
+        let video = offsets[0] as usize;
+        assert_eq!(&data[video..video + 12], &TD_AND_SEQUENCE_HEADER[2..]);
+        assert_eq!(
+            &data[video + 12..video + 17],
+            &[0x32, 0x03, 0x10, 0x00, 0xAA]
+        );
+
+        let audio = offsets[1] as usize;
+        assert_eq!(&data[audio..audio + 2], &[0xFC, 0x00]);
+    }
+
+    #[test]
+    fn test_mp4_muxer_without_faststart() {
+        let data = mux_test_file(false);
+        let boxes = top_level_boxes(&data);
+        let order: Vec<&[u8; 4]> = boxes.iter().map(|(t, _, _)| t).collect();
+        assert_eq!(order, vec![b"ftyp", b"mdat", b"moov"]);
+
+        let offsets = chunk_offsets(&data);
+        assert_eq!(
+            &data[offsets[1] as usize..offsets[1] as usize + 2],
+            &[0xFC, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_mp4_muxer_sample_entries() {
+        let data = mux_test_file(true);
+        let contains = |needle: &[u8]| data.windows(needle.len()).any(|w| w == needle);
+
+        assert!(contains(b"av01"));
+        assert!(contains(&[b'a', b'v', b'1', b'C', 0x81, 0x1f, 0x0c, 0x00]));
+        assert!(contains(b"dOps"));
+        // Only the first video sample is a sync sample
+        assert!(contains(&[
+            b's', b't', b's', b's', 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1
+        ]));
+        // Opus pre-skip is applied through an edit list
+        assert!(contains(b"elst"));
+    }
+
+    #[test]
+    fn test_mp4_muxer_trimmed_durations() {
+        let data = mux_test_file(true);
+        let field = |fourcc: &[u8], nth: usize, offset: usize| {
+            let at = data
+                .windows(4)
+                .enumerate()
+                .filter(|(_, w)| *w == fourcc)
+                .nth(nth)
+                .unwrap()
+                .0;
+            u32::from_be_bytes(data[at + offset..at + offset + 4].try_into().unwrap())
+        };
+
+        // Four 20 ms Opus packets less 312 samples of pre-skip: 73 ms in
+        // both the Opus track header and its edit list
+        let tkhd_duration = field(b"tkhd", 1, 24);
+        let elst_duration = field(b"elst", 0, 12);
+        assert_eq!((tkhd_duration, elst_duration), (73, 73));
+    }
+
+    #[test]
+    fn test_mp4_muxer_hdr_boxes() {
+        let config = Mp4MuxerConfig {
+            tracks: vec![Mp4TrackConfig::Av1 {
+                width: 64,
+                height: 64,
+                fps_num: 30,
+                fps_den: 1,
+            }],
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        let mut muxer = Mp4Muxer::new(&mut cursor, config).unwrap();
+
+        // HDR_CLL metadata OBU (MaxCLL 1000, MaxFALL 400) in the first temporal unit
+        let mut packet = av1_packet(0, true);
+        packet
+            .data
+            .extend_from_slice(&[0x2a, 0x05, 0x01, 0x03, 0xe8, 0x01, 0x90]);
+        muxer.write_packet(packet).unwrap();
+        muxer.finalize().unwrap();
+
+        let data = cursor.into_inner();
+        let contains = |needle: &[u8]| data.windows(needle.len()).any(|w| w == needle);
+        assert!(contains(&[
+            0, 0, 0, 12, b'c', b'l', b'l', b'i', 0x03, 0xe8, 0x01, 0x90
+        ]));
+        // The test sequence header has no color description
+        assert!(!contains(b"colr") && !contains(b"mdcv"));
+    }
+
+    #[test]
+    fn test_mp4_color_boxes() {
+        let colorimetry = Colorimetry {
+            primaries: crate::color::ColorPrimaries::Bt2020,
+            transfer: crate::color::TransferCharacteristics::Smpte2084,
+            matrix: crate::color::MatrixCoefficients::Bt2020Ncl,
+            mastering_display: Some(
+                "G(0.17,0.797)B(0.131,0.046)R(0.708,0.292)WP(0.3127,0.329)L(1000,0.0001)"
+                    .parse()
+                    .unwrap(),
+            ),
+            ..Default::default()
+        };
+        let mut buf = Vec::new();
+        write_color_boxes(&mut buf, &colorimetry);
+
+        assert_eq!(
+            &buf[..19],
+            &[
+                0, 0, 0, 19, b'c', b'o', b'l', b'r', b'n', b'c', b'l', b'x', 0, 9, 0, 16, 0, 9, 0
+            ]
+        );
+        let mdcv = &buf[19..];
+        assert_eq!(&mdcv[..8], &[0, 0, 0, 32, b'm', b'd', b'c', b'v']);
+        // Green primary first, 0.00002 units
+        assert_eq!(u16::from_be_bytes([mdcv[8], mdcv[9]]), 8500);
+        assert_eq!(u16::from_be_bytes([mdcv[10], mdcv[11]]), 39850);
+        assert_eq!(
+            u32::from_be_bytes(mdcv[24..28].try_into().unwrap()),
+            10_000_000
+        );
+        assert_eq!(u32::from_be_bytes(mdcv[28..32].try_into().unwrap()), 1);
+    }
+
+    #[test]
+    fn test_mp4_muxer_rejects_bad_input() {
+        let config = || Mp4MuxerConfig {
+            tracks: vec![Mp4TrackConfig::Av1 {
+                width: 64,
+                height: 64,
+                fps_num: 30,
+                fps_den: 1,
+            }],
+        };
+
+        // Unknown stream index
+        let mut muxer = Mp4Muxer::new(Cursor::new(Vec::new()), config()).unwrap();
+        let mut packet = av1_packet(0, true);
+        packet.stream_index = 3;
+        assert!(muxer.write_packet(packet).is_err());
+
+        // Non-monotonic timestamps
+        muxer.write_packet(av1_packet(1, true)).unwrap();
+        assert!(muxer.write_packet(av1_packet(1, false)).is_err());
+
+        // No sequence header in the stream
+        let mut muxer = Mp4Muxer::new(Cursor::new(Vec::new()), config()).unwrap();
+        muxer.write_packet(av1_packet(0, false)).unwrap();
+        assert!(muxer.finalize().is_err());
+
+        // Empty track list
+        let empty = Mp4MuxerConfig { tracks: Vec::new() };
+        assert!(Mp4Muxer::new(Cursor::new(Vec::new()), empty).is_err());
+    }
+
     fn create_large_test_mp4(size: usize) -> Vec<u8> {
         // Create a large test file that looks like MP4 but isn't valid
         // This is for testing memory usage with large inputs
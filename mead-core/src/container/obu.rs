@@ -0,0 +1,668 @@
+//! Raw AV1 elementary streams: low-overhead OBU (`.obu`) and Annex B
+//!
+//! The low-overhead format (AV1 spec section 5) is a plain sequence of OBUs,
+//! each carrying its own size field; temporal units are delimited by
+//! temporal delimiter OBUs. Annex B instead prefixes every temporal unit,
+//! frame unit and OBU with a LEB128 length, and OBUs usually omit their size
+//! field.
+//!
+//! Neither format carries timestamps, so packets are numbered in temporal
+//! units, the same frame-unit timebase [`IvfMuxer`](super::ivf::IvfMuxer)
+//! uses. Packet data is always in low-overhead form, starting with a
+//! temporal delimiter.
+
+use super::{Demuxer, Metadata, Muxer, Packet};
+use crate::codec::av1::obu::{Av1Parser, Obu, ObuType, parse_obus, read_leb128, write_leb128};
+use crate::{Error, MediaSource, Result};
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// `obu_has_size_field` bit in the OBU header
+const OBU_HAS_SIZE_FIELD: u8 = 0x02;
+
+/// `obu_extension_flag` bit in the OBU header
+const OBU_EXTENSION_FLAG: u8 = 0x04;
+
+/// A temporal delimiter OBU in low-overhead form
+const TEMPORAL_DELIMITER: [u8; 2] = [0x12, 0x00];
+
+/// Upper bound on a single OBU or temporal unit (guards corrupt sizes)
+const MAX_UNIT_SIZE: u64 = 256 * 1024 * 1024;
+
+/// AV1 elementary stream framing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ObuFormat {
+    /// Low-overhead bitstream format (section 5), as written by most encoders
+    #[default]
+    LowOverhead,
+    /// Length-delimited bitstream format (Annex B), used by conformance streams
+    AnnexB,
+}
+
+impl ObuFormat {
+    fn name(self) -> &'static str {
+        match self {
+            Self::LowOverhead => "OBU",
+            Self::AnnexB => "Annex B",
+        }
+    }
+}
+
+/// Demuxer for raw AV1 OBU and Annex B streams
+///
+/// Returns one packet per temporal unit. The framing is detected from the
+/// first bytes of the stream unless given explicitly with
+/// [`with_format`](Self::with_format). Never seeks, so it works with stdin.
+///
+/// # Example
+/// ```no_run
+/// use mead_core::container::obu::ObuDemuxer;
+/// use mead_core::container::Demuxer;
+/// use std::fs::File;
+///
+/// let file = File::open("input.obu")?;
+/// let mut demuxer = ObuDemuxer::new(file)?;
+///
+/// while let Some(packet) = demuxer.read_packet()? {
+///     // Decode temporal unit
+/// }
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+pub struct ObuDemuxer<R: MediaSource> {
+    reader: BufReader<R>,
+    format: ObuFormat,
+    metadata: Metadata,
+    /// OBUs of the temporal unit being assembled (low-overhead only)
+    current: Vec<u8>,
+    units_read: u64,
+    parser: Av1Parser,
+}
+
+impl<R: MediaSource> ObuDemuxer<R> {
+    /// Create a new demuxer, detecting low-overhead or Annex B framing
+    pub fn new(source: R) -> Result<Self> {
+        let mut reader = BufReader::new(source);
+        let format = detect_format(reader.fill_buf()?)?;
+        Ok(Self::from_reader(reader, format))
+    }
+
+    /// Create a new demuxer for a known framing
+    pub fn with_format(source: R, format: ObuFormat) -> Self {
+        Self::from_reader(BufReader::new(source), format)
+    }
+
+    fn from_reader(reader: BufReader<R>, format: ObuFormat) -> Self {
+        tracing::info!("{} stream opened", format.name());
+
+        Self {
+            reader,
+            format,
+            metadata: Metadata {
+                duration_ms: None,
+                stream_count: 1,
+                format: format.name().to_string(),
+            },
+            current: Vec::new(),
+            units_read: 0,
+            parser: Av1Parser::new(),
+        }
+    }
+
+    /// Get the stream framing
+    pub fn format(&self) -> ObuFormat {
+        self.format
+    }
+
+    /// Get number of temporal units read so far
+    pub fn units_read(&self) -> u64 {
+        self.units_read
+    }
+
+    /// Get the stream's parser, which holds the latest sequence header
+    pub fn parser(&self) -> &Av1Parser {
+        &self.parser
+    }
+
+    /// Read one low-overhead OBU, returning `None` on a clean EOF
+    fn read_obu(&mut self) -> Result<Option<Vec<u8>>> {
+        let header = match read_byte(&mut self.reader)? {
+            Some(byte) => byte,
+            None => return Ok(None),
+        };
+        if header & 0x80 != 0 {
+            return Err(Error::ContainerParse(
+                "AV1 OBU forbidden bit set".to_string(),
+            ));
+        }
+        if header & OBU_HAS_SIZE_FIELD == 0 {
+            return Err(Error::ContainerParse(
+                "Low-overhead OBU without size field".to_string(),
+            ));
+        }
+
+        let mut obu = vec![header];
+        if header & OBU_EXTENSION_FLAG != 0 {
+            obu.push(read_byte(&mut self.reader)?.ok_or_else(truncated)?);
+        }
+        let size = read_stream_leb128(&mut self.reader, &mut obu)?.ok_or_else(truncated)?;
+        read_exactly(&mut self.reader, size, &mut obu)?;
+
+        Ok(Some(obu))
+    }
+
+    fn read_low_overhead(&mut self) -> Result<Option<Vec<u8>>> {
+        loop {
+            match self.read_obu()? {
+                None if self.current.is_empty() => return Ok(None),
+                None => return Ok(Some(std::mem::take(&mut self.current))),
+                Some(obu) => {
+                    let obu_type = ObuType::from_u8((obu[0] >> 3) & 0x0F);
+                    if obu_type == ObuType::TemporalDelimiter && !self.current.is_empty() {
+                        return Ok(Some(std::mem::replace(&mut self.current, obu)));
+                    }
+                    self.current.extend_from_slice(&obu);
+                    if self.current.len() as u64 > MAX_UNIT_SIZE {
+                        return Err(Error::ContainerParse(format!(
+                            "AV1 temporal unit exceeds {} bytes",
+                            MAX_UNIT_SIZE
+                        )));
+                    }
+                }
+            }
+        }
+    }
+
+    fn read_annexb(&mut self) -> Result<Option<Vec<u8>>> {
+        let size = match read_stream_leb128(&mut self.reader, &mut Vec::new())? {
+            Some(size) => size,
+            None => return Ok(None),
+        };
+        let mut unit = Vec::new();
+        read_exactly(&mut self.reader, size, &mut unit)?;
+        annexb_to_low_overhead(&unit).map(Some)
+    }
+}
+
+impl<R: MediaSource> std::fmt::Debug for ObuDemuxer<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObuDemuxer")
+            .field("format", &self.format)
+            .field("units_read", &self.units_read)
+            .finish()
+    }
+}
+
+impl<R: MediaSource> Demuxer for ObuDemuxer<R> {
+    fn read_packet(&mut self) -> Result<Option<Packet>> {
+        let data = match self.format {
+            ObuFormat::LowOverhead => self.read_low_overhead()?,
+            ObuFormat::AnnexB => self.read_annexb()?,
+        };
+        let Some(data) = data else {
+            return Ok(None);
+        };
+
+        let is_keyframe = self
+            .parser
+            .parse_temporal_unit(&data)
+            .map_err(into_parse_error)?
+            .is_keyframe();
+
+        let packet = Packet {
+            stream_index: 0,
+            data,
+            pts: Some(self.units_read as i64),
+            dts: None,
+            is_keyframe,
+        };
+        self.units_read += 1;
+
+        Ok(Some(packet))
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+/// Muxer writing AV1 temporal units as a raw OBU or Annex B stream
+///
+/// Accepts low-overhead temporal units (as produced by the AV1 encoders and
+/// [`ObuDemuxer`]); a temporal delimiter is inserted where one is missing.
+/// Timestamps are ignored since neither format can store them.
+///
+/// # Example
+/// ```no_run
+/// use mead_core::container::obu::{ObuFormat, ObuMuxer};
+/// use mead_core::container::{Muxer, Packet};
+/// use std::fs::File;
+///
+/// let file = File::create("output.obu")?;
+/// let mut muxer = ObuMuxer::new(file, ObuFormat::LowOverhead);
+///
+/// let packet = Packet {
+///     stream_index: 0,
+///     data: vec![/* AV1 temporal unit */],
+///     pts: Some(0),
+///     dts: None,
+///     is_keyframe: true,
+/// };
+/// muxer.write_packet(packet)?;
+///
+/// muxer.finalize()?;
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+pub struct ObuMuxer<W: Write> {
+    writer: W,
+    format: ObuFormat,
+    units_written: u64,
+}
+
+impl<W: Write> ObuMuxer<W> {
+    /// Create a new OBU stream muxer
+    pub fn new(writer: W, format: ObuFormat) -> Self {
+        tracing::info!("Creating {} muxer", format.name());
+
+        Self {
+            writer,
+            format,
+            units_written: 0,
+        }
+    }
+
+    /// Get number of temporal units written
+    pub fn units_written(&self) -> u64 {
+        self.units_written
+    }
+}
+
+impl<W: Write> std::fmt::Debug for ObuMuxer<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ObuMuxer")
+            .field("format", &self.format)
+            .field("units_written", &self.units_written)
+            .finish()
+    }
+}
+
+impl<W: Write> Muxer for ObuMuxer<W> {
+    fn write_packet(&mut self, packet: Packet) -> Result<()> {
+        if packet.stream_index != 0 {
+            return Err(Error::InvalidInput(format!(
+                "OBU muxer has no stream index {}",
+                packet.stream_index
+            )));
+        }
+
+        let obus = parse_obus(&packet.data)?;
+        let has_delimiter = obus
+            .first()
+            .is_some_and(|obu| obu.obu_type() == ObuType::TemporalDelimiter);
+
+        match self.format {
+            ObuFormat::LowOverhead => {
+                let mut unit = Vec::with_capacity(packet.data.len() + 2);
+                if !has_delimiter {
+                    unit.extend_from_slice(&TEMPORAL_DELIMITER);
+                }
+                for obu in &obus {
+                    push_obu(&mut unit, obu, true);
+                }
+                self.writer.write_all(&unit)?;
+            }
+            ObuFormat::AnnexB => {
+                let unit = low_overhead_to_annexb(&obus, !has_delimiter);
+                let mut size = Vec::new();
+                write_leb128(&mut size, unit.len() as u64);
+                self.writer.write_all(&size)?;
+                self.writer.write_all(&unit)?;
+            }
+        }
+
+        self.units_written += 1;
+        Ok(())
+    }
+
+    fn finalize(mut self) -> Result<()> {
+        tracing::info!(
+            "Finalizing {} stream: {} temporal units",
+            self.format.name(),
+            self.units_written
+        );
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Guess the framing from the first bytes of a stream
+fn detect_format(head: &[u8]) -> Result<ObuFormat> {
+    let is_delimiter = |header: u8| {
+        header & 0x80 == 0 && ObuType::from_u8((header >> 3) & 0x0F) == ObuType::TemporalDelimiter
+    };
+
+    let header = *head
+        .first()
+        .ok_or_else(|| Error::ContainerParse("Empty AV1 stream".to_string()))?;
+
+    // A low-overhead temporal delimiter has an obu_size of zero, which tells
+    // it apart from an Annex B temporal_unit_size that happens to look alike
+    if is_delimiter(header) && header & OBU_HAS_SIZE_FIELD != 0 {
+        let size_pos = 1 + usize::from(header & OBU_EXTENSION_FLAG != 0);
+        if head.get(size_pos) == Some(&0) {
+            return Ok(ObuFormat::LowOverhead);
+        }
+    }
+
+    // Annex B: temporal_unit_size, frame_unit_size, obu_length, then the
+    // temporal delimiter's header
+    let mut pos = 0;
+    for _ in 0..3 {
+        match read_leb128(&head[pos..]) {
+            Ok((_, len)) => pos += len,
+            Err(_) => break,
+        }
+    }
+    match head.get(pos) {
+        Some(&header) if pos > 0 && is_delimiter(header) => Ok(ObuFormat::AnnexB),
+        _ => Err(Error::ContainerParse(
+            "Unrecognized AV1 elementary stream".to_string(),
+        )),
+    }
+}
+
+/// Append an OBU, adding or removing its size field
+fn push_obu(out: &mut Vec<u8>, obu: &Obu<'_>, sized: bool) {
+    let header = obu.header_bytes();
+    if sized {
+        out.push(header[0] | OBU_HAS_SIZE_FIELD);
+        out.extend_from_slice(&header[1..]);
+        write_leb128(out, obu.payload.len() as u64);
+    } else {
+        out.push(header[0] & !OBU_HAS_SIZE_FIELD);
+        out.extend_from_slice(&header[1..]);
+    }
+    out.extend_from_slice(obu.payload);
+}
+
+/// Convert an Annex B temporal unit (without its size prefix) to low-overhead form
+fn annexb_to_low_overhead(unit: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(unit.len() + 16);
+    let mut pos = 0;
+
+    while pos < unit.len() {
+        let frame_unit = read_sized(unit, &mut pos)?;
+        let mut frame_pos = 0;
+        while frame_pos < frame_unit.len() {
+            let obu = read_sized(frame_unit, &mut frame_pos)?;
+            if obu.is_empty() {
+                return Err(Error::ContainerParse("Empty Annex B OBU".to_string()));
+            }
+            // An OBU without a size field spans the whole obu_length
+            for parsed in parse_obus(obu).map_err(into_parse_error)? {
+                push_obu(&mut out, &parsed, true);
+            }
+        }
+    }
+
+    Ok(out)
+}
+
+/// Convert a low-overhead temporal unit to an Annex B temporal unit body
+fn low_overhead_to_annexb(obus: &[Obu<'_>], add_delimiter: bool) -> Vec<u8> {
+    // Group OBUs into frame units: each frame (header) starts a new one,
+    // carrying along any sequence headers or metadata that precede it
+    let mut frame_units: Vec<Vec<u8>> = vec![Vec::new()];
+    let mut has_frame = false;
+
+    fn push(frame_unit: &mut Vec<u8>, obu_bytes: &[u8]) {
+        write_leb128(frame_unit, obu_bytes.len() as u64);
+        frame_unit.extend_from_slice(obu_bytes);
+    }
+
+    if add_delimiter {
+        push(
+            &mut frame_units[0],
+            &[TEMPORAL_DELIMITER[0] & !OBU_HAS_SIZE_FIELD],
+        );
+    }
+
+    let mut pending: Vec<Vec<u8>> = Vec::new();
+    for obu in obus {
+        let mut obu_bytes = Vec::with_capacity(obu.data.len());
+        push_obu(&mut obu_bytes, obu, false);
+
+        let starts_frame = matches!(obu.obu_type(), ObuType::Frame | ObuType::FrameHeader);
+        if starts_frame {
+            if has_frame {
+                frame_units.push(Vec::new());
+            }
+            has_frame = true;
+            let current = frame_units.last_mut().expect("at least one frame unit");
+            for held in pending.drain(..) {
+                push(current, &held);
+            }
+            push(current, &obu_bytes);
+        } else if has_frame
+            && !matches!(
+                obu.obu_type(),
+                ObuType::SequenceHeader | ObuType::TemporalDelimiter
+            )
+        {
+            // Tile groups, padding and metadata belong to the current frame
+            push(
+                frame_units.last_mut().expect("at least one frame unit"),
+                &obu_bytes,
+            );
+        } else {
+            pending.push(obu_bytes);
+        }
+    }
+
+    let current = frame_units.last_mut().expect("at least one frame unit");
+    for held in pending {
+        push(current, &held);
+    }
+
+    let size: usize = frame_units.iter().map(Vec::len).sum();
+    let mut unit = Vec::with_capacity(size + 8 * frame_units.len());
+    for frame_unit in frame_units.iter().filter(|fu| !fu.is_empty()) {
+        write_leb128(&mut unit, frame_unit.len() as u64);
+        unit.extend_from_slice(frame_unit);
+    }
+    unit
+}
+
+/// Read a LEB128-prefixed slice from `data`, advancing `pos`
+fn read_sized<'a>(data: &'a [u8], pos: &mut usize) -> Result<&'a [u8]> {
+    let (size, len) = read_leb128(&data[*pos..]).map_err(into_parse_error)?;
+    let start = *pos + len;
+    let end = usize::try_from(size)
+        .ok()
+        .and_then(|size| start.checked_add(size))
+        .filter(|&end| end <= data.len())
+        .ok_or_else(|| Error::ContainerParse("Truncated Annex B unit".to_string()))?;
+    *pos = end;
+    Ok(&data[start..end])
+}
+
+/// Read a LEB128 value from a stream, appending its bytes to `raw`
+///
+/// Returns `None` on a clean EOF before the first byte.
+fn read_stream_leb128<R: Read>(reader: &mut R, raw: &mut Vec<u8>) -> Result<Option<u64>> {
+    let mut value = 0u64;
+    for i in 0..8 {
+        let byte = match read_byte(reader)? {
+            Some(byte) => byte,
+            None if i == 0 => return Ok(None),
+            None => return Err(truncated()),
+        };
+        raw.push(byte);
+        value |= u64::from(byte & 0x7F) << (i * 7);
+        if byte & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+    }
+    Err(Error::ContainerParse(
+        "AV1 leb128 longer than 8 bytes".to_string(),
+    ))
+}
+
+fn read_byte<R: Read>(reader: &mut R) -> Result<Option<u8>> {
+    let mut byte = [0u8; 1];
+    loop {
+        match reader.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(Error::Io(e)),
+        }
+    }
+}
+
+/// Append exactly `size` bytes from the stream to `out`
+fn read_exactly<R: Read>(reader: &mut R, size: u64, out: &mut Vec<u8>) -> Result<()> {
+    if size > MAX_UNIT_SIZE {
+        return Err(Error::ContainerParse(format!(
+            "AV1 unit of {} bytes exceeds maximum",
+            size
+        )));
+    }
+
+    // Read through `take` so a corrupt size can't force a huge allocation
+    let read = reader.take(size).read_to_end(out)?;
+    if read as u64 != size {
+        return Err(truncated());
+    }
+    Ok(())
+}
+
+fn truncated() -> Error {
+    Error::ContainerParse("Truncated AV1 elementary stream".to_string())
+}
+
+/// Bitstream errors from the shared OBU helpers are framing errors here
+fn into_parse_error(error: Error) -> Error {
+    match error {
+        Error::InvalidInput(message) => Error::ContainerParse(message),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::av1::obu::test_data::{TD_AND_SEQUENCE_HEADER, temporal_unit};
+    use crate::io::ReadOnlySource;
+    use std::io::Cursor;
+
+    fn mux(format: ObuFormat, units: &[Vec<u8>]) -> Vec<u8> {
+        let mut output = Vec::new();
+        let mut muxer = ObuMuxer::new(&mut output, format);
+        for (i, data) in units.iter().enumerate() {
+            muxer
+                .write_packet(Packet {
+                    stream_index: 0,
+                    data: data.clone(),
+                    pts: Some(i as i64),
+                    dts: None,
+                    is_keyframe: false,
+                })
+                .unwrap();
+        }
+        muxer.finalize().unwrap();
+        output
+    }
+
+    fn read_all(data: Vec<u8>) -> (ObuFormat, Vec<Packet>) {
+        let mut demuxer = ObuDemuxer::new(ReadOnlySource::new(Cursor::new(data))).unwrap();
+        let mut packets = Vec::new();
+        while let Some(packet) = demuxer.read_packet().unwrap() {
+            packets.push(packet);
+        }
+        (demuxer.format(), packets)
+    }
+
+    #[test]
+    fn test_low_overhead_round_trip() {
+        let units: Vec<Vec<u8>> = (0..5).map(|i| temporal_unit(i, i % 4 == 0)).collect();
+        let data = mux(ObuFormat::LowOverhead, &units);
+        assert_eq!(data, units.concat());
+
+        let (format, packets) = read_all(data);
+        assert_eq!(format, ObuFormat::LowOverhead);
+        assert_eq!(packets.len(), 5);
+        for (i, packet) in packets.iter().enumerate() {
+            assert_eq!(packet.data, units[i]);
+            assert_eq!(packet.pts, Some(i as i64));
+            assert_eq!(packet.is_keyframe, i % 4 == 0);
+        }
+    }
+
+    #[test]
+    fn test_annexb_round_trip() {
+        let units: Vec<Vec<u8>> = (0..3).map(|i| temporal_unit(i, i == 0)).collect();
+        let data = mux(ObuFormat::AnnexB, &units);
+
+        // temporal_unit_size, frame_unit_size, obu_length, TD header without size
+        assert_eq!(&data[..4], &[20, 19, 1, 0x10]);
+
+        let (format, packets) = read_all(data);
+        assert_eq!(format, ObuFormat::AnnexB);
+        assert_eq!(packets.len(), 3);
+        for (packet, expected) in packets.iter().zip(&units) {
+            assert_eq!(&packet.data, expected);
+        }
+        assert!(packets[0].is_keyframe);
+        assert!(!packets[1].is_keyframe);
+    }
+
+    #[test]
+    fn test_annexb_frame_units() {
+        // Two frames in one temporal unit (e.g. a hidden ARF and a shown frame)
+        let mut unit = TD_AND_SEQUENCE_HEADER.to_vec();
+        unit.extend_from_slice(&[0x32, 0x01, 0x01, 0x32, 0x01, 0x02]);
+        let data = mux(ObuFormat::AnnexB, &[unit.clone()]);
+
+        let body = &data[1..];
+        let mut pos = 0;
+        let mut frame_units = Vec::new();
+        while pos < body.len() {
+            frame_units.push(read_sized(body, &mut pos).unwrap().len());
+        }
+        // TD + sequence header + frame, then the second frame alone
+        assert_eq!(frame_units, vec![(1 + 1) + (1 + 11) + (1 + 2), 1 + 2]);
+
+        assert_eq!(read_all(data).1[0].data, unit);
+    }
+
+    #[test]
+    fn test_muxer_inserts_temporal_delimiter() {
+        let frame = vec![0x32, 0x02, 0x01, 0x02];
+        let data = mux(ObuFormat::LowOverhead, std::slice::from_ref(&frame));
+        assert_eq!(&data[..2], &TEMPORAL_DELIMITER);
+        assert_eq!(&data[2..], &frame[..]);
+    }
+
+    #[test]
+    fn test_demuxer_rejects_bad_input() {
+        assert!(ObuDemuxer::new(Cursor::new(Vec::new())).is_err());
+        assert!(ObuDemuxer::new(Cursor::new(b"DKIF".to_vec())).is_err());
+
+        // Truncated OBU payload
+        let mut data = temporal_unit(0, true);
+        data.truncate(data.len() - 2);
+        let mut demuxer = ObuDemuxer::new(Cursor::new(data)).unwrap();
+        assert!(matches!(
+            demuxer.read_packet(),
+            Err(Error::ContainerParse(_))
+        ));
+
+        // Annex B temporal unit larger than the stream
+        let mut data = mux(ObuFormat::AnnexB, &[temporal_unit(0, false)]);
+        data[0] += 10;
+        let mut demuxer = ObuDemuxer::with_format(Cursor::new(data), ObuFormat::AnnexB);
+        assert!(matches!(
+            demuxer.read_packet(),
+            Err(Error::ContainerParse(_))
+        ));
+    }
+}
@@ -0,0 +1,942 @@
+//! Ogg container support for Opus streams (RFC 3533, RFC 7845)
+//!
+//! Ogg is a sequence of CRC-protected pages. Each page belongs to a logical
+//! stream (identified by its serial number) and carries packet fragments
+//! described by a lacing table. A page's granule position is the sample
+//! count, at 48 kHz for Opus, at the end of the last packet completed on it.
+//!
+//! The demuxer follows the first Opus stream in the file and ignores other
+//! multiplexed streams. Chained streams (a new stream after end-of-stream)
+//! are not followed.
+
+use super::{Demuxer, Metadata, Muxer, Packet};
+use crate::codec::opus::{self, OpusHead, OpusTags};
+use crate::{Error, MediaSource, Result};
+use std::collections::VecDeque;
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+
+/// Page capture pattern
+const CAPTURE_PATTERN: &[u8; 4] = b"OggS";
+
+/// Fixed part of the page header, before the lacing table
+const PAGE_HEADER_SIZE: usize = 27;
+
+/// Offset of the CRC field within the page header
+const CRC_OFFSET: usize = 22;
+
+/// Header type flags
+const FLAG_CONTINUED: u8 = 0x01;
+const FLAG_BOS: u8 = 0x02;
+const FLAG_EOS: u8 = 0x04;
+
+/// Granule position of a page on which no packet ends
+const NO_GRANULE: i64 = -1;
+
+/// Largest packet the demuxer reassembles (guards endless continuations)
+const MAX_PACKET_SIZE: usize = 16 * 1024 * 1024;
+
+/// The muxer closes a page once it holds this many samples (1 s at 48 kHz)
+const MAX_PAGE_SAMPLES: i64 = 48000;
+
+/// The muxer closes a page once it holds this many bytes
+const MAX_PAGE_BYTES: usize = 8192;
+
+/// Bytes searched at the end of a seekable file for the last granule position
+const DURATION_SCAN_SIZE: u64 = 64 * 1024;
+
+/// CRC-32 lookup table (polynomial 0x04c11db7, unreflected, as used by Ogg)
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04c1_1db7
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc = (crc << 8) ^ CRC_TABLE[((crc >> 24) as u8 ^ byte) as usize];
+    }
+    crc
+}
+
+/// A single Ogg page
+#[derive(Debug, Clone)]
+struct OggPage {
+    header_type: u8,
+    granule_position: i64,
+    serial: u32,
+    sequence: u32,
+    /// Lacing values, one per segment
+    segments: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl OggPage {
+    /// Read a page, returning `None` on a clean EOF
+    fn read<R: Read>(reader: &mut R) -> Result<Option<Self>> {
+        let mut header = [0u8; PAGE_HEADER_SIZE];
+        let mut filled = 0;
+        while filled < header.len() {
+            match reader.read(&mut header[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => {
+                    return Err(Error::ContainerParse(
+                        "Truncated Ogg page header".to_string(),
+                    ));
+                }
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::Io(e)),
+            }
+        }
+
+        if &header[..4] != CAPTURE_PATTERN {
+            return Err(Error::ContainerParse(
+                "Invalid Ogg capture pattern".to_string(),
+            ));
+        }
+        if header[4] != 0 {
+            return Err(Error::UnsupportedFormat(format!(
+                "Ogg version {}",
+                header[4]
+            )));
+        }
+
+        let truncated = |e: std::io::Error| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => {
+                Error::ContainerParse("Truncated Ogg page".to_string())
+            }
+            _ => Error::Io(e),
+        };
+
+        let mut segments = vec![0u8; usize::from(header[26])];
+        reader.read_exact(&mut segments).map_err(truncated)?;
+        let data_size: usize = segments.iter().map(|&s| usize::from(s)).sum();
+        let mut data = vec![0u8; data_size];
+        reader.read_exact(&mut data).map_err(truncated)?;
+
+        let stored_crc = u32::from_le_bytes([header[22], header[23], header[24], header[25]]);
+        header[CRC_OFFSET..CRC_OFFSET + 4].fill(0);
+        let crc = crc_update(crc_update(crc_update(0, &header), &segments), &data);
+        if crc != stored_crc {
+            return Err(Error::ContainerParse(format!(
+                "Ogg page CRC mismatch: expected 0x{:08x}, got 0x{:08x}",
+                stored_crc, crc
+            )));
+        }
+
+        let mut granule = [0u8; 8];
+        granule.copy_from_slice(&header[6..14]);
+
+        Ok(Some(Self {
+            header_type: header[5],
+            granule_position: i64::from_le_bytes(granule),
+            serial: u32::from_le_bytes([header[14], header[15], header[16], header[17]]),
+            sequence: u32::from_le_bytes([header[18], header[19], header[20], header[21]]),
+            segments,
+            data,
+        }))
+    }
+
+    /// Serialize the page, computing its CRC
+    fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut header = Vec::with_capacity(PAGE_HEADER_SIZE + self.segments.len());
+        header.extend_from_slice(CAPTURE_PATTERN);
+        header.push(0); // version
+        header.push(self.header_type);
+        header.extend_from_slice(&self.granule_position.to_le_bytes());
+        header.extend_from_slice(&self.serial.to_le_bytes());
+        header.extend_from_slice(&self.sequence.to_le_bytes());
+        header.extend_from_slice(&[0; 4]); // CRC placeholder
+        header.push(self.segments.len() as u8);
+        header.extend_from_slice(&self.segments);
+
+        let crc = crc_update(crc_update(0, &header), &self.data);
+        header[CRC_OFFSET..CRC_OFFSET + 4].copy_from_slice(&crc.to_le_bytes());
+
+        writer.write_all(&header)?;
+        writer.write_all(&self.data)?;
+        Ok(())
+    }
+
+    fn is_bos(&self) -> bool {
+        self.header_type & FLAG_BOS != 0
+    }
+
+    fn is_eos(&self) -> bool {
+        self.header_type & FLAG_EOS != 0
+    }
+
+    fn is_continued(&self) -> bool {
+        self.header_type & FLAG_CONTINUED != 0
+    }
+}
+
+/// Ogg Opus demuxer
+///
+/// Parses the `OpusHead` and `OpusTags` headers on construction and returns
+/// one packet per Opus packet. Timestamps are in 48 kHz samples in the
+/// granule position domain, so the first [`OpusHead::pre_skip`] decoded
+/// samples must be discarded; [`end_trim`](Self::end_trim) reports how many
+/// samples to drop from the end of the last packet.
+///
+/// # Example
+/// ```no_run
+/// use mead_core::container::ogg::OggDemuxer;
+/// use mead_core::container::Demuxer;
+/// use std::fs::File;
+///
+/// let file = File::open("audio.opus")?;
+/// let mut demuxer = OggDemuxer::new(file)?;
+///
+/// println!("Channels: {}", demuxer.head().channels);
+/// println!("Pre-skip: {}", demuxer.head().pre_skip);
+///
+/// while let Some(packet) = demuxer.read_packet()? {
+///     // Decode packet
+/// }
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+pub struct OggDemuxer<R: MediaSource> {
+    reader: BufReader<R>,
+    metadata: Metadata,
+    serial: u32,
+    head: OpusHead,
+    tags: OpusTags,
+    /// Packets from the current page not yet returned
+    pending: VecDeque<Packet>,
+    /// Packet data continued from the previous page
+    partial: Vec<u8>,
+    next_sequence: u32,
+    /// Timestamp of the next packet, known after the first audio page
+    next_pts: Option<i64>,
+    end_trim: u32,
+    eos: bool,
+}
+
+impl<R: MediaSource> OggDemuxer<R> {
+    /// Create a new Ogg demuxer, parsing the Opus headers
+    pub fn new(source: R) -> Result<Self> {
+        let seekable = source.is_seekable();
+        let mut reader = BufReader::new(source);
+
+        // Find the first Opus stream
+        let (serial, sequence, head) = loop {
+            let page = OggPage::read(&mut reader)?
+                .ok_or_else(|| Error::ContainerParse("No Opus stream in Ogg file".to_string()))?;
+            if !page.is_bos() {
+                continue;
+            }
+            if !page.data.starts_with(b"OpusHead") {
+                tracing::debug!("Skipping non-Opus Ogg stream 0x{:08x}", page.serial);
+                continue;
+            }
+            if page.segments.last().is_some_and(|&s| s == 255) || page.granule_position != 0 {
+                return Err(Error::ContainerParse(
+                    "OpusHead must be alone on the first page".to_string(),
+                ));
+            }
+            break (page.serial, page.sequence, OpusHead::parse(&page.data)?);
+        };
+
+        let mut demuxer = Self {
+            reader,
+            metadata: Metadata {
+                duration_ms: None,
+                stream_count: 1,
+                format: "Ogg".to_string(),
+            },
+            serial,
+            head,
+            tags: OpusTags::default(),
+            pending: VecDeque::new(),
+            partial: Vec::new(),
+            next_sequence: sequence.wrapping_add(1),
+            next_pts: None,
+            end_trim: 0,
+            eos: false,
+        };
+
+        // OpusTags may span pages but must end its page
+        let tags = loop {
+            let (page, packets) = demuxer
+                .read_stream_page()?
+                .ok_or_else(|| Error::ContainerParse("Missing OpusTags header".to_string()))?;
+            match packets.len() {
+                0 => continue,
+                1 if demuxer.partial.is_empty() => break (page, packets),
+                _ => {
+                    return Err(Error::ContainerParse(
+                        "Audio data on the OpusTags page".to_string(),
+                    ));
+                }
+            }
+        };
+        demuxer.tags = OpusTags::parse(&tags.1[0])?;
+        demuxer.eos = tags.0.is_eos();
+
+        if seekable {
+            demuxer.metadata.duration_ms = demuxer.scan_duration()?;
+        }
+
+        tracing::info!(
+            "Ogg Opus opened: {} channels, pre-skip {}, duration: {:?}ms",
+            demuxer.head.channels,
+            demuxer.head.pre_skip,
+            demuxer.metadata.duration_ms
+        );
+
+        Ok(demuxer)
+    }
+
+    /// Get the identification header
+    pub fn head(&self) -> &OpusHead {
+        &self.head
+    }
+
+    /// Get the comment header
+    pub fn tags(&self) -> &OpusTags {
+        &self.tags
+    }
+
+    /// Get the serial number of the Opus logical stream
+    pub fn serial(&self) -> u32 {
+        self.serial
+    }
+
+    /// Samples (per channel) to discard from the end of the last packet
+    ///
+    /// Non-zero only once the final packet of the stream has been returned.
+    pub fn end_trim(&self) -> u32 {
+        if self.eos && self.pending.is_empty() {
+            self.end_trim
+        } else {
+            0
+        }
+    }
+
+    /// Read the next page of our stream and return its completed packets
+    fn read_stream_page(&mut self) -> Result<Option<(OggPage, Vec<Vec<u8>>)>> {
+        let page = loop {
+            match OggPage::read(&mut self.reader)? {
+                Some(page) if page.serial == self.serial => break page,
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+        };
+
+        if page.sequence != self.next_sequence {
+            tracing::warn!(
+                "Ogg page sequence gap: expected {}, got {}",
+                self.next_sequence,
+                page.sequence
+            );
+            self.partial.clear();
+        }
+        self.next_sequence = page.sequence.wrapping_add(1);
+
+        // A continuation without its start (after a gap) can't be used
+        let mut skip_first = page.is_continued() && self.partial.is_empty();
+        let mut packets = Vec::new();
+        let mut offset = 0;
+
+        for &lace in &page.segments {
+            let end = offset + usize::from(lace);
+            if !skip_first {
+                self.partial.extend_from_slice(&page.data[offset..end]);
+                if self.partial.len() > MAX_PACKET_SIZE {
+                    return Err(Error::ContainerParse(format!(
+                        "Ogg packet exceeds {} bytes",
+                        MAX_PACKET_SIZE
+                    )));
+                }
+            }
+            offset = end;
+
+            if lace < 255 {
+                if !skip_first {
+                    packets.push(std::mem::take(&mut self.partial));
+                }
+                skip_first = false;
+            }
+        }
+
+        Ok(Some((page, packets)))
+    }
+
+    /// Assign timestamps to an audio page's packets and queue them
+    fn queue_audio_page(&mut self, page: &OggPage, packets: Vec<Vec<u8>>) -> Result<()> {
+        let durations = packets
+            .iter()
+            .map(|p| {
+                opus::packet_duration(p)
+                    .map(i64::from)
+                    .ok_or_else(|| Error::ContainerParse("Malformed Opus packet".to_string()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let total: i64 = durations.iter().sum();
+        let granule = page.granule_position;
+
+        let start = match self.next_pts {
+            Some(pts) => pts,
+            // The first audio page's granule gives the stream's start offset,
+            // except on the last page where it may also encode end trimming
+            None if page.is_eos() || granule == NO_GRANULE => 0,
+            None => {
+                if granule < total {
+                    return Err(Error::ContainerParse(format!(
+                        "First Ogg page granule {} is less than its {} samples",
+                        granule, total
+                    )));
+                }
+                granule - total
+            }
+        };
+
+        let mut pts = start;
+        for (data, duration) in packets.into_iter().zip(&durations) {
+            self.pending.push_back(Packet {
+                stream_index: 0,
+                data,
+                pts: Some(pts),
+                dts: None,
+                is_keyframe: true,
+            });
+            pts += duration;
+        }
+        self.next_pts = Some(pts);
+
+        if page.is_eos() && granule != NO_GRANULE && pts > granule {
+            // Trimming is limited to the final packet
+            let last = durations.last().copied().unwrap_or(0);
+            self.end_trim = (pts - granule).min(last) as u32;
+        }
+
+        Ok(())
+    }
+
+    /// Find the last granule position of our stream near the end of the file
+    fn scan_duration(&mut self) -> Result<Option<u64>> {
+        let resume = self.reader.stream_position()?;
+        let end = self.reader.seek(SeekFrom::End(0))?;
+        let start = end.saturating_sub(DURATION_SCAN_SIZE);
+
+        self.reader.seek(SeekFrom::Start(start))?;
+        let mut tail = Vec::new();
+        self.reader
+            .get_mut()
+            .take(end - start)
+            .read_to_end(&mut tail)?;
+        self.reader.seek(SeekFrom::Start(resume))?;
+
+        let serial = self.serial.to_le_bytes();
+        let granule = (0..tail.len().saturating_sub(PAGE_HEADER_SIZE - 1))
+            .rev()
+            .filter(|&i| &tail[i..i + 4] == CAPTURE_PATTERN && tail[i + 14..i + 18] == serial)
+            .map(|i| {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(&tail[i + 6..i + 14]);
+                i64::from_le_bytes(bytes)
+            })
+            .find(|&granule| granule != NO_GRANULE);
+
+        Ok(granule.map(|g| {
+            let samples = (g - i64::from(self.head.pre_skip)).max(0) as u64;
+            samples * 1000 / 48000
+        }))
+    }
+}
+
+impl<R: MediaSource> std::fmt::Debug for OggDemuxer<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OggDemuxer")
+            .field("metadata", &self.metadata)
+            .field("serial", &self.serial)
+            .field("head", &self.head)
+            .field("next_pts", &self.next_pts)
+            .finish()
+    }
+}
+
+impl<R: MediaSource> Demuxer for OggDemuxer<R> {
+    fn read_packet(&mut self) -> Result<Option<Packet>> {
+        loop {
+            if let Some(packet) = self.pending.pop_front() {
+                return Ok(Some(packet));
+            }
+            if self.eos {
+                return Ok(None);
+            }
+
+            let (page, packets) = match self.read_stream_page()? {
+                Some(page) => page,
+                None => {
+                    tracing::warn!("Ogg stream ended without an end-of-stream page");
+                    self.eos = true;
+                    return Ok(None);
+                }
+            };
+
+            self.eos = page.is_eos();
+            self.queue_audio_page(&page, packets)?;
+        }
+    }
+
+    fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+}
+
+/// Ogg Opus muxer configuration
+#[derive(Debug, Clone)]
+pub struct OggMuxerConfig {
+    /// Channel count (1 or 2)
+    pub channels: u8,
+    /// Sample rate of the original input (informational)
+    pub input_sample_rate: u32,
+    /// Samples to discard from the start of the decoded stream
+    pub pre_skip: u16,
+    /// Output gain in Q7.8 dB
+    pub output_gain: i16,
+    /// Logical stream serial number
+    pub serial: u32,
+    /// User comments for the `OpusTags` header, conventionally `KEY=value`
+    pub comments: Vec<String>,
+}
+
+impl Default for OggMuxerConfig {
+    fn default() -> Self {
+        Self {
+            channels: 2,
+            input_sample_rate: 48000,
+            pre_skip: 312,
+            output_gain: 0,
+            serial: 0x6d65_6164,
+            comments: Vec::new(),
+        }
+    }
+}
+
+/// Ogg Opus muxer
+///
+/// Writes the `OpusHead` and `OpusTags` headers on construction, then packs
+/// Opus packets into pages of up to one second. Packet timestamps are in
+/// 48 kHz samples (the granule position domain, starting at 0); packets
+/// without a timestamp follow on from the previous one. Never seeks, so it
+/// can write to pipes.
+///
+/// # Example
+/// ```no_run
+/// use mead_core::container::ogg::{OggMuxer, OggMuxerConfig};
+/// use mead_core::container::{Muxer, Packet};
+/// use std::fs::File;
+///
+/// let file = File::create("output.opus")?;
+/// let mut muxer = OggMuxer::new(file, OggMuxerConfig::default())?;
+///
+/// let packet = Packet {
+///     stream_index: 0,
+///     data: vec![/* Opus packet */],
+///     pts: Some(0),
+///     dts: None,
+///     is_keyframe: true,
+/// };
+/// muxer.write_packet(packet)?;
+///
+/// muxer.finalize()?;
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+pub struct OggMuxer<W: Write> {
+    writer: W,
+    serial: u32,
+    sequence: u32,
+    /// Lacing values of the page being built
+    segments: Vec<u8>,
+    data: Vec<u8>,
+    /// The page being built starts with the tail of a packet
+    continued: bool,
+    /// Granule position after the last packet completed on this page
+    page_granule: i64,
+    /// Granule position at the start of the page being built
+    page_start: i64,
+    granule: i64,
+    packet_count: u64,
+}
+
+impl<W: Write> OggMuxer<W> {
+    /// Create a new Ogg Opus muxer and write the stream headers
+    pub fn new(writer: W, config: OggMuxerConfig) -> Result<Self> {
+        if !(1..=2).contains(&config.channels) {
+            return Err(Error::UnsupportedFormat(format!(
+                "Ogg Opus with {} channels",
+                config.channels
+            )));
+        }
+
+        tracing::info!(
+            "Creating Ogg Opus muxer: {} channels, pre-skip {}",
+            config.channels,
+            config.pre_skip
+        );
+
+        let mut muxer = Self {
+            writer,
+            serial: config.serial,
+            sequence: 0,
+            segments: Vec::new(),
+            data: Vec::new(),
+            continued: false,
+            page_granule: NO_GRANULE,
+            page_start: 0,
+            granule: 0,
+            packet_count: 0,
+        };
+
+        let head = OpusHead {
+            output_gain: config.output_gain,
+            ..OpusHead::new(config.channels, config.pre_skip, config.input_sample_rate)
+        };
+        let tags = OpusTags {
+            vendor: concat!("mead-core ", env!("CARGO_PKG_VERSION")).to_string(),
+            comments: config.comments,
+        };
+
+        // Each header gets its own page, with granule position 0
+        muxer.append_packet(&head.to_bytes(), 0)?;
+        muxer.flush_page(FLAG_BOS)?;
+        muxer.append_packet(&tags.to_bytes(), 0)?;
+        muxer.flush_page(0)?;
+
+        Ok(muxer)
+    }
+
+    /// Get number of audio packets written
+    pub fn packet_count(&self) -> u64 {
+        self.packet_count
+    }
+
+    /// Add a packet to the current page, spilling onto new pages as needed
+    fn append_packet(&mut self, data: &[u8], granule: i64) -> Result<()> {
+        let mut lacing = vec![255u8; data.len() / 255];
+        lacing.push((data.len() % 255) as u8);
+
+        // A page holds at most 255 lacing values
+        if self.segments.len() == 255 {
+            self.flush_page(0)?;
+        }
+
+        let mut laces = lacing.as_slice();
+        let mut offset = 0;
+        loop {
+            let room = 255 - self.segments.len();
+            let take = room.min(laces.len());
+            let bytes: usize = laces[..take].iter().map(|&l| usize::from(l)).sum();
+            self.segments.extend_from_slice(&laces[..take]);
+            self.data.extend_from_slice(&data[offset..offset + bytes]);
+            offset += bytes;
+            laces = &laces[take..];
+
+            if laces.is_empty() {
+                self.page_granule = granule;
+                return Ok(());
+            }
+
+            // The page is full mid-packet
+            self.flush_page(0)?;
+            self.continued = offset > 0;
+        }
+    }
+
+    fn flush_page(&mut self, flags: u8) -> Result<()> {
+        let header_type = flags | if self.continued { FLAG_CONTINUED } else { 0 };
+        let page = OggPage {
+            header_type,
+            granule_position: self.page_granule,
+            serial: self.serial,
+            sequence: self.sequence,
+            segments: std::mem::take(&mut self.segments),
+            data: std::mem::take(&mut self.data),
+        };
+        page.write(&mut self.writer)?;
+
+        self.sequence += 1;
+        self.continued = false;
+        if self.page_granule != NO_GRANULE {
+            self.page_start = self.page_granule;
+        }
+        self.page_granule = NO_GRANULE;
+        Ok(())
+    }
+}
+
+impl<W: Write> std::fmt::Debug for OggMuxer<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OggMuxer")
+            .field("serial", &self.serial)
+            .field("sequence", &self.sequence)
+            .field("granule", &self.granule)
+            .field("packet_count", &self.packet_count)
+            .finish()
+    }
+}
+
+impl<W: Write> Muxer for OggMuxer<W> {
+    fn write_packet(&mut self, packet: Packet) -> Result<()> {
+        if packet.stream_index != 0 {
+            return Err(Error::InvalidInput(format!(
+                "Ogg muxer has no stream index {}",
+                packet.stream_index
+            )));
+        }
+
+        let duration = opus::packet_duration(&packet.data)
+            .ok_or_else(|| Error::InvalidInput("Malformed Opus packet".to_string()))?;
+        let pts = packet.pts.unwrap_or(self.granule);
+        if pts < self.granule {
+            return Err(Error::InvalidInput(format!(
+                "Non-monotonic Opus timestamp {} (expected at least {})",
+                pts, self.granule
+            )));
+        }
+
+        // Close the page before it grows past the size or latency limits
+        if !self.segments.is_empty()
+            && (self.data.len() + packet.data.len() > MAX_PAGE_BYTES
+                || pts + i64::from(duration) - self.page_start > MAX_PAGE_SAMPLES)
+        {
+            self.flush_page(0)?;
+        }
+
+        self.granule = pts + i64::from(duration);
+        self.append_packet(&packet.data, self.granule)?;
+        self.packet_count += 1;
+        Ok(())
+    }
+
+    fn finalize(mut self) -> Result<()> {
+        tracing::info!(
+            "Finalizing Ogg Opus stream: {} packets, {} samples",
+            self.packet_count,
+            self.granule
+        );
+
+        // An empty stream still needs an end-of-stream page
+        if self.page_granule == NO_GRANULE && self.segments.is_empty() {
+            self.page_granule = self.granule;
+        }
+        self.flush_page(FLAG_EOS)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::ReadOnlySource;
+    use std::io::Cursor;
+
+    /// CELT 20 ms packet (960 samples)
+    fn opus_packet(index: u8, len: usize) -> Vec<u8> {
+        let mut data = vec![0xFC, index];
+        data.resize(len.max(2), index);
+        data
+    }
+
+    fn mux(packets: &[Vec<u8>]) -> Vec<u8> {
+        let mut output = Vec::new();
+        let mut muxer = OggMuxer::new(&mut output, OggMuxerConfig::default()).unwrap();
+        for data in packets {
+            muxer
+                .write_packet(Packet {
+                    stream_index: 0,
+                    data: data.clone(),
+                    pts: None,
+                    dts: None,
+                    is_keyframe: true,
+                })
+                .unwrap();
+        }
+        muxer.finalize().unwrap();
+        output
+    }
+
+    fn read_all<R: MediaSource>(demuxer: &mut OggDemuxer<R>) -> Vec<Packet> {
+        let mut packets = Vec::new();
+        while let Some(packet) = demuxer.read_packet().unwrap() {
+            packets.push(packet);
+        }
+        packets
+    }
+
+    #[test]
+    fn test_crc() {
+        // CRC-32/MPEG-2 variant without final xor or init value
+        assert_eq!(crc_update(0, b"123456789"), 0x89a1_897f);
+    }
+
+    #[test]
+    fn test_ogg_round_trip() {
+        let packets: Vec<Vec<u8>> = (0..120).map(|i| opus_packet(i as u8, 40)).collect();
+        let data = mux(&packets);
+
+        let mut demuxer = OggDemuxer::new(Cursor::new(data)).unwrap();
+        assert_eq!(demuxer.head().channels, 2);
+        assert_eq!(demuxer.head().pre_skip, 312);
+        assert!(demuxer.tags().vendor.starts_with("mead-core"));
+        // 120 * 960 samples minus pre-skip
+        assert_eq!(
+            demuxer.metadata().duration_ms,
+            Some((120 * 960 - 312) * 1000 / 48000)
+        );
+
+        let read = read_all(&mut demuxer);
+        assert_eq!(read.len(), 120);
+        for (i, packet) in read.iter().enumerate() {
+            assert_eq!(packet.data, packets[i]);
+            assert_eq!(packet.pts, Some(i as i64 * 960));
+        }
+        assert_eq!(demuxer.end_trim(), 0);
+    }
+
+    #[test]
+    fn test_ogg_large_packets_span_pages() {
+        // 70 KB needs more than 255 lacing values, so it continues across pages
+        let packets = vec![
+            opus_packet(1, 100),
+            opus_packet(2, 70_000),
+            opus_packet(3, 510),
+        ];
+        let data = mux(&packets);
+
+        let mut demuxer = OggDemuxer::new(ReadOnlySource::new(Cursor::new(data))).unwrap();
+        assert_eq!(demuxer.metadata().duration_ms, None);
+
+        let read = read_all(&mut demuxer);
+        assert_eq!(read.len(), 3);
+        for (packet, expected) in read.iter().zip(&packets) {
+            assert_eq!(&packet.data, expected);
+        }
+        assert_eq!(read[2].pts, Some(1920));
+    }
+
+    #[test]
+    fn test_ogg_full_page_of_tiny_packets() {
+        // 2.5 ms CELT packets are one lacing value each, so 255 of them
+        // fill a page before the size or latency limits close it
+        let packets: Vec<Vec<u8>> = (0..300).map(|i| vec![0x80, i as u8]).collect();
+        let data = mux(&packets);
+
+        let mut demuxer = OggDemuxer::new(Cursor::new(data)).unwrap();
+        let read = read_all(&mut demuxer);
+        assert_eq!(read.len(), 300);
+        for (i, packet) in read.iter().enumerate() {
+            assert_eq!(packet.data, packets[i]);
+            assert_eq!(packet.pts, Some(i as i64 * 120));
+        }
+    }
+
+    #[test]
+    fn test_ogg_end_trim() {
+        let mut data = mux(&[opus_packet(1, 10), opus_packet(2, 10)]);
+
+        // Rewrite the final page's granule position to trim 100 samples
+        let last = data.windows(4).rposition(|w| w == CAPTURE_PATTERN).unwrap();
+        let mut page = OggPage::read(&mut &data[last..]).unwrap().unwrap();
+        assert!(page.is_eos());
+        page.granule_position -= 100;
+        data.truncate(last);
+        page.write(&mut data).unwrap();
+
+        let mut demuxer = OggDemuxer::new(Cursor::new(data)).unwrap();
+        demuxer.read_packet().unwrap().unwrap();
+        assert_eq!(demuxer.end_trim(), 0, "only reported after the last packet");
+        demuxer.read_packet().unwrap().unwrap();
+        assert_eq!(demuxer.end_trim(), 100);
+        assert!(demuxer.read_packet().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_ogg_crc_mismatch() {
+        let mut data = mux(&[opus_packet(1, 10)]);
+        let len = data.len();
+        data[len - 1] ^= 0xFF;
+
+        let mut demuxer = OggDemuxer::new(ReadOnlySource::new(Cursor::new(data))).unwrap();
+        assert!(matches!(
+            demuxer.read_packet(),
+            Err(Error::ContainerParse(_))
+        ));
+    }
+
+    #[test]
+    fn test_ogg_rejects_non_opus() {
+        let mut page = Vec::new();
+        OggPage {
+            header_type: FLAG_BOS,
+            granule_position: 0,
+            serial: 1,
+            sequence: 0,
+            segments: vec![30],
+            data: b"\x01vorbis"
+                .iter()
+                .copied()
+                .chain(std::iter::repeat_n(0, 23))
+                .collect(),
+        }
+        .write(&mut page)
+        .unwrap();
+
+        assert!(OggDemuxer::new(Cursor::new(page)).is_err());
+        assert!(OggDemuxer::new(Cursor::new(b"OggS".to_vec())).is_err());
+    }
+
+    #[test]
+    fn test_ogg_muxer_rejects_bad_input() {
+        let mut output = Vec::new();
+        let mut muxer = OggMuxer::new(&mut output, OggMuxerConfig::default()).unwrap();
+        let packet = Packet {
+            stream_index: 0,
+            data: opus_packet(0, 2),
+            pts: Some(960),
+            dts: None,
+            is_keyframe: true,
+        };
+        muxer.write_packet(packet.clone()).unwrap();
+        assert!(
+            muxer
+                .write_packet(Packet {
+                    pts: Some(0),
+                    ..packet.clone()
+                })
+                .is_err()
+        );
+        assert!(
+            muxer
+                .write_packet(Packet {
+                    stream_index: 1,
+                    ..packet
+                })
+                .is_err()
+        );
+
+        let config = OggMuxerConfig {
+            channels: 6,
+            ..Default::default()
+        };
+        assert!(OggMuxer::new(Vec::new(), config).is_err());
+    }
+}
@@ -8,8 +8,56 @@
 //! ffmpeg -i input.mp4 -f yuv4mpeg - | mead encode -o output.ivf --codec av1
 //! ```
 
-use crate::{Error, Frame, PixelFormat, Result};
-use std::io::Read;
+use crate::color::{ChromaSamplePosition, ColorRange, Colorimetry};
+use crate::{Error, FieldOrder, Frame, FramePool, PixelFormat, PlaneLayout, Result};
+use std::io::{Read, Write};
+
+/// Y4M interlacing mode (the `I` header parameter)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interlace {
+    /// Progressive frames (`Ip`)
+    #[default]
+    Progressive,
+    /// Interlaced, top field first (`It`)
+    TopFieldFirst,
+    /// Interlaced, bottom field first (`Ib`)
+    BottomFieldFirst,
+    /// Mixed modes, signalled per frame (`Im`)
+    Mixed,
+}
+
+impl Interlace {
+    /// Y4M header tag character for this mode
+    fn tag(self) -> char {
+        match self {
+            Self::Progressive => 'p',
+            Self::TopFieldFirst => 't',
+            Self::BottomFieldFirst => 'b',
+            Self::Mixed => 'm',
+        }
+    }
+
+    /// Mode for a header tag character
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            b'p' => Some(Self::Progressive),
+            b't' => Some(Self::TopFieldFirst),
+            b'b' => Some(Self::BottomFieldFirst),
+            b'm' => Some(Self::Mixed),
+            _ => None,
+        }
+    }
+
+    /// Field order of every frame, or `None` when it is signalled per frame
+    pub fn field_order(self) -> Option<FieldOrder> {
+        match self {
+            Self::Progressive => Some(FieldOrder::Progressive),
+            Self::TopFieldFirst => Some(FieldOrder::TopFieldFirst),
+            Self::BottomFieldFirst => Some(FieldOrder::BottomFieldFirst),
+            Self::Mixed => None,
+        }
+    }
+}
 
 /// Y4M demuxer for reading raw YUV video
 ///
@@ -38,18 +86,31 @@ use std::io::Read;
 /// # Ok::<(), mead_core::Error>(())
 /// ```
 pub struct Y4mDemuxer<R: Read> {
-    decoder: y4m::Decoder<R>,
+    reader: R,
     width: u32,
     height: u32,
     framerate: (u64, u64),
     pixel_format: PixelFormat,
+    colorimetry: Colorimetry,
+    interlace: Interlace,
+    pool: FramePool,
+    /// Whether `pool` was supplied by the caller, making it a hard limit
+    bounded: bool,
     frame_count: u64,
 }
 
+/// Longest `FRAME` line accepted, including frame parameters
+const MAX_FRAME_HEADER: usize = 1024;
+
+/// Frames kept for reuse by the demuxer's own pool
+const DEFAULT_POOL_CAPACITY: usize = 8;
+
 impl<R: Read> Y4mDemuxer<R> {
     /// Create a new Y4M demuxer
-    pub fn new(reader: R) -> Result<Self> {
-        let decoder = y4m::Decoder::new(reader)
+    pub fn new(mut reader: R) -> Result<Self> {
+        // Only the stream header goes through the y4m crate; frames are read
+        // straight into plane rows to avoid an intermediate copy
+        let decoder = y4m::Decoder::new(&mut reader)
             .map_err(|e| Error::ContainerParse(format!("Failed to parse Y4M: {}", e)))?;
 
         let width = decoder.get_width();
@@ -68,11 +129,18 @@ impl<R: Read> Y4mDemuxer<R> {
 
         // Map Y4M colorspace to our PixelFormat
         let pixel_format = match colorspace {
-            y4m::Colorspace::C420 | y4m::Colorspace::C420jpeg | y4m::Colorspace::C420paldv | y4m::Colorspace::C420mpeg2 => {
-                PixelFormat::Yuv420p
-            }
+            y4m::Colorspace::C420
+            | y4m::Colorspace::C420jpeg
+            | y4m::Colorspace::C420paldv
+            | y4m::Colorspace::C420mpeg2 => PixelFormat::Yuv420p,
             y4m::Colorspace::C422 => PixelFormat::Yuv422p,
             y4m::Colorspace::C444 => PixelFormat::Yuv444p,
+            y4m::Colorspace::C420p10 => PixelFormat::Yuv420p10,
+            y4m::Colorspace::C422p10 => PixelFormat::Yuv422p10,
+            y4m::Colorspace::C444p10 => PixelFormat::Yuv444p10,
+            y4m::Colorspace::C420p12 => PixelFormat::Yuv420p12,
+            y4m::Colorspace::C422p12 => PixelFormat::Yuv422p12,
+            y4m::Colorspace::C444p12 => PixelFormat::Yuv444p12,
             other => {
                 return Err(Error::InvalidInput(format!(
                     "Unsupported Y4M colorspace: {:?}",
@@ -81,12 +149,33 @@ impl<R: Read> Y4mDemuxer<R> {
             }
         };
 
+        // The 4:2:0 variants only differ in chroma siting; C420 and C420jpeg
+        // are centered, which AV1 can only signal as unknown
+        let chroma_position = match colorspace {
+            y4m::Colorspace::C420mpeg2 => ChromaSamplePosition::Vertical,
+            y4m::Colorspace::C420paldv => ChromaSamplePosition::Colocated,
+            _ => ChromaSamplePosition::Unknown,
+        };
+
+        let colorimetry = Colorimetry {
+            range: parse_color_range(decoder.get_raw_params())?,
+            chroma_position,
+            ..Default::default()
+        };
+        let interlace = parse_interlace(decoder.get_raw_params())?;
+        drop(decoder);
+
+        let (width, height) = (width as u32, height as u32);
         Ok(Self {
-            decoder,
-            width: width as u32,
-            height: height as u32,
+            reader,
+            width,
+            height,
             framerate: (framerate.num as u64, framerate.den as u64),
             pixel_format,
+            colorimetry,
+            interlace,
+            pool: FramePool::new(width, height, pixel_format, DEFAULT_POOL_CAPACITY),
+            bounded: false,
             frame_count: 0,
         })
     }
@@ -111,44 +200,422 @@ impl<R: Read> Y4mDemuxer<R> {
         self.pixel_format
     }
 
+    /// Get colorimetry from the colorspace variant and `XCOLORRANGE` tag
+    ///
+    /// Y4M has no way to signal primaries, transfer or matrix, so those are
+    /// left unspecified.
+    pub fn colorimetry(&self) -> Colorimetry {
+        self.colorimetry
+    }
+
+    /// Get the interlacing mode from the `I` header parameter
+    ///
+    /// Frames carry the matching [`FieldOrder`]; in mixed streams it comes
+    /// from each frame's own `I` parameter, and frames without one are
+    /// taken as progressive.
+    pub fn interlace(&self) -> Interlace {
+        self.interlace
+    }
+
     /// Get number of frames read so far
     pub fn frame_count(&self) -> u64 {
         self.frame_count
     }
 
+    /// Set the row alignment and padding of frames returned by [`read_frame`](Self::read_frame)
+    ///
+    /// Lets frames be read directly into the layout an encoder prefers.
+    /// Replaces any pool set with [`set_frame_pool`](Self::set_frame_pool).
+    pub fn set_plane_layout(&mut self, layout: PlaneLayout) {
+        self.pool = FramePool::with_layout(
+            self.width,
+            self.height,
+            self.pixel_format,
+            layout,
+            DEFAULT_POOL_CAPACITY,
+        );
+        self.bounded = false;
+    }
+
+    /// Allocate frames from `pool`, blocking while it is exhausted
+    ///
+    /// By default frames come from an internal pool and are freshly
+    /// allocated once it runs dry, so holding on to frames never stalls
+    /// reading. With a caller-supplied pool, [`read_frame`](Self::read_frame)
+    /// instead waits until a frame is dropped, bounding memory use and
+    /// applying backpressure to the reader. Frames must then be dropped on
+    /// another thread once the pool's capacity is reached.
+    pub fn set_frame_pool(&mut self, pool: FramePool) -> Result<()> {
+        if (pool.width(), pool.height(), pool.format())
+            != (self.width, self.height, self.pixel_format)
+        {
+            return Err(Error::InvalidInput(format!(
+                "Frame pool is {}x{} {:?}, stream is {}x{} {:?}",
+                pool.width(),
+                pool.height(),
+                pool.format(),
+                self.width,
+                self.height,
+                self.pixel_format
+            )));
+        }
+        self.pool = pool;
+        self.bounded = true;
+        Ok(())
+    }
+
     /// Read the next frame
     ///
-    /// Returns `Ok(None)` when EOF is reached.
+    /// Frames are timestamped with their index, in units of one frame at
+    /// the stream's frame rate. Returns `Ok(None)` when EOF is reached.
     pub fn read_frame(&mut self) -> Result<Option<Frame>> {
-        let y4m_frame = match self.decoder.read_frame() {
-            Ok(frame) => frame,
-            Err(y4m::Error::EOF) => return Ok(None),
-            Err(e) => return Err(Error::ContainerParse(format!("Y4M read error: {}", e))),
+        let Some(params) = self.read_frame_header()? else {
+            return Ok(None);
+        };
+        let field_order = match self.interlace.field_order() {
+            Some(field_order) => field_order,
+            None => parse_frame_field_order(&params),
         };
 
-        // Extract plane data
-        let y_plane = y4m_frame.get_y_plane();
-        let u_plane = y4m_frame.get_u_plane();
-        let v_plane = y4m_frame.get_v_plane();
+        let mut frame = if self.bounded {
+            self.pool.acquire()
+        } else {
+            self.pool.try_acquire().unwrap_or_else(|| {
+                Frame::with_layout(
+                    self.width,
+                    self.height,
+                    self.pixel_format,
+                    self.pool.layout(),
+                )
+            })
+        };
+        frame.set_colorimetry(self.colorimetry);
+        frame.set_pts(self.frame_count as i64);
+        frame.set_field_order(field_order);
+
+        for plane in frame.planes_mut() {
+            for y in 0..plane.height() {
+                self.reader
+                    .read_exact(plane.row_mut(y))
+                    .map_err(|e| match e.kind() {
+                        std::io::ErrorKind::UnexpectedEof => {
+                            Error::ContainerParse("Truncated Y4M frame".to_string())
+                        }
+                        _ => Error::Io(e),
+                    })?;
+            }
+        }
+
+        self.frame_count += 1;
+
+        if self.frame_count % 100 == 0 {
+            tracing::debug!("Read {} frames from Y4M", self.frame_count);
+        }
+
+        Ok(Some(frame))
+    }
+}
+
+impl<R: Read> Y4mDemuxer<R> {
+    /// Consume a `FRAME` line, returning its parameters, or `None` at a
+    /// clean end of stream
+    fn read_frame_header(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) if line.is_empty() => return Ok(None),
+                Ok(0) => {
+                    return Err(Error::ContainerParse(
+                        "Truncated Y4M frame header".to_string(),
+                    ));
+                }
+                Ok(_) if byte[0] == b'\n' => break,
+                Ok(_) => line.push(byte[0]),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+            if line.len() > MAX_FRAME_HEADER {
+                return Err(Error::ContainerParse(
+                    "Y4M frame header too long".to_string(),
+                ));
+            }
+        }
+
+        // Frame parameters, if any, follow a space
+        match line.strip_prefix(b"FRAME") {
+            Some(params) if params.is_empty() || params[0] == b' ' => Ok(Some(params.to_vec())),
+            _ => Err(Error::ContainerParse(
+                "Expected Y4M FRAME header".to_string(),
+            )),
+        }
+    }
+}
+
+impl<R: Read> std::fmt::Debug for Y4mDemuxer<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Y4mDemuxer")
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("framerate", &self.framerate)
+            .field("pixel_format", &self.pixel_format)
+            .field("interlace", &self.interlace)
+            .field("frame_count", &self.frame_count)
+            .finish()
+    }
+}
+
+/// Parse the `XCOLORRANGE` extension tag written by ffmpeg
+fn parse_color_range(params: &[u8]) -> Result<ColorRange> {
+    let mut range = ColorRange::Limited;
+    for param in params.split(|&b| b == b' ') {
+        if let Some(value) = param.strip_prefix(b"XCOLORRANGE=") {
+            range = match value {
+                b"FULL" => ColorRange::Full,
+                b"LIMITED" => ColorRange::Limited,
+                _ => {
+                    return Err(Error::ContainerParse(format!(
+                        "Invalid Y4M color range: {}",
+                        String::from_utf8_lossy(value)
+                    )));
+                }
+            };
+        }
+    }
+    Ok(range)
+}
+
+/// Parse the `I` header parameter, progressive when absent
+fn parse_interlace(params: &[u8]) -> Result<Interlace> {
+    let mut interlace = Interlace::Progressive;
+    for param in params.split(|&b| b == b' ') {
+        if let Some(value) = param.strip_prefix(b"I") {
+            interlace = match value {
+                [tag] => Interlace::from_tag(*tag),
+                _ => None,
+            }
+            .ok_or_else(|| {
+                Error::ContainerParse(format!(
+                    "Invalid Y4M interlacing: {}",
+                    String::from_utf8_lossy(value)
+                ))
+            })?;
+        }
+    }
+    Ok(interlace)
+}
+
+/// Field order from a mixed-mode frame's `Ixyz` parameter
+///
+/// `x` is the field order (`t`, `T`, `b`, `B`, or `1`-`3` for progressive
+/// frames) and `y` the sampling (`p` progressive, `i` interlaced);
+/// progressively sampled frames stored as fields stay progressive.
+fn parse_frame_field_order(params: &[u8]) -> FieldOrder {
+    params
+        .split(|&b| b == b' ')
+        .find_map(|param| match param {
+            [b'I', b't' | b'T', b'i', ..] => Some(FieldOrder::TopFieldFirst),
+            [b'I', b'b' | b'B', b'i', ..] => Some(FieldOrder::BottomFieldFirst),
+            _ => None,
+        })
+        .unwrap_or(FieldOrder::Progressive)
+}
 
-        let mut frame = Frame::new(self.width, self.height, self.pixel_format);
+/// Y4M muxer configuration
+#[derive(Debug, Clone)]
+pub struct Y4mMuxerConfig {
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Frame rate as (numerator, denominator)
+    pub framerate: (u64, u64),
+    /// Pixel format of the frames that will be written
+    pub pixel_format: PixelFormat,
+    /// Interlacing mode
+    pub interlace: Interlace,
+    /// Pixel aspect ratio as (numerator, denominator), (0, 0) = unknown
+    pub pixel_aspect: (u64, u64),
+    /// Color range and 4:2:0 chroma siting to signal in the header
+    pub colorimetry: Colorimetry,
+}
 
-        // Copy Y plane
-        frame.planes_mut()[0].data_mut().copy_from_slice(y_plane);
+impl Default for Y4mMuxerConfig {
+    fn default() -> Self {
+        Self {
+            width: 0,  // Must be set
+            height: 0, // Must be set
+            framerate: (25, 1),
+            pixel_format: PixelFormat::Yuv420p,
+            interlace: Interlace::Progressive,
+            pixel_aspect: (1, 1),
+            colorimetry: Colorimetry::default(),
+        }
+    }
+}
 
-        // Copy U plane
-        frame.planes_mut()[1].data_mut()[..u_plane.len()].copy_from_slice(u_plane);
+/// Y4M muxer for writing raw YUV video
+///
+/// Writes the stream header on creation and one `FRAME` record per call to
+/// [`write_frame`](Self::write_frame). Works with any `Write`, including stdout,
+/// so output can be piped into other tools.
+///
+/// # Example
+/// ```no_run
+/// use mead_core::container::y4m::{Y4mMuxer, Y4mMuxerConfig};
+/// use mead_core::{Frame, PixelFormat};
+///
+/// let config = Y4mMuxerConfig {
+///     width: 1920,
+///     height: 1080,
+///     framerate: (30, 1),
+///     ..Default::default()
+/// };
+/// let mut muxer = Y4mMuxer::new(std::io::stdout().lock(), config)?;
+///
+/// let frame = Frame::new(1920, 1080, PixelFormat::Yuv420p);
+/// muxer.write_frame(&frame)?;
+///
+/// muxer.finalize()?;
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+pub struct Y4mMuxer<W: Write> {
+    writer: W,
+    config: Y4mMuxerConfig,
+    frame_count: u64,
+}
 
-        // Copy V plane
-        frame.planes_mut()[2].data_mut()[..v_plane.len()].copy_from_slice(v_plane);
+impl<W: Write> Y4mMuxer<W> {
+    /// Create a new Y4M muxer and write the stream header
+    pub fn new(mut writer: W, config: Y4mMuxerConfig) -> Result<Self> {
+        if config.width == 0 || config.height == 0 {
+            return Err(Error::InvalidInput(
+                "Width and height must be set".to_string(),
+            ));
+        }
+
+        let (fps_num, fps_den) = config.framerate;
+        if fps_num == 0 || fps_den == 0 {
+            return Err(Error::InvalidInput(format!(
+                "Invalid Y4M framerate {}/{}",
+                fps_num, fps_den
+            )));
+        }
+
+        let colorspace = match config.pixel_format {
+            PixelFormat::Yuv420p => match config.colorimetry.chroma_position {
+                ChromaSamplePosition::Unknown => "420jpeg",
+                ChromaSamplePosition::Vertical => "420mpeg2",
+                ChromaSamplePosition::Colocated => "420paldv",
+            },
+            PixelFormat::Yuv422p => "422",
+            PixelFormat::Yuv444p => "444",
+            PixelFormat::Yuv420p10 => "420p10",
+            PixelFormat::Yuv422p10 => "422p10",
+            PixelFormat::Yuv444p10 => "444p10",
+            PixelFormat::Yuv420p12 => "420p12",
+            PixelFormat::Yuv422p12 => "422p12",
+            PixelFormat::Yuv444p12 => "444p12",
+            other => {
+                return Err(Error::UnsupportedFormat(format!(
+                    "Y4M cannot store {:?}",
+                    other
+                )));
+            }
+        };
+
+        tracing::info!(
+            "Creating Y4M muxer: {}x{} @ {}/{} fps, C{}",
+            config.width,
+            config.height,
+            fps_num,
+            fps_den,
+            colorspace
+        );
+
+        write!(
+            writer,
+            "YUV4MPEG2 W{} H{} F{}:{} I{} A{}:{} C{}",
+            config.width,
+            config.height,
+            fps_num,
+            fps_den,
+            config.interlace.tag(),
+            config.pixel_aspect.0,
+            config.pixel_aspect.1,
+            colorspace
+        )?;
+        if config.colorimetry.range == ColorRange::Full {
+            write!(writer, " XCOLORRANGE=FULL")?;
+        }
+        writeln!(writer)?;
+
+        Ok(Self {
+            writer,
+            config,
+            frame_count: 0,
+        })
+    }
+
+    /// Get number of frames written so far
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Write a frame as a `FRAME` record
+    ///
+    /// The frame must match the size and pixel format given at creation.
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<()> {
+        if frame.width() != self.config.width || frame.height() != self.config.height {
+            return Err(Error::InvalidInput(format!(
+                "Frame dimensions {}x{} do not match Y4M stream {}x{}",
+                frame.width(),
+                frame.height(),
+                self.config.width,
+                self.config.height
+            )));
+        }
+
+        if frame.format() != self.config.pixel_format {
+            return Err(Error::InvalidInput(format!(
+                "Frame format {:?} does not match Y4M stream {:?}",
+                frame.format(),
+                self.config.pixel_format
+            )));
+        }
+
+        self.writer.write_all(b"FRAME\n")?;
+
+        // Write row by row so padded strides are never leaked into the output
+        for plane in frame.planes() {
+            for y in 0..plane.height() {
+                self.writer.write_all(plane.row(y))?;
+            }
+        }
 
         self.frame_count += 1;
 
         if self.frame_count % 100 == 0 {
-            tracing::debug!("Read {} frames from Y4M", self.frame_count);
+            tracing::debug!("Wrote {} frames to Y4M", self.frame_count);
         }
 
-        Ok(Some(frame))
+        Ok(())
+    }
+
+    /// Flush and close the stream
+    pub fn finalize(mut self) -> Result<()> {
+        tracing::info!("Finalizing Y4M stream with {} frames", self.frame_count);
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> std::fmt::Debug for Y4mMuxer<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Y4mMuxer")
+            .field("config", &self.config)
+            .field("frame_count", &self.frame_count)
+            .finish()
     }
 }
 
@@ -217,7 +684,236 @@ mod tests {
         let mut demuxer = Y4mDemuxer::new(cursor).unwrap();
 
         assert_eq!(demuxer.frame_count(), 0);
-        demuxer.read_frame().unwrap();
+        let frame = demuxer.read_frame().unwrap().unwrap();
         assert_eq!(demuxer.frame_count(), 1);
+        assert_eq!(frame.pts(), Some(0));
+    }
+
+    fn muxer_config(width: u32, height: u32, pixel_format: PixelFormat) -> Y4mMuxerConfig {
+        Y4mMuxerConfig {
+            width,
+            height,
+            framerate: (30000, 1001),
+            pixel_format,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_y4m_muxer_header() {
+        let mut buf = Vec::new();
+        let config = Y4mMuxerConfig {
+            interlace: Interlace::TopFieldFirst,
+            pixel_aspect: (16, 15),
+            ..muxer_config(4, 2, PixelFormat::Yuv422p)
+        };
+        Y4mMuxer::new(&mut buf, config).unwrap().finalize().unwrap();
+
+        assert_eq!(buf, b"YUV4MPEG2 W4 H2 F30000:1001 It A16:15 C422\n");
+    }
+
+    #[test]
+    fn test_y4m_muxer_10bit() {
+        let mut frame = Frame::new(2, 2, PixelFormat::Yuv420p10);
+        frame.planes_mut()[0]
+            .row_mut(0)
+            .copy_from_slice(&1023u16.to_le_bytes().repeat(2));
+
+        let mut buf = Vec::new();
+        let mut muxer =
+            Y4mMuxer::new(&mut buf, muxer_config(2, 2, PixelFormat::Yuv420p10)).unwrap();
+        muxer.write_frame(&frame).unwrap();
+        muxer.finalize().unwrap();
+
+        let header = b"YUV4MPEG2 W2 H2 F30000:1001 Ip A1:1 C420p10\nFRAME\n";
+        assert_eq!(&buf[..header.len()], header);
+        // 2x2 luma and two 1x1 chroma planes, two bytes per sample
+        assert_eq!(buf.len(), header.len() + 12);
+        assert_eq!(
+            &buf[header.len()..header.len() + 4],
+            &[0xFF, 0x03, 0xFF, 0x03]
+        );
+    }
+
+    #[test]
+    fn test_y4m_colorimetry() {
+        let header = b"YUV4MPEG2 W2 H2 F25:1 Ip A0:0 C420mpeg2 XCOLORRANGE=FULL\n";
+        let mut data = header.to_vec();
+        data.extend_from_slice(b"FRAME\n");
+        data.extend_from_slice(&[0u8; 6]);
+
+        let mut demuxer = Y4mDemuxer::new(Cursor::new(data)).unwrap();
+        let colorimetry = demuxer.colorimetry();
+        assert_eq!(colorimetry.range, ColorRange::Full);
+        assert_eq!(colorimetry.chroma_position, ChromaSamplePosition::Vertical);
+
+        let frame = demuxer.read_frame().unwrap().unwrap();
+        assert_eq!(frame.colorimetry(), &colorimetry);
+
+        // The muxer writes the same tags back
+        let mut buf = Vec::new();
+        let config = Y4mMuxerConfig {
+            framerate: (25, 1),
+            pixel_aspect: (0, 0),
+            colorimetry,
+            ..muxer_config(2, 2, PixelFormat::Yuv420p)
+        };
+        Y4mMuxer::new(&mut buf, config).unwrap().finalize().unwrap();
+        assert_eq!(buf, header);
+
+        let default = Y4mDemuxer::new(Cursor::new(create_minimal_y4m())).unwrap();
+        assert_eq!(default.colorimetry(), Colorimetry::default());
+    }
+
+    #[test]
+    fn test_y4m_interlace() {
+        let stream = |interlace: &str, frames: &[&str]| {
+            let mut data = format!("YUV4MPEG2 W2 H2 F25:1 {} C420jpeg\n", interlace).into_bytes();
+            for frame in frames {
+                data.extend_from_slice(frame.as_bytes());
+                data.extend_from_slice(&[0u8; 6]);
+            }
+            Y4mDemuxer::new(Cursor::new(data))
+        };
+
+        let mut tff = stream("It", &["FRAME\n"]).unwrap();
+        assert_eq!(tff.interlace(), Interlace::TopFieldFirst);
+        let frame = tff.read_frame().unwrap().unwrap();
+        assert_eq!(frame.field_order(), FieldOrder::TopFieldFirst);
+
+        // Mixed streams signal each frame; progressive frames stored as
+        // fields stay progressive
+        let mut mixed = stream("Im", &["FRAME Ibi?\n", "FRAME Itp?\n", "FRAME\n"]).unwrap();
+        assert_eq!(mixed.interlace(), Interlace::Mixed);
+        let orders: Vec<_> = std::iter::from_fn(|| mixed.read_frame().unwrap())
+            .map(|frame| frame.field_order())
+            .collect();
+        assert_eq!(
+            orders,
+            [
+                FieldOrder::BottomFieldFirst,
+                FieldOrder::Progressive,
+                FieldOrder::Progressive
+            ]
+        );
+
+        assert!(stream("Ix", &[]).is_err());
+    }
+
+    #[test]
+    fn test_y4m_padded_layout() {
+        let mut data = b"YUV4MPEG2 W3 H2 F25:1 C420jpeg\nFRAME\n".to_vec();
+        data.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
+        data.extend_from_slice(b"FRAME Ixyz\n");
+        data.extend_from_slice(&[0; 10]);
+
+        let mut demuxer = Y4mDemuxer::new(Cursor::new(data)).unwrap();
+        demuxer.set_plane_layout(PlaneLayout {
+            alignment: 64,
+            padding: 8,
+        });
+
+        let frame = demuxer.read_frame().unwrap().unwrap();
+        assert_eq!(frame.planes()[0].stride() % 64, 0);
+        assert_eq!(frame.planes()[0].row(0), &[1, 2, 3]);
+        assert_eq!(frame.planes()[0].row(1), &[4, 5, 6]);
+        // Odd width: chroma rounds up to 2x1
+        assert_eq!(frame.planes()[1].row(0), &[7, 8]);
+        assert_eq!(frame.planes()[2].row(0), &[9, 10]);
+
+        assert!(demuxer.read_frame().unwrap().is_some());
+        assert!(demuxer.read_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_y4m_frame_pool() {
+        let mut data = create_minimal_y4m();
+        data.extend_from_slice(b"FRAME\n");
+        data.extend_from_slice(&[0x20; 6]);
+
+        let mut demuxer = Y4mDemuxer::new(Cursor::new(data)).unwrap();
+        assert!(
+            demuxer
+                .set_frame_pool(FramePool::new(4, 2, PixelFormat::Yuv420p, 1))
+                .is_err()
+        );
+
+        let pool = FramePool::new(2, 2, PixelFormat::Yuv420p, 1);
+        demuxer.set_frame_pool(pool.clone()).unwrap();
+
+        let frame = demuxer.read_frame().unwrap().unwrap();
+        let luma = frame.planes()[0].data().as_ptr();
+        assert_eq!(pool.outstanding(), 1);
+        drop(frame);
+
+        // The second frame is read into the first frame's buffers
+        let frame = demuxer.read_frame().unwrap().unwrap();
+        assert_eq!(frame.planes()[0].data().as_ptr(), luma);
+        assert_eq!(frame.planes()[0].data(), &[0x20; 4]);
+    }
+
+    #[test]
+    fn test_y4m_truncated_frame() {
+        let mut data = create_minimal_y4m();
+        data.pop();
+        let mut demuxer = Y4mDemuxer::new(Cursor::new(data)).unwrap();
+        assert!(demuxer.read_frame().is_err());
+
+        let data = b"YUV4MPEG2 W2 H2 F25:1 C420jpeg\nFRAMX\n".to_vec();
+        let mut demuxer = Y4mDemuxer::new(Cursor::new(data)).unwrap();
+        assert!(demuxer.read_frame().is_err());
+    }
+
+    #[test]
+    fn test_y4m_muxer_rejects_rgb() {
+        let config = muxer_config(4, 4, PixelFormat::Rgb24);
+        assert!(Y4mMuxer::new(Vec::new(), config).is_err());
+    }
+
+    #[test]
+    fn test_y4m_muxer_wrong_dimensions() {
+        let mut muxer =
+            Y4mMuxer::new(Vec::new(), muxer_config(4, 4, PixelFormat::Yuv420p)).unwrap();
+        let frame = Frame::new(8, 8, PixelFormat::Yuv420p);
+        assert!(muxer.write_frame(&frame).is_err());
+    }
+
+    #[test]
+    fn test_y4m_round_trip() {
+        let formats = [
+            PixelFormat::Yuv420p,
+            PixelFormat::Yuv422p,
+            PixelFormat::Yuv444p,
+            PixelFormat::Yuv420p10,
+            PixelFormat::Yuv422p10,
+            PixelFormat::Yuv444p12,
+        ];
+        for format in formats {
+            let mut frame = Frame::new(4, 4, format);
+            for (i, plane) in frame.planes_mut().iter_mut().enumerate() {
+                for (j, byte) in plane.data_mut().iter_mut().enumerate() {
+                    *byte = (i * 50 + j) as u8;
+                }
+            }
+
+            let mut buf = Vec::new();
+            let mut muxer = Y4mMuxer::new(&mut buf, muxer_config(4, 4, format)).unwrap();
+            muxer.write_frame(&frame).unwrap();
+            muxer.write_frame(&frame).unwrap();
+            assert_eq!(muxer.frame_count(), 2);
+            muxer.finalize().unwrap();
+
+            let mut demuxer = Y4mDemuxer::new(Cursor::new(buf)).unwrap();
+            assert_eq!(demuxer.pixel_format(), format);
+            assert_eq!(demuxer.framerate(), (30000, 1001));
+
+            for _ in 0..2 {
+                let decoded = demuxer.read_frame().unwrap().unwrap();
+                for (a, b) in decoded.planes().iter().zip(frame.planes()) {
+                    assert_eq!(a.data(), b.data());
+                }
+            }
+            assert!(demuxer.read_frame().unwrap().is_none());
+        }
     }
 }
@@ -0,0 +1,739 @@
+//! Pixel format conversion
+//!
+//! Converts between chroma subsamplings (4:2:0, 4:2:2, 4:4:4), bit depths,
+//! planar and semi-planar (NV12, P010) layouts, and between RGB and YUV.
+//!
+//! Chroma is filtered according to the frame's [`ChromaSamplePosition`]:
+//! co-sited chroma uses a [1, 2, 1] filter when downsampling and linear
+//! interpolation when upsampling, centered chroma a [1, 3, 3, 1] filter and
+//! its matching quarter-phase interpolation. Reducing bit depth can apply
+//! an ordered dither to avoid banding.
+//!
+//! # Example
+//! ```no_run
+//! use mead_core::convert::{self, ConvertConfig};
+//! use mead_core::{Frame, PixelFormat};
+//!
+//! let frame = Frame::new(1920, 1080, PixelFormat::Yuv444p10);
+//! let converted = convert::convert(&frame, PixelFormat::Yuv420p, &ConvertConfig::default())?;
+//! assert_eq!(converted.format(), PixelFormat::Yuv420p);
+//! # Ok::<(), mead_core::Error>(())
+//! ```
+
+use crate::color::{ChromaSamplePosition, ColorRange, MatrixCoefficients};
+use crate::filter::{Filter, VideoInfo};
+use crate::{ArcFrame, Error, Frame, FramePool, PixelFormat, Plane, PlaneLayout, Result};
+use std::sync::Arc;
+
+/// Dithering applied when reducing bit depth
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dither {
+    /// Round to the nearest value
+    None,
+    /// 8x8 ordered (Bayer) dither, which hides banding in smooth gradients
+    #[default]
+    Ordered,
+}
+
+/// Conversion options
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConvertConfig {
+    /// Dithering when reducing bit depth
+    pub dither: Dither,
+    /// Matrix for RGB and YUV conversion
+    ///
+    /// `Unspecified` uses the frame's matrix when converting from YUV, and
+    /// otherwise BT.709 for frames taller than 576 lines and BT.601 below.
+    pub matrix: MatrixCoefficients,
+    /// Range of YUV converted from RGB
+    pub range: ColorRange,
+    /// Plane layout of converted frames
+    pub layout: PlaneLayout,
+}
+
+/// Output frames kept for reuse by a [`Converter`]
+const POOL_CAPACITY: usize = 8;
+
+/// 8x8 Bayer matrix, thresholds in 64ths
+const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// Converts frames to one pixel format, reusing output frames
+///
+/// Output frames come from an internal [`FramePool`], so a steady stream
+/// of same-sized frames does not allocate.
+///
+/// # Example
+/// ```no_run
+/// use mead_core::convert::{ConvertConfig, Converter};
+/// use mead_core::{Frame, PixelFormat};
+///
+/// let mut converter = Converter::new(PixelFormat::Yuv420p10, ConvertConfig::default());
+///
+/// let frame = Frame::new(3840, 2160, PixelFormat::Yuv422p12);
+/// let converted = converter.convert(&frame)?;
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Converter {
+    format: PixelFormat,
+    config: ConvertConfig,
+    pool: Option<FramePool>,
+}
+
+impl Converter {
+    /// Create a converter to `format`
+    pub fn new(format: PixelFormat, config: ConvertConfig) -> Self {
+        Self {
+            format,
+            config,
+            pool: None,
+        }
+    }
+
+    /// Get the output pixel format
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    /// Convert a frame, keeping its timestamp
+    ///
+    /// Frames already in the output format are returned as-is, sharing
+    /// their planes.
+    pub fn convert(&mut self, frame: &Frame) -> Result<Frame> {
+        if frame.format() == self.format {
+            return Ok(frame.clone());
+        }
+
+        let (width, height) = (frame.width(), frame.height());
+        let pool = match &mut self.pool {
+            Some(pool) if (pool.width(), pool.height()) == (width, height) => pool,
+            pool => pool.insert(FramePool::with_layout(
+                width,
+                height,
+                self.format,
+                self.config.layout,
+                POOL_CAPACITY,
+            )),
+        };
+
+        // Every sample is overwritten, so stale pooled content never leaks
+        let mut output = pool
+            .try_acquire()
+            .unwrap_or_else(|| Frame::with_layout(width, height, self.format, self.config.layout));
+        convert_into(frame, &mut output, &self.config)?;
+        Ok(output)
+    }
+}
+
+/// Converts to the converter's pixel format, passing matching frames through
+impl Filter for Converter {
+    fn name(&self) -> &'static str {
+        "format"
+    }
+
+    fn configure(&mut self, input: VideoInfo) -> Result<VideoInfo> {
+        Ok(VideoInfo {
+            format: self.format(),
+            ..input
+        })
+    }
+
+    fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>> {
+        if frame.format() == self.format() {
+            return Ok(vec![frame]);
+        }
+        Ok(vec![Arc::new(self.convert(&frame)?)])
+    }
+}
+
+/// Convert a frame to `format`, keeping its timestamp
+pub fn convert(frame: &Frame, format: PixelFormat, config: &ConvertConfig) -> Result<Frame> {
+    let mut output = Frame::with_layout(frame.width(), frame.height(), format, config.layout);
+    convert_into(frame, &mut output, config)?;
+    Ok(output)
+}
+
+/// Convert a frame into an existing frame of the same size
+///
+/// The output's format selects the conversion. Its colorimetry is taken
+/// from the input, with the matrix and range updated when converting
+/// between RGB and YUV.
+pub fn convert_into(frame: &Frame, output: &mut Frame, config: &ConvertConfig) -> Result<()> {
+    if (frame.width(), frame.height()) != (output.width(), output.height()) {
+        return Err(Error::InvalidInput(format!(
+            "Cannot convert {}x{} frame into {}x{}",
+            frame.width(),
+            frame.height(),
+            output.width(),
+            output.height()
+        )));
+    }
+
+    let (from, to) = (frame.format(), output.format());
+    let mut colorimetry = *frame.colorimetry();
+    let position = colorimetry.chroma_position;
+    let mut image = Image::read(frame);
+
+    if !from.is_yuv() && to.is_yuv() {
+        let matrix = resolve_matrix(config.matrix, frame.height());
+        image = image.rgb_to_yuv(matrix, config.range, to.bit_depth())?;
+        colorimetry.matrix = matrix;
+        colorimetry.range = config.range;
+    } else if from.is_yuv() && !to.is_yuv() {
+        let matrix = match config.matrix {
+            MatrixCoefficients::Unspecified => resolve_matrix(colorimetry.matrix, frame.height()),
+            matrix => matrix,
+        };
+        image.resample((0, 0), position);
+        image = image.yuv_to_rgb(matrix, colorimetry.range, config.dither)?;
+        colorimetry.matrix = MatrixCoefficients::Identity;
+        colorimetry.range = ColorRange::Full;
+    }
+
+    if to.is_yuv() {
+        // Filter chroma at the higher of the two bit depths
+        let depth = to.bit_depth();
+        if depth > image.depth {
+            image.set_depth(depth, colorimetry.range, config.dither);
+        }
+        image.resample(to.chroma_shift(), position);
+        if depth < image.depth {
+            image.set_depth(depth, colorimetry.range, config.dither);
+        }
+    }
+
+    image.write(output);
+    if let Some(pts) = frame.pts() {
+        output.set_pts(pts);
+    }
+    output.set_colorimetry(colorimetry);
+    Ok(())
+}
+
+/// Pick the YUV matrix, defaulting by resolution like most players do
+pub(crate) fn resolve_matrix(matrix: MatrixCoefficients, height: u32) -> MatrixCoefficients {
+    match matrix {
+        MatrixCoefficients::Unspecified if height > 576 => MatrixCoefficients::Bt709,
+        MatrixCoefficients::Unspecified => MatrixCoefficients::Bt601,
+        matrix => matrix,
+    }
+}
+
+/// Luma weights (Kr, Kb) of a matrix, `None` for identity (GBR)
+fn luma_weights(matrix: MatrixCoefficients) -> Result<Option<(f32, f32)>> {
+    match matrix {
+        MatrixCoefficients::Identity => Ok(None),
+        MatrixCoefficients::Bt709 => Ok(Some((0.2126, 0.0722))),
+        MatrixCoefficients::Bt601 | MatrixCoefficients::Bt470Bg => Ok(Some((0.299, 0.114))),
+        MatrixCoefficients::Fcc => Ok(Some((0.30, 0.11))),
+        MatrixCoefficients::Smpte240 => Ok(Some((0.212, 0.087))),
+        MatrixCoefficients::Bt2020Ncl => Ok(Some((0.2627, 0.0593))),
+        other => Err(Error::UnsupportedFormat(format!(
+            "RGB conversion with {} matrix",
+            other.name()
+        ))),
+    }
+}
+
+/// Scale and offset mapping normalized luma and chroma to code values
+///
+/// Returns `((luma_scale, luma_offset), (chroma_scale, chroma_offset))`.
+fn quantization(range: ColorRange, depth: u8) -> ((f32, f32), (f32, f32)) {
+    match range {
+        ColorRange::Limited => {
+            let unit = f32::from(1u16 << (depth - 8));
+            ((219.0 * unit, 16.0 * unit), (224.0 * unit, 128.0 * unit))
+        }
+        ColorRange::Full => {
+            let max = ((1u32 << depth) - 1) as f32;
+            ((max, 0.0), (max, f32::from(1u16 << (depth - 1))))
+        }
+    }
+}
+
+/// Dither threshold in 64ths for a sample position
+fn threshold(dither: Dither, x: usize, y: usize) -> u8 {
+    match dither {
+        Dither::None => 32,
+        Dither::Ordered => BAYER[y & 7][x & 7],
+    }
+}
+
+/// One plane of samples widened to 16 bits
+#[derive(Clone)]
+struct Samples {
+    width: usize,
+    height: usize,
+    data: Vec<u16>,
+}
+
+impl Samples {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            data: vec![0; width * height],
+        }
+    }
+
+    fn row(&self, y: usize) -> &[u16] {
+        &self.data[y * self.width..(y + 1) * self.width]
+    }
+
+    fn row_mut(&mut self, y: usize) -> &mut [u16] {
+        &mut self.data[y * self.width..(y + 1) * self.width]
+    }
+
+    fn transpose(&self) -> Self {
+        let mut out = Self::new(self.height, self.width);
+        for y in 0..self.height {
+            for (x, &sample) in self.row(y).iter().enumerate() {
+                out.data[x * self.height + y] = sample;
+            }
+        }
+        out
+    }
+
+    /// Resample every row to `width`, halving or doubling it
+    fn resample_rows(&self, width: usize, cosited: bool) -> Self {
+        let mut out = Self::new(width, self.height);
+        for y in 0..self.height {
+            resample_line(self.row(y), out.row_mut(y), cosited);
+        }
+        out
+    }
+
+    /// Split interleaved components into separate planes
+    fn deinterleave(&self, count: usize) -> Vec<Self> {
+        (0..count)
+            .map(|component| Self {
+                width: self.width / count,
+                height: self.height,
+                data: self
+                    .data
+                    .iter()
+                    .skip(component)
+                    .step_by(count)
+                    .copied()
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Interleave equally sized planes into one
+    fn interleave(planes: &[Self]) -> Self {
+        let data = (0..planes[0].data.len())
+            .flat_map(|index| planes.iter().map(move |plane| plane.data[index]))
+            .collect();
+        Self {
+            width: planes[0].width * planes.len(),
+            height: planes[0].height,
+            data,
+        }
+    }
+}
+
+/// Halve or double a line of chroma samples
+///
+/// Co-sited chroma sits on even luma samples, centered chroma between two.
+fn resample_line(src: &[u16], dst: &mut [u16], cosited: bool) {
+    let last = src.len() as isize - 1;
+    let at = |i: isize| u32::from(src[i.clamp(0, last) as usize]);
+
+    if dst.len() < src.len() {
+        for (k, out) in dst.iter_mut().enumerate() {
+            let i = 2 * k as isize;
+            *out = if cosited {
+                (at(i - 1) + 2 * at(i) + at(i + 1) + 2) / 4
+            } else {
+                (at(i - 1) + 3 * at(i) + 3 * at(i + 1) + at(i + 2) + 4) / 8
+            } as u16;
+        }
+    } else {
+        for (j, out) in dst.iter_mut().enumerate() {
+            let k = (j / 2) as isize;
+            *out = match (cosited, j % 2) {
+                (true, 0) => at(k),
+                (true, _) => (at(k) + at(k + 1)).div_ceil(2),
+                (false, 0) => (at(k - 1) + 3 * at(k) + 2) / 4,
+                (false, _) => (3 * at(k) + at(k + 1) + 2) / 4,
+            } as u16;
+        }
+    }
+}
+
+/// Planar working copy of a frame: Y, U, V or R, G, B
+struct Image {
+    planes: Vec<Samples>,
+    depth: u8,
+    /// Chroma subsampling of planes 1 and 2
+    shift: (u32, u32),
+}
+
+impl Image {
+    fn read(frame: &Frame) -> Self {
+        let format = frame.format();
+        let mut planes: Vec<Samples> = frame
+            .planes()
+            .iter()
+            .map(|plane| read_plane(plane, format))
+            .collect();
+
+        if !format.is_yuv() {
+            planes = planes[0].deinterleave(3);
+        } else if format.is_semi_planar() {
+            let uv = planes.pop().expect("semi-planar frames have a UV plane");
+            planes.extend(uv.deinterleave(2));
+        }
+
+        Self {
+            planes,
+            depth: format.bit_depth(),
+            shift: format.chroma_shift(),
+        }
+    }
+
+    fn write(&self, frame: &mut Frame) {
+        let format = frame.format();
+        let interleaved;
+        let planes: Vec<&Samples> = if !format.is_yuv() {
+            interleaved = Samples::interleave(&self.planes);
+            vec![&interleaved]
+        } else if format.is_semi_planar() {
+            interleaved = Samples::interleave(&self.planes[1..]);
+            vec![&self.planes[0], &interleaved]
+        } else {
+            self.planes.iter().collect()
+        };
+
+        for (plane, samples) in frame.planes_mut().iter_mut().zip(planes) {
+            write_plane(samples, plane, format);
+        }
+    }
+
+    /// Resample the chroma planes to a new subsampling
+    fn resample(&mut self, shift: (u32, u32), position: ChromaSamplePosition) {
+        let (width, height) = (self.planes[0].width, self.planes[0].height);
+        let cosited_x = position != ChromaSamplePosition::Unknown;
+        let cosited_y = position == ChromaSamplePosition::Colocated;
+
+        for plane in &mut self.planes[1..] {
+            if shift.0 != self.shift.0 {
+                *plane = plane.resample_rows(width.div_ceil(1 << shift.0), cosited_x);
+            }
+            if shift.1 != self.shift.1 {
+                *plane = plane
+                    .transpose()
+                    .resample_rows(height.div_ceil(1 << shift.1), cosited_y)
+                    .transpose();
+            }
+        }
+        self.shift = shift;
+    }
+
+    /// Rescale every sample to a new bit depth
+    ///
+    /// Limited range scales by a power of two, full range maps white to
+    /// white. Dithering only applies when reducing depth.
+    fn set_depth(&mut self, depth: u8, range: ColorRange, dither: Dither) {
+        let (num, den) = match range {
+            ColorRange::Limited => (1u64 << depth, 1u64 << self.depth),
+            ColorRange::Full => ((1u64 << depth) - 1, (1u64 << self.depth) - 1),
+        };
+        let dither = if depth < self.depth {
+            dither
+        } else {
+            Dither::None
+        };
+        let max = (1u64 << depth) - 1;
+
+        for plane in &mut self.planes {
+            for y in 0..plane.height {
+                for (x, sample) in plane.row_mut(y).iter_mut().enumerate() {
+                    let t = u64::from(threshold(dither, x, y));
+                    *sample =
+                        ((u64::from(*sample) * num * 64 + t * den) / (den * 64)).min(max) as u16;
+                }
+            }
+        }
+        self.depth = depth;
+    }
+
+    /// Convert full range 8-bit R, G, B planes to 4:4:4 YUV
+    fn rgb_to_yuv(&self, matrix: MatrixCoefficients, range: ColorRange, depth: u8) -> Result<Self> {
+        let weights = luma_weights(matrix)?;
+        let ((luma_scale, luma_offset), (chroma_scale, chroma_offset)) = quantization(range, depth);
+        let max = ((1u32 << depth) - 1) as f32;
+        let quantize = |value: f32| (value.round().clamp(0.0, max)) as u16;
+
+        let [r, g, b] = [0, 1, 2].map(|index| &self.planes[index].data);
+        let (width, height) = (self.planes[0].width, self.planes[0].height);
+        let mut planes = vec![Samples::new(width, height); 3];
+
+        for index in 0..width * height {
+            let [r, g, b] = [r[index], g[index], b[index]].map(|v| f32::from(v) / 255.0);
+            let yuv = match weights {
+                // GBR: green in the luma plane, blue and red as chroma
+                None => [g, b, r].map(|v| quantize(v * luma_scale + luma_offset)),
+                Some((kr, kb)) => {
+                    let y = kr * r + (1.0 - kr - kb) * g + kb * b;
+                    let u = (b - y) / (2.0 * (1.0 - kb));
+                    let v = (r - y) / (2.0 * (1.0 - kr));
+                    [
+                        quantize(y * luma_scale + luma_offset),
+                        quantize(u * chroma_scale + chroma_offset),
+                        quantize(v * chroma_scale + chroma_offset),
+                    ]
+                }
+            };
+            for (plane, value) in planes.iter_mut().zip(yuv) {
+                plane.data[index] = value;
+            }
+        }
+
+        Ok(Self {
+            planes,
+            depth,
+            shift: (0, 0),
+        })
+    }
+
+    /// Convert 4:4:4 YUV to full range 8-bit R, G, B planes
+    fn yuv_to_rgb(
+        &self,
+        matrix: MatrixCoefficients,
+        range: ColorRange,
+        dither: Dither,
+    ) -> Result<Self> {
+        let weights = luma_weights(matrix)?;
+        let ((luma_scale, luma_offset), (chroma_scale, chroma_offset)) =
+            quantization(range, self.depth);
+
+        let [y_plane, u_plane, v_plane] = [0, 1, 2].map(|index| &self.planes[index]);
+        let (width, height) = (y_plane.width, y_plane.height);
+        let mut planes = vec![Samples::new(width, height); 3];
+
+        for row in 0..height {
+            for x in 0..width {
+                let index = row * width + x;
+                let [y, u, v] =
+                    [y_plane, u_plane, v_plane].map(|plane| f32::from(plane.data[index]));
+                let rgb = match weights {
+                    None => {
+                        let [g, b, r] = [y, u, v].map(|value| (value - luma_offset) / luma_scale);
+                        [r, g, b]
+                    }
+                    Some((kr, kb)) => {
+                        let y = (y - luma_offset) / luma_scale;
+                        let u = (u - chroma_offset) / chroma_scale;
+                        let v = (v - chroma_offset) / chroma_scale;
+                        let r = y + 2.0 * (1.0 - kr) * v;
+                        let b = y + 2.0 * (1.0 - kb) * u;
+                        let g = (y - kr * r - kb * b) / (1.0 - kr - kb);
+                        [r, g, b]
+                    }
+                };
+
+                let t = f32::from(threshold(dither, x, row)) / 64.0;
+                for (plane, value) in planes.iter_mut().zip(rgb) {
+                    plane.data[index] = (value * 255.0 + t).floor().clamp(0.0, 255.0) as u16;
+                }
+            }
+        }
+
+        Ok(Self {
+            planes,
+            depth: 8,
+            shift: (0, 0),
+        })
+    }
+}
+
+/// Read a plane into 16-bit samples, unpacking P010's high-bit storage
+fn read_plane(plane: &Plane, format: PixelFormat) -> Samples {
+    let mut samples = Samples::new(plane.width(), plane.height());
+    for y in 0..plane.height() {
+        let row = plane.row(y);
+        let out = samples.row_mut(y);
+        if plane.bytes_per_sample() == 1 {
+            for (dst, &src) in out.iter_mut().zip(row) {
+                *dst = u16::from(src);
+            }
+        } else {
+            let shift = if format == PixelFormat::P010 { 6 } else { 0 };
+            for (dst, src) in out.iter_mut().zip(row.chunks_exact(2)) {
+                *dst = u16::from_le_bytes([src[0], src[1]]) >> shift;
+            }
+        }
+    }
+    samples
+}
+
+/// Write 16-bit samples into a plane, packing P010's high-bit storage
+fn write_plane(samples: &Samples, plane: &mut Plane, format: PixelFormat) {
+    for y in 0..plane.height() {
+        let src = samples.row(y);
+        if plane.bytes_per_sample() == 1 {
+            for (dst, &sample) in plane.row_mut(y).iter_mut().zip(src) {
+                *dst = sample as u8;
+            }
+        } else {
+            let shift = if format == PixelFormat::P010 { 6 } else { 0 };
+            for (dst, &sample) in plane.row_mut(y).chunks_exact_mut(2).zip(src) {
+                dst.copy_from_slice(&(sample << shift).to_le_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(frame: &mut Frame, value: impl Fn(usize, usize, usize) -> u16) {
+        let bytes = frame.format().bytes_per_sample();
+        for (index, plane) in frame.planes_mut().iter_mut().enumerate() {
+            for y in 0..plane.height() {
+                for (x, sample) in plane.row_mut(y).chunks_exact_mut(bytes).enumerate() {
+                    sample.copy_from_slice(&value(index, x, y).to_le_bytes()[..bytes]);
+                }
+            }
+        }
+    }
+
+    fn samples(plane: &Plane) -> Vec<u16> {
+        read_plane(plane, PixelFormat::Yuv420p10).data
+    }
+
+    #[test]
+    fn test_chroma_roundtrip() {
+        let mut frame = Frame::new(8, 4, PixelFormat::Yuv420p);
+        fill(&mut frame, |index, x, _| {
+            if index == 0 { 100 } else { 60 + 10 * x as u16 }
+        });
+        let mut colorimetry = *frame.colorimetry();
+        colorimetry.chroma_position = ChromaSamplePosition::Colocated;
+        frame.set_colorimetry(colorimetry);
+
+        let config = ConvertConfig::default();
+        let full = convert(&frame, PixelFormat::Yuv444p, &config).unwrap();
+        assert_eq!(full.planes()[1].width(), 8);
+        // Co-sited chroma lands unchanged on even samples, interpolated between
+        assert_eq!(full.planes()[1].row(0), &[60, 65, 70, 75, 80, 85, 90, 90]);
+
+        let back = convert(&full, PixelFormat::Yuv420p, &config).unwrap();
+        assert_eq!(back.planes()[0].data(), frame.planes()[0].data());
+        assert_eq!(back.planes()[1].row(0), &[61, 70, 80, 89]);
+    }
+
+    #[test]
+    fn test_bit_depth_dither() {
+        // 514 is 128.5 in 8-bit terms
+        let mut frame = Frame::new(8, 8, PixelFormat::Yuv444p10);
+        fill(&mut frame, |_, _, _| 514);
+
+        let rounded = ConvertConfig {
+            dither: Dither::None,
+            ..Default::default()
+        };
+        let plain = convert(&frame, PixelFormat::Yuv444p, &rounded).unwrap();
+        assert!(plain.planes()[0].data().iter().all(|&v| v == 129));
+
+        let dithered = convert(&frame, PixelFormat::Yuv444p, &ConvertConfig::default()).unwrap();
+        let luma = dithered.planes()[0].data();
+        assert!(luma.iter().all(|&v| v == 128 || v == 129));
+        assert_eq!(
+            luma.iter().map(|&v| u32::from(v)).sum::<u32>(),
+            128 * 64 + 32
+        );
+
+        // Back up to 10-bit is a plain shift in limited range
+        let restored = convert(&plain, PixelFormat::Yuv444p10, &rounded).unwrap();
+        assert!(samples(&restored.planes()[0]).iter().all(|&v| v == 516));
+    }
+
+    #[test]
+    fn test_semi_planar_roundtrip() {
+        let mut frame = Frame::new(4, 2, PixelFormat::Yuv420p10);
+        fill(&mut frame, |index, x, y| (index * 300 + x * 10 + y) as u16);
+
+        let p010 = convert(&frame, PixelFormat::P010, &ConvertConfig::default()).unwrap();
+        let uv = p010.plane_uv().unwrap();
+        // U and V alternate, stored in the high ten bits
+        assert_eq!(
+            &uv.row(0)[..4],
+            &[(300u16 << 6).to_le_bytes(), (600u16 << 6).to_le_bytes()].concat()
+        );
+
+        let back = convert(&p010, PixelFormat::Yuv420p10, &ConvertConfig::default()).unwrap();
+        for index in 0..3 {
+            assert_eq!(back.planes()[index].data(), frame.planes()[index].data());
+        }
+
+        let nv12 = convert(&frame, PixelFormat::Nv12, &ConvertConfig::default()).unwrap();
+        assert_eq!(nv12.plane_uv().unwrap().width(), 4);
+    }
+
+    #[test]
+    fn test_rgb_yuv() {
+        let mut rgb = Frame::new(4, 2, PixelFormat::Rgb24);
+        // White, black, red, green across the top row; grey below
+        let top = [255, 255, 255, 0, 0, 0, 255, 0, 0, 0, 255, 0];
+        rgb.planes_mut()[0].row_mut(0).copy_from_slice(&top);
+        rgb.planes_mut()[0].row_mut(1).fill(128);
+
+        let config = ConvertConfig {
+            matrix: MatrixCoefficients::Bt709,
+            ..Default::default()
+        };
+        let yuv = convert(&rgb, PixelFormat::Yuv444p, &config).unwrap();
+        assert_eq!(yuv.colorimetry().matrix, MatrixCoefficients::Bt709);
+        assert_eq!(yuv.planes()[0].row(0), &[235, 16, 63, 173]);
+        assert_eq!(yuv.planes()[1].row(0), &[128, 128, 102, 42]);
+        assert_eq!(yuv.planes()[2].row(0), &[128, 128, 240, 26]);
+
+        let rounded = ConvertConfig {
+            dither: Dither::None,
+            ..Default::default()
+        };
+        let back = convert(&yuv, PixelFormat::Rgb24, &rounded).unwrap();
+        for (a, b) in back.planes()[0].data().iter().zip(rgb.planes()[0].data()) {
+            assert!(a.abs_diff(*b) <= 1, "{} vs {}", a, b);
+        }
+    }
+
+    #[test]
+    fn test_converter_reuses_frames() {
+        let mut converter = Converter::new(PixelFormat::Yuv420p, ConvertConfig::default());
+        let mut frame = Frame::new(16, 16, PixelFormat::Yuv444p);
+        frame.set_pts(3);
+
+        let first = converter.convert(&frame).unwrap();
+        assert_eq!(first.pts(), Some(3));
+        let luma = first.planes()[0].data().as_ptr();
+        drop(first);
+        assert_eq!(
+            converter.convert(&frame).unwrap().planes()[0]
+                .data()
+                .as_ptr(),
+            luma
+        );
+
+        // Already in the output format: shared, not copied
+        let same = Frame::new(16, 16, PixelFormat::Yuv420p);
+        let passed = converter.convert(&same).unwrap();
+        assert_eq!(
+            passed.planes()[0].data().as_ptr(),
+            same.planes()[0].data().as_ptr()
+        );
+    }
+}
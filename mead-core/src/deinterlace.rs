@@ -0,0 +1,543 @@
+//! Deinterlacing
+//!
+//! [`DeinterlaceFilter`] turns interlaced frames into progressive ones,
+//! following each frame's [`FieldOrder`], so progressive frames of mixed
+//! streams pass through untouched. [`DeinterlaceMode::Bob`] rebuilds the
+//! missing field from the lines above and below. [`DeinterlaceMode::Adaptive`]
+//! works like yadif: it compares the previous and next frames to find
+//! motion, keeps static areas at full vertical resolution and interpolates
+//! along edges where the picture moves.
+//!
+//! By default each frame keeps its first field; with
+//! [`DeinterlaceConfig::field_rate`] both fields become frames, doubling
+//! the frame rate and keeping all motion.
+//!
+//! # Example
+//! ```no_run
+//! use mead_core::container::y4m::Y4mDemuxer;
+//! use mead_core::deinterlace::{DeinterlaceConfig, DeinterlaceFilter};
+//! use mead_core::filter::Filter;
+//! use std::fs::File;
+//! use std::sync::Arc;
+//!
+//! let mut demuxer = Y4mDemuxer::new(File::open("broadcast.y4m")?)?;
+//! let mut deinterlace = DeinterlaceFilter::new(DeinterlaceConfig {
+//!     field_rate: true,
+//!     ..Default::default()
+//! });
+//!
+//! while let Some(frame) = demuxer.read_frame()? {
+//!     for progressive in deinterlace.filter(Arc::new(frame))? {
+//!         // Encode progressive frames at twice the input rate
+//!     }
+//! }
+//! let remaining = deinterlace.flush()?;
+//! # Ok::<(), mead_core::Error>(())
+//! ```
+
+use crate::filter::{Filter, VideoInfo};
+use crate::{ArcFrame, Error, FieldOrder, Frame, FramePool, PixelFormat, Plane, Result};
+use std::cell::OnceCell;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How the missing field is rebuilt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeinterlaceMode {
+    /// Interpolate between the lines above and below
+    Bob,
+    /// Motion adaptive (yadif): weave where static, interpolate where moving
+    #[default]
+    Adaptive,
+}
+
+/// Parses `bob` and `adaptive` (or `yadif`)
+impl FromStr for DeinterlaceMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "bob" => Ok(Self::Bob),
+            "adaptive" | "yadif" => Ok(Self::Adaptive),
+            _ => Err(Error::InvalidInput(format!(
+                "Unknown deinterlace mode: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Deinterlacer configuration
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeinterlaceConfig {
+    /// How the missing field is rebuilt
+    pub mode: DeinterlaceMode,
+    /// Output one frame per field, doubling the frame rate
+    pub field_rate: bool,
+    /// Field order to use instead of each frame's own, for sources that
+    /// do not signal interlacing
+    pub field_order: Option<FieldOrder>,
+}
+
+/// Deinterlaced frames kept for reuse
+const POOL_CAPACITY: usize = 4;
+
+/// Deinterlaces frames by their field order
+///
+/// The adaptive mode looks one frame ahead, so it holds the latest frame
+/// until the next arrives or the filter is flushed.
+#[derive(Debug)]
+pub struct DeinterlaceFilter {
+    config: DeinterlaceConfig,
+    prev: Option<Picture>,
+    cur: Option<Picture>,
+    /// Timestamp given to the next input frame that has none
+    count: i64,
+    pool: Option<FramePool>,
+}
+
+impl DeinterlaceFilter {
+    /// Create a deinterlacer
+    pub fn new(config: DeinterlaceConfig) -> Self {
+        Self {
+            config,
+            prev: None,
+            cur: None,
+            count: 0,
+            pool: None,
+        }
+    }
+
+    /// Output frames for `cur`, using its neighbours for motion detection
+    fn process(
+        &mut self,
+        cur: &Picture,
+        prev: Option<&Picture>,
+        next: Option<&Picture>,
+    ) -> Result<Vec<ArcFrame>> {
+        let pts = cur.frame.pts().unwrap_or(self.count);
+        self.count = pts + 1;
+        let fields = if self.config.field_rate { 2 } else { 1 };
+        let retime = |index: i64| if fields == 2 { pts * 2 + index } else { pts };
+
+        let top_first = match self.config.field_order.unwrap_or(cur.frame.field_order()) {
+            FieldOrder::TopFieldFirst => true,
+            FieldOrder::BottomFieldFirst => false,
+            FieldOrder::Progressive => {
+                return Ok((0..fields)
+                    .map(|index| {
+                        let mut frame = Frame::clone(&cur.frame);
+                        frame.set_pts(retime(index));
+                        Arc::new(frame)
+                    })
+                    .collect());
+            }
+        };
+
+        // Neighbours of another size can't help, so fall back to the frame itself
+        let matches = |other: &&Picture| {
+            let (a, b) = (&other.frame, &cur.frame);
+            (a.width(), a.height(), a.format()) == (b.width(), b.height(), b.format())
+        };
+        let prev = prev.filter(matches).unwrap_or(cur);
+        let next = next.filter(matches).unwrap_or(cur);
+
+        let mut frames = Vec::with_capacity(fields as usize);
+        for index in 0..fields {
+            let first = index == 0;
+            let neighbours = Neighbours {
+                prev,
+                next,
+                // The missing lines around the kept field's time
+                before: if first { prev } else { cur },
+                after: if first { cur } else { next },
+            };
+            let mut frame = self.render(cur, &neighbours, top_first == first)?;
+            frame.set_pts(retime(index));
+            frames.push(Arc::new(frame));
+        }
+        Ok(frames)
+    }
+
+    /// Build a progressive frame keeping the top or bottom field of `cur`
+    fn render(
+        &mut self,
+        cur: &Picture,
+        neighbours: &Neighbours<'_>,
+        keep_top: bool,
+    ) -> Result<Frame> {
+        let source = &cur.frame;
+        let (width, height, format) = (source.width(), source.height(), source.format());
+        let pool = match &mut self.pool {
+            Some(pool)
+                if (pool.width(), pool.height(), pool.format()) == (width, height, format) =>
+            {
+                pool
+            }
+            pool => pool.insert(FramePool::new(width, height, format, POOL_CAPACITY)),
+        };
+        // Every sample is overwritten, so stale pooled content never leaks
+        let mut output = pool
+            .try_acquire()
+            .unwrap_or_else(|| Frame::new(width, height, format));
+
+        let max = (1i32 << format.bit_depth()) - 1;
+        let shift = if format == PixelFormat::P010 { 6 } else { 0 };
+        let mut line = Vec::new();
+        for (index, dst) in output.planes_mut().iter_mut().enumerate() {
+            let kept = if keep_top { 0 } else { 1 };
+            for y in 0..dst.height() {
+                if y % 2 == kept {
+                    dst.row_mut(y)
+                        .copy_from_slice(source.planes()[index].row(y));
+                    continue;
+                }
+                let plane = PlaneRef {
+                    cur: &cur.planes()[index],
+                    prev: &neighbours.prev.planes()[index],
+                    next: &neighbours.next.planes()[index],
+                    before: &neighbours.before.planes()[index],
+                    after: &neighbours.after.planes()[index],
+                };
+                match self.config.mode {
+                    DeinterlaceMode::Bob => plane.bob(y, &mut line),
+                    DeinterlaceMode::Adaptive => plane.adaptive(y, &mut line),
+                }
+                pack_row(&line, dst, y, max, shift);
+            }
+        }
+
+        output.set_colorimetry(*source.colorimetry());
+        Ok(output)
+    }
+}
+
+impl Filter for DeinterlaceFilter {
+    fn name(&self) -> &'static str {
+        "deinterlace"
+    }
+
+    fn configure(&mut self, input: VideoInfo) -> Result<VideoInfo> {
+        let (num, den) = input.framerate;
+        let framerate = if self.config.field_rate {
+            (num * 2, den)
+        } else {
+            (num, den)
+        };
+        Ok(VideoInfo { framerate, ..input })
+    }
+
+    fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>> {
+        let picture = Picture::new(frame);
+        if self.config.mode == DeinterlaceMode::Bob {
+            return self.process(&picture, None, None);
+        }
+
+        let prev = self.prev.take();
+        let frames = match self.cur.take() {
+            Some(cur) => {
+                let frames = self.process(&cur, prev.as_ref(), Some(&picture))?;
+                self.prev = Some(cur);
+                frames
+            }
+            None => Vec::new(),
+        };
+        self.cur = Some(picture);
+        Ok(frames)
+    }
+
+    fn flush(&mut self) -> Result<Vec<ArcFrame>> {
+        let prev = self.prev.take();
+        match self.cur.take() {
+            Some(cur) => self.process(&cur, prev.as_ref(), None),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// A frame with its samples unpacked on first use
+#[derive(Debug)]
+struct Picture {
+    frame: ArcFrame,
+    planes: OnceCell<Vec<Samples>>,
+}
+
+impl Picture {
+    fn new(frame: ArcFrame) -> Self {
+        Self {
+            frame,
+            planes: OnceCell::new(),
+        }
+    }
+
+    fn planes(&self) -> &[Samples] {
+        self.planes.get_or_init(|| {
+            let format = self.frame.format();
+            self.frame
+                .planes()
+                .iter()
+                .enumerate()
+                .map(|(index, plane)| Samples::new(plane, format, index))
+                .collect()
+        })
+    }
+}
+
+/// One plane's samples, widened for filtering
+#[derive(Debug)]
+struct Samples {
+    /// Width in samples
+    width: usize,
+    height: usize,
+    /// Interleaved components per pixel (3 for RGB, 2 for NV12 chroma)
+    components: usize,
+    data: Vec<i32>,
+}
+
+impl Samples {
+    fn new(plane: &Plane, format: PixelFormat, index: usize) -> Self {
+        let components = match (format.is_yuv(), index) {
+            (false, _) => 3,
+            (true, 1) if format.is_semi_planar() => 2,
+            _ => 1,
+        };
+        let shift = if format == PixelFormat::P010 { 6 } else { 0 };
+        let mut data = Vec::with_capacity(plane.width() * plane.height());
+        for y in 0..plane.height() {
+            let row = plane.row(y);
+            if plane.bytes_per_sample() == 1 {
+                data.extend(row.iter().map(|&v| i32::from(v)));
+            } else {
+                data.extend(
+                    row.chunks_exact(2)
+                        .map(|v| i32::from(u16::from_le_bytes([v[0], v[1]]) >> shift)),
+                );
+            }
+        }
+        Self {
+            width: plane.width(),
+            height: plane.height(),
+            components,
+            data,
+        }
+    }
+
+    /// Sample at (`x`, `y`), clamping `x` to the row
+    fn at(&self, x: isize, y: usize) -> i32 {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        self.data[y * self.width + x]
+    }
+}
+
+/// Frames consulted when rebuilding one field
+struct Neighbours<'a> {
+    prev: &'a Picture,
+    next: &'a Picture,
+    /// Frame holding the missing lines just before the kept field in time
+    before: &'a Picture,
+    /// Frame holding the missing lines just after the kept field in time
+    after: &'a Picture,
+}
+
+/// One plane of the current frame and its neighbours
+struct PlaneRef<'a> {
+    cur: &'a Samples,
+    prev: &'a Samples,
+    next: &'a Samples,
+    before: &'a Samples,
+    after: &'a Samples,
+}
+
+impl PlaneRef<'_> {
+    /// Lines above and below `y` in the kept field, mirrored at the edges
+    fn around(&self, y: usize) -> (usize, usize) {
+        let last = self.cur.height - 1;
+        let above = if y > 0 { y - 1 } else { (y + 1).min(last) };
+        let below = if y < last { y + 1 } else { y.saturating_sub(1) };
+        (above, below)
+    }
+
+    /// Average the lines above and below
+    fn bob(&self, y: usize, line: &mut Vec<i32>) {
+        let (above, below) = self.around(y);
+        line.clear();
+        line.extend(
+            (0..self.cur.width as isize)
+                .map(|x| (self.cur.at(x, above) + self.cur.at(x, below) + 1) >> 1),
+        );
+    }
+
+    /// yadif: an edge-directed spatial prediction, limited by how much the
+    /// pixel changes over time
+    fn adaptive(&self, y: usize, line: &mut Vec<i32>) {
+        let (above, below) = self.around(y);
+        // Lines two away on both sides, absent at the picture edges
+        let outer = (y >= 2 && y + 2 < self.cur.height).then(|| (y - 2, y + 2));
+        let step = self.cur.components as isize;
+        let cur = self.cur;
+
+        line.clear();
+        for x in 0..cur.width as isize {
+            let c = cur.at(x, above);
+            let e = cur.at(x, below);
+            let (before, after) = (self.before.at(x, y), self.after.at(x, y));
+            let d = (before + after) >> 1;
+
+            let temporal = (before - after).abs() >> 1;
+            let prev_diff =
+                ((self.prev.at(x, above) - c).abs() + (self.prev.at(x, below) - e).abs()) >> 1;
+            let next_diff =
+                ((self.next.at(x, above) - c).abs() + (self.next.at(x, below) - e).abs()) >> 1;
+            let mut diff = temporal.max(prev_diff).max(next_diff);
+
+            // Follow the edge direction with the best match across the gap
+            let score = |j: isize| {
+                (cur.at(x - step + j * step, above) - cur.at(x - step - j * step, below)).abs()
+                    + (cur.at(x + j * step, above) - cur.at(x - j * step, below)).abs()
+                    + (cur.at(x + step + j * step, above) - cur.at(x + step - j * step, below))
+                        .abs()
+            };
+            let mut best = score(0) - 1;
+            let mut spatial = (c + e) >> 1;
+            for direction in [-1, 1] {
+                for j in [direction, direction * 2] {
+                    let candidate = score(j);
+                    if candidate >= best {
+                        break;
+                    }
+                    best = candidate;
+                    spatial = (cur.at(x + j * step, above) + cur.at(x - j * step, below)) >> 1;
+                }
+            }
+
+            // Allow more change where the lines two away agree it is there
+            if let Some((up2, down2)) = outer {
+                let b = (self.before.at(x, up2) + self.after.at(x, up2)) >> 1;
+                let f = (self.before.at(x, down2) + self.after.at(x, down2)) >> 1;
+                let high = (d - e).max(d - c).max((b - c).min(f - e));
+                let low = (d - e).min(d - c).min((b - c).max(f - e));
+                diff = diff.max(low).max(-high);
+            }
+
+            line.push(spatial.clamp(d - diff, d + diff));
+        }
+    }
+}
+
+/// Write widened samples into row `y` of a plane
+fn pack_row(line: &[i32], plane: &mut Plane, y: usize, max: i32, shift: u32) {
+    let row = plane.row_mut(y);
+    if max <= 255 {
+        for (dst, &sample) in row.iter_mut().zip(line) {
+            *dst = sample.clamp(0, max) as u8;
+        }
+    } else {
+        for (dst, &sample) in row.chunks_exact_mut(2).zip(line) {
+            dst.copy_from_slice(&((sample.clamp(0, max) as u16) << shift).to_le_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 4:4:4 frame whose luma is `top` on even lines and `bottom` on odd ones
+    fn fields(top: u8, bottom: u8, pts: i64) -> ArcFrame {
+        let mut frame = Frame::new(8, 8, PixelFormat::Yuv444p);
+        let luma = &mut frame.planes_mut()[0];
+        for y in 0..8 {
+            luma.row_mut(y).fill(if y % 2 == 0 { top } else { bottom });
+        }
+        frame.set_pts(pts);
+        frame.set_field_order(FieldOrder::TopFieldFirst);
+        Arc::new(frame)
+    }
+
+    fn run(config: DeinterlaceConfig, frames: Vec<ArcFrame>) -> Vec<ArcFrame> {
+        let mut filter = DeinterlaceFilter::new(config);
+        let mut output = Vec::new();
+        for frame in frames {
+            output.extend(filter.filter(frame).unwrap());
+        }
+        output.extend(filter.flush().unwrap());
+        output
+    }
+
+    fn luma(frame: &Frame) -> &[u8] {
+        frame.planes()[0].data()
+    }
+
+    #[test]
+    fn test_bob() {
+        let bob = DeinterlaceConfig {
+            mode: DeinterlaceMode::Bob,
+            ..Default::default()
+        };
+        let output = run(bob, vec![fields(100, 200, 0)]);
+        assert_eq!(output.len(), 1);
+        assert!(luma(&output[0]).iter().all(|&v| v == 100));
+        assert_eq!(output[0].field_order(), FieldOrder::Progressive);
+
+        // Both fields, the top one first, at twice the rate
+        let output = run(
+            DeinterlaceConfig {
+                field_rate: true,
+                ..bob
+            },
+            vec![fields(100, 200, 3)],
+        );
+        let pts: Vec<_> = output.iter().map(|frame| frame.pts()).collect();
+        assert_eq!(pts, [Some(6), Some(7)]);
+        assert!(luma(&output[0]).iter().all(|&v| v == 100));
+        assert!(luma(&output[1]).iter().all(|&v| v == 200));
+    }
+
+    #[test]
+    fn test_adaptive_static_and_moving() {
+        // Static detail survives at full resolution
+        let mut gradient = Frame::new(8, 8, PixelFormat::Yuv420p);
+        for y in 0..8 {
+            gradient.planes_mut()[0].row_mut(y).fill(20 * y as u8);
+        }
+        gradient.set_field_order(FieldOrder::BottomFieldFirst);
+        let gradient = Arc::new(gradient);
+        let output = run(
+            DeinterlaceConfig::default(),
+            vec![gradient.clone(), gradient.clone(), gradient.clone()],
+        );
+        assert_eq!(output.len(), 3);
+        assert!(output.iter().all(|frame| luma(frame) == luma(&gradient)));
+
+        // Fields that change between frames are combing, and are interpolated away
+        let output = run(
+            DeinterlaceConfig::default(),
+            vec![fields(0, 255, 0), fields(255, 0, 1), fields(0, 255, 2)],
+        );
+        assert!(output.iter().all(|frame| {
+            let first = luma(frame)[0];
+            luma(frame).iter().all(|&v| v == first)
+        }));
+    }
+
+    #[test]
+    fn test_progressive_passthrough() {
+        let mut progressive = Frame::new(8, 8, PixelFormat::Yuv420p);
+        progressive.set_pts(5);
+        let progressive = Arc::new(progressive);
+        let output = run(
+            DeinterlaceConfig {
+                field_rate: true,
+                ..Default::default()
+            },
+            vec![progressive.clone()],
+        );
+        assert_eq!(output.len(), 2);
+        assert_eq!((output[0].pts(), output[1].pts()), (Some(10), Some(11)));
+        // Passed through without copying
+        assert_eq!(
+            output[0].planes()[0].data().as_ptr(),
+            progressive.planes()[0].data().as_ptr()
+        );
+    }
+}
@@ -0,0 +1,618 @@
+//! Denoising
+//!
+//! [`Denoiser`] removes sensor and film noise in the style of hqdn3d. A
+//! spatial pass averages each sample with its 3x3 neighbours, weighted by
+//! how close they are in value so edges stay sharp, and a temporal pass
+//! blends the result with the previous denoised frame where the picture
+//! holds still. Rows are processed on several threads.
+//!
+//! Noise costs a lot of bits to encode, but removing it also removes the
+//! texture it gives. With [`DenoiseConfig::grain`] the denoiser measures
+//! what it took out at each brightness, and [`NoiseEstimate::film_grain`]
+//! turns that into AV1 film grain parameters, so decoders add matching
+//! grain back for free.
+//!
+//! # Example
+//! ```no_run
+//! use mead_core::codec::av1::{Av1Config, Av1Encoder};
+//! use mead_core::codec::VideoEncoder;
+//! use mead_core::container::y4m::Y4mDemuxer;
+//! use mead_core::denoise::{DenoiseConfig, Denoiser};
+//! use std::fs::File;
+//! use std::sync::Arc;
+//!
+//! let mut demuxer = Y4mDemuxer::new(File::open("camera.y4m")?)?;
+//! let mut denoiser = Denoiser::new(DenoiseConfig {
+//!     grain: true,
+//!     ..Default::default()
+//! })?;
+//! let noise = denoiser.noise().expect("grain estimation is enabled");
+//!
+//! // Measure the noise on the first frame before configuring the encoder
+//! let first = demuxer.read_frame()?.expect("input has a frame");
+//! let first = denoiser.denoise(&first)?;
+//! let mut encoder = Av1Encoder::with_config(first.width(), first.height(), Av1Config {
+//!     film_grain: noise.film_grain(),
+//!     ..Default::default()
+//! })?;
+//!
+//! encoder.send_frame(Some(Arc::new(first)))?;
+//! while let Some(frame) = demuxer.read_frame()? {
+//!     encoder.send_frame(Some(Arc::new(denoiser.denoise(&frame)?)))?;
+//! }
+//! # Ok::<(), mead_core::Error>(())
+//! ```
+
+use crate::codec::av1::FilmGrain;
+use crate::filter::Filter;
+use crate::parallel::for_each_row;
+use crate::{ArcFrame, Error, Frame, FramePool, PixelFormat, Plane, Result};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Denoiser configuration
+///
+/// Strengths are in 8-bit sample steps at any bit depth: neighbours that
+/// differ from a sample by about the strength count a quarter as much as
+/// equal ones, and 0 turns a pass off.
+#[derive(Debug, Clone, Copy)]
+pub struct DenoiseConfig {
+    /// Spatial strength on luma
+    pub luma_spatial: f32,
+    /// Spatial strength on chroma
+    pub chroma_spatial: f32,
+    /// Temporal strength on luma
+    pub luma_temporal: f32,
+    /// Temporal strength on chroma
+    pub chroma_temporal: f32,
+    /// Number of threads (0 = auto-detect from CPU cores)
+    pub threads: usize,
+    /// Measure the removed noise for film grain synthesis
+    pub grain: bool,
+}
+
+impl DenoiseConfig {
+    /// Configuration with a luma spatial strength, deriving the others
+    /// from it the way hqdn3d does
+    pub fn with_strength(strength: f32) -> Self {
+        Self {
+            luma_spatial: strength,
+            chroma_spatial: strength * 0.75,
+            luma_temporal: strength * 1.5,
+            chroma_temporal: strength * 1.125,
+            threads: 0,
+            grain: false,
+        }
+    }
+}
+
+impl Default for DenoiseConfig {
+    fn default() -> Self {
+        Self::with_strength(4.0)
+    }
+}
+
+/// Denoised frames kept for reuse
+const POOL_CAPACITY: usize = 4;
+
+/// Removes noise from frames, using the previous frame for the temporal pass
+///
+/// The temporal history is dropped when the frame size or format changes.
+#[derive(Debug)]
+pub struct Denoiser {
+    config: DenoiseConfig,
+    threads: usize,
+    /// Luma and chroma weights, for the bit depth they were built for
+    weights: Option<(u8, [Weights; 2])>,
+    /// Denoised samples of the previous frame, by plane
+    history: Option<History>,
+    noise: Option<NoiseEstimate>,
+    pool: Option<FramePool>,
+}
+
+impl Denoiser {
+    /// Create a denoiser
+    pub fn new(config: DenoiseConfig) -> Result<Self> {
+        let strengths = [
+            config.luma_spatial,
+            config.chroma_spatial,
+            config.luma_temporal,
+            config.chroma_temporal,
+        ];
+        if let Some(strength) = strengths.iter().find(|s| !s.is_finite() || **s < 0.0) {
+            return Err(Error::InvalidInput(format!(
+                "Invalid denoise strength {}",
+                strength
+            )));
+        }
+
+        let threads = if config.threads == 0 {
+            num_cpus::get()
+        } else {
+            config.threads
+        };
+
+        Ok(Self {
+            config,
+            threads,
+            weights: None,
+            history: None,
+            noise: config.grain.then(NoiseEstimate::default),
+            pool: None,
+        })
+    }
+
+    /// Handle to the noise measured so far, if [`DenoiseConfig::grain`] is set
+    ///
+    /// The handle stays valid while the denoiser keeps working, so it can
+    /// be read after the denoiser has moved into a filter chain.
+    pub fn noise(&self) -> Option<NoiseEstimate> {
+        self.noise.clone()
+    }
+
+    /// Denoise a frame, keeping its timestamp and colorimetry
+    pub fn denoise(&mut self, frame: &Frame) -> Result<Frame> {
+        let (width, height, format) = (frame.width(), frame.height(), frame.format());
+        let depth = format.bit_depth();
+        let weights = match &mut self.weights {
+            Some((built, weights)) if *built == depth => weights,
+            weights => {
+                let config = &self.config;
+                let luma = Weights::new(config.luma_spatial, config.luma_temporal, depth);
+                let chroma = Weights::new(config.chroma_spatial, config.chroma_temporal, depth);
+                &mut weights.insert((depth, [luma, chroma])).1
+            }
+        };
+        let history = match self.history.take() {
+            Some(history)
+                if (history.width, history.height, history.format) == (width, height, format) =>
+            {
+                Some(history.planes)
+            }
+            _ => None,
+        };
+
+        let pool = match &mut self.pool {
+            Some(pool)
+                if (pool.width(), pool.height(), pool.format()) == (width, height, format) =>
+            {
+                pool
+            }
+            pool => pool.insert(FramePool::new(width, height, format, POOL_CAPACITY)),
+        };
+        // Every sample is overwritten, so stale pooled content never leaks
+        let mut output = pool
+            .try_acquire()
+            .unwrap_or_else(|| Frame::new(width, height, format));
+
+        let shift = if format == PixelFormat::P010 { 6 } else { 0 };
+        let max = (1i32 << depth) - 1;
+        let mut measured = NoiseStats::default();
+        let mut planes = Vec::with_capacity(frame.planes().len());
+        for (index, (src, dst)) in frame.planes().iter().zip(output.planes_mut()).enumerate() {
+            let samples = unpack(src, shift);
+            let previous = history.as_ref().map(|planes| planes[index].as_slice());
+            let plane = PlaneSamples {
+                samples: &samples,
+                previous,
+                width: src.width(),
+                components: components(format, index),
+                max,
+            };
+            let denoised = plane.denoise(&weights[usize::from(index > 0)], self.threads);
+            if self.noise.is_some() {
+                measured.add(&plane, &denoised, format, index);
+            }
+            pack(&denoised, dst, shift, self.threads);
+            planes.push(denoised);
+        }
+
+        if let Some(noise) = &self.noise {
+            noise.merge(&measured);
+        }
+        self.history = Some(History {
+            width,
+            height,
+            format,
+            planes,
+        });
+
+        if let Some(pts) = frame.pts() {
+            output.set_pts(pts);
+        }
+        output.set_colorimetry(*frame.colorimetry());
+        output.set_field_order(frame.field_order());
+        Ok(output)
+    }
+}
+
+impl Filter for Denoiser {
+    fn name(&self) -> &'static str {
+        "denoise"
+    }
+
+    fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>> {
+        Ok(vec![Arc::new(self.denoise(&frame)?)])
+    }
+}
+
+/// Noise measured by a [`Denoiser`], shared with the denoiser that fills it
+#[derive(Debug, Clone, Default)]
+pub struct NoiseEstimate {
+    stats: Arc<Mutex<NoiseStats>>,
+}
+
+/// Brightness ranges noise is measured over, and film grain points per plane
+const NOISE_BINS: usize = 8;
+
+/// Samples a brightness range needs for its noise level to count
+const MIN_BIN_SAMPLES: u64 = 256;
+
+/// Film grain scaling for a noise standard deviation of one 8-bit step,
+/// as used by libaom's photon noise tables
+const GRAIN_SCALING: f64 = 7.88;
+
+/// Film grain seed, fixed so encodes are reproducible
+const GRAIN_SEED: u16 = 7391;
+
+impl NoiseEstimate {
+    /// Number of frames measured
+    pub fn frames(&self) -> u64 {
+        self.lock().frames
+    }
+
+    /// Film grain matching the noise measured so far
+    ///
+    /// Returns `None` until a frame with measurable luma noise has been
+    /// denoised. Chroma grain is included when both chroma planes have
+    /// noise; RGB frames are not measured.
+    pub fn film_grain(&self) -> Option<FilmGrain> {
+        let stats = self.lock();
+        let points = |channel: &[Bin; NOISE_BINS]| -> Vec<(u8, u8)> {
+            let points: Vec<_> = channel
+                .iter()
+                .enumerate()
+                .filter(|(_, bin)| bin.count >= MIN_BIN_SAMPLES)
+                .map(|(index, bin)| {
+                    let level = (index * 256 / NOISE_BINS + 128 / NOISE_BINS) as u8;
+                    let sigma = (bin.sum / bin.count as f64).sqrt();
+                    (level, (sigma * GRAIN_SCALING).round().min(255.0) as u8)
+                })
+                .collect();
+            if points.iter().all(|&(_, scaling)| scaling == 0) {
+                Vec::new()
+            } else {
+                points
+            }
+        };
+
+        let luma = points(&stats.channels[0]);
+        if luma.is_empty() {
+            return None;
+        }
+        let (mut cb, mut cr) = (points(&stats.channels[1]), points(&stats.channels[2]));
+        if cb.is_empty() || cr.is_empty() {
+            cb.clear();
+            cr.clear();
+        }
+        Some(FilmGrain {
+            luma,
+            cb,
+            cr,
+            seed: GRAIN_SEED,
+        })
+    }
+
+    fn merge(&self, measured: &NoiseStats) {
+        let mut stats = self.lock();
+        stats.frames += 1;
+        for (channel, bins) in stats.channels.iter_mut().zip(&measured.channels) {
+            for (bin, measured) in channel.iter_mut().zip(bins) {
+                bin.sum += measured.sum;
+                bin.count += measured.count;
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, NoiseStats> {
+        self.stats.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Squared noise by brightness for luma, Cb and Cr
+#[derive(Debug, Default)]
+struct NoiseStats {
+    frames: u64,
+    channels: [[Bin; NOISE_BINS]; 3],
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Bin {
+    /// Sum of squared noise, in 8-bit steps
+    sum: f64,
+    count: u64,
+}
+
+impl NoiseStats {
+    /// Add the difference between a plane and its denoised samples
+    fn add(
+        &mut self,
+        plane: &PlaneSamples<'_>,
+        denoised: &[i32],
+        format: PixelFormat,
+        index: usize,
+    ) {
+        if !format.is_yuv() {
+            return;
+        }
+        let scale = f64::from(1u32 << (format.bit_depth() - 8));
+        let level_shift = u32::from(format.bit_depth()) - 8;
+        for (position, (&sample, &clean)) in plane.samples.iter().zip(denoised).enumerate() {
+            let channel = if index == 0 {
+                0
+            } else if plane.components == 2 {
+                1 + position % 2
+            } else {
+                index
+            };
+            let bin = (clean >> level_shift) as usize * NOISE_BINS / 256;
+            let noise = f64::from(sample - clean) / scale;
+            let bin = &mut self.channels[channel][bin.min(NOISE_BINS - 1)];
+            bin.sum += noise * noise;
+            bin.count += 1;
+        }
+    }
+}
+
+/// Denoised planes of the previous frame
+#[derive(Debug)]
+struct History {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    planes: Vec<Vec<i32>>,
+}
+
+/// Weights by absolute sample difference, for each pass that is on
+#[derive(Debug)]
+struct Weights {
+    spatial: Option<Vec<f32>>,
+    temporal: Option<Vec<f32>>,
+}
+
+impl Weights {
+    fn new(spatial: f32, temporal: f32, depth: u8) -> Self {
+        Self {
+            spatial: weight_table(spatial, depth),
+            temporal: weight_table(temporal, depth),
+        }
+    }
+}
+
+/// hqdn3d's similarity curve: 1 for equal samples, a quarter at `strength`
+/// 8-bit steps apart, falling to 0 across the full range
+fn weight_table(strength: f32, depth: u8) -> Option<Vec<f32>> {
+    if strength <= 0.0 {
+        return None;
+    }
+    let gamma = 0.25f64.ln() / (1.0 - f64::from(strength.min(252.0)) / 255.0 - 0.00001).ln();
+    let scale = f64::from(1u32 << (depth - 8));
+    let table = (0..1u32 << depth)
+        .map(|difference| {
+            let similarity = 1.0 - f64::from(difference) / scale / 255.0;
+            similarity.max(0.0).powf(gamma) as f32
+        })
+        .collect();
+    Some(table)
+}
+
+/// A plane's samples with the previous frame's denoised ones
+struct PlaneSamples<'a> {
+    samples: &'a [i32],
+    previous: Option<&'a [i32]>,
+    /// Width in samples
+    width: usize,
+    /// Interleaved components per pixel (3 for RGB, 2 for NV12 chroma)
+    components: usize,
+    max: i32,
+}
+
+impl PlaneSamples<'_> {
+    fn denoise(&self, weights: &Weights, threads: usize) -> Vec<i32> {
+        let width = self.width;
+        let height = self.samples.len() / width.max(1);
+        let step = self.components as isize;
+        let mut output = vec![0; self.samples.len()];
+        for_each_row(output.chunks_mut(width).collect(), threads, |y, row| {
+            for (x, out) in row.iter_mut().enumerate() {
+                let center = self.samples[y * width + x];
+                let mut value = center as f32;
+
+                if let Some(table) = &weights.spatial {
+                    let (mut sum, mut total) = (value, 1.0);
+                    for ny in y.saturating_sub(1)..(y + 2).min(height) {
+                        for dx in [-step, 0, step] {
+                            let nx = x as isize + dx;
+                            if (nx == x as isize && ny == y) || nx < 0 || nx >= width as isize {
+                                continue;
+                            }
+                            let neighbour = self.samples[ny * width + nx as usize];
+                            let weight = table[(neighbour - center).unsigned_abs() as usize];
+                            sum += weight * neighbour as f32;
+                            total += weight;
+                        }
+                    }
+                    value = sum / total;
+                }
+
+                if let (Some(table), Some(previous)) = (&weights.temporal, self.previous) {
+                    let previous = previous[y * width + x] as f32;
+                    let difference = (previous - value).abs().round() as usize;
+                    value += (previous - value) * table[difference.min(table.len() - 1)];
+                }
+
+                *out = (value.round() as i32).clamp(0, self.max);
+            }
+        });
+        output
+    }
+}
+
+/// Interleaved components per pixel of a plane
+fn components(format: PixelFormat, index: usize) -> usize {
+    match (format.is_yuv(), index) {
+        (false, _) => 3,
+        (true, 1) if format.is_semi_planar() => 2,
+        _ => 1,
+    }
+}
+
+/// Widen a plane's samples
+fn unpack(plane: &Plane, shift: u32) -> Vec<i32> {
+    let mut samples = Vec::with_capacity(plane.width() * plane.height());
+    for y in 0..plane.height() {
+        let row = plane.row(y);
+        if plane.bytes_per_sample() == 1 {
+            samples.extend(row.iter().map(|&v| i32::from(v)));
+        } else {
+            samples.extend(
+                row.chunks_exact(2)
+                    .map(|v| i32::from(u16::from_le_bytes([v[0], v[1]]) >> shift)),
+            );
+        }
+    }
+    samples
+}
+
+/// Write widened samples into a plane
+fn pack(samples: &[i32], plane: &mut Plane, shift: u32, threads: usize) {
+    let (width, bytes, stride) = (plane.width(), plane.bytes_per_sample(), plane.stride());
+    for_each_row(
+        plane.data_mut().chunks_mut(stride).collect(),
+        threads,
+        |y, row| {
+            let line = &samples[y * width..(y + 1) * width];
+            if bytes == 1 {
+                for (dst, &sample) in row.iter_mut().zip(line) {
+                    *dst = sample as u8;
+                }
+            } else {
+                for (dst, &sample) in row.chunks_exact_mut(2).zip(line) {
+                    dst.copy_from_slice(&((sample as u16) << shift).to_le_bytes());
+                }
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A grey 4:2:0 frame with uniform noise of up to `amplitude` steps
+    fn noisy(amplitude: i32, seed: u32) -> Frame {
+        let mut state = seed;
+        let mut frame = Frame::new(64, 64, PixelFormat::Yuv420p);
+        for plane in frame.planes_mut() {
+            for y in 0..plane.height() {
+                for sample in plane.row_mut(y) {
+                    state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                    let noise = (state >> 16) as i32 % (2 * amplitude + 1) - amplitude;
+                    *sample = (150 + noise) as u8;
+                }
+            }
+        }
+        frame
+    }
+
+    fn deviation(plane: &Plane) -> f64 {
+        let data: Vec<f64> = (0..plane.height())
+            .flat_map(|y| plane.row(y).iter().map(|&v| f64::from(v)))
+            .collect();
+        let mean = data.iter().sum::<f64>() / data.len() as f64;
+        (data.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / data.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn test_denoise_reduces_noise() {
+        let mut denoiser = Denoiser::new(DenoiseConfig::default()).unwrap();
+        let first = noisy(4, 1);
+        let once = denoiser.denoise(&first).unwrap();
+        assert!(deviation(&once.planes()[0]) < deviation(&first.planes()[0]) * 0.7);
+
+        // The temporal pass removes more with every frame of history
+        let second = denoiser.denoise(&noisy(4, 2)).unwrap();
+        assert!(deviation(&second.planes()[0]) < deviation(&once.planes()[0]));
+    }
+
+    #[test]
+    fn test_denoise_keeps_edges() {
+        let mut frame = Frame::new(16, 16, PixelFormat::Yuv420p10);
+        let luma = &mut frame.planes_mut()[0];
+        for y in 0..16 {
+            for (x, sample) in luma.row_mut(y).chunks_exact_mut(2).enumerate() {
+                let value: u16 = if x < 8 { 64 } else { 940 };
+                sample.copy_from_slice(&value.to_le_bytes());
+            }
+        }
+        frame.set_pts(9);
+
+        let mut denoiser = Denoiser::new(DenoiseConfig::with_strength(10.0)).unwrap();
+        let output = denoiser.denoise(&frame).unwrap();
+        assert_eq!(output.planes()[0].data(), frame.planes()[0].data());
+        assert_eq!(output.pts(), Some(9));
+    }
+
+    #[test]
+    fn test_threads_match_single_thread() {
+        let frame = noisy(6, 3);
+        let denoise = |threads| {
+            let mut denoiser = Denoiser::new(DenoiseConfig {
+                threads,
+                ..Default::default()
+            })
+            .unwrap();
+            denoiser.denoise(&frame).unwrap()
+        };
+        let (single, threaded) = (denoise(1), denoise(4));
+        for (a, b) in single.planes().iter().zip(threaded.planes()) {
+            assert_eq!(a.data(), b.data());
+        }
+    }
+
+    #[test]
+    fn test_film_grain_estimate() {
+        let mut denoiser = Denoiser::new(DenoiseConfig {
+            grain: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let noise = denoiser.noise().unwrap();
+        assert_eq!(noise.film_grain(), None);
+        denoiser.denoise(&noisy(0, 4)).unwrap();
+        assert_eq!(noise.frames(), 1);
+        assert_eq!(noise.film_grain(), None);
+
+        let mut denoiser = Denoiser::new(DenoiseConfig {
+            grain: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let noise = denoiser.noise().unwrap();
+        denoiser.denoise(&noisy(4, 4)).unwrap();
+        let grain = noise.film_grain().unwrap();
+        // Only one grey level was seen, so each plane has a single point
+        assert_eq!(grain.luma.len(), 1);
+        assert_eq!(grain.luma[0].0, 144);
+        assert!(grain.luma[0].1 > 0);
+        assert_eq!((grain.cb.len(), grain.cr.len()), (1, 1));
+        assert!(grain.validate().is_ok());
+
+        assert!(
+            Denoiser::new(DenoiseConfig::default())
+                .unwrap()
+                .noise()
+                .is_none()
+        );
+        assert!(Denoiser::new(DenoiseConfig::with_strength(-1.0)).is_err());
+    }
+}
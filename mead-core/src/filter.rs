@@ -0,0 +1,822 @@
+//! Frame filters and filter chains
+//!
+//! A [`Filter`] takes frames one at a time and returns zero or more frames,
+//! so a stage can drop, duplicate or hold back frames and release them on
+//! [`Filter::flush`]. A [`FilterChain`] runs filters in order; it is built
+//! in code or parsed from a textual spec of comma-separated filters, each
+//! `name=arg:arg:key=value`:
+//!
+//! | Filter | Arguments |
+//! |--------|-----------|
+//! | `crop` | `left:top:right:bottom`, `horizontal:vertical` or one value |
+//! | `pad` | borders as for `crop`, `color=` (black, white, gray, #rrggbb) |
+//! | `rotate` | `90`, `180` or `270` degrees clockwise |
+//! | `hflip`, `vflip` | none |
+//! | `scale` | `width:height` or `WxH`, -1 keeps the aspect ratio; `kernel=`, `fit=1` |
+//! | `format` | pixel format name; `matrix=` and `range=` for RGB conversion |
+//! | `fps` | frame rate as `N`, `N/D` or a decimal; `mode=nearest` or `mode=blend` |
+//! | `deinterlace` | optional `bob` or `adaptive`; `rate=field` for a frame per field, `order=tff` or `bff` |
+//! | `denoise` | optional `luma_spatial:chroma_spatial:luma_temporal:chroma_temporal` strengths, missing ones derived from the first |
+//!
+//! # Example
+//! ```no_run
+//! use mead_core::filter::{Filter, FilterChain, VideoInfo};
+//! use mead_core::{Frame, PixelFormat};
+//! use std::sync::Arc;
+//!
+//! let mut chain: FilterChain = "crop=0:140,scale=1280:-1,format=yuv420p10".parse()?;
+//! let output = chain.configure(VideoInfo {
+//!     width: 1920,
+//!     height: 1080,
+//!     format: PixelFormat::Yuv420p,
+//!     framerate: (24, 1),
+//! })?;
+//! assert_eq!((output.width, output.height), (1280, 534));
+//!
+//! let frame = Arc::new(Frame::new(1920, 1080, PixelFormat::Yuv420p));
+//! for filtered in chain.filter(frame)? {
+//!     assert_eq!(filtered.format(), PixelFormat::Yuv420p10);
+//! }
+//! let remaining = chain.flush()?;
+//! # Ok::<(), mead_core::Error>(())
+//! ```
+
+use crate::convert::{ConvertConfig, Converter};
+use crate::deinterlace::{DeinterlaceConfig, DeinterlaceFilter, DeinterlaceMode};
+use crate::denoise::{DenoiseConfig, Denoiser};
+use crate::fps::{self, FpsFilter, FpsMode};
+use crate::scale::{Kernel, ScaleConfig, Scaler, fit_size};
+use crate::transform::{self, Borders, Color, Flip, Rotation};
+use crate::{ArcFrame, Error, PixelFormat, Result};
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Properties of the video flowing into or out of a filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoInfo {
+    /// Frame width
+    pub width: u32,
+    /// Frame height
+    pub height: u32,
+    /// Pixel format
+    pub format: PixelFormat,
+    /// Frame rate as (numerator, denominator)
+    pub framerate: (u64, u64),
+}
+
+/// Trait for frame filters
+pub trait Filter {
+    /// Short name, as used in filter specs
+    fn name(&self) -> &'static str;
+
+    /// Prepare for input with the given properties and describe the output
+    ///
+    /// Called once before the first frame; filters that keep size, format
+    /// and frame rate need not override it.
+    fn configure(&mut self, input: VideoInfo) -> Result<VideoInfo> {
+        Ok(input)
+    }
+
+    /// Process one frame, returning zero or more output frames
+    fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>>;
+
+    /// Drain frames still buffered in the filter at end of stream
+    fn flush(&mut self) -> Result<Vec<ArcFrame>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Filters applied in sequence
+///
+/// Each frame passes through every filter in order. Flushing drains the
+/// filters front to back, so frames released by one stage still pass
+/// through the stages after it.
+///
+/// # Example
+/// ```no_run
+/// use mead_core::filter::{CropFilter, FilterChain, ScaleFilter};
+/// use mead_core::scale::ScaleConfig;
+/// use mead_core::transform::Borders;
+///
+/// let chain = FilterChain::new()
+///     .with(CropFilter::new(Borders { top: 140, bottom: 140, ..Default::default() }))
+///     .with(ScaleFilter::new(ScaleConfig { width: 1280, height: 0, ..Default::default() }));
+/// assert_eq!(chain.len(), 2);
+/// ```
+#[derive(Default)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn Filter>>,
+}
+
+impl FilterChain {
+    /// Create an empty chain, which passes frames through unchanged
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a filter, builder style
+    pub fn with(mut self, filter: impl Filter + 'static) -> Self {
+        self.push(filter);
+        self
+    }
+
+    /// Append a filter
+    pub fn push(&mut self, filter: impl Filter + 'static) {
+        self.filters.push(Box::new(filter));
+    }
+
+    /// Append every filter of another chain
+    pub fn extend(&mut self, other: FilterChain) {
+        self.filters.extend(other.filters);
+    }
+
+    /// Number of filters in the chain
+    pub fn len(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// Returns true when the chain has no filters
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Names of the filters, in order
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.filters.iter().map(|filter| filter.name())
+    }
+}
+
+impl std::fmt::Debug for FilterChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FilterChain")
+            .field("filters", &self.names().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Filter for FilterChain {
+    fn name(&self) -> &'static str {
+        "chain"
+    }
+
+    fn configure(&mut self, input: VideoInfo) -> Result<VideoInfo> {
+        self.filters.iter_mut().try_fold(input, |info, filter| {
+            let output = filter.configure(info)?;
+            tracing::debug!(
+                "Filter {}: {}x{} {:?} -> {}x{} {:?}",
+                filter.name(),
+                info.width,
+                info.height,
+                info.format,
+                output.width,
+                output.height,
+                output.format
+            );
+            Ok(output)
+        })
+    }
+
+    fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>> {
+        run(&mut self.filters, vec![frame])
+    }
+
+    fn flush(&mut self) -> Result<Vec<ArcFrame>> {
+        let mut frames = Vec::new();
+        for index in 0..self.filters.len() {
+            let (done, rest) = self.filters.split_at_mut(index + 1);
+            let released = done[index].flush()?;
+            frames.extend(run(rest, released)?);
+        }
+        Ok(frames)
+    }
+}
+
+/// Pass frames through each of `filters` in turn
+fn run(filters: &mut [Box<dyn Filter>], mut frames: Vec<ArcFrame>) -> Result<Vec<ArcFrame>> {
+    for filter in filters {
+        let mut output = Vec::with_capacity(frames.len());
+        for frame in frames {
+            output.extend(filter.filter(frame)?);
+        }
+        frames = output;
+    }
+    Ok(frames)
+}
+
+/// Parses a comma-separated filter spec, e.g. `crop=0:140,scale=1280:-1`
+impl FromStr for FilterChain {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut chain = Self::new();
+        for spec in s.split(',').map(str::trim).filter(|spec| !spec.is_empty()) {
+            chain.filters.push(parse_filter(spec)?);
+        }
+        Ok(chain)
+    }
+}
+
+/// Build one filter from `name=arg:arg:key=value`
+fn parse_filter(spec: &str) -> Result<Box<dyn Filter>> {
+    let mut args = Args::parse(spec);
+    let filter: Box<dyn Filter> = match args.name {
+        "crop" => Box::new(CropFilter::new(args.borders()?)),
+        "pad" => {
+            let color = args.option("color").map(Color::from_str).transpose()?;
+            Box::new(PadFilter::new(
+                args.borders()?,
+                color.unwrap_or(Color::BLACK),
+            ))
+        }
+        "rotate" => Box::new(RotateFilter::new(args.required("angle")?.parse()?)),
+        "hflip" => Box::new(FlipFilter::new(Flip::Horizontal)),
+        "vflip" => Box::new(FlipFilter::new(Flip::Vertical)),
+        "scale" => {
+            let (width, height) = match args.required("size")? {
+                size if size.contains('x') => size.split_once('x').unwrap_or_default(),
+                width => (width, args.required("height")?),
+            };
+            let (width, height) = (parse_side(width)?, parse_side(height)?);
+            if width == 0 && height == 0 {
+                return Err(Error::InvalidInput(
+                    "Only one side of scale can be -1".to_string(),
+                ));
+            }
+            let kernel = args.option("kernel").map(Kernel::from_str).transpose()?;
+            let fit = args.option("fit").map(parse_flag).transpose()?;
+            let filter = ScaleFilter::new(ScaleConfig {
+                width,
+                height,
+                kernel: kernel.unwrap_or_default(),
+                ..Default::default()
+            });
+            Box::new(if fit.unwrap_or(false) {
+                filter.fit()
+            } else {
+                filter
+            })
+        }
+        "format" => {
+            let format = args.required("pixel format")?.parse()?;
+            let matrix = args.option("matrix").map(str::parse).transpose()?;
+            let range = args.option("range").map(str::parse).transpose()?;
+            let config = ConvertConfig {
+                matrix: matrix.unwrap_or_default(),
+                range: range.unwrap_or_default(),
+                ..Default::default()
+            };
+            Box::new(Converter::new(format, config))
+        }
+        "fps" => {
+            let rate = fps::parse_rate(args.required("frame rate")?)?;
+            let mode = args.option("mode").map(FpsMode::from_str).transpose()?;
+            Box::new(FpsFilter::new(rate, mode.unwrap_or_default())?)
+        }
+        "deinterlace" => {
+            let mode = args.optional().map(DeinterlaceMode::from_str).transpose()?;
+            let field_rate = match args.option("rate") {
+                None | Some("frame") => false,
+                Some("field") => true,
+                Some(rate) => {
+                    return Err(Error::InvalidInput(format!(
+                        "Expected rate=frame or rate=field, got {}",
+                        rate
+                    )));
+                }
+            };
+            let field_order = args.option("order").map(str::parse).transpose()?;
+            Box::new(DeinterlaceFilter::new(DeinterlaceConfig {
+                mode: mode.unwrap_or_default(),
+                field_rate,
+                field_order,
+            }))
+        }
+        "denoise" => {
+            let mut strengths = Vec::new();
+            while let Some(value) = args.optional() {
+                strengths.push(value.parse::<f32>().map_err(|_| {
+                    Error::InvalidInput(format!("Invalid denoise strength {}", value))
+                })?);
+            }
+            let mut config = match strengths.first() {
+                Some(&luma) => DenoiseConfig::with_strength(luma),
+                None => DenoiseConfig::default(),
+            };
+            let fields = [
+                &mut config.luma_spatial,
+                &mut config.chroma_spatial,
+                &mut config.luma_temporal,
+                &mut config.chroma_temporal,
+            ];
+            if strengths.len() > fields.len() {
+                return Err(Error::InvalidInput(format!(
+                    "Filter denoise takes at most {} strengths",
+                    fields.len()
+                )));
+            }
+            for (field, strength) in fields.into_iter().zip(strengths) {
+                *field = strength;
+            }
+            Box::new(Denoiser::new(config)?)
+        }
+        name => return Err(Error::InvalidInput(format!("Unknown filter: {}", name))),
+    };
+    args.finish()?;
+    Ok(filter)
+}
+
+/// Parse one side of a scale size, where -1 (stored as 0) keeps the aspect ratio
+fn parse_side(value: &str) -> Result<u32> {
+    match value {
+        "-1" => Ok(0),
+        _ => value
+            .parse::<u32>()
+            .ok()
+            .filter(|&v| v > 0)
+            .ok_or_else(|| Error::InvalidInput(format!("Invalid size {}", value))),
+    }
+}
+
+fn parse_flag(value: &str) -> Result<bool> {
+    match value {
+        "1" | "true" => Ok(true),
+        "0" | "false" => Ok(false),
+        _ => Err(Error::InvalidInput(format!(
+            "Expected 0 or 1, got {}",
+            value
+        ))),
+    }
+}
+
+/// Arguments of one filter in a spec
+struct Args<'a> {
+    name: &'a str,
+    positional: VecDeque<&'a str>,
+    options: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> Args<'a> {
+    fn parse(spec: &'a str) -> Self {
+        let (name, args) = spec.split_once('=').unwrap_or((spec, ""));
+        let mut positional = VecDeque::new();
+        let mut options = Vec::new();
+        for arg in args.split(':').filter(|arg| !arg.is_empty()) {
+            match arg.split_once('=') {
+                Some(option) => options.push(option),
+                None => positional.push_back(arg),
+            }
+        }
+        Self {
+            name: name.trim(),
+            positional,
+            options,
+        }
+    }
+
+    /// Take the next positional argument
+    fn required(&mut self, what: &str) -> Result<&'a str> {
+        self.positional
+            .pop_front()
+            .ok_or_else(|| Error::InvalidInput(format!("Filter {} needs a {}", self.name, what)))
+    }
+
+    /// Take the next positional argument if there is one
+    fn optional(&mut self) -> Option<&'a str> {
+        self.positional.pop_front()
+    }
+
+    /// Take the remaining positional arguments as borders
+    fn borders(&mut self) -> Result<Borders> {
+        let values: Vec<_> = self.positional.drain(..).collect();
+        if values.is_empty() {
+            return Err(Error::InvalidInput(format!(
+                "Filter {} needs borders",
+                self.name
+            )));
+        }
+        values.join(":").parse()
+    }
+
+    /// Take a `key=value` option
+    fn option(&mut self, key: &str) -> Option<&'a str> {
+        let index = self.options.iter().position(|&(name, _)| name == key)?;
+        Some(self.options.remove(index).1)
+    }
+
+    /// Reject arguments the filter did not use
+    fn finish(self) -> Result<()> {
+        let unused = self
+            .positional
+            .front()
+            .copied()
+            .or_else(|| self.options.first().map(|&(key, _)| key));
+        match unused {
+            Some(arg) => Err(Error::InvalidInput(format!(
+                "Unexpected argument {} for filter {}",
+                arg, self.name
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Removes borders without copying; see [`transform::crop`]
+#[derive(Debug, Clone, Copy)]
+pub struct CropFilter {
+    borders: Borders,
+}
+
+impl CropFilter {
+    /// Create a filter cropping `borders`
+    pub fn new(borders: Borders) -> Self {
+        Self { borders }
+    }
+}
+
+impl Filter for CropFilter {
+    fn name(&self) -> &'static str {
+        "crop"
+    }
+
+    fn configure(&mut self, input: VideoInfo) -> Result<VideoInfo> {
+        let b = self.borders;
+        b.check_alignment(input.format, [b.left, b.top, b.right, b.bottom])?;
+        let (width, height) = b.cropped_size(input.width, input.height)?;
+        Ok(VideoInfo {
+            width,
+            height,
+            ..input
+        })
+    }
+
+    fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>> {
+        if self.borders.is_empty() {
+            return Ok(vec![frame]);
+        }
+        Ok(vec![Arc::new(transform::crop(&frame, self.borders)?)])
+    }
+}
+
+/// Adds borders of a solid color; see [`transform::pad`]
+#[derive(Debug, Clone, Copy)]
+pub struct PadFilter {
+    borders: Borders,
+    color: Color,
+}
+
+impl PadFilter {
+    /// Create a filter adding `borders` filled with `color`
+    pub fn new(borders: Borders, color: Color) -> Self {
+        Self { borders, color }
+    }
+}
+
+impl Filter for PadFilter {
+    fn name(&self) -> &'static str {
+        "pad"
+    }
+
+    fn configure(&mut self, input: VideoInfo) -> Result<VideoInfo> {
+        let b = self.borders;
+        b.check_alignment(input.format, [b.left, b.top, 0, 0])?;
+        let (width, height) = b.padded_size(input.width, input.height)?;
+        Ok(VideoInfo {
+            width,
+            height,
+            ..input
+        })
+    }
+
+    fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>> {
+        if self.borders.is_empty() {
+            return Ok(vec![frame]);
+        }
+        Ok(vec![Arc::new(transform::pad(
+            &frame,
+            self.borders,
+            self.color,
+        )?)])
+    }
+}
+
+/// Rotates clockwise; see [`transform::rotate`]
+#[derive(Debug, Clone, Copy)]
+pub struct RotateFilter {
+    rotation: Rotation,
+}
+
+impl RotateFilter {
+    /// Create a filter rotating by `rotation`
+    pub fn new(rotation: Rotation) -> Self {
+        Self { rotation }
+    }
+}
+
+impl Filter for RotateFilter {
+    fn name(&self) -> &'static str {
+        "rotate"
+    }
+
+    fn configure(&mut self, input: VideoInfo) -> Result<VideoInfo> {
+        if self.rotation == Rotation::Rotate180 {
+            return Ok(input);
+        }
+        let (shift_x, shift_y) = input.format.chroma_shift();
+        if shift_x != shift_y {
+            return Err(Error::UnsupportedFormat(format!(
+                "Cannot rotate {:?} by a quarter turn",
+                input.format
+            )));
+        }
+        Ok(VideoInfo {
+            width: input.height,
+            height: input.width,
+            ..input
+        })
+    }
+
+    fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>> {
+        Ok(vec![Arc::new(transform::rotate(&frame, self.rotation)?)])
+    }
+}
+
+/// Mirrors horizontally or vertically; see [`transform::flip`]
+#[derive(Debug, Clone, Copy)]
+pub struct FlipFilter {
+    flip: Flip,
+}
+
+impl FlipFilter {
+    /// Create a filter mirroring along `flip`
+    pub fn new(flip: Flip) -> Self {
+        Self { flip }
+    }
+}
+
+impl Filter for FlipFilter {
+    fn name(&self) -> &'static str {
+        match self.flip {
+            Flip::Horizontal => "hflip",
+            Flip::Vertical => "vflip",
+        }
+    }
+
+    fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>> {
+        Ok(vec![Arc::new(transform::flip(&frame, self.flip))])
+    }
+}
+
+/// Scales to a fixed size, or one derived from the input aspect ratio
+///
+/// A configured width or height of 0 follows the input aspect ratio, as
+/// in [`fit_size`]. The output size is resolved from the configured input,
+/// or from the first frame when [`Filter::configure`] was not called, and
+/// frames already at that size pass through untouched.
+#[derive(Debug)]
+pub struct ScaleFilter {
+    config: ScaleConfig,
+    fit: bool,
+    /// Input size the scaler was resolved for
+    source: Option<(u32, u32)>,
+    scaler: Option<Scaler>,
+}
+
+impl ScaleFilter {
+    /// Create a filter scaling to `config.width`x`config.height`
+    pub fn new(config: ScaleConfig) -> Self {
+        Self {
+            config,
+            fit: false,
+            source: None,
+            scaler: None,
+        }
+    }
+
+    /// Fit inside the configured size keeping the aspect ratio, instead of stretching
+    pub fn fit(mut self) -> Self {
+        self.fit = true;
+        self
+    }
+
+    /// Output size for a `width`x`height` input, creating the scaler if needed
+    fn resolve(&mut self, width: u32, height: u32, format: PixelFormat) -> Result<(u32, u32)> {
+        if self.source != Some((width, height)) {
+            let size = match (self.config.width, self.config.height) {
+                (w, h) if self.fit || w == 0 || h == 0 => fit_size(width, height, w, h, format),
+                size => size,
+            };
+            self.scaler = if size == (width, height) {
+                None
+            } else {
+                Some(Scaler::new(ScaleConfig {
+                    width: size.0,
+                    height: size.1,
+                    ..self.config
+                })?)
+            };
+            self.source = Some((width, height));
+        }
+        Ok(self
+            .scaler
+            .as_ref()
+            .map_or((width, height), |scaler| (scaler.width(), scaler.height())))
+    }
+}
+
+impl Filter for ScaleFilter {
+    fn name(&self) -> &'static str {
+        "scale"
+    }
+
+    fn configure(&mut self, input: VideoInfo) -> Result<VideoInfo> {
+        let (width, height) = self.resolve(input.width, input.height, input.format)?;
+        Ok(VideoInfo {
+            width,
+            height,
+            ..input
+        })
+    }
+
+    fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>> {
+        self.resolve(frame.width(), frame.height(), frame.format())?;
+        match self.scaler.as_mut() {
+            Some(scaler) => Ok(vec![Arc::new(scaler.scale(&frame)?)]),
+            None => Ok(vec![frame]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Frame;
+
+    fn info(width: u32, height: u32, format: PixelFormat) -> VideoInfo {
+        VideoInfo {
+            width,
+            height,
+            format,
+            framerate: (25, 1),
+        }
+    }
+
+    /// Holds frames back and releases them in pairs
+    struct Pairs(Option<ArcFrame>);
+
+    impl Filter for Pairs {
+        fn name(&self) -> &'static str {
+            "pairs"
+        }
+
+        fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>> {
+            Ok(match self.0.take() {
+                Some(held) => vec![held, frame],
+                None => {
+                    self.0 = Some(frame);
+                    Vec::new()
+                }
+            })
+        }
+
+        fn flush(&mut self) -> Result<Vec<ArcFrame>> {
+            Ok(self.0.take().into_iter().collect())
+        }
+    }
+
+    #[test]
+    fn test_chain_sizes() {
+        let mut chain = FilterChain::new()
+            .with(CropFilter::new(Borders {
+                top: 2,
+                bottom: 2,
+                ..Default::default()
+            }))
+            .with(RotateFilter::new(Rotation::Rotate90))
+            .with(ScaleFilter::new(ScaleConfig {
+                width: 0,
+                height: 48,
+                ..Default::default()
+            }))
+            .with(PadFilter::new(
+                Borders {
+                    left: 2,
+                    right: 2,
+                    ..Default::default()
+                },
+                Color::BLACK,
+            ))
+            .with(Converter::new(
+                PixelFormat::Yuv420p10,
+                ConvertConfig::default(),
+            ));
+
+        let output = chain.configure(info(32, 20, PixelFormat::Yuv420p)).unwrap();
+        assert_eq!(output, info(28, 48, PixelFormat::Yuv420p10));
+
+        let mut frame = Frame::new(32, 20, PixelFormat::Yuv420p);
+        frame.set_pts(7);
+        let frames = chain.filter(Arc::new(frame)).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!((frames[0].width(), frames[0].height()), (28, 48));
+        assert_eq!(frames[0].format(), PixelFormat::Yuv420p10);
+        assert_eq!(frames[0].pts(), Some(7));
+
+        // Quarter turns of 4:2:2 and odd 4:2:0 crops are caught up front
+        assert!(chain.configure(info(32, 20, PixelFormat::Yuv422p)).is_err());
+        let mut odd = FilterChain::new().with(CropFilter::new(Borders {
+            left: 1,
+            ..Default::default()
+        }));
+        assert!(odd.configure(info(32, 20, PixelFormat::Yuv420p)).is_err());
+
+        // User-supplied borders too large to add up
+        let mut huge = FilterChain::new().with(CropFilter::new(Borders {
+            left: u32::MAX - 1,
+            right: 2,
+            ..Default::default()
+        }));
+        assert!(huge.configure(info(32, 20, PixelFormat::Yuv420p)).is_err());
+        let mut huge = FilterChain::new().with(PadFilter::new(
+            Borders {
+                bottom: u32::MAX,
+                ..Default::default()
+            },
+            Color::BLACK,
+        ));
+        assert!(huge.configure(info(32, 20, PixelFormat::Yuv420p)).is_err());
+    }
+
+    #[test]
+    fn test_chain_flush() {
+        let mut chain = FilterChain::new()
+            .with(Pairs(None))
+            .with(FlipFilter::new(Flip::Vertical))
+            .with(Pairs(None));
+
+        let mut outputs = Vec::new();
+        for pts in 0..3 {
+            let mut frame = Frame::new(4, 4, PixelFormat::Yuv420p);
+            frame.set_pts(pts);
+            outputs.extend(chain.filter(Arc::new(frame)).unwrap());
+        }
+        let pts: Vec<_> = outputs.iter().map(|frame| frame.pts()).collect();
+        assert_eq!(pts, [Some(0), Some(1)]);
+
+        // Frame 2 is released by the first stage, then held by the last
+        // until it is flushed too
+        let flushed = chain.flush().unwrap();
+        assert_eq!(flushed.len(), 1);
+        assert_eq!(flushed[0].pts(), Some(2));
+        assert!(chain.flush().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let mut chain: FilterChain =
+            "crop=0:140, scale=1280:-1:kernel=lanczos,hflip,denoise=6:4,format=yuv420p10"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            chain.names().collect::<Vec<_>>(),
+            ["crop", "scale", "hflip", "denoise", "format"]
+        );
+        let output = chain
+            .configure(info(1920, 1080, PixelFormat::Yuv420p))
+            .unwrap();
+        assert_eq!(output, info(1280, 534, PixelFormat::Yuv420p10));
+
+        let mut fit: FilterChain =
+            "deinterlace=bob:rate=field,scale=640x640:fit=1,pad=2:color=white,rotate=-90,fps=30000/1001:mode=blend"
+                .parse()
+                .unwrap();
+        let output = fit
+            .configure(info(1920, 1080, PixelFormat::Yuv420p))
+            .unwrap();
+        assert_eq!((output.width, output.height), (364, 644));
+        assert_eq!(output.framerate, (30000, 1001));
+        assert_eq!(fit.names().next(), Some("deinterlace"));
+
+        assert!("".parse::<FilterChain>().unwrap().is_empty());
+        let invalid = [
+            "blur=3",
+            "scale=1280",
+            "scale=-1:-1",
+            "scale=64x64:fast=1",
+            "rotate=45",
+            "crop",
+            "hflip=1",
+            "fps",
+            "fps=0",
+            "fps=25:mode=fast",
+            "deinterlace=weave",
+            "deinterlace=bob:rate=double",
+            "denoise=strong",
+            "denoise=-1",
+            "denoise=1:2:3:4:5",
+        ];
+        for invalid in invalid {
+            assert!(invalid.parse::<FilterChain>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! Frame rate conversion
+//!
+//! [`FpsFilter`] retimes a stream to another rational frame rate. Each
+//! output frame shows the input frame nearest to its time, so frames are
+//! dropped when lowering the rate and repeated when raising it; with
+//! [`FpsMode::Blend`], output frames falling between two input frames mix
+//! them instead. Input timestamps count frames at the input rate, as
+//! [`Y4mDemuxer`](crate::container::y4m::Y4mDemuxer) produces, and output
+//! timestamps count frames at the output rate.
+//!
+//! # Example
+//! ```no_run
+//! use mead_core::filter::{Filter, VideoInfo};
+//! use mead_core::fps::{self, FpsFilter, FpsMode};
+//! use mead_core::PixelFormat;
+//!
+//! // 29.97 to 25 fps for a PAL delivery
+//! let mut filter = FpsFilter::new(fps::parse_rate("25")?, FpsMode::Nearest)?;
+//! let output = filter.configure(VideoInfo {
+//!     width: 1920,
+//!     height: 1080,
+//!     format: PixelFormat::Yuv420p,
+//!     framerate: fps::parse_rate("29.97")?,
+//! })?;
+//! assert_eq!(output.framerate, (25, 1));
+//! # Ok::<(), mead_core::Error>(())
+//! ```
+
+use crate::filter::{Filter, VideoInfo};
+use crate::{ArcFrame, Error, Frame, FramePool, PixelFormat, Result};
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// How output frames between input frames are filled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FpsMode {
+    /// Show the nearest input frame, dropping or repeating frames
+    #[default]
+    Nearest,
+    /// Mix the two surrounding input frames by distance
+    Blend,
+}
+
+/// Parses `nearest` (or `drop`) and `blend`
+impl FromStr for FpsMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "nearest" | "drop" => Ok(Self::Nearest),
+            "blend" => Ok(Self::Blend),
+            _ => Err(Error::InvalidInput(format!("Unknown fps mode: {}", s))),
+        }
+    }
+}
+
+/// Parse a frame rate as `N`, `N/D` or a decimal
+///
+/// Rounded NTSC rates such as 23.976, 29.97 and 59.94 map to their exact
+/// fractions (24000/1001, 30000/1001, 60000/1001).
+///
+/// # Example
+/// ```
+/// use mead_core::fps::parse_rate;
+///
+/// assert_eq!(parse_rate("25")?, (25, 1));
+/// assert_eq!(parse_rate("29.97")?, (30000, 1001));
+/// assert_eq!(parse_rate("12.5")?, (25, 2));
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+pub fn parse_rate(s: &str) -> Result<(u64, u64)> {
+    let invalid = || Error::InvalidInput(format!("Invalid frame rate: {}", s));
+    let (num, den) = match s.trim().split_once(['/', '.']) {
+        None => (s.trim().parse().map_err(|_| invalid())?, 1),
+        Some((num, den)) if s.contains('/') => (
+            num.parse().map_err(|_| invalid())?,
+            den.parse().map_err(|_| invalid())?,
+        ),
+        Some((_, fraction)) => {
+            let rate: f64 = s.trim().parse().map_err(|_| invalid())?;
+            let ntsc = (rate * 1.001).round();
+            let scale = u32::try_from(fraction.len())
+                .ok()
+                .and_then(|digits| 10u64.checked_pow(digits))
+                .ok_or_else(invalid)?;
+            if rate.fract() != 0.0 && (ntsc / 1.001 - rate).abs() < 0.005 {
+                (ntsc as u64 * 1000, 1001)
+            } else {
+                ((rate * scale as f64).round() as u64, scale)
+            }
+        }
+    };
+    if num == 0 || den == 0 {
+        return Err(invalid());
+    }
+    let divisor = gcd(num, den);
+    Ok((num / divisor, den / divisor))
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Blended output frames kept for reuse
+const POOL_CAPACITY: usize = 4;
+
+/// Converts the frame rate by dropping, repeating or blending frames
+///
+/// Frames without a timestamp are numbered on from the previous one. The
+/// last input frame is held until the next one arrives, since its
+/// duration is only known then, and released by [`Filter::flush`].
+#[derive(Debug)]
+pub struct FpsFilter {
+    rate: (u64, u64),
+    mode: FpsMode,
+    /// Duration of one input frame in output frames, as (numerator, denominator)
+    step: Option<(i128, i128)>,
+    /// Timestamp of the next output frame, set by the first input frame
+    next: Option<i64>,
+    /// Last input frame and its time in output frames, over `step.1`
+    held: Option<(ArcFrame, i128)>,
+    /// Timestamp given to the next input frame that has none
+    count: i64,
+    pool: Option<FramePool>,
+}
+
+impl FpsFilter {
+    /// Create a filter converting to `rate` frames per second, as (numerator, denominator)
+    pub fn new(rate: (u64, u64), mode: FpsMode) -> Result<Self> {
+        if rate.0 == 0 || rate.1 == 0 {
+            return Err(Error::InvalidInput(format!(
+                "Invalid frame rate {}/{}",
+                rate.0, rate.1
+            )));
+        }
+        Ok(Self {
+            rate,
+            mode,
+            step: None,
+            next: None,
+            held: None,
+            count: 0,
+            pool: None,
+        })
+    }
+
+    /// Get the output frame rate
+    pub fn rate(&self) -> (u64, u64) {
+        self.rate
+    }
+
+    /// Output frames for the held frame, up to the output time `until` over `step.1`
+    ///
+    /// `following` is the next input frame, blended with the held frame in
+    /// [`FpsMode::Blend`]; at end of stream there is none.
+    fn emit(
+        &mut self,
+        held: &ArcFrame,
+        held_time: i128,
+        until: i128,
+        following: Option<&ArcFrame>,
+    ) -> Result<Vec<ArcFrame>> {
+        let den = self.step.map_or(1, |(_, den)| den);
+        let boundary = |time: i128| match self.mode {
+            // Output frame n shows the input frame whose time rounds to n
+            FpsMode::Nearest => (2 * time + den).div_euclid(2 * den),
+            // Output frame n lies between the input frames around it
+            FpsMode::Blend => -(-time).div_euclid(den),
+        };
+        let limit = i64::try_from(boundary(until)).unwrap_or(i64::MAX);
+        let first = i64::try_from(boundary(held_time)).unwrap_or(i64::MAX);
+        let next = *self.next.get_or_insert(first);
+
+        let mut frames = Vec::new();
+        for pts in next..limit {
+            let offset = i128::from(pts) * den - held_time;
+            let mut frame = match following {
+                Some(following) if self.mode == FpsMode::Blend && offset > 0 => {
+                    let gap = until - held_time;
+                    let weight = ((offset * 256 + gap / 2) / gap) as u32;
+                    self.blend(held, following, weight)?
+                }
+                _ => Frame::clone(held),
+            };
+            frame.set_pts(pts);
+            frames.push(Arc::new(frame));
+        }
+        self.next = Some(next.max(limit));
+        Ok(frames)
+    }
+
+    /// Mix two frames, `weight` 256ths of the way from `a` to `b`
+    fn blend(&mut self, a: &Frame, b: &Frame, weight: u32) -> Result<Frame> {
+        let (width, height, format) = (a.width(), a.height(), a.format());
+        if (b.width(), b.height(), b.format()) != (width, height, format) {
+            return Err(Error::InvalidInput(format!(
+                "Cannot blend a {}x{} {:?} frame with a {}x{} {:?} frame",
+                width,
+                height,
+                format,
+                b.width(),
+                b.height(),
+                b.format()
+            )));
+        }
+
+        let pool = match &mut self.pool {
+            Some(pool)
+                if (pool.width(), pool.height(), pool.format()) == (width, height, format) =>
+            {
+                pool
+            }
+            pool => pool.insert(FramePool::new(width, height, format, POOL_CAPACITY)),
+        };
+        // Every sample is overwritten, so stale pooled content never leaks
+        let mut output = pool
+            .try_acquire()
+            .unwrap_or_else(|| Frame::new(width, height, format));
+
+        let mix = |x: u32, y: u32| (x * (256 - weight) + y * weight + 128) >> 8;
+        let shift = if format == PixelFormat::P010 { 6 } else { 0 };
+        for ((src_a, src_b), dst) in a.planes().iter().zip(b.planes()).zip(output.planes_mut()) {
+            let wide = dst.bytes_per_sample() == 2;
+            for y in 0..dst.height() {
+                let (row_a, row_b, row) = (src_a.row(y), src_b.row(y), dst.row_mut(y));
+                if wide {
+                    for ((out, x), y) in row
+                        .chunks_exact_mut(2)
+                        .zip(row_a.chunks_exact(2))
+                        .zip(row_b.chunks_exact(2))
+                    {
+                        let x = u32::from(u16::from_le_bytes([x[0], x[1]]) >> shift);
+                        let y = u32::from(u16::from_le_bytes([y[0], y[1]]) >> shift);
+                        out.copy_from_slice(&((mix(x, y) as u16) << shift).to_le_bytes());
+                    }
+                } else {
+                    for ((out, &x), &y) in row.iter_mut().zip(row_a).zip(row_b) {
+                        *out = mix(u32::from(x), u32::from(y)) as u8;
+                    }
+                }
+            }
+        }
+
+        output.set_colorimetry(*a.colorimetry());
+        Ok(output)
+    }
+}
+
+impl Filter for FpsFilter {
+    fn name(&self) -> &'static str {
+        "fps"
+    }
+
+    fn configure(&mut self, input: VideoInfo) -> Result<VideoInfo> {
+        let (in_num, in_den) = input.framerate;
+        if in_num == 0 || in_den == 0 {
+            return Err(Error::InvalidInput(format!(
+                "Invalid input frame rate {}/{}",
+                in_num, in_den
+            )));
+        }
+        let (out_num, out_den) = self.rate;
+        self.step = Some((
+            i128::from(in_den) * i128::from(out_num),
+            i128::from(in_num) * i128::from(out_den),
+        ));
+        Ok(VideoInfo {
+            framerate: self.rate,
+            ..input
+        })
+    }
+
+    fn filter(&mut self, frame: ArcFrame) -> Result<Vec<ArcFrame>> {
+        let (per_frame, _) = self.step.ok_or_else(|| {
+            Error::InvalidInput(
+                "The fps filter needs the input frame rate from configure".to_string(),
+            )
+        })?;
+        let pts = frame.pts().unwrap_or(self.count);
+        self.count = pts + 1;
+        let time = i128::from(pts) * per_frame;
+
+        let frames = match self.held.take() {
+            Some((held, held_time)) => self.emit(&held, held_time, time, Some(&frame))?,
+            None => Vec::new(),
+        };
+        self.held = Some((frame, time));
+        Ok(frames)
+    }
+
+    fn flush(&mut self) -> Result<Vec<ArcFrame>> {
+        match (self.held.take(), self.step) {
+            // The last frame lasts one input frame
+            (Some((held, held_time)), Some((per_frame, _))) => {
+                self.emit(&held, held_time, held_time + per_frame, None)
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(from: (u64, u64), to: (u64, u64), mode: FpsMode, frames: i64) -> Vec<(i64, u8)> {
+        let mut filter = FpsFilter::new(to, mode).unwrap();
+        filter
+            .configure(VideoInfo {
+                width: 4,
+                height: 4,
+                format: PixelFormat::Yuv420p,
+                framerate: from,
+            })
+            .unwrap();
+
+        let mut output = Vec::new();
+        for pts in 0..frames {
+            // Tag each input frame by its luma value
+            let mut frame = Frame::new(4, 4, PixelFormat::Yuv420p);
+            frame.planes_mut()[0].data_mut().fill((pts * 10) as u8);
+            frame.set_pts(pts);
+            output.extend(filter.filter(Arc::new(frame)).unwrap());
+        }
+        output.extend(filter.flush().unwrap());
+        output
+            .iter()
+            .map(|frame| (frame.pts().unwrap(), frame.planes()[0].data()[0]))
+            .collect()
+    }
+
+    #[test]
+    fn test_fps_halve_and_double() {
+        // 60 to 30 keeps every other frame, the ones on output frame times
+        let halved = convert((60, 1), (30, 1), FpsMode::Nearest, 6);
+        assert_eq!(halved, [(0, 0), (1, 20), (2, 40)]);
+
+        let doubled = convert((25, 1), (50, 1), FpsMode::Nearest, 3);
+        assert_eq!(
+            doubled,
+            [(0, 0), (1, 0), (2, 10), (3, 10), (4, 20), (5, 20)]
+        );
+    }
+
+    #[test]
+    fn test_fps_ntsc_to_pal() {
+        // 30 frames of 29.97 fps last 1.001s, or 25 frames at 25 fps
+        let output = convert((30000, 1001), (25, 1), FpsMode::Nearest, 30);
+        assert_eq!(output.len(), 25);
+        assert!(
+            output
+                .iter()
+                .enumerate()
+                .all(|(index, &(pts, _))| pts == index as i64)
+        );
+        // One frame in six is dropped
+        let kept: Vec<_> = output.iter().take(6).map(|&(_, tag)| tag / 10).collect();
+        assert_eq!(kept, [0, 1, 2, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_fps_blend() {
+        // 30 to 40 fps: output frames 1 and 2 fall three quarters and half
+        // way between input frames, and the last repeats the final input
+        let output = convert((30, 1), (40, 1), FpsMode::Blend, 3);
+        assert_eq!(output, [(0, 0), (1, 8), (2, 15), (3, 20)]);
+    }
+
+    #[test]
+    fn test_parse_rate() {
+        assert_eq!(parse_rate("30000/1001").unwrap(), (30000, 1001));
+        assert_eq!(parse_rate("50/2").unwrap(), (25, 1));
+        assert_eq!(parse_rate("23.976").unwrap(), (24000, 1001));
+        assert_eq!(parse_rate("59.94").unwrap(), (60000, 1001));
+        assert_eq!(parse_rate("25.0").unwrap(), (25, 1));
+        for invalid in ["", "0", "30/0", "fast", "1.5.2"] {
+            assert!(parse_rate(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -1,7 +1,9 @@
 //! Video frame data structures
 
-use aligned_vec::AVec;
-use std::sync::Arc;
+use crate::{Colorimetry, Error, Result};
+use aligned_vec::{AVec, CACHELINE_ALIGN};
+use std::str::FromStr;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak};
 
 /// Pixel format for video frames
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -14,60 +16,385 @@ pub enum PixelFormat {
     Yuv444p,
     /// RGB 24-bit
     Rgb24,
+    /// YUV 4:2:0 planar, 10-bit samples in 16-bit little-endian storage
+    Yuv420p10,
+    /// YUV 4:2:2 planar, 10-bit samples in 16-bit little-endian storage
+    Yuv422p10,
+    /// YUV 4:4:4 planar, 10-bit samples in 16-bit little-endian storage
+    Yuv444p10,
+    /// YUV 4:2:0 planar, 12-bit samples in 16-bit little-endian storage
+    Yuv420p12,
+    /// YUV 4:2:2 planar, 12-bit samples in 16-bit little-endian storage
+    Yuv422p12,
+    /// YUV 4:4:4 planar, 12-bit samples in 16-bit little-endian storage
+    Yuv444p12,
+    /// YUV 4:2:0 semi-planar: a Y plane and one interleaved UV plane
+    Nv12,
+    /// YUV 4:2:0 semi-planar, 10-bit samples in the high bits of 16-bit
+    /// little-endian storage
+    P010,
+}
+
+impl PixelFormat {
+    /// Bits per sample
+    pub fn bit_depth(self) -> u8 {
+        match self {
+            Self::Yuv420p10 | Self::Yuv422p10 | Self::Yuv444p10 | Self::P010 => 10,
+            Self::Yuv420p12 | Self::Yuv422p12 | Self::Yuv444p12 => 12,
+            Self::Yuv420p | Self::Yuv422p | Self::Yuv444p | Self::Rgb24 | Self::Nv12 => 8,
+        }
+    }
+
+    /// Bytes used to store one sample (1 for 8-bit, 2 for high bit depth)
+    pub fn bytes_per_sample(self) -> usize {
+        if self.bit_depth() > 8 { 2 } else { 1 }
+    }
+
+    /// Returns true for YUV formats, planar or semi-planar
+    pub fn is_yuv(self) -> bool {
+        self != Self::Rgb24
+    }
+
+    /// Returns true for semi-planar formats, whose U and V share one plane
+    pub fn is_semi_planar(self) -> bool {
+        matches!(self, Self::Nv12 | Self::P010)
+    }
+
+    /// Chroma subsampling as (horizontal, vertical) shifts, e.g. (1, 1) for 4:2:0
+    pub fn chroma_shift(self) -> (u32, u32) {
+        match self {
+            Self::Yuv420p | Self::Yuv420p10 | Self::Yuv420p12 | Self::Nv12 | Self::P010 => (1, 1),
+            Self::Yuv422p | Self::Yuv422p10 | Self::Yuv422p12 => (1, 0),
+            Self::Yuv444p | Self::Yuv444p10 | Self::Yuv444p12 | Self::Rgb24 => (0, 0),
+        }
+    }
+}
+
+/// Parses lowercase names such as `yuv420p`, `yuv422p10`, `rgb24`, `nv12`
+/// and `p010`; high bit depth names may end in `le`
+impl FromStr for PixelFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.to_ascii_lowercase();
+        match name.strip_suffix("le").unwrap_or(&name) {
+            "yuv420p" | "i420" => Ok(Self::Yuv420p),
+            "yuv422p" => Ok(Self::Yuv422p),
+            "yuv444p" => Ok(Self::Yuv444p),
+            "rgb24" | "rgb" => Ok(Self::Rgb24),
+            "yuv420p10" => Ok(Self::Yuv420p10),
+            "yuv422p10" => Ok(Self::Yuv422p10),
+            "yuv444p10" => Ok(Self::Yuv444p10),
+            "yuv420p12" => Ok(Self::Yuv420p12),
+            "yuv422p12" => Ok(Self::Yuv422p12),
+            "yuv444p12" => Ok(Self::Yuv444p12),
+            "nv12" => Ok(Self::Nv12),
+            "p010" => Ok(Self::P010),
+            _ => Err(Error::InvalidInput(format!("Unknown pixel format: {}", s))),
+        }
+    }
+}
+
+/// How the lines of a frame were captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FieldOrder {
+    /// All lines sampled at once
+    #[default]
+    Progressive,
+    /// Interlaced, the top field (even lines) first in time
+    TopFieldFirst,
+    /// Interlaced, the bottom field (odd lines) first in time
+    BottomFieldFirst,
+}
+
+impl FieldOrder {
+    /// Returns true for interlaced frames
+    pub fn is_interlaced(self) -> bool {
+        self != Self::Progressive
+    }
+}
+
+/// Parses `progressive`, `tff` (or `top`) and `bff` (or `bottom`)
+impl FromStr for FieldOrder {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "progressive" => Ok(Self::Progressive),
+            "tff" | "top" => Ok(Self::TopFieldFirst),
+            "bff" | "bottom" => Ok(Self::BottomFieldFirst),
+            _ => Err(Error::InvalidInput(format!("Unknown field order: {}", s))),
+        }
+    }
+}
+
+/// Minimum alignment of plane buffers, enough for AVX
+const BUFFER_ALIGNMENT: usize = 32;
+
+/// Row alignment and edge padding for newly allocated planes
+///
+/// The default is tightly packed rows (`stride == width * bytes_per_sample`)
+/// without padding, matching [`Plane::new`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaneLayout {
+    /// Stride and row start alignment in bytes (1 = tightly packed)
+    pub alignment: usize,
+    /// Border in pixels on each side of the plane, filled by [`Plane::extend_edges`]
+    pub padding: usize,
+}
+
+impl Default for PlaneLayout {
+    fn default() -> Self {
+        Self {
+            alignment: 1,
+            padding: 0,
+        }
+    }
+}
+
+impl PlaneLayout {
+    /// Rows aligned to `alignment` bytes, without padding
+    pub fn aligned(alignment: usize) -> Self {
+        Self {
+            alignment,
+            ..Default::default()
+        }
+    }
+}
+
+/// Backing storage of a plane, shared between clones and views
+#[derive(Clone)]
+enum Buffer {
+    /// Aligned buffer allocated by mead
+    Aligned(Arc<AVec<u8>>),
+    /// Externally owned buffer
+    External(Arc<dyn AsRef<[u8]> + Send + Sync>),
+}
+
+impl Buffer {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            Self::Aligned(buffer) => buffer,
+            Self::External(buffer) => (**buffer).as_ref(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Buffer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            Self::Aligned(_) => "Aligned",
+            Self::External(_) => "External",
+        };
+        f.debug_struct(kind)
+            .field("len", &self.as_slice().len())
+            .finish()
+    }
 }
 
 /// A single plane of pixel data
 ///
 /// Uses SIMD-aligned memory (AVec) for optimal performance
-/// with vectorized operations (SSE, AVX, NEON).
+/// with vectorized operations (SSE, AVX, NEON). High bit depth
+/// samples are stored as 16-bit little-endian pairs of bytes.
+///
+/// The buffer is reference counted: cloning a plane or taking a
+/// [`view`](Self::view) never copies pixels, and the first mutation of a
+/// shared buffer copies it (copy-on-write). Rows may be padded, so always
+/// step through the data with [`stride`](Self::stride) or use
+/// [`row`](Self::row).
 #[derive(Debug, Clone)]
 pub struct Plane {
-    /// Aligned pixel data
-    data: AVec<u8>,
+    /// Pixel data, possibly shared with other planes
+    buffer: Buffer,
+    /// Byte offset of the first visible sample in `buffer`
+    offset: usize,
     /// Stride (bytes per row) - may be larger than width due to alignment
     stride: usize,
     /// Plane width in pixels
     width: usize,
     /// Plane height in pixels
     height: usize,
+    /// Bytes per sample (1 or 2)
+    bytes_per_sample: usize,
+    /// Border in pixels available on each side for edge extension
+    padding: usize,
 }
 
 impl Plane {
-    /// Create a new plane with specified dimensions
+    /// Create a new 8-bit plane with specified dimensions
     ///
     /// Data is allocated with SIMD alignment (32 bytes)
     pub fn new(width: usize, height: usize) -> Self {
-        let stride = width; // For now, stride = width
-        let size = stride * height;
-        let data = AVec::from_iter(32, std::iter::repeat_n(0, size));
+        Self::with_sample_size(width, height, 1)
+    }
+
+    /// Create a new plane storing `bytes_per_sample` bytes per pixel
+    pub fn with_sample_size(width: usize, height: usize, bytes_per_sample: usize) -> Self {
+        Self::with_layout(width, height, bytes_per_sample, PlaneLayout::default())
+    }
+
+    /// Create a new plane with aligned rows and a padded border
+    ///
+    /// The stride is a multiple of `layout.alignment` bytes, and so is the
+    /// first visible sample of every row for alignments up to a cache line.
+    pub fn with_layout(
+        width: usize,
+        height: usize,
+        bytes_per_sample: usize,
+        layout: PlaneLayout,
+    ) -> Self {
+        let alignment = layout.alignment.max(1);
+        let border = layout.padding * bytes_per_sample;
+        let left = border.next_multiple_of(alignment);
+        let stride = (left + width * bytes_per_sample + border).next_multiple_of(alignment);
+        let size = stride * (height + 2 * layout.padding);
+
+        // AVec can't align beyond a cache line; strides stay multiples of `alignment`
+        let buffer_alignment = alignment
+            .next_power_of_two()
+            .clamp(BUFFER_ALIGNMENT, CACHELINE_ALIGN);
+        let data = AVec::from_iter(buffer_alignment, std::iter::repeat_n(0, size));
 
         Self {
-            data,
+            buffer: Buffer::Aligned(Arc::new(data)),
+            offset: layout.padding * stride + left,
             stride,
             width,
             height,
+            bytes_per_sample,
+            padding: layout.padding,
         }
     }
 
-    /// Create a plane from existing data
+    /// Create an 8-bit plane from existing data
     pub fn from_data(data: Vec<u8>, width: usize, height: usize, stride: usize) -> Self {
-        let aligned_data = AVec::from_iter(32, data);
+        let aligned_data = AVec::from_iter(BUFFER_ALIGNMENT, data);
         Self {
-            data: aligned_data,
+            buffer: Buffer::Aligned(Arc::new(aligned_data)),
+            offset: 0,
+            stride,
+            width,
+            height,
+            bytes_per_sample: 1,
+            padding: 0,
+        }
+    }
+
+    /// Wrap an externally owned buffer without copying
+    ///
+    /// The first visible sample is at `offset` and rows are `stride` bytes
+    /// apart. The buffer is copied only if the plane is later mutated.
+    ///
+    /// # Example
+    /// ```
+    /// use mead_core::Plane;
+    /// use std::sync::Arc;
+    ///
+    /// let pixels: Arc<Vec<u8>> = Arc::new(vec![0u8; 64 * 48]);
+    /// let plane = Plane::from_shared(pixels, 0, 64, 64, 48, 1)?;
+    /// assert_eq!(plane.row(47).len(), 64);
+    /// # Ok::<(), mead_core::Error>(())
+    /// ```
+    pub fn from_shared(
+        buffer: Arc<dyn AsRef<[u8]> + Send + Sync>,
+        offset: usize,
+        stride: usize,
+        width: usize,
+        height: usize,
+        bytes_per_sample: usize,
+    ) -> Result<Self> {
+        let plane = Self {
+            buffer: Buffer::External(buffer),
+            offset,
             stride,
             width,
             height,
+            bytes_per_sample,
+            padding: 0,
+        };
+
+        if width * bytes_per_sample > stride
+            || offset + plane.data_len() > plane.buffer.as_slice().len()
+        {
+            return Err(Error::InvalidInput(format!(
+                "{}x{} plane with stride {} at offset {} does not fit in a {} byte buffer",
+                width,
+                height,
+                stride,
+                offset,
+                plane.buffer.as_slice().len()
+            )));
+        }
+        Ok(plane)
+    }
+
+    /// Borrow a sub-rectangle of this plane without copying
+    ///
+    /// `x`, `y`, `width` and `height` are in pixels. The view shares the
+    /// buffer and has no padding of its own.
+    pub fn view(&self, x: usize, y: usize, width: usize, height: usize) -> Result<Self> {
+        if x + width > self.width || y + height > self.height {
+            return Err(Error::InvalidInput(format!(
+                "View {}x{} at ({}, {}) exceeds {}x{} plane",
+                width, height, x, y, self.width, self.height
+            )));
+        }
+
+        Ok(Self {
+            buffer: self.buffer.clone(),
+            offset: self.offset + y * self.stride + x * self.bytes_per_sample,
+            stride: self.stride,
+            width,
+            height,
+            bytes_per_sample: self.bytes_per_sample,
+            padding: 0,
+        })
+    }
+
+    /// Bytes from the first visible sample to the last one
+    fn data_len(&self) -> usize {
+        match self.height {
+            0 => 0,
+            height => (height - 1) * self.stride + self.width * self.bytes_per_sample,
         }
     }
 
     /// Get the raw data
+    ///
+    /// Starts at the first visible sample and ends after the last one;
+    /// rows are [`stride`](Self::stride) bytes apart.
     pub fn data(&self) -> &[u8] {
-        &self.data
+        &self.buffer.as_slice()[self.offset..self.offset + self.data_len()]
     }
 
     /// Get mutable raw data
+    ///
+    /// Copies the buffer first if it is shared with another plane.
     pub fn data_mut(&mut self) -> &mut [u8] {
-        &mut self.data
+        let range = self.offset..self.offset + self.data_len();
+        &mut self.buffer_mut()[range]
+    }
+
+    /// Unique, mutable access to the whole buffer, copying it if shared
+    fn buffer_mut(&mut self) -> &mut [u8] {
+        if let Buffer::External(buffer) = &self.buffer {
+            let copy = AVec::from_slice(BUFFER_ALIGNMENT, (**buffer).as_ref());
+            self.buffer = Buffer::Aligned(Arc::new(copy));
+        }
+
+        match &mut self.buffer {
+            Buffer::Aligned(buffer) => Arc::make_mut(buffer).as_mut_slice(),
+            Buffer::External(_) => unreachable!("external buffers are copied above"),
+        }
+    }
+
+    /// Returns true if the buffer is shared with another plane or owner
+    pub fn is_shared(&self) -> bool {
+        match &self.buffer {
+            Buffer::Aligned(buffer) => Arc::strong_count(buffer) > 1,
+            Buffer::External(_) => true,
+        }
     }
 
     /// Get stride (bytes per row)
@@ -85,25 +412,75 @@ impl Plane {
         self.height
     }
 
-    /// Get a row of pixels
+    /// Get bytes per sample (1 for 8-bit, 2 for high bit depth)
+    pub fn bytes_per_sample(&self) -> usize {
+        self.bytes_per_sample
+    }
+
+    /// Get border in pixels available on each side (see [`PlaneLayout`])
+    pub fn padding(&self) -> usize {
+        self.padding
+    }
+
+    /// Get a row of pixels (`width * bytes_per_sample` bytes)
     pub fn row(&self, y: usize) -> &[u8] {
         let start = y * self.stride;
-        let end = start + self.width;
-        &self.data[start..end]
+        let end = start + self.width * self.bytes_per_sample;
+        &self.data()[start..end]
     }
 
-    /// Get a mutable row of pixels
+    /// Get a mutable row of pixels (`width * bytes_per_sample` bytes)
     pub fn row_mut(&mut self, y: usize) -> &mut [u8] {
         let start = y * self.stride;
-        let end = start + self.width;
-        &mut self.data[start..end]
+        let end = start + self.width * self.bytes_per_sample;
+        &mut self.data_mut()[start..end]
+    }
+
+    /// Fill the padded border by replicating the outermost samples
+    ///
+    /// Encoders and scalers that read past the picture edge expect this.
+    /// Does nothing for planes without padding.
+    pub fn extend_edges(&mut self) {
+        if self.padding == 0 || self.width == 0 || self.height == 0 {
+            return;
+        }
+
+        let (padding, bps, stride, width, height) = (
+            self.padding,
+            self.bytes_per_sample,
+            self.stride,
+            self.width,
+            self.height,
+        );
+        let border = padding * bps;
+        let origin = self.offset;
+        let buffer = self.buffer_mut();
+
+        for y in 0..height {
+            let row = origin + y * stride;
+            let (first, last) = (row, row + (width - 1) * bps);
+            for x in 1..=padding {
+                buffer.copy_within(first..first + bps, first - x * bps);
+                buffer.copy_within(last..last + bps, last + x * bps);
+            }
+        }
+
+        // Whole padded rows, top and bottom
+        let first_row = origin - border;
+        let last_row = first_row + (height - 1) * stride;
+        let row_len = width * bps + 2 * border;
+        for y in 1..=padding {
+            buffer.copy_within(first_row..first_row + row_len, first_row - y * stride);
+            buffer.copy_within(last_row..last_row + row_len, last_row + y * stride);
+        }
     }
 }
 
 /// A decoded video frame with multiple planes
 ///
 /// Typically used with Arc for zero-copy sharing between pipeline stages.
-/// For YUV formats, planes are ordered as [Y, U, V].
+/// For YUV formats, planes are ordered as [Y, U, V], or [Y, UV] when
+/// semi-planar.
 #[derive(Debug, Clone)]
 pub struct Frame {
     /// Pixel planes (Y, U, V for YUV formats)
@@ -116,43 +493,44 @@ pub struct Frame {
     format: PixelFormat,
     /// Presentation timestamp
     pts: Option<i64>,
+    /// Color description and HDR metadata
+    colorimetry: Colorimetry,
+    /// Interlacing of the picture
+    field_order: FieldOrder,
+    /// Pool the planes return to when the frame is dropped
+    recycler: Recycler,
 }
 
 impl Frame {
     /// Create a new frame with specified format and dimensions
+    ///
+    /// Subsampled chroma planes round up, so odd sizes keep their last
+    /// column and row of chroma.
     pub fn new(width: u32, height: u32, format: PixelFormat) -> Self {
-        let planes = match format {
-            PixelFormat::Yuv420p => {
-                // Y plane: full resolution
-                // U, V planes: half resolution (4:2:0 subsampling)
-                vec![
-                    Plane::new(width as usize, height as usize),
-                    Plane::new((width / 2) as usize, (height / 2) as usize),
-                    Plane::new((width / 2) as usize, (height / 2) as usize),
-                ]
-            }
-            PixelFormat::Yuv422p => {
-                // Y plane: full resolution
-                // U, V planes: half width (4:2:2 subsampling)
-                vec![
-                    Plane::new(width as usize, height as usize),
-                    Plane::new((width / 2) as usize, height as usize),
-                    Plane::new((width / 2) as usize, height as usize),
-                ]
-            }
-            PixelFormat::Yuv444p => {
-                // All planes full resolution
-                vec![
-                    Plane::new(width as usize, height as usize),
-                    Plane::new(width as usize, height as usize),
-                    Plane::new(width as usize, height as usize),
-                ]
-            }
-            PixelFormat::Rgb24 => {
-                // Single interleaved RGB plane
-                vec![Plane::new((width * 3) as usize, height as usize)]
-            }
-        };
+        Self::with_layout(width, height, format, PlaneLayout::default())
+    }
+
+    /// Create a new frame whose planes use the given row alignment and padding
+    ///
+    /// # Example
+    /// ```
+    /// use mead_core::{Frame, PixelFormat, PlaneLayout};
+    ///
+    /// let layout = PlaneLayout { alignment: 64, padding: 32 };
+    /// let mut frame = Frame::with_layout(1918, 1080, PixelFormat::Yuv420p, layout);
+    /// assert_eq!(frame.planes()[0].stride() % 64, 0);
+    ///
+    /// // After filling the picture, replicate its edges into the padding
+    /// frame.extend_edges();
+    /// ```
+    pub fn with_layout(width: u32, height: u32, format: PixelFormat, layout: PlaneLayout) -> Self {
+        let bytes = format.bytes_per_sample();
+        let planes = Self::plane_sizes(width as usize, height as usize, format)
+            .into_iter()
+            .map(|(plane_width, plane_height)| {
+                Plane::with_layout(plane_width, plane_height, bytes, layout)
+            })
+            .collect();
 
         Self {
             planes,
@@ -160,6 +538,123 @@ impl Frame {
             height,
             format,
             pts: None,
+            colorimetry: Colorimetry::default(),
+            field_order: FieldOrder::Progressive,
+            recycler: Recycler::default(),
+        }
+    }
+
+    /// Build a frame from existing planes, e.g. ones wrapping external buffers
+    ///
+    /// The plane count, sizes and sample size must match `format`.
+    pub fn from_planes(
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        planes: Vec<Plane>,
+    ) -> Result<Self> {
+        let expected = Self::plane_sizes(width as usize, height as usize, format);
+        let matches = planes.len() == expected.len()
+            && planes.iter().zip(&expected).all(|(plane, &(w, h))| {
+                plane.width() == w
+                    && plane.height() == h
+                    && plane.bytes_per_sample() == format.bytes_per_sample()
+            });
+
+        if !matches {
+            return Err(Error::InvalidInput(format!(
+                "Planes do not match a {}x{} {:?} frame",
+                width, height, format
+            )));
+        }
+
+        Ok(Self {
+            planes,
+            width,
+            height,
+            format,
+            pts: None,
+            colorimetry: Colorimetry::default(),
+            field_order: FieldOrder::Progressive,
+            recycler: Recycler::default(),
+        })
+    }
+
+    /// Plane sizes in samples for a frame of the given format
+    ///
+    /// Interleaved planes count every component: a single RGB plane is
+    /// three samples per pixel wide, an NV12 UV plane two per chroma pixel.
+    fn plane_sizes(width: usize, height: usize, format: PixelFormat) -> Vec<(usize, usize)> {
+        if !format.is_yuv() {
+            return vec![(width * 3, height)];
+        }
+        let (chroma_width, chroma_height) = chroma_size(format, width, height);
+        if format.is_semi_planar() {
+            vec![(width, height), (chroma_width * 2, chroma_height)]
+        } else {
+            vec![
+                (width, height),
+                (chroma_width, chroma_height),
+                (chroma_width, chroma_height),
+            ]
+        }
+    }
+
+    /// Crop to a sub-rectangle without copying
+    ///
+    /// For subsampled formats, `x` and `y` must fall on a chroma sample.
+    /// The view keeps the timestamp and colorimetry; writing to it copies
+    /// the shared planes first.
+    pub fn view(&self, x: u32, y: u32, width: u32, height: u32) -> Result<Self> {
+        let (shift_x, shift_y) = self.format.chroma_shift();
+        if x % (1 << shift_x) != 0 || y % (1 << shift_y) != 0 {
+            return Err(Error::InvalidInput(format!(
+                "View origin ({}, {}) is not aligned to {:?} chroma",
+                x, y, self.format
+            )));
+        }
+        if x + width > self.width || y + height > self.height {
+            return Err(Error::InvalidInput(format!(
+                "View {}x{} at ({}, {}) exceeds {}x{} frame",
+                width, height, x, y, self.width, self.height
+            )));
+        }
+
+        let (x, y) = (x as usize, y as usize);
+        let sizes = Self::plane_sizes(width as usize, height as usize, self.format);
+        let planes = self
+            .planes
+            .iter()
+            .zip(sizes)
+            .enumerate()
+            .map(
+                |(index, (plane, (w, h)))| match (self.format.is_yuv(), index) {
+                    (true, 0) => plane.view(x, y, w, h),
+                    (true, _) if self.format.is_semi_planar() => {
+                        plane.view((x >> shift_x) * 2, y >> shift_y, w, h)
+                    }
+                    (true, _) => plane.view(x >> shift_x, y >> shift_y, w, h),
+                    (false, _) => plane.view(x * 3, y, w, h),
+                },
+            )
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            planes,
+            width,
+            height,
+            format: self.format,
+            pts: self.pts,
+            colorimetry: self.colorimetry,
+            field_order: self.field_order,
+            recycler: Recycler::default(),
+        })
+    }
+
+    /// Replicate edge samples into the padding of every plane
+    pub fn extend_edges(&mut self) {
+        for plane in &mut self.planes {
+            plane.extend_edges();
         }
     }
 
@@ -188,6 +683,26 @@ impl Frame {
         self.pts = Some(pts);
     }
 
+    /// Get color description and HDR metadata
+    pub fn colorimetry(&self) -> &Colorimetry {
+        &self.colorimetry
+    }
+
+    /// Set color description and HDR metadata
+    pub fn set_colorimetry(&mut self, colorimetry: Colorimetry) {
+        self.colorimetry = colorimetry;
+    }
+
+    /// Get the field order, [`FieldOrder::Progressive`] unless set by the source
+    pub fn field_order(&self) -> FieldOrder {
+        self.field_order
+    }
+
+    /// Set the field order
+    pub fn set_field_order(&mut self, field_order: FieldOrder) {
+        self.field_order = field_order;
+    }
+
     /// Get reference to planes
     pub fn planes(&self) -> &[Plane] {
         &self.planes
@@ -200,41 +715,274 @@ impl Frame {
 
     /// Get Y plane (luma) for YUV formats
     pub fn plane_y(&self) -> Option<&Plane> {
-        matches!(self.format, PixelFormat::Yuv420p | PixelFormat::Yuv422p | PixelFormat::Yuv444p)
-            .then(|| &self.planes[0])
+        self.format.is_yuv().then(|| &self.planes[0])
     }
 
     /// Get mutable Y plane (luma) for YUV formats
     pub fn plane_y_mut(&mut self) -> Option<&mut Plane> {
-        matches!(self.format, PixelFormat::Yuv420p | PixelFormat::Yuv422p | PixelFormat::Yuv444p)
-            .then(|| &mut self.planes[0])
+        self.format.is_yuv().then(|| &mut self.planes[0])
     }
 
-    /// Get U plane (chroma) for YUV formats
+    /// Get U plane (chroma) for planar YUV formats
     pub fn plane_u(&self) -> Option<&Plane> {
-        matches!(self.format, PixelFormat::Yuv420p | PixelFormat::Yuv422p | PixelFormat::Yuv444p)
-            .then(|| &self.planes[1])
+        self.is_planar_yuv().then(|| &self.planes[1])
     }
 
-    /// Get mutable U plane (chroma) for YUV formats
+    /// Get mutable U plane (chroma) for planar YUV formats
     pub fn plane_u_mut(&mut self) -> Option<&mut Plane> {
-        matches!(self.format, PixelFormat::Yuv420p | PixelFormat::Yuv422p | PixelFormat::Yuv444p)
-            .then(|| &mut self.planes[1])
+        self.is_planar_yuv().then(|| &mut self.planes[1])
     }
 
-    /// Get V plane (chroma) for YUV formats
+    /// Get V plane (chroma) for planar YUV formats
     pub fn plane_v(&self) -> Option<&Plane> {
-        matches!(self.format, PixelFormat::Yuv420p | PixelFormat::Yuv422p | PixelFormat::Yuv444p)
-            .then(|| &self.planes[2])
+        self.is_planar_yuv().then(|| &self.planes[2])
     }
 
-    /// Get mutable V plane (chroma) for YUV formats
+    /// Get mutable V plane (chroma) for planar YUV formats
     pub fn plane_v_mut(&mut self) -> Option<&mut Plane> {
-        matches!(self.format, PixelFormat::Yuv420p | PixelFormat::Yuv422p | PixelFormat::Yuv444p)
-            .then(|| &mut self.planes[2])
+        self.is_planar_yuv().then(|| &mut self.planes[2])
+    }
+
+    /// Get interleaved UV plane (chroma) for semi-planar formats
+    pub fn plane_uv(&self) -> Option<&Plane> {
+        self.format.is_semi_planar().then(|| &self.planes[1])
+    }
+
+    /// Get mutable interleaved UV plane (chroma) for semi-planar formats
+    pub fn plane_uv_mut(&mut self) -> Option<&mut Plane> {
+        self.format.is_semi_planar().then(|| &mut self.planes[1])
+    }
+
+    fn is_planar_yuv(&self) -> bool {
+        self.format.is_yuv() && !self.format.is_semi_planar()
     }
 }
 
+impl Drop for Frame {
+    fn drop(&mut self) {
+        if let Some(pool) = self.recycler.0.take().and_then(|pool| pool.upgrade()) {
+            pool.recycle(std::mem::take(&mut self.planes));
+        }
+    }
+}
+
+/// Link from a pooled frame back to its pool
+///
+/// Clones are not pooled: only the frame handed out by the pool returns
+/// its planes.
+#[derive(Default)]
+struct Recycler(Option<Weak<PoolShared>>);
+
+impl Clone for Recycler {
+    fn clone(&self) -> Self {
+        Self(None)
+    }
+}
+
+impl std::fmt::Debug for Recycler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(if self.0.is_some() {
+            "Pooled"
+        } else {
+            "Unpooled"
+        })
+    }
+}
+
+/// Pool of recycled frames of one size and format
+///
+/// Frames handed out by the pool return their planes when dropped, which
+/// for an [`ArcFrame`] is when the last reference goes away. At most
+/// `capacity` frames are out at once: [`acquire`](Self::acquire) blocks
+/// until one is returned, which gives producers backpressure, while
+/// [`try_acquire`](Self::try_acquire) returns `None` instead.
+///
+/// Recycled frames keep their old pixels; every sample must be written
+/// before use. Planes still shared with a clone or view when the frame is
+/// dropped are not recycled.
+///
+/// # Example
+/// ```
+/// use mead_core::{FramePool, PixelFormat};
+/// use std::sync::Arc;
+///
+/// let pool = FramePool::new(1920, 1080, PixelFormat::Yuv420p, 4);
+///
+/// let frame = Arc::new(pool.acquire());
+/// assert_eq!(pool.outstanding(), 1);
+///
+/// // Dropping the last reference hands the planes back to the pool
+/// drop(frame);
+/// assert_eq!(pool.outstanding(), 0);
+/// ```
+#[derive(Clone)]
+pub struct FramePool {
+    shared: Arc<PoolShared>,
+}
+
+struct PoolShared {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    layout: PlaneLayout,
+    capacity: usize,
+    state: Mutex<PoolState>,
+    returned: Condvar,
+}
+
+#[derive(Default)]
+struct PoolState {
+    /// Planes of returned frames, ready for reuse
+    free: Vec<Vec<Plane>>,
+    /// Frames handed out and not yet dropped
+    outstanding: usize,
+}
+
+impl FramePool {
+    /// Create a pool of tightly packed frames, at most `capacity` in use at once
+    pub fn new(width: u32, height: u32, format: PixelFormat, capacity: usize) -> Self {
+        Self::with_layout(width, height, format, PlaneLayout::default(), capacity)
+    }
+
+    /// Create a pool of frames with the given plane layout
+    pub fn with_layout(
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        layout: PlaneLayout,
+        capacity: usize,
+    ) -> Self {
+        Self {
+            shared: Arc::new(PoolShared {
+                width,
+                height,
+                format,
+                layout,
+                capacity: capacity.max(1),
+                state: Mutex::new(PoolState::default()),
+                returned: Condvar::new(),
+            }),
+        }
+    }
+
+    /// Get a frame, waiting for one to be returned if `capacity` are in use
+    pub fn acquire(&self) -> Frame {
+        let mut state = self.shared.lock();
+        while state.outstanding >= self.shared.capacity {
+            state = self
+                .shared
+                .returned
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        self.checkout(state)
+    }
+
+    /// Get a frame, or `None` if `capacity` frames are in use
+    pub fn try_acquire(&self) -> Option<Frame> {
+        let state = self.shared.lock();
+        (state.outstanding < self.shared.capacity).then(|| self.checkout(state))
+    }
+
+    fn checkout(&self, mut state: MutexGuard<'_, PoolState>) -> Frame {
+        state.outstanding += 1;
+        let planes = state.free.pop();
+        drop(state);
+
+        let shared = &self.shared;
+        let mut frame = match planes {
+            Some(planes) => Frame {
+                planes,
+                width: shared.width,
+                height: shared.height,
+                format: shared.format,
+                pts: None,
+                colorimetry: Colorimetry::default(),
+                field_order: FieldOrder::Progressive,
+                recycler: Recycler::default(),
+            },
+            None => Frame::with_layout(shared.width, shared.height, shared.format, shared.layout),
+        };
+        frame.recycler = Recycler(Some(Arc::downgrade(&self.shared)));
+        frame
+    }
+
+    /// Get frame width
+    pub fn width(&self) -> u32 {
+        self.shared.width
+    }
+
+    /// Get frame height
+    pub fn height(&self) -> u32 {
+        self.shared.height
+    }
+
+    /// Get pixel format
+    pub fn format(&self) -> PixelFormat {
+        self.shared.format
+    }
+
+    /// Get plane layout
+    pub fn layout(&self) -> PlaneLayout {
+        self.shared.layout
+    }
+
+    /// Get the maximum number of frames in use at once
+    pub fn capacity(&self) -> usize {
+        self.shared.capacity
+    }
+
+    /// Get the number of frames currently in use
+    pub fn outstanding(&self) -> usize {
+        self.shared.lock().outstanding
+    }
+}
+
+impl std::fmt::Debug for FramePool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FramePool")
+            .field("width", &self.shared.width)
+            .field("height", &self.shared.height)
+            .field("format", &self.shared.format)
+            .field("capacity", &self.shared.capacity)
+            .field("outstanding", &self.outstanding())
+            .finish()
+    }
+}
+
+impl PoolShared {
+    fn lock(&self) -> MutexGuard<'_, PoolState> {
+        // The state stays consistent even if a holder panicked
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Take back the planes of a dropped frame
+    fn recycle(&self, planes: Vec<Plane>) {
+        let sizes = Frame::plane_sizes(self.width as usize, self.height as usize, self.format);
+        let reusable = planes.len() == sizes.len()
+            && planes.iter().zip(sizes).all(|(plane, (width, height))| {
+                !plane.is_shared()
+                    && plane.width() == width
+                    && plane.height() == height
+                    && plane.padding() == self.layout.padding
+            });
+
+        let mut state = self.lock();
+        state.outstanding = state.outstanding.saturating_sub(1);
+        if reusable && state.free.len() < self.capacity {
+            state.free.push(planes);
+        }
+        drop(state);
+        self.returned.notify_one();
+    }
+}
+
+/// Chroma plane size in pixels, rounding up for odd luma sizes
+fn chroma_size(format: PixelFormat, width: usize, height: usize) -> (usize, usize) {
+    let (shift_x, shift_y) = format.chroma_shift();
+    (width.div_ceil(1 << shift_x), height.div_ceil(1 << shift_y))
+}
+
 /// Type alias for reference-counted frames (zero-copy sharing)
 pub type ArcFrame = Arc<Frame>;
 
@@ -289,6 +1037,24 @@ mod tests {
         assert_eq!(frame.planes()[1].height(), 64);
     }
 
+    #[test]
+    fn test_yuv420p10_frame() {
+        let frame = Frame::new(64, 32, PixelFormat::Yuv420p10);
+        assert_eq!(frame.format().bit_depth(), 10);
+
+        // Two bytes per sample, width still in pixels
+        let y = frame.plane_y().unwrap();
+        assert_eq!(y.width(), 64);
+        assert_eq!(y.bytes_per_sample(), 2);
+        assert_eq!(y.stride(), 128);
+        assert_eq!(y.row(0).len(), 128);
+        assert_eq!(y.data().len(), 128 * 32);
+
+        let u = frame.plane_u().unwrap();
+        assert_eq!((u.width(), u.height()), (32, 16));
+        assert_eq!(u.row(0).len(), 64);
+    }
+
     #[test]
     fn test_frame_arc() {
         let frame = Arc::new(Frame::new(64, 64, PixelFormat::Yuv420p));
@@ -302,4 +1068,154 @@ mod tests {
         let ptr = plane.data().as_ptr() as usize;
         assert_eq!(ptr % 32, 0); // 32-byte alignment for AVX
     }
+
+    #[test]
+    fn test_plane_layout() {
+        let layout = PlaneLayout {
+            alignment: 64,
+            padding: 4,
+        };
+        let plane = Plane::with_layout(100, 10, 2, layout);
+        assert_eq!(plane.stride() % 64, 0);
+        assert!(plane.stride() >= (100 + 8) * 2);
+        assert_eq!(plane.data().as_ptr() as usize % 64, 0);
+        assert_eq!(plane.row(9).len(), 200);
+        assert_eq!(plane.data().len(), 9 * plane.stride() + 200);
+    }
+
+    #[test]
+    fn test_plane_extend_edges() {
+        let mut plane = Plane::with_layout(
+            2,
+            2,
+            1,
+            PlaneLayout {
+                alignment: 1,
+                padding: 2,
+            },
+        );
+        plane.row_mut(0).copy_from_slice(&[1, 2]);
+        plane.row_mut(1).copy_from_slice(&[3, 4]);
+        plane.extend_edges();
+
+        // Read the whole padded buffer back: 6x6 with the picture in the middle
+        let buffer = plane.buffer.as_slice();
+        assert_eq!(plane.stride(), 6);
+        assert_eq!(&buffer[..6], &[1, 1, 1, 2, 2, 2]);
+        assert_eq!(&buffer[2 * 6..3 * 6], &[1, 1, 1, 2, 2, 2]);
+        assert_eq!(&buffer[3 * 6..4 * 6], &[3, 3, 3, 4, 4, 4]);
+        assert_eq!(&buffer[5 * 6..], &[3, 3, 3, 4, 4, 4]);
+    }
+
+    #[test]
+    fn test_frame_view_shares_planes() {
+        let mut frame = Frame::new(8, 8, PixelFormat::Yuv420p);
+        for (y, value) in (0..8).zip(10u8..) {
+            frame.planes_mut()[0].row_mut(y).fill(value);
+        }
+        frame.planes_mut()[1]
+            .row_mut(1)
+            .copy_from_slice(&[5, 6, 7, 8]);
+
+        let mut view = frame.view(2, 2, 4, 4).unwrap();
+        assert!(view.planes()[0].is_shared());
+        assert_eq!(view.planes()[0].row(0), &[12, 12, 12, 12]);
+        assert_eq!(view.planes()[1].row(0), &[6, 7]);
+        assert_eq!(
+            view.planes()[1].data().as_ptr(),
+            frame.planes()[1].row(1)[1..].as_ptr()
+        );
+
+        // Writing to the view copies instead of touching the original
+        view.planes_mut()[0].row_mut(0).fill(0);
+        assert!(!view.planes()[0].is_shared());
+        assert_eq!(frame.planes()[0].row(2), &[12; 8]);
+
+        assert!(frame.view(1, 0, 4, 4).is_err());
+        assert!(frame.view(6, 0, 4, 4).is_err());
+    }
+
+    #[test]
+    fn test_frame_from_shared_planes() {
+        let luma: Arc<Vec<u8>> = Arc::new((0..32).collect());
+        let chroma: Arc<Vec<u8>> = Arc::new(vec![128; 4]);
+        let planes = vec![
+            Plane::from_shared(luma, 0, 8, 4, 4, 1).unwrap(),
+            Plane::from_shared(chroma.clone(), 0, 2, 2, 2, 1).unwrap(),
+            Plane::from_shared(chroma, 0, 2, 2, 2, 1).unwrap(),
+        ];
+        let frame = Frame::from_planes(4, 4, PixelFormat::Yuv420p, planes).unwrap();
+        assert_eq!(frame.planes()[0].row(1), &[8, 9, 10, 11]);
+
+        assert!(Plane::from_shared(Arc::new(vec![0u8; 10]), 0, 4, 4, 4, 1).is_err());
+        let planes = vec![Plane::new(4, 4)];
+        assert!(Frame::from_planes(4, 4, PixelFormat::Yuv420p, planes).is_err());
+    }
+
+    #[test]
+    fn test_frame_pool_recycles() {
+        let pool = FramePool::new(64, 64, PixelFormat::Yuv420p, 2);
+
+        let mut frame = pool.acquire();
+        frame.set_pts(7);
+        let luma = frame.planes()[0].data().as_ptr();
+        drop(Arc::new(frame));
+        assert_eq!(pool.outstanding(), 0);
+
+        // Same buffer, fresh metadata
+        let frame = pool.acquire();
+        assert_eq!(frame.planes()[0].data().as_ptr(), luma);
+        assert_eq!(frame.pts(), None);
+
+        // Clones are not pooled, and shared planes are not recycled
+        let copy = frame.clone();
+        drop(frame);
+        assert_eq!(pool.outstanding(), 0);
+        let fresh = pool.acquire();
+        assert_ne!(
+            fresh.planes()[0].data().as_ptr(),
+            copy.planes()[0].data().as_ptr()
+        );
+    }
+
+    #[test]
+    fn test_frame_pool_backpressure() {
+        let pool = FramePool::new(16, 16, PixelFormat::Yuv444p10, 1);
+        let frame = Arc::new(pool.acquire());
+        assert!(pool.try_acquire().is_none());
+
+        let held = frame.clone();
+        let consumer = std::thread::spawn(move || drop(held));
+        drop(frame);
+        consumer.join().unwrap();
+
+        // Blocks until the last reference is gone, then reuses the planes
+        let frame = pool.acquire();
+        assert_eq!(frame.format(), PixelFormat::Yuv444p10);
+        assert_eq!(pool.outstanding(), 1);
+    }
+
+    #[test]
+    fn test_semi_planar_frame() {
+        let mut frame = Frame::new(6, 4, PixelFormat::P010);
+        assert_eq!(frame.planes().len(), 2);
+        assert!(frame.plane_u().is_none());
+
+        let uv = frame.plane_uv_mut().unwrap();
+        assert_eq!((uv.width(), uv.height(), uv.bytes_per_sample()), (6, 2, 2));
+        uv.row_mut(1)[8..12].copy_from_slice(&[1, 2, 3, 4]);
+
+        // A view keeps U and V of the same chroma pixel together
+        let view = frame.view(4, 2, 2, 2).unwrap();
+        assert_eq!(view.plane_uv().unwrap().row(0), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_odd_size_chroma_rounds_up() {
+        let frame = Frame::new(5, 3, PixelFormat::Yuv420p);
+        assert_eq!(
+            (frame.planes()[1].width(), frame.planes()[1].height()),
+            (3, 2)
+        );
+    }
 }
@@ -1,6 +1,6 @@
 //! I/O abstractions for media sources
 
-use std::io::{Read, Seek, Result};
+use std::io::{Read, Result, Seek, SeekFrom, Write};
 
 /// Trait for media data sources (files, streams, network)
 ///
@@ -73,7 +73,7 @@ impl<R: Read> Seek for ReadOnlySource<R> {
     fn seek(&mut self, _pos: std::io::SeekFrom) -> Result<u64> {
         Err(std::io::Error::new(
             std::io::ErrorKind::Unsupported,
-            "Source does not support seeking"
+            "Source does not support seeking",
         ))
     }
 }
@@ -88,6 +88,54 @@ impl<R: Read> MediaSource for ReadOnlySource<R> {
     }
 }
 
+/// Wrapper for non-seekable sinks (stdout, pipes)
+///
+/// Counterpart to [`ReadOnlySource`] for muxers: tracks the write position so
+/// `stream_position()` works, but any seek that would move it returns an error.
+/// Muxers with a streaming mode that never patches headers can write through it.
+#[derive(Debug)]
+pub struct WriteOnlySink<W: Write> {
+    inner: W,
+    position: u64,
+}
+
+impl<W: Write> WriteOnlySink<W> {
+    /// Create a new write-only sink
+    pub fn new(inner: W) -> Self {
+        Self { inner, position: 0 }
+    }
+
+    /// Unwrap the inner writer
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for WriteOnlySink<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Seek for WriteOnlySink<W> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        match pos {
+            SeekFrom::Current(0) => Ok(self.position),
+            SeekFrom::Start(offset) if offset == self.position => Ok(self.position),
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Sink does not support seeking",
+            )),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -119,4 +167,13 @@ mod tests {
         let result = source.seek(SeekFrom::Start(0));
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_write_only_sink_position() {
+        let mut sink = WriteOnlySink::new(Vec::new());
+        sink.write_all(&[1, 2, 3]).unwrap();
+        assert_eq!(sink.stream_position().unwrap(), 3);
+        assert!(sink.seek(SeekFrom::Start(0)).is_err());
+        assert_eq!(sink.into_inner(), vec![1, 2, 3]);
+    }
 }
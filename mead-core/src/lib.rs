@@ -11,14 +11,24 @@
     missing_debug_implementations
 )]
 
-pub mod container;
 pub mod codec;
+pub mod color;
+pub mod container;
+pub mod convert;
+pub mod deinterlace;
+pub mod denoise;
 pub mod error;
+pub mod filter;
+pub mod fps;
 pub mod frame;
 pub mod io;
+mod parallel;
+pub mod scale;
+pub mod transform;
 
+pub use color::Colorimetry;
 pub use error::{Error, Result};
-pub use frame::{ArcFrame, Frame, PixelFormat, Plane};
+pub use frame::{ArcFrame, FieldOrder, Frame, FramePool, PixelFormat, Plane, PlaneLayout};
 pub use io::MediaSource;
 
 /// Library version
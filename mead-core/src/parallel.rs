@@ -0,0 +1,27 @@
+//! Row-parallel processing shared by the filters
+
+/// Run `f` on every row, spreading contiguous runs of rows over threads
+pub(crate) fn for_each_row<T: Send>(
+    mut rows: Vec<&mut [T]>,
+    threads: usize,
+    f: impl Fn(usize, &mut [T]) + Sync,
+) {
+    let per_thread = rows.len().div_ceil(threads.max(1)).max(1);
+    if per_thread >= rows.len() {
+        for (y, row) in rows.into_iter().enumerate() {
+            f(y, row);
+        }
+        return;
+    }
+
+    std::thread::scope(|scope| {
+        for (chunk, rows) in rows.chunks_mut(per_thread).enumerate() {
+            let f = &f;
+            scope.spawn(move || {
+                for (offset, row) in rows.iter_mut().enumerate() {
+                    f(chunk * per_thread + offset, row);
+                }
+            });
+        }
+    });
+}
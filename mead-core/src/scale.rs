@@ -0,0 +1,602 @@
+//! Frame scaling
+//!
+//! Resizes frames with separable resampling kernels. Chroma planes are
+//! scaled in the luma coordinate system, keeping the frame's
+//! [`ChromaSamplePosition`] aligned with luma at any ratio, and rows are
+//! processed on several threads.
+//!
+//! # Example
+//! ```no_run
+//! use mead_core::scale::{Kernel, ScaleConfig, Scaler};
+//! use mead_core::{Frame, PixelFormat};
+//!
+//! let mut scaler = Scaler::new(ScaleConfig {
+//!     width: 1280,
+//!     height: 720,
+//!     kernel: Kernel::Lanczos,
+//!     ..Default::default()
+//! })?;
+//!
+//! let frame = Frame::new(1920, 1080, PixelFormat::Yuv420p);
+//! let scaled = scaler.scale(&frame)?;
+//! assert_eq!((scaled.width(), scaled.height()), (1280, 720));
+//! # Ok::<(), mead_core::Error>(())
+//! ```
+
+use crate::color::ChromaSamplePosition;
+use crate::parallel::for_each_row;
+use crate::{Error, Frame, FramePool, PixelFormat, Plane, PlaneLayout, Result};
+use std::f64::consts::PI;
+use std::str::FromStr;
+
+/// Resampling kernel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Kernel {
+    /// Linear interpolation, fast but soft
+    Bilinear,
+    /// Catmull-Rom cubic, a good balance of sharpness and ringing
+    #[default]
+    Bicubic,
+    /// Three-lobe Lanczos, sharpest, with some ringing on hard edges
+    Lanczos,
+    /// Spline36, close to Lanczos with less ringing
+    Spline,
+}
+
+impl Kernel {
+    /// Kernel radius in source samples when upscaling
+    fn support(self) -> f64 {
+        match self {
+            Self::Bilinear => 1.0,
+            Self::Bicubic => 2.0,
+            Self::Lanczos | Self::Spline => 3.0,
+        }
+    }
+
+    /// Kernel weight at distance `x`
+    fn weight(self, x: f64) -> f64 {
+        let x = x.abs();
+        match self {
+            Self::Bilinear => (1.0 - x).max(0.0),
+            Self::Bicubic => {
+                // Catmull-Rom: B = 0, C = 0.5
+                if x < 1.0 {
+                    (1.5 * x - 2.5) * x * x + 1.0
+                } else if x < 2.0 {
+                    ((-0.5 * x + 2.5) * x - 4.0) * x + 2.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Lanczos => {
+                if x < 3.0 {
+                    sinc(x) * sinc(x / 3.0)
+                } else {
+                    0.0
+                }
+            }
+            Self::Spline => {
+                if x < 1.0 {
+                    ((13.0 / 11.0 * x - 453.0 / 209.0) * x - 3.0 / 209.0) * x + 1.0
+                } else if x < 2.0 {
+                    let x = x - 1.0;
+                    ((-6.0 / 11.0 * x + 270.0 / 209.0) * x - 156.0 / 209.0) * x
+                } else if x < 3.0 {
+                    let x = x - 2.0;
+                    ((1.0 / 11.0 * x - 45.0 / 209.0) * x + 26.0 / 209.0) * x
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Kernel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "bilinear" => Ok(Self::Bilinear),
+            "bicubic" => Ok(Self::Bicubic),
+            "lanczos" => Ok(Self::Lanczos),
+            "spline" | "spline36" => Ok(Self::Spline),
+            _ => Err(Error::InvalidInput(format!(
+                "Unknown scaling kernel: {}",
+                s
+            ))),
+        }
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Scaler configuration
+#[derive(Debug, Clone, Copy)]
+pub struct ScaleConfig {
+    /// Output width
+    pub width: u32,
+    /// Output height
+    pub height: u32,
+    /// Resampling kernel
+    pub kernel: Kernel,
+    /// Number of threads (0 = auto-detect from CPU cores)
+    pub threads: usize,
+    /// Plane layout of scaled frames
+    pub layout: PlaneLayout,
+}
+
+impl Default for ScaleConfig {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            kernel: Kernel::default(),
+            threads: 0,
+            layout: PlaneLayout::default(),
+        }
+    }
+}
+
+/// Output frames kept for reuse by a [`Scaler`]
+const POOL_CAPACITY: usize = 8;
+
+/// Scales frames to a fixed output size
+///
+/// Filter coefficients are computed once per input size and format, and
+/// output frames come from an internal [`FramePool`].
+#[derive(Debug)]
+pub struct Scaler {
+    config: ScaleConfig,
+    threads: usize,
+    /// Input size, format and chroma siting the filters were built for
+    source: Option<(u32, u32, PixelFormat, ChromaSamplePosition)>,
+    /// Horizontal and vertical filter of each plane
+    filters: Vec<(Filter, Filter)>,
+    pool: Option<FramePool>,
+}
+
+impl Scaler {
+    /// Create a scaler to the configured output size
+    pub fn new(config: ScaleConfig) -> Result<Self> {
+        if config.width == 0 || config.height == 0 {
+            return Err(Error::InvalidInput(format!(
+                "Invalid output size {}x{}",
+                config.width, config.height
+            )));
+        }
+
+        let threads = if config.threads == 0 {
+            num_cpus::get()
+        } else {
+            config.threads
+        };
+
+        Ok(Self {
+            config,
+            threads,
+            source: None,
+            filters: Vec::new(),
+            pool: None,
+        })
+    }
+
+    /// Get output width
+    pub fn width(&self) -> u32 {
+        self.config.width
+    }
+
+    /// Get output height
+    pub fn height(&self) -> u32 {
+        self.config.height
+    }
+
+    /// Scale a frame, keeping its timestamp and colorimetry
+    pub fn scale(&mut self, frame: &Frame) -> Result<Frame> {
+        let format = frame.format();
+        let position = frame.colorimetry().chroma_position;
+        let source = (frame.width(), frame.height(), format, position);
+        if self.source != Some(source) {
+            self.filters = plane_geometry(format, position)
+                .map(|geometry| {
+                    (
+                        Filter::new(
+                            self.config.kernel,
+                            frame.width(),
+                            self.config.width,
+                            geometry.x,
+                        ),
+                        Filter::new(
+                            self.config.kernel,
+                            frame.height(),
+                            self.config.height,
+                            geometry.y,
+                        ),
+                    )
+                })
+                .collect();
+            self.source = Some(source);
+        }
+
+        let (width, height) = (self.config.width, self.config.height);
+        let pool = match &mut self.pool {
+            Some(pool) if pool.format() == format => pool,
+            pool => pool.insert(FramePool::with_layout(
+                width,
+                height,
+                format,
+                self.config.layout,
+                POOL_CAPACITY,
+            )),
+        };
+        // Every sample is overwritten, so stale pooled content never leaks
+        let mut output = pool
+            .try_acquire()
+            .unwrap_or_else(|| Frame::with_layout(width, height, format, self.config.layout));
+
+        let shift = if format == PixelFormat::P010 { 6 } else { 0 };
+        let max = (1u32 << format.bit_depth()) - 1;
+        for (((src, dst), (horizontal, vertical)), geometry) in frame
+            .planes()
+            .iter()
+            .zip(output.planes_mut())
+            .zip(&self.filters)
+            .zip(plane_geometry(format, position))
+        {
+            let samples = Samples {
+                components: geometry.components,
+                shift,
+                max,
+            };
+            scale_plane(src, dst, samples, horizontal, vertical, self.threads);
+        }
+
+        if let Some(pts) = frame.pts() {
+            output.set_pts(pts);
+        }
+        output.set_colorimetry(*frame.colorimetry());
+        Ok(output)
+    }
+}
+
+/// Output size keeping the aspect ratio of a `width`x`height` input
+///
+/// A target dimension of 0 is derived from the other one; with both set,
+/// the result is the largest size that fits inside the target. Derived
+/// dimensions are rounded to a whole number of chroma samples of `format`.
+///
+/// # Example
+/// ```
+/// use mead_core::scale::fit_size;
+/// use mead_core::PixelFormat;
+///
+/// assert_eq!(fit_size(1920, 1080, 1280, 0, PixelFormat::Yuv420p), (1280, 720));
+/// assert_eq!(fit_size(1920, 800, 1280, 720, PixelFormat::Yuv420p), (1280, 534));
+/// ```
+pub fn fit_size(
+    width: u32,
+    height: u32,
+    target_width: u32,
+    target_height: u32,
+    format: PixelFormat,
+) -> (u32, u32) {
+    let (shift_x, shift_y) = format.chroma_shift();
+    let round = |value: f64, shift: u32| {
+        let step = f64::from(1u32 << shift);
+        ((value / step).round() * step).max(step) as u32
+    };
+    let aspect = f64::from(width) / f64::from(height);
+
+    match (target_width, target_height) {
+        (0, 0) => (width, height),
+        (w, 0) => (w, round(f64::from(w) / aspect, shift_y)),
+        (0, h) => (round(f64::from(h) * aspect, shift_x), h),
+        (w, h) if f64::from(w) / f64::from(h) > aspect => {
+            (round(f64::from(h) * aspect, shift_x), h)
+        }
+        (w, _) => (w, round(f64::from(w) / aspect, shift_y)),
+    }
+}
+
+/// How one plane maps onto the luma grid along one axis
+#[derive(Clone, Copy)]
+struct Axis {
+    /// Subsampling factor (1 or 2)
+    factor: f64,
+    /// Luma position of the first sample
+    phase: f64,
+}
+
+/// Layout of one plane of a frame
+#[derive(Clone, Copy)]
+struct PlaneGeometry {
+    /// Interleaved components per pixel (3 for RGB, 2 for NV12 chroma)
+    components: usize,
+    x: Axis,
+    y: Axis,
+}
+
+/// Geometry of each plane of a format with the given chroma siting
+fn plane_geometry(
+    format: PixelFormat,
+    position: ChromaSamplePosition,
+) -> impl Iterator<Item = PlaneGeometry> {
+    let full = Axis {
+        factor: 1.0,
+        phase: 0.0,
+    };
+    let (shift_x, shift_y) = format.chroma_shift();
+    let chroma_axis = |shift: u32, cosited: bool| Axis {
+        factor: f64::from(1u32 << shift),
+        phase: if shift > 0 && !cosited { 0.5 } else { 0.0 },
+    };
+    let chroma = PlaneGeometry {
+        components: if format.is_semi_planar() { 2 } else { 1 },
+        x: chroma_axis(shift_x, position != ChromaSamplePosition::Unknown),
+        y: chroma_axis(shift_y, position == ChromaSamplePosition::Colocated),
+    };
+
+    let luma = PlaneGeometry {
+        components: 1,
+        x: full,
+        y: full,
+    };
+
+    let planes = if !format.is_yuv() {
+        vec![PlaneGeometry {
+            components: 3,
+            ..luma
+        }]
+    } else if format.is_semi_planar() {
+        vec![luma, chroma]
+    } else {
+        vec![luma, chroma, chroma]
+    };
+    planes.into_iter()
+}
+
+/// Filter taps for every output sample along one axis
+#[derive(Debug)]
+struct Filter {
+    /// Taps per output sample
+    taps: usize,
+    /// First source sample of each output sample, may lie outside the plane
+    starts: Vec<isize>,
+    /// `taps` normalized weights per output sample
+    weights: Vec<f32>,
+    /// Source plane length, for clamping
+    source_len: usize,
+}
+
+impl Filter {
+    /// Build the filter scaling a frame dimension from `source` to `target`
+    fn new(kernel: Kernel, source: u32, target: u32, axis: Axis) -> Self {
+        let source_len = (f64::from(source) / axis.factor).ceil() as usize;
+        let target_len = (f64::from(target) / axis.factor).ceil() as usize;
+        let scale = f64::from(source) / f64::from(target);
+
+        // Widen the kernel when downscaling so it also low-passes
+        let stretch = scale.max(1.0);
+        let radius = kernel.support() * stretch;
+        let taps = (2.0 * radius).ceil() as usize + 1;
+
+        let mut starts = Vec::with_capacity(target_len);
+        let mut weights = Vec::with_capacity(target_len * taps);
+        for index in 0..target_len {
+            // Map the output sample's luma position back into the source plane
+            let luma = axis.factor * index as f64 + axis.phase;
+            let center = ((luma + 0.5) * scale - 0.5 - axis.phase) / axis.factor;
+            let start = (center - radius).floor() as isize + 1;
+
+            let first = weights.len();
+            weights.extend((0..taps).map(|tap| {
+                let distance = (start + tap as isize) as f64 - center;
+                kernel.weight(distance / stretch) as f32
+            }));
+            let sum: f32 = weights[first..].iter().sum();
+            weights[first..]
+                .iter_mut()
+                .for_each(|weight| *weight /= sum);
+            starts.push(start);
+        }
+
+        Self {
+            taps,
+            starts,
+            weights,
+            source_len,
+        }
+    }
+
+    /// Source sample indices and weights of output sample `index`
+    fn taps(&self, index: usize) -> impl Iterator<Item = (usize, f32)> + '_ {
+        let last = self.source_len as isize - 1;
+        let start = self.starts[index];
+        self.weights[index * self.taps..(index + 1) * self.taps]
+            .iter()
+            .enumerate()
+            .map(move |(tap, &weight)| ((start + tap as isize).clamp(0, last) as usize, weight))
+    }
+}
+
+/// How samples of a plane are stored
+#[derive(Clone, Copy)]
+struct Samples {
+    components: usize,
+    /// Left shift of stored values (6 for P010)
+    shift: u32,
+    max: u32,
+}
+
+/// Resample one plane, horizontally into a float buffer then vertically
+fn scale_plane(
+    src: &Plane,
+    dst: &mut Plane,
+    samples: Samples,
+    horizontal: &Filter,
+    vertical: &Filter,
+    threads: usize,
+) {
+    let bytes = src.bytes_per_sample();
+    let components = samples.components;
+    let width = dst.width();
+
+    let mut rows = vec![0f32; src.height() * width];
+    for_each_row(rows.chunks_mut(width).collect(), threads, |y, out| {
+        let row = src.row(y);
+        let sample = |index: usize| {
+            let value = if bytes == 1 {
+                u32::from(row[index])
+            } else {
+                u32::from(u16::from_le_bytes([row[2 * index], row[2 * index + 1]]))
+            };
+            (value >> samples.shift) as f32
+        };
+        for (x, pixel) in out.chunks_exact_mut(components).enumerate() {
+            for (component, value) in pixel.iter_mut().enumerate() {
+                *value = horizontal
+                    .taps(x)
+                    .map(|(source, weight)| weight * sample(source * components + component))
+                    .sum();
+            }
+        }
+    });
+
+    let stride = dst.stride();
+    let max = samples.max as f32;
+    for_each_row(
+        dst.data_mut().chunks_mut(stride).collect(),
+        threads,
+        |y, out| {
+            for (x, bytes_out) in out[..width * bytes].chunks_exact_mut(bytes).enumerate() {
+                let value: f32 = vertical
+                    .taps(y)
+                    .map(|(source, weight)| weight * rows[source * width + x])
+                    .sum();
+                let value = (value.round().clamp(0.0, max) as u32) << samples.shift;
+                bytes_out.copy_from_slice(&value.to_le_bytes()[..bytes]);
+            }
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(width: u32, height: u32, format: PixelFormat) -> Frame {
+        let mut frame = Frame::new(width, height, format);
+        for (index, plane) in frame.planes_mut().iter_mut().enumerate() {
+            for y in 0..plane.height() {
+                for (x, sample) in plane.row_mut(y).iter_mut().enumerate() {
+                    *sample = ((x * 7 + y * 13 + index * 50) % 256) as u8;
+                }
+            }
+        }
+        frame
+    }
+
+    fn scaler(width: u32, height: u32, kernel: Kernel, threads: usize) -> Scaler {
+        Scaler::new(ScaleConfig {
+            width,
+            height,
+            kernel,
+            threads,
+            layout: PlaneLayout::default(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_same_size_is_identity() {
+        let frame = pattern(16, 8, PixelFormat::Yuv420p);
+        for kernel in [
+            Kernel::Bilinear,
+            Kernel::Bicubic,
+            Kernel::Lanczos,
+            Kernel::Spline,
+        ] {
+            let scaled = scaler(16, 8, kernel, 1).scale(&frame).unwrap();
+            for (a, b) in scaled.planes().iter().zip(frame.planes()) {
+                assert_eq!(a.data(), b.data(), "{:?}", kernel);
+            }
+        }
+    }
+
+    #[test]
+    fn test_constant_frame_stays_constant() {
+        let mut frame = Frame::new(64, 48, PixelFormat::Yuv420p10);
+        for plane in frame.planes_mut() {
+            for sample in plane.data_mut().chunks_exact_mut(2) {
+                sample.copy_from_slice(&700u16.to_le_bytes());
+            }
+        }
+
+        for (width, height) in [(33, 17), (128, 100)] {
+            let scaled = scaler(width, height, Kernel::Lanczos, 2)
+                .scale(&frame)
+                .unwrap();
+            let chroma = &scaled.planes()[1];
+            assert_eq!(
+                (chroma.width(), chroma.height()),
+                (width.div_ceil(2) as usize, height.div_ceil(2) as usize)
+            );
+            for plane in scaled.planes() {
+                assert!(
+                    plane
+                        .data()
+                        .chunks_exact(2)
+                        .all(|s| u16::from_le_bytes([s[0], s[1]]) == 700)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_threads_match_single_thread() {
+        let frame = pattern(40, 30, PixelFormat::Rgb24);
+        let single = scaler(23, 61, Kernel::Spline, 1).scale(&frame).unwrap();
+        let threaded = scaler(23, 61, Kernel::Spline, 4).scale(&frame).unwrap();
+        assert_eq!(single.planes()[0].data(), threaded.planes()[0].data());
+    }
+
+    #[test]
+    fn test_downscale_averages() {
+        let mut frame = Frame::new(4, 2, PixelFormat::Yuv444p);
+        for y in 0..2 {
+            frame.planes_mut()[0]
+                .row_mut(y)
+                .copy_from_slice(&[0, 80, 0, 80]);
+        }
+        // Triangle filter stretched over four samples, edges clamped
+        let scaled = scaler(2, 1, Kernel::Bilinear, 1).scale(&frame).unwrap();
+        assert_eq!(scaled.planes()[0].row(0), &[30, 50]);
+    }
+
+    #[test]
+    fn test_fit_size() {
+        assert_eq!(
+            fit_size(1920, 1080, 0, 720, PixelFormat::Yuv420p),
+            (1280, 720)
+        );
+        assert_eq!(
+            fit_size(1920, 1080, 640, 640, PixelFormat::Yuv420p),
+            (640, 360)
+        );
+        // Odd derived sizes round to whole chroma samples
+        assert_eq!(
+            fit_size(1000, 1000, 0, 333, PixelFormat::Yuv420p),
+            (334, 333)
+        );
+        assert_eq!(
+            fit_size(1000, 1000, 0, 333, PixelFormat::Yuv444p),
+            (333, 333)
+        );
+    }
+}
@@ -0,0 +1,760 @@
+//! Geometric frame transforms: crop, pad, rotate and flip
+//!
+//! Cropping is zero-copy, the other transforms write a new frame. Edges of
+//! subsampled formats must fall on chroma samples, and [`CropDetect`]
+//! finds letterboxing and pillarboxing by scanning frames for black
+//! borders.
+//!
+//! # Example
+//! ```no_run
+//! use mead_core::transform::{self, Borders, Color, Rotation};
+//! use mead_core::{Frame, PixelFormat};
+//!
+//! let frame = Frame::new(1920, 1080, PixelFormat::Yuv420p);
+//!
+//! // Strip 2.40:1 letterboxing, then rotate to portrait
+//! let cropped = transform::crop(&frame, Borders { top: 140, bottom: 140, ..Default::default() })?;
+//! let rotated = transform::rotate(&cropped, Rotation::Rotate90)?;
+//!
+//! // Pillarbox back into a 16:9 frame
+//! let padded = transform::pad(&rotated, Borders { left: 720, right: 720, ..Default::default() }, Color::BLACK)?;
+//! # Ok::<(), mead_core::Error>(())
+//! ```
+
+use crate::color::ColorRange;
+use crate::convert::{self, ConvertConfig};
+use crate::{Error, Frame, PixelFormat, Plane, Result};
+use std::str::FromStr;
+
+/// Pixels added or removed at each edge of a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Borders {
+    /// Left edge
+    pub left: u32,
+    /// Top edge
+    pub top: u32,
+    /// Right edge
+    pub right: u32,
+    /// Bottom edge
+    pub bottom: u32,
+}
+
+impl Borders {
+    /// Returns true when every edge is zero
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check that the given edges fall on chroma samples of `format`
+    pub(crate) fn check_alignment(&self, format: PixelFormat, edges: [u32; 4]) -> Result<()> {
+        let (shift_x, shift_y) = format.chroma_shift();
+        let aligned = edges[0] % (1 << shift_x) == 0
+            && edges[1] % (1 << shift_y) == 0
+            && edges[2] % (1 << shift_x) == 0
+            && edges[3] % (1 << shift_y) == 0;
+        if !aligned {
+            return Err(Error::InvalidInput(format!(
+                "Borders {}:{}:{}:{} are not aligned to {:?} chroma",
+                self.left, self.top, self.right, self.bottom, format
+            )));
+        }
+        Ok(())
+    }
+
+    /// Size of a `width`x`height` picture with the borders removed
+    pub(crate) fn cropped_size(&self, width: u32, height: u32) -> Result<(u32, u32)> {
+        let shrink =
+            |size: u32, a: u32, b: u32| a.checked_add(b).and_then(|edges| size.checked_sub(edges));
+        match (
+            shrink(width, self.left, self.right),
+            shrink(height, self.top, self.bottom),
+        ) {
+            (Some(width), Some(height)) if width > 0 && height > 0 => Ok((width, height)),
+            _ => Err(Error::InvalidInput(format!(
+                "Cannot crop {}:{}:{}:{} from a {}x{} frame",
+                self.left, self.top, self.right, self.bottom, width, height
+            ))),
+        }
+    }
+
+    /// Size of a `width`x`height` picture with the borders added
+    pub(crate) fn padded_size(&self, width: u32, height: u32) -> Result<(u32, u32)> {
+        let grow = |size: u32, a: u32, b: u32| size.checked_add(a)?.checked_add(b);
+        match (
+            grow(width, self.left, self.right),
+            grow(height, self.top, self.bottom),
+        ) {
+            (Some(width), Some(height)) => Ok((width, height)),
+            _ => Err(Error::InvalidInput(format!(
+                "Cannot pad a {}x{} frame by {}:{}:{}:{}",
+                width, height, self.left, self.top, self.right, self.bottom
+            ))),
+        }
+    }
+}
+
+/// Parses `left:top:right:bottom`, `horizontal:vertical` or one value for all edges
+impl FromStr for Borders {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let values = s
+            .split(':')
+            .map(|value| value.trim().parse::<u32>())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|_| Error::InvalidInput(format!("Invalid borders: {}", s)))?;
+
+        match values[..] {
+            [all] => Ok(Self {
+                left: all,
+                top: all,
+                right: all,
+                bottom: all,
+            }),
+            [horizontal, vertical] => Ok(Self {
+                left: horizontal,
+                top: vertical,
+                right: horizontal,
+                bottom: vertical,
+            }),
+            [left, top, right, bottom] => Ok(Self {
+                left,
+                top,
+                right,
+                bottom,
+            }),
+            _ => Err(Error::InvalidInput(format!(
+                "Expected left:top:right:bottom borders, got {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Fill color for padding, as 8-bit RGB
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    /// Red
+    pub r: u8,
+    /// Green
+    pub g: u8,
+    /// Blue
+    pub b: u8,
+}
+
+impl Color {
+    /// Black
+    pub const BLACK: Self = Self { r: 0, g: 0, b: 0 };
+    /// White
+    pub const WHITE: Self = Self {
+        r: 255,
+        g: 255,
+        b: 255,
+    };
+
+    /// Sample values of this color for each component of `frame`'s format
+    ///
+    /// YUV values use the frame's matrix and range.
+    fn components(self, frame: &Frame) -> Result<Vec<u16>> {
+        let format = frame.format();
+        if !format.is_yuv() {
+            return Ok(vec![self.r.into(), self.g.into(), self.b.into()]);
+        }
+
+        let mut rgb = Frame::new(1, 1, PixelFormat::Rgb24);
+        rgb.planes_mut()[0]
+            .row_mut(0)
+            .copy_from_slice(&[self.r, self.g, self.b]);
+        let target = match format.bit_depth() {
+            8 => PixelFormat::Yuv444p,
+            10 => PixelFormat::Yuv444p10,
+            _ => PixelFormat::Yuv444p12,
+        };
+        let config = ConvertConfig {
+            matrix: convert::resolve_matrix(frame.colorimetry().matrix, frame.height()),
+            range: frame.colorimetry().range,
+            dither: convert::Dither::None,
+            ..Default::default()
+        };
+        let yuv = convert::convert(&rgb, target, &config)?;
+        Ok(yuv
+            .planes()
+            .iter()
+            .map(|plane| match plane.row(0) {
+                [value] => u16::from(*value),
+                row => u16::from_le_bytes([row[0], row[1]]),
+            })
+            .collect())
+    }
+}
+
+/// Parses `black`, `white`, `gray` or a hex `#rrggbb`
+impl FromStr for Color {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "black" => return Ok(Self::BLACK),
+            "white" => return Ok(Self::WHITE),
+            "gray" | "grey" => {
+                return Ok(Self {
+                    r: 128,
+                    g: 128,
+                    b: 128,
+                });
+            }
+            _ => {}
+        }
+
+        let hex = s.trim_start_matches('#').trim_start_matches("0x");
+        let value = (hex.len() == 6)
+            .then(|| u32::from_str_radix(hex, 16).ok())
+            .flatten()
+            .ok_or_else(|| Error::InvalidInput(format!("Invalid color: {}", s)))?;
+        Ok(Self {
+            r: (value >> 16) as u8,
+            g: (value >> 8) as u8,
+            b: value as u8,
+        })
+    }
+}
+
+/// Clockwise rotation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// 90 degrees clockwise
+    Rotate90,
+    /// 180 degrees
+    Rotate180,
+    /// 270 degrees clockwise (90 counter-clockwise)
+    Rotate270,
+}
+
+impl FromStr for Rotation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "90" | "-270" => Ok(Self::Rotate90),
+            "180" | "-180" => Ok(Self::Rotate180),
+            "270" | "-90" => Ok(Self::Rotate270),
+            _ => Err(Error::InvalidInput(format!(
+                "Rotation must be 90, 180 or 270, got {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Mirror axis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flip {
+    /// Mirror left and right
+    Horizontal,
+    /// Mirror top and bottom
+    Vertical,
+}
+
+/// Remove borders without copying
+///
+/// For subsampled formats every edge must fall on a chroma sample, so
+/// 4:2:0 crops by even amounts.
+pub fn crop(frame: &Frame, borders: Borders) -> Result<Frame> {
+    let edges = [borders.left, borders.top, borders.right, borders.bottom];
+    borders.check_alignment(frame.format(), edges)?;
+
+    let (width, height) = borders.cropped_size(frame.width(), frame.height())?;
+    frame.view(borders.left, borders.top, width, height)
+}
+
+/// Add borders filled with `color`
+///
+/// For subsampled formats the left and top borders must fall on a chroma
+/// sample.
+pub fn pad(frame: &Frame, borders: Borders, color: Color) -> Result<Frame> {
+    let format = frame.format();
+    borders.check_alignment(format, [borders.left, borders.top, 0, 0])?;
+
+    let (width, height) = borders.padded_size(frame.width(), frame.height())?;
+    let mut output = Frame::new(width, height, format);
+    let components = color.components(frame)?;
+
+    let mut next_component = components.iter();
+    for ((src, dst), shape) in frame
+        .planes()
+        .iter()
+        .zip(output.planes_mut())
+        .zip(plane_shapes(format))
+    {
+        let fill: Vec<u8> = next_component
+            .by_ref()
+            .take(shape.components)
+            .flat_map(|&value| encode_sample(value, format))
+            .collect();
+        for y in 0..dst.height() {
+            for pixel in dst.row_mut(y).chunks_exact_mut(fill.len()) {
+                pixel.copy_from_slice(&fill);
+            }
+        }
+
+        let left = (borders.left >> shape.shift_x) as usize * fill.len();
+        let top = (borders.top >> shape.shift_y) as usize;
+        for y in 0..src.height() {
+            let row = src.row(y);
+            dst.row_mut(top + y)[left..left + row.len()].copy_from_slice(row);
+        }
+    }
+
+    output.set_colorimetry(*frame.colorimetry());
+    if let Some(pts) = frame.pts() {
+        output.set_pts(pts);
+    }
+    Ok(output)
+}
+
+/// Rotate clockwise
+///
+/// Quarter turns swap the chroma subsampling axes, so 4:2:2 input is
+/// rejected for them; convert it to 4:2:0 or 4:4:4 first.
+pub fn rotate(frame: &Frame, rotation: Rotation) -> Result<Frame> {
+    let (shift_x, shift_y) = frame.format().chroma_shift();
+    if rotation != Rotation::Rotate180 && shift_x != shift_y {
+        return Err(Error::UnsupportedFormat(format!(
+            "Cannot rotate {:?} by a quarter turn",
+            frame.format()
+        )));
+    }
+
+    Ok(match rotation {
+        Rotation::Rotate90 => remap(frame, true, |x, y, _, height| (y, height - 1 - x)),
+        Rotation::Rotate180 => remap(frame, false, |x, y, width, height| {
+            (width - 1 - x, height - 1 - y)
+        }),
+        Rotation::Rotate270 => remap(frame, true, |x, y, width, _| (width - 1 - y, x)),
+    })
+}
+
+/// Mirror horizontally or vertically
+pub fn flip(frame: &Frame, flip: Flip) -> Frame {
+    match flip {
+        Flip::Horizontal => remap(frame, false, |x, y, width, _| (width - 1 - x, y)),
+        Flip::Vertical => remap(frame, false, |x, y, _, height| (x, height - 1 - y)),
+    }
+}
+
+/// Build a frame whose pixel (x, y) is the source pixel `map(x, y, width, height)`
+///
+/// `map` works in plane pixels and gets the source plane size; `transpose`
+/// swaps the output dimensions.
+fn remap(
+    frame: &Frame,
+    transpose: bool,
+    map: impl Fn(usize, usize, usize, usize) -> (usize, usize),
+) -> Frame {
+    let format = frame.format();
+    let (width, height) = if transpose {
+        (frame.height(), frame.width())
+    } else {
+        (frame.width(), frame.height())
+    };
+    let mut output = Frame::new(width, height, format);
+
+    for ((src, dst), shape) in frame
+        .planes()
+        .iter()
+        .zip(output.planes_mut())
+        .zip(plane_shapes(format))
+    {
+        let pixel = shape.components * src.bytes_per_sample();
+        let (src_width, src_height) = (src.width() / shape.components, src.height());
+        for y in 0..dst.height() {
+            for (x, out) in dst.row_mut(y).chunks_exact_mut(pixel).enumerate() {
+                let (sx, sy) = map(x, y, src_width, src_height);
+                out.copy_from_slice(&src.row(sy)[sx * pixel..(sx + 1) * pixel]);
+            }
+        }
+    }
+
+    output.set_colorimetry(*frame.colorimetry());
+    if let Some(pts) = frame.pts() {
+        output.set_pts(pts);
+    }
+    output
+}
+
+/// Detects black borders across a sample of frames
+///
+/// Rows and columns whose average luma stays within `threshold` of black
+/// count as border. The result is the smallest border seen in any
+/// analyzed frame, so content that only sometimes reaches the edge is
+/// kept. Entirely black frames, like fades, are ignored.
+///
+/// # Example
+/// ```no_run
+/// use mead_core::container::y4m::Y4mDemuxer;
+/// use mead_core::transform::{self, CropDetect};
+/// use std::fs::File;
+///
+/// let mut demuxer = Y4mDemuxer::new(File::open("input.y4m")?)?;
+/// let mut detect = CropDetect::default();
+/// while let Some(frame) = demuxer.read_frame()? {
+///     if demuxer.frame_count() % 10 == 0 {
+///         detect.analyze(&frame)?;
+///     }
+/// }
+/// println!("crop {:?}", detect.borders());
+/// # Ok::<(), mead_core::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct CropDetect {
+    threshold: u8,
+    borders: Option<Borders>,
+    frames: usize,
+}
+
+impl Default for CropDetect {
+    fn default() -> Self {
+        Self::new(10)
+    }
+}
+
+impl CropDetect {
+    /// Create a detector; `threshold` is in 8-bit steps above black
+    pub fn new(threshold: u8) -> Self {
+        Self {
+            threshold,
+            borders: None,
+            frames: 0,
+        }
+    }
+
+    /// Measure the black borders of a YUV frame
+    pub fn analyze(&mut self, frame: &Frame) -> Result<()> {
+        let format = frame.format();
+        if !format.is_yuv() {
+            return Err(Error::UnsupportedFormat(format!(
+                "Crop detection needs YUV input, got {:?}",
+                format
+            )));
+        }
+
+        let luma = &frame.planes()[0];
+        let depth = u32::from(format.bit_depth());
+        let black = match frame.colorimetry().range {
+            ColorRange::Limited => 16 << (depth - 8),
+            ColorRange::Full => 0,
+        };
+        let limit = u64::from(black + (u32::from(self.threshold) << (depth - 8)));
+
+        let (width, height) = (luma.width(), luma.height());
+        let mut rows = vec![0u64; height];
+        let mut columns = vec![0u64; width];
+        for (y, row_sum) in rows.iter_mut().enumerate() {
+            for (x, sample) in luma_samples(luma, y, format).enumerate() {
+                *row_sum += sample;
+                columns[x] += sample;
+            }
+        }
+
+        let is_black = |sum: u64, count: usize| sum <= limit * count as u64;
+        let top = rows.iter().take_while(|&&sum| is_black(sum, width)).count();
+        if top == height {
+            return Ok(());
+        }
+        let bottom = rows
+            .iter()
+            .rev()
+            .take_while(|&&sum| is_black(sum, width))
+            .count();
+        let left = columns
+            .iter()
+            .take_while(|&&sum| is_black(sum, height))
+            .count();
+        let right = columns
+            .iter()
+            .rev()
+            .take_while(|&&sum| is_black(sum, height))
+            .count();
+
+        // Round down to whole chroma samples, keeping a sliver of border
+        // rather than cutting into the picture
+        let (shift_x, shift_y) = format.chroma_shift();
+        let align = |value: usize, shift: u32| (value as u32 >> shift) << shift;
+        let found = Borders {
+            left: align(left, shift_x),
+            top: align(top, shift_y),
+            right: align(right, shift_x),
+            bottom: align(bottom, shift_y),
+        };
+
+        self.borders = Some(match self.borders {
+            Some(seen) => Borders {
+                left: seen.left.min(found.left),
+                top: seen.top.min(found.top),
+                right: seen.right.min(found.right),
+                bottom: seen.bottom.min(found.bottom),
+            },
+            None => found,
+        });
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Number of frames with picture content analyzed so far
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Borders that are black in every analyzed frame
+    pub fn borders(&self) -> Borders {
+        self.borders.unwrap_or_default()
+    }
+}
+
+/// Luma samples of one row, unpacking high bit depth storage
+fn luma_samples<'a>(
+    plane: &'a Plane,
+    y: usize,
+    format: PixelFormat,
+) -> impl Iterator<Item = u64> + 'a {
+    let row = plane.row(y);
+    let bytes = plane.bytes_per_sample();
+    let shift = if format == PixelFormat::P010 { 6 } else { 0 };
+    row.chunks_exact(bytes).map(move |sample| match sample {
+        [value] => u64::from(*value),
+        _ => u64::from(u16::from_le_bytes([sample[0], sample[1]]) >> shift),
+    })
+}
+
+/// Store one sample value as it is laid out in `format`
+fn encode_sample(value: u16, format: PixelFormat) -> Vec<u8> {
+    match format.bytes_per_sample() {
+        1 => vec![value as u8],
+        _ if format == PixelFormat::P010 => (value << 6).to_le_bytes().to_vec(),
+        _ => value.to_le_bytes().to_vec(),
+    }
+}
+
+/// Components per pixel and chroma subsampling of one plane
+struct PlaneShape {
+    components: usize,
+    shift_x: u32,
+    shift_y: u32,
+}
+
+fn plane_shapes(format: PixelFormat) -> Vec<PlaneShape> {
+    let (shift_x, shift_y) = format.chroma_shift();
+    let luma = PlaneShape {
+        components: 1,
+        shift_x: 0,
+        shift_y: 0,
+    };
+    let chroma = |components| PlaneShape {
+        components,
+        shift_x,
+        shift_y,
+    };
+
+    if !format.is_yuv() {
+        vec![PlaneShape {
+            components: 3,
+            ..luma
+        }]
+    } else if format.is_semi_planar() {
+        vec![luma, chroma(2)]
+    } else {
+        vec![luma, chroma(1), chroma(1)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 4x4 luma numbered 0..16, chroma numbered from 100 and 200
+    fn numbered(format: PixelFormat) -> Frame {
+        let mut frame = Frame::new(4, 4, format);
+        for (index, plane) in frame.planes_mut().iter_mut().enumerate() {
+            let width = plane.width();
+            for y in 0..plane.height() {
+                for (x, sample) in plane.row_mut(y).iter_mut().enumerate() {
+                    *sample = (index * 100 + y * width + x) as u8;
+                }
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn test_crop_alignment() {
+        let frame = numbered(PixelFormat::Yuv420p);
+        let borders = Borders {
+            left: 2,
+            top: 2,
+            ..Default::default()
+        };
+        let cropped = crop(&frame, borders).unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (2, 2));
+        assert_eq!(cropped.planes()[0].row(0), &[10, 11]);
+        assert_eq!(cropped.planes()[1].row(0), &[103]);
+
+        assert!(
+            crop(
+                &frame,
+                Borders {
+                    left: 1,
+                    ..Default::default()
+                }
+            )
+            .is_err()
+        );
+        assert!(
+            crop(
+                &frame,
+                Borders {
+                    left: 2,
+                    right: 2,
+                    ..Default::default()
+                }
+            )
+            .is_err()
+        );
+        // Edges that overflow when summed
+        assert!(
+            crop(
+                &frame,
+                Borders {
+                    left: u32::MAX - 1,
+                    right: 2,
+                    ..Default::default()
+                }
+            )
+            .is_err()
+        );
+        assert!(
+            crop(
+                &numbered(PixelFormat::Yuv444p),
+                Borders {
+                    left: 1,
+                    ..Default::default()
+                }
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_pad_color() {
+        let frame = numbered(PixelFormat::Yuv420p);
+        let borders = Borders {
+            left: 2,
+            bottom: 2,
+            ..Default::default()
+        };
+        let padded = pad(&frame, borders, Color::WHITE).unwrap();
+        assert_eq!((padded.width(), padded.height()), (6, 6));
+        assert_eq!(padded.planes()[0].row(0), &[235, 235, 0, 1, 2, 3]);
+        assert_eq!(padded.planes()[0].row(5), &[235; 6]);
+        assert_eq!(padded.planes()[1].row(0), &[128, 100, 101]);
+
+        assert!(
+            pad(
+                &frame,
+                Borders {
+                    top: 1,
+                    ..Default::default()
+                },
+                Color::BLACK
+            )
+            .is_err()
+        );
+        assert!(
+            pad(
+                &frame,
+                Borders {
+                    right: u32::MAX,
+                    ..Default::default()
+                },
+                Color::BLACK
+            )
+            .is_err()
+        );
+        assert_eq!(
+            "#ff8000".parse::<Color>().unwrap(),
+            Color {
+                r: 255,
+                g: 128,
+                b: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_rotate_and_flip() {
+        let frame = numbered(PixelFormat::Yuv444p);
+        let rotated = rotate(&frame, Rotation::Rotate90).unwrap();
+        assert_eq!(rotated.planes()[0].row(0), &[12, 8, 4, 0]);
+        assert_eq!(
+            rotate(&frame, Rotation::Rotate270).unwrap().planes()[0].row(0),
+            &[3, 7, 11, 15]
+        );
+        assert_eq!(
+            rotate(&frame, Rotation::Rotate180).unwrap().planes()[0].row(0),
+            &[15, 14, 13, 12]
+        );
+
+        let flipped = flip(&frame, Flip::Horizontal);
+        assert_eq!(flipped.planes()[2].row(1), &[207, 206, 205, 204]);
+        assert_eq!(
+            flip(&frame, Flip::Vertical).planes()[0].row(0),
+            &[12, 13, 14, 15]
+        );
+
+        // Four quarter turns of a non-square NV12 frame are a no-op
+        let mut nv12 = Frame::new(6, 4, PixelFormat::Nv12);
+        nv12.planes_mut()[1].row_mut(0)[..2].copy_from_slice(&[90, 91]);
+        let mut turned = nv12.clone();
+        for _ in 0..4 {
+            turned = rotate(&turned, Rotation::Rotate90).unwrap();
+        }
+        assert_eq!(turned.planes()[1].data(), nv12.planes()[1].data());
+
+        assert!(rotate(&numbered(PixelFormat::Yuv422p), Rotation::Rotate90).is_err());
+    }
+
+    #[test]
+    fn test_crop_detect() {
+        // Letterboxed 16x12 frame: 2 black rows top and bottom, 4 columns left
+        let mut frame = Frame::new(16, 12, PixelFormat::Yuv420p);
+        let luma = &mut frame.planes_mut()[0];
+        for y in 0..12 {
+            let row = luma.row_mut(y);
+            row.fill(16);
+            if (2..10).contains(&y) {
+                row[4..].fill(120);
+            }
+        }
+
+        let mut detect = CropDetect::default();
+        detect
+            .analyze(&Frame::new(16, 12, PixelFormat::Yuv420p))
+            .unwrap();
+        assert_eq!(detect.frames(), 0);
+
+        detect.analyze(&frame).unwrap();
+        assert_eq!(
+            detect.borders(),
+            Borders {
+                left: 4,
+                top: 2,
+                right: 0,
+                bottom: 2
+            }
+        );
+
+        // A later frame reaching further up shrinks the crop
+        frame.planes_mut()[0].row_mut(1)[4..].fill(120);
+        detect.analyze(&frame).unwrap();
+        assert_eq!(detect.borders().top, 0);
+    }
+}
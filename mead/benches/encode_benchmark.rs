@@ -3,9 +3,12 @@
 //! Compares different encoder configurations and provides performance metrics.
 
 use mead_core::{
-    codec::{av1::{Av1Config, Av1Encoder}, VideoEncoder},
-    container::{ivf::IvfMuxer, Muxer, Packet},
-    ArcFrame, PixelFormat, Frame,
+    ArcFrame, Frame, PixelFormat,
+    codec::{
+        VideoEncoder,
+        av1::{Av1Config, Av1Encoder},
+    },
+    container::{Muxer, Packet, ivf::IvfMuxer},
 };
 use std::fs::File;
 use std::io::BufWriter;
@@ -44,11 +47,10 @@ impl BenchmarkConfig {
     fn to_av1_config(&self) -> Av1Config {
         Av1Config {
             speed: self.speed,
-            quantizer: 100,
-            bitrate_kbps: None,
             tile_cols: self.tile_cols,
             tile_rows: self.tile_rows,
             threads: self.threads,
+            ..Default::default()
         }
     }
 }
@@ -116,37 +118,34 @@ fn run_benchmark(config: &BenchmarkConfig) -> anyhow::Result<BenchmarkResult> {
     // Encode frames
     let start = Instant::now();
     let mut cpu_start = get_thread_time();
-    let mut pts = 0i64;
 
     for frame in &frames {
         encoder.send_frame(Some(frame.clone()))?;
 
         // Collect packets
-        while let Some(packet_data) = encoder.receive_packet()? {
+        while let Some(encoded) = encoder.receive_packet()? {
             let packet = Packet {
                 stream_index: 0,
-                data: packet_data,
-                pts: Some(pts),
-                dts: None,
-                is_keyframe: pts == 0,
+                is_keyframe: encoded.is_keyframe(),
+                data: encoded.data,
+                pts: Some(encoded.pts),
+                dts: Some(encoded.dts),
             };
             muxer.write_packet(packet)?;
-            pts += 1;
         }
     }
 
     // Flush encoder
     encoder.send_frame(None)?;
-    while let Some(packet_data) = encoder.receive_packet()? {
+    while let Some(encoded) = encoder.receive_packet()? {
         let packet = Packet {
             stream_index: 0,
-            data: packet_data,
-            pts: Some(pts),
-            dts: None,
-            is_keyframe: false,
+            is_keyframe: encoded.is_keyframe(),
+            data: encoded.data,
+            pts: Some(encoded.pts),
+            dts: Some(encoded.dts),
         };
         muxer.write_packet(packet)?;
-        pts += 1;
     }
 
     let cpu_end = get_thread_time();
@@ -0,0 +1,380 @@
+//! Safe wrapper around the dav1d AV1 decoder
+
+use dav1d_sys::*;
+use mead_core::codec::VideoDecoder;
+use mead_core::color::{
+    ChromaSamplePosition, ColorPrimaries, ColorRange, ContentLightLevel, MasteringDisplay,
+    MatrixCoefficients, TransferCharacteristics,
+};
+use mead_core::{ArcFrame, Colorimetry, Error, Frame, FramePool, PixelFormat, Result};
+use std::collections::VecDeque;
+use std::ptr;
+use std::sync::Arc;
+
+/// Configuration for the dav1d decoder
+#[derive(Debug, Clone)]
+pub struct Dav1dConfig {
+    /// Worker threads (0 = one per logical CPU)
+    pub threads: u32,
+
+    /// Apply film grain synthesis to output frames
+    pub apply_grain: bool,
+}
+
+impl Default for Dav1dConfig {
+    fn default() -> Self {
+        Self {
+            threads: 0,        // Auto
+            apply_grain: true, // Match what players display
+        }
+    }
+}
+
+/// Safe wrapper around the dav1d decoder
+///
+/// Takes low-overhead AV1 temporal units, as returned by the IVF, OBU,
+/// MP4 and Matroska demuxers, and returns 8-bit, 10-bit or 12-bit frames.
+pub struct Dav1dDecoder {
+    context: *mut Dav1dContext,
+    /// Decoded frames not yet returned
+    pending: VecDeque<ArcFrame>,
+    /// Recycled output frames, replaced when the picture size or format changes
+    pool: Option<FramePool>,
+}
+
+/// Output frames kept for reuse
+const POOL_CAPACITY: usize = 8;
+
+impl Dav1dDecoder {
+    /// Create new decoder with configuration
+    pub fn new(config: Dav1dConfig) -> Result<Self> {
+        let threads = i32::try_from(config.threads)
+            .map_err(|_| Error::InvalidInput("Thread count out of range".to_string()))?;
+
+        unsafe {
+            let mut settings = std::mem::zeroed::<Dav1dSettings>();
+            dav1d_default_settings(&mut settings);
+
+            settings.n_threads = threads;
+            // One temporal unit in, one frame out
+            settings.max_frame_delay = 1;
+            settings.apply_grain = i32::from(config.apply_grain);
+
+            let mut context: *mut Dav1dContext = ptr::null_mut();
+            let err = dav1d_open(&mut context, &settings);
+            if err < 0 {
+                return Err(Error::Codec(format!(
+                    "Failed to open dav1d decoder: {}",
+                    err
+                )));
+            }
+
+            Ok(Self {
+                context,
+                pending: VecDeque::new(),
+                pool: None,
+            })
+        }
+    }
+
+    /// Move every picture dav1d has ready into the pending queue
+    fn drain_pictures(&mut self) -> Result<()> {
+        unsafe {
+            loop {
+                let mut picture = std::mem::zeroed::<Dav1dPicture>();
+                let err = dav1d_get_picture(self.context, &mut picture);
+                if err == DAV1D_EAGAIN {
+                    return Ok(());
+                }
+                if err < 0 {
+                    return Err(Error::Codec(format!("Failed to get picture: {}", err)));
+                }
+
+                let frame = picture_to_frame(&picture, &mut self.pool);
+                dav1d_picture_unref(&mut picture);
+
+                self.pending.push_back(Arc::new(frame?));
+            }
+        }
+    }
+}
+
+impl VideoDecoder for Dav1dDecoder {
+    fn decode(&mut self, data: &[u8]) -> Result<Option<ArcFrame>> {
+        if !data.is_empty() {
+            unsafe {
+                // dav1d takes ownership of a reference-counted copy of the packet
+                let mut input = std::mem::zeroed::<Dav1dData>();
+                let buffer = dav1d_data_create(&mut input, data.len());
+                if buffer.is_null() {
+                    return Err(Error::Codec("Failed to allocate dav1d data".to_string()));
+                }
+                ptr::copy_nonoverlapping(data.as_ptr(), buffer, data.len());
+
+                // dav1d consumes the data as it goes and returns EAGAIN once
+                // pictures must be drained before it accepts the rest
+                while input.sz > 0 {
+                    let err = dav1d_send_data(self.context, &mut input);
+                    let result = if err == DAV1D_EAGAIN {
+                        self.drain_pictures()
+                    } else if err < 0 {
+                        Err(Error::Codec(format!("Failed to send data: {}", err)))
+                    } else {
+                        Ok(())
+                    };
+
+                    if let Err(e) = result {
+                        dav1d_data_unref(&mut input);
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        self.drain_pictures()?;
+        Ok(self.pending.pop_front())
+    }
+
+    fn flush(&mut self) -> Result<Vec<ArcFrame>> {
+        // Without further input, dav1d hands out delayed pictures until EAGAIN
+        self.drain_pictures()?;
+        Ok(self.pending.drain(..).collect())
+    }
+}
+
+/// Copy a dav1d picture into a frame from `pool`
+///
+/// Monochrome pictures get neutral chroma planes so every output is YUV.
+/// The pool is replaced when the picture size or format changes, and a
+/// fresh frame is allocated when every pooled frame is still in use.
+///
+/// # Safety
+/// `picture` must have been returned by `dav1d_get_picture` and not yet released.
+#[allow(non_upper_case_globals)] // bindgen names the layout constants
+unsafe fn picture_to_frame(picture: &Dav1dPicture, pool: &mut Option<FramePool>) -> Result<Frame> {
+    let params = &picture.p;
+    let format = match (params.layout, params.bpc) {
+        (
+            Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I400 | Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I420,
+            8,
+        ) => PixelFormat::Yuv420p,
+        (Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I422, 8) => PixelFormat::Yuv422p,
+        (Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I444, 8) => PixelFormat::Yuv444p,
+        (
+            Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I400 | Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I420,
+            10,
+        ) => PixelFormat::Yuv420p10,
+        (Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I422, 10) => PixelFormat::Yuv422p10,
+        (Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I444, 10) => PixelFormat::Yuv444p10,
+        (
+            Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I400 | Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I420,
+            12,
+        ) => PixelFormat::Yuv420p12,
+        (Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I422, 12) => PixelFormat::Yuv422p12,
+        (Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I444, 12) => PixelFormat::Yuv444p12,
+        (layout, bpc) => {
+            return Err(Error::UnsupportedFormat(format!(
+                "dav1d output with layout {} at {} bits per component",
+                layout, bpc
+            )));
+        }
+    };
+
+    let monochrome = params.layout == Dav1dPixelLayout_DAV1D_PIXEL_LAYOUT_I400;
+    let (width, height) = (params.w as u32, params.h as u32);
+    let pool = match pool {
+        Some(pool) if (pool.width(), pool.height(), pool.format()) == (width, height, format) => {
+            pool
+        }
+        _ => pool.insert(FramePool::new(width, height, format, POOL_CAPACITY)),
+    };
+    // Every sample is overwritten below, so stale pooled content never leaks
+    let mut frame = pool
+        .try_acquire()
+        .unwrap_or_else(|| Frame::new(width, height, format));
+
+    for (index, plane) in frame.planes_mut().iter_mut().enumerate() {
+        let bytes_per_sample = plane.bytes_per_sample();
+
+        if monochrome && index > 0 {
+            let neutral = (1u16 << (params.bpc - 1)).to_le_bytes();
+            for sample in plane.data_mut().chunks_exact_mut(bytes_per_sample) {
+                sample.copy_from_slice(&neutral[..bytes_per_sample]);
+            }
+            continue;
+        }
+
+        // stride[0] is luma, stride[1] is shared by both chroma planes
+        let source = picture.data[index] as *const u8;
+        let stride = picture.stride[index.min(1)];
+        let row_bytes = plane.width() * bytes_per_sample;
+
+        for y in 0..plane.height() {
+            let row = unsafe {
+                std::slice::from_raw_parts(source.offset(y as isize * stride), row_bytes)
+            };
+            let out = plane.row_mut(y);
+
+            if bytes_per_sample == 2 {
+                // dav1d stores native-endian u16, planes are little-endian
+                for (dst, src) in out.chunks_exact_mut(2).zip(row.chunks_exact(2)) {
+                    dst.copy_from_slice(&u16::from_ne_bytes([src[0], src[1]]).to_le_bytes());
+                }
+            } else {
+                out.copy_from_slice(row);
+            }
+        }
+    }
+
+    frame.set_colorimetry(unsafe { picture_colorimetry(picture) });
+    Ok(frame)
+}
+
+/// Read the color description and HDR metadata attached to a picture
+///
+/// # Safety
+/// `picture` must have been returned by `dav1d_get_picture` and not yet released.
+unsafe fn picture_colorimetry(picture: &Dav1dPicture) -> Colorimetry {
+    let mut colorimetry = Colorimetry::default();
+
+    // dav1d uses the AV1 code points for all of these
+    if let Some(header) = unsafe { picture.seq_hdr.as_ref() } {
+        colorimetry.primaries = ColorPrimaries::from_code(header.pri as u8);
+        colorimetry.transfer = TransferCharacteristics::from_code(header.trc as u8);
+        colorimetry.matrix = MatrixCoefficients::from_code(header.mtrx as u8);
+        colorimetry.chroma_position = ChromaSamplePosition::from_code(header.chr as u8);
+        if header.color_range != 0 {
+            colorimetry.range = ColorRange::Full;
+        }
+    }
+
+    if let Some(level) = unsafe { picture.content_light.as_ref() } {
+        colorimetry.content_light = Some(ContentLightLevel {
+            max_cll: level.max_content_light_level,
+            max_fall: level.max_frame_average_light_level,
+        });
+    }
+
+    if let Some(display) = unsafe { picture.mastering_display.as_ref() } {
+        colorimetry.mastering_display = Some(MasteringDisplay {
+            primaries: display.primaries.map(|[x, y]| (x, y)),
+            white_point: (display.white_point[0], display.white_point[1]),
+            max_luminance: display.max_luminance,
+            min_luminance: display.min_luminance,
+        });
+    }
+
+    colorimetry
+}
+
+impl Drop for Dav1dDecoder {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.context.is_null() {
+                // Also sets the context to null
+                dav1d_close(&mut self.context);
+            }
+        }
+    }
+}
+
+// Safety: a dav1d context may be used from any thread, one at a time
+unsafe impl Send for Dav1dDecoder {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mead_core::codec::VideoEncoder;
+    use mead_core::codec::av1::{Av1Config, Av1Encoder};
+    use mead_core::codec::rate::RateControl;
+
+    const FRAMES: usize = 5;
+
+    /// Losslessly encode flat frames whose luma is `value`, decode them
+    /// back and return what came out
+    fn round_trip(format: PixelFormat, colorimetry: Colorimetry, value: u16) -> Vec<ArcFrame> {
+        let config = Av1Config {
+            speed: 10,
+            rate_control: RateControl::Crf(0),
+            pixel_format: format,
+            colorimetry,
+            ..Default::default()
+        };
+        let mut encoder = Av1Encoder::with_config(64, 48, config).unwrap();
+        for _ in 0..FRAMES {
+            let mut frame = Frame::new(64, 48, format);
+            let luma = &mut frame.planes_mut()[0];
+            let sample = value.to_le_bytes();
+            let bytes_per_sample = luma.bytes_per_sample();
+            for chunk in luma.data_mut().chunks_exact_mut(bytes_per_sample) {
+                chunk.copy_from_slice(&sample[..bytes_per_sample]);
+            }
+            encoder.send_frame(Some(Arc::new(frame))).unwrap();
+        }
+        let packets = encoder.finish().unwrap();
+
+        let mut decoder = Dav1dDecoder::new(Dav1dConfig::default()).unwrap();
+        let mut frames = Vec::new();
+        for packet in &packets {
+            frames.extend(decoder.decode(&packet.data).unwrap());
+        }
+        frames.extend(decoder.flush().unwrap());
+        frames
+    }
+
+    #[test]
+    fn test_config_validation() {
+        let config = Dav1dConfig {
+            threads: u32::MAX,
+            ..Default::default()
+        };
+        assert!(Dav1dDecoder::new(config).is_err());
+    }
+
+    #[test]
+    fn test_decoder_creation() {
+        let mut decoder = Dav1dDecoder::new(Dav1dConfig::default()).unwrap();
+
+        // No input yet: nothing to output
+        assert!(decoder.decode(&[]).unwrap().is_none());
+        assert!(decoder.flush().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_decode_8bit() {
+        let frames = round_trip(PixelFormat::Yuv420p, Colorimetry::default(), 100);
+        assert_eq!(frames.len(), FRAMES);
+        for frame in &frames {
+            assert_eq!((frame.width(), frame.height()), (64, 48));
+            assert_eq!(frame.format(), PixelFormat::Yuv420p);
+            assert_eq!(frame.planes()[0].row(0)[..4], [100; 4]);
+            assert_eq!(frame.planes()[1].row(0)[0], 0);
+        }
+    }
+
+    #[test]
+    fn test_decode_10bit_hdr() {
+        let colorimetry = Colorimetry {
+            primaries: ColorPrimaries::Bt2020,
+            transfer: TransferCharacteristics::Smpte2084,
+            matrix: MatrixCoefficients::Bt2020Ncl,
+            range: ColorRange::Full,
+            chroma_position: ChromaSamplePosition::Colocated,
+            mastering_display: Some(
+                "G(0.17,0.797)B(0.131,0.046)R(0.708,0.292)WP(0.3127,0.329)L(1000,0.005)"
+                    .parse()
+                    .unwrap(),
+            ),
+            content_light: Some("1000,400".parse().unwrap()),
+        };
+        let frames = round_trip(PixelFormat::Yuv420p10, colorimetry, 700);
+        assert_eq!(frames.len(), FRAMES);
+        for frame in &frames {
+            assert_eq!((frame.width(), frame.height()), (64, 48));
+            assert_eq!(frame.format(), PixelFormat::Yuv420p10);
+            // Samples come out as little-endian u16
+            assert_eq!(frame.planes()[0].row(0)[..4], [0xBC, 0x02, 0xBC, 0x02]);
+            assert_eq!(frame.colorimetry(), &colorimetry);
+        }
+    }
+}
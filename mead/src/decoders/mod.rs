@@ -0,0 +1,29 @@
+//! Video decoder implementations
+//!
+//! This module provides safe wrappers around decoder backends:
+//! - dav1d: AV1 decoder (optional, enabled with the `dav1d` feature)
+
+#[cfg(feature = "dav1d")]
+pub mod dav1d;
+
+// Re-export the VideoDecoder trait from mead-core for unified interface
+pub use mead_core::codec::VideoDecoder;
+
+use mead_core::Result;
+
+/// Create an AV1 decoder using the backend compiled into this build
+pub fn create_av1_decoder() -> Result<Box<dyn VideoDecoder>> {
+    #[cfg(feature = "dav1d")]
+    {
+        Ok(Box::new(dav1d::Dav1dDecoder::new(
+            dav1d::Dav1dConfig::default(),
+        )?))
+    }
+
+    #[cfg(not(feature = "dav1d"))]
+    {
+        Err(mead_core::Error::UnsupportedFormat(
+            "AV1 decoding requires mead built with `--features dav1d`".to_string(),
+        ))
+    }
+}
@@ -1,10 +1,17 @@
 //! Safe wrapper around SVT-AV1 encoder
 
-use mead_core::{ArcFrame, PixelFormat, Error, Result};
-use mead_core::codec::VideoEncoder;
+use mead_core::codec::preset::{Preset, Tune};
+use mead_core::codec::rate::{Pass, PassStats, RateControl};
+use mead_core::codec::registry::{EncoderCapabilities, EncoderFactory, EncoderOptions};
+use mead_core::codec::{EncodedPacket, FrameStats, FrameType, VideoEncoder};
+use mead_core::color::ColorRange;
+use mead_core::{ArcFrame, Colorimetry, Error, PixelFormat, PlaneLayout, Result};
 use std::ptr;
 use svt_av1_sys::*;
 
+/// Name first pass statistics are tagged with
+const STATS_ENCODER: &str = "svt-av1";
+
 /// Configuration for SVT-AV1 encoder
 #[derive(Debug, Clone)]
 pub struct SvtAv1Config {
@@ -23,11 +30,10 @@ pub struct SvtAv1Config {
     /// Frame rate denominator
     pub fps_den: u32,
 
-    /// Quantization parameter (0-63, lower=better quality)
-    /// Only used in CRF mode
-    pub qp: u32,
+    /// Constant quality or target bitrate
+    pub rate_control: RateControl,
 
-    /// Bit depth (8 or 10)
+    /// Bit depth (8 or 10); frames must be Yuv420p or Yuv420p10 to match
     pub bit_depth: u32,
 
     /// Number of tile columns (0 = auto)
@@ -35,22 +41,125 @@ pub struct SvtAv1Config {
 
     /// Number of tile rows (0 = auto)
     pub tile_rows: i32,
+
+    /// Color description and HDR metadata written to the sequence header
+    pub colorimetry: Colorimetry,
+
+    /// Single pass, or which pass of a two-pass encode
+    pub pass: Pass,
+
+    /// Content to tune for (None = SVT-AV1 defaults)
+    pub tune: Option<Tune>,
+
+    /// Most frames between keyframes (None = SVT-AV1 default)
+    pub keyframe_interval: Option<u64>,
+
+    /// Thread budget (0 = auto)
+    pub threads: u32,
 }
 
 impl Default for SvtAv1Config {
     fn default() -> Self {
         Self {
-            preset: 8,        // Balanced preset
-            width: 0,         // Must be set
-            height: 0,        // Must be set
+            preset: 8, // Balanced preset
+            width: 0,  // Must be set
+            height: 0, // Must be set
             fps_num: 30,
             fps_den: 1,
-            qp: 35,          // Reasonable quality
+            rate_control: RateControl::Crf(35), // Reasonable quality
             bit_depth: 8,
-            tile_cols: 0,    // Auto
-            tile_rows: 0,    // Auto
+            tile_cols: 0, // Auto
+            tile_rows: 0, // Auto
+            colorimetry: Colorimetry::default(),
+            pass: Pass::Single,
+            tune: None,
+            keyframe_interval: None,
+            threads: 0,
+        }
+    }
+}
+
+impl SvtAv1Config {
+    /// Configuration for a preset, and the content's quality if tuned;
+    /// the size still has to be set
+    pub fn from_preset(preset: Preset, tune: Option<Tune>) -> Self {
+        let defaults = Self::default();
+        Self {
+            preset: preset.svt_av1_preset(),
+            rate_control: tune.map_or(defaults.rate_control, |tune| RateControl::Crf(tune.crf())),
+            tune,
+            ..defaults
         }
     }
+
+    /// Configuration for backend-independent options
+    ///
+    /// SVT-AV1 places keyframes on a fixed period, so the minimum keyframe
+    /// interval is not used. Fails if the frame rate or thread count does
+    /// not fit SVT-AV1's 32-bit fields.
+    pub fn from_options(options: &EncoderOptions) -> Result<Self> {
+        let defaults = Self::from_preset(options.preset, options.tune);
+        let (fps_num, fps_den) = options.framerate;
+        let (Ok(fps_num), Ok(fps_den)) = (u32::try_from(fps_num), u32::try_from(fps_den)) else {
+            return Err(Error::InvalidInput(format!(
+                "Frame rate {fps_num}/{fps_den} out of range"
+            )));
+        };
+        let threads = u32::try_from(options.threads)
+            .map_err(|_| Error::InvalidInput("Thread count out of range".to_string()))?;
+        Ok(Self {
+            width: options.width,
+            height: options.height,
+            fps_num,
+            fps_den,
+            rate_control: options.rate_control.unwrap_or(defaults.rate_control),
+            bit_depth: u32::from(options.pixel_format.bit_depth()),
+            colorimetry: options.colorimetry,
+            pass: options.pass.clone(),
+            keyframe_interval: options.max_keyframe_interval,
+            threads,
+            ..defaults
+        })
+    }
+}
+
+/// Builds SVT-AV1 encoders for an [`EncoderRegistry`](mead_core::codec::registry::EncoderRegistry)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SvtAv1Factory;
+
+impl EncoderFactory for SvtAv1Factory {
+    fn name(&self) -> &'static str {
+        STATS_ENCODER
+    }
+
+    fn aliases(&self) -> &'static [&'static str] {
+        &["svtav1"]
+    }
+
+    fn capabilities(&self) -> EncoderCapabilities {
+        EncoderCapabilities {
+            pixel_formats: &[PixelFormat::Yuv420p, PixelFormat::Yuv420p10],
+            bit_depths: &[8, 10],
+            two_pass: true,
+            film_grain: false,
+            max_bitrate: false,
+            // Frames are read in place, so SIMD-friendly strides pay off
+            plane_layout: PlaneLayout::aligned(64),
+        }
+    }
+
+    fn settings(&self, options: &EncoderOptions) -> String {
+        match SvtAv1Config::from_options(options) {
+            Ok(config) => format!("{config:#?}"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn create(&self, options: &EncoderOptions) -> Result<Box<dyn VideoEncoder>> {
+        Ok(Box::new(SvtAv1Encoder::new(SvtAv1Config::from_options(
+            options,
+        )?)?))
+    }
 }
 
 /// Safe wrapper around SVT-AV1 encoder
@@ -58,23 +167,35 @@ pub struct SvtAv1Encoder {
     handle: *mut EbComponentType,
     width: u32,
     height: u32,
+    pixel_format: PixelFormat,
     frame_count: u64,
+    /// Whether this is a first pass, whose stats are read at end of stream
+    first_pass: bool,
+    /// Second pass stats; SVT-AV1 reads them in place until deinit
+    _stats_in: Option<Vec<u8>>,
+    /// First pass stats, once the encoder has been flushed
+    stats_out: Option<Vec<u8>>,
 }
 
 impl SvtAv1Encoder {
     /// Create new encoder with configuration
     pub fn new(config: SvtAv1Config) -> Result<Self> {
         if config.width == 0 || config.height == 0 {
-            return Err(Error::InvalidInput("Width and height must be set".to_string()));
+            return Err(Error::InvalidInput(
+                "Width and height must be set".to_string(),
+            ));
         }
 
         if config.preset > 13 {
             return Err(Error::InvalidInput("Preset must be 0-13".to_string()));
         }
 
-        if config.qp > 63 {
-            return Err(Error::InvalidInput("QP must be 0-63".to_string()));
-        }
+        config.rate_control.validate()?;
+        config.pass.validate(&config.rate_control)?;
+        let mut stats_in = match &config.pass {
+            Pass::Second(stats) => Some(stats.data_for(STATS_ENCODER)?.to_vec()),
+            Pass::Single | Pass::First => None,
+        };
 
         if config.bit_depth != 8 && config.bit_depth != 10 {
             return Err(Error::InvalidInput("Bit depth must be 8 or 10".to_string()));
@@ -88,7 +209,10 @@ impl SvtAv1Encoder {
             // Initialize handle (this populates enc_config with defaults)
             let err = svt_av1_enc_init_handle(&mut handle, &mut enc_config);
             if err != 0 {
-                return Err(Error::Codec(format!("Failed to initialize encoder handle: {}", err)));
+                return Err(Error::Codec(format!(
+                    "Failed to initialize encoder handle: {}",
+                    err
+                )));
             }
 
             // Configure encoder
@@ -99,34 +223,152 @@ impl SvtAv1Encoder {
             enc_config.frame_rate_denominator = config.fps_den;
             enc_config.encoder_bit_depth = config.bit_depth;
             enc_config.encoder_color_format = 1; // YUV420 (EB_YUV420 = 1)
-            enc_config.qp = config.qp;
-            enc_config.rate_control_mode = 0; // CRF mode
+            match config.rate_control {
+                RateControl::Crf(crf) => {
+                    enc_config.rate_control_mode = 0; // CRF mode
+                    enc_config.qp = u32::from(crf);
+                }
+                RateControl::Vbr {
+                    bitrate,
+                    max_bitrate,
+                    ..
+                } => {
+                    enc_config.rate_control_mode = 1; // VBR mode
+                    enc_config.target_bit_rate = bitrate.saturating_mul(1000);
+                    // SVT-AV1 only accepts a max bitrate for capped CRF
+                    if max_bitrate.is_some() {
+                        tracing::warn!(
+                            "SVT-AV1 cannot cap the bitrate in VBR mode, ignoring the max bitrate"
+                        );
+                    }
+                }
+                RateControl::Cbr { bitrate, .. } => {
+                    enc_config.rate_control_mode = 2; // CBR mode
+                    enc_config.target_bit_rate = bitrate.saturating_mul(1000);
+                    // SVT-AV1 only runs CBR with low-delay prediction
+                    enc_config.pred_structure = 1; // SVT_AV1_PRED_LOW_DELAY_B
+                }
+            }
+            if let Some(buffer_ms) = config.rate_control.buffer_ms() {
+                enc_config.maximum_buffer_size_ms = buffer_ms as i64;
+            }
+            match &mut stats_in {
+                Some(stats) => {
+                    enc_config.pass = 2; // ENC_SECOND_PASS
+                    enc_config.rc_stats_buffer.buf = stats.as_mut_ptr() as *mut _;
+                    enc_config.rc_stats_buffer.sz = stats.len() as u64;
+                }
+                None if config.pass == Pass::First => enc_config.pass = 1, // ENC_FIRST_PASS
+                None => {}
+            }
+            if let Some(tune) = config.tune {
+                enc_config.tune = if tune.psychovisual() { 0 } else { 1 }; // VQ or PSNR
+                if tune.screen_content() {
+                    enc_config.screen_content_mode = 1; // Instead of auto-detecting
+                }
+                if !tune.temporal_filter() {
+                    enc_config.enable_tf = 0;
+                }
+            }
+            if let Some(interval) = config.keyframe_interval {
+                // The intra period counts the frames between keyframes
+                enc_config.intra_period_length =
+                    i32::try_from(interval.saturating_sub(1)).unwrap_or(i32::MAX);
+            }
+            if config.threads > 0 {
+                enc_config.level_of_parallelism = config.threads;
+            }
             enc_config.tile_columns = config.tile_cols;
             enc_config.tile_rows = config.tile_rows;
 
+            // Color description, using the same H.273 code points as AV1
+            let colorimetry = &config.colorimetry;
+            enc_config.color_primaries = u32::from(colorimetry.primaries.code());
+            enc_config.transfer_characteristics = u32::from(colorimetry.transfer.code());
+            enc_config.matrix_coefficients = u32::from(colorimetry.matrix.code());
+            enc_config.color_range = u32::from(colorimetry.range == ColorRange::Full);
+            enc_config.chroma_sample_position = u32::from(colorimetry.chroma_position.code());
+            enc_config.high_dynamic_range_input = u8::from(colorimetry.transfer.is_hdr());
+
+            // HDR metadata, in the same fixed-point units as the AV1 metadata OBUs
+            if let Some(display) = &colorimetry.mastering_display {
+                let [r, g, b] = display.primaries;
+                enc_config.mastering_display.r.x = r.0;
+                enc_config.mastering_display.r.y = r.1;
+                enc_config.mastering_display.g.x = g.0;
+                enc_config.mastering_display.g.y = g.1;
+                enc_config.mastering_display.b.x = b.0;
+                enc_config.mastering_display.b.y = b.1;
+                enc_config.mastering_display.white_point.x = display.white_point.0;
+                enc_config.mastering_display.white_point.y = display.white_point.1;
+                enc_config.mastering_display.max_luma = display.max_luminance;
+                enc_config.mastering_display.min_luma = display.min_luminance;
+            }
+            if let Some(level) = &colorimetry.content_light {
+                enc_config.content_light_level.max_cll = level.max_cll;
+                enc_config.content_light_level.max_fall = level.max_fall;
+            }
+
             // Set configuration
             let err = svt_av1_enc_set_parameter(handle, &mut enc_config);
             if err != 0 {
                 svt_av1_enc_deinit_handle(handle);
-                return Err(Error::Codec(format!("Failed to set encoder parameters: {}", err)));
+                return Err(Error::Codec(format!(
+                    "Failed to set encoder parameters: {}",
+                    err
+                )));
             }
 
             // Initialize encoder
             let err = svt_av1_enc_init(handle);
             if err != 0 {
                 svt_av1_enc_deinit_handle(handle);
-                return Err(Error::Codec(format!("Failed to initialize encoder: {}", err)));
+                return Err(Error::Codec(format!(
+                    "Failed to initialize encoder: {}",
+                    err
+                )));
             }
 
             Ok(Self {
                 handle,
                 width: config.width,
                 height: config.height,
+                pixel_format: if config.bit_depth == 10 {
+                    PixelFormat::Yuv420p10
+                } else {
+                    PixelFormat::Yuv420p
+                },
                 frame_count: 0,
+                first_pass: config.pass == Pass::First,
+                _stats_in: stats_in,
+                stats_out: None,
             })
         }
     }
 
+    /// Copy out the first pass stats, which SVT-AV1 owns until deinit
+    fn read_first_pass_stats(&self) -> Result<Vec<u8>> {
+        unsafe {
+            let mut stats = std::mem::zeroed::<SvtAv1FixedBuf>();
+            let err = svt_av1_enc_get_stream_info(
+                self.handle,
+                1, // SVT_AV1_STREAM_INFO_FIRST_PASS_STATS_OUT
+                &mut stats as *mut _ as *mut _,
+            );
+            if err != 0 {
+                return Err(Error::Codec(format!(
+                    "Failed to read first pass stats: {} ({})",
+                    Self::error_string(err),
+                    err
+                )));
+            }
+            if stats.buf.is_null() {
+                return Ok(Vec::new());
+            }
+            Ok(std::slice::from_raw_parts(stats.buf as *const u8, stats.sz as usize).to_vec())
+        }
+    }
+
     /// Convert error code to string
     fn error_string(code: i32) -> &'static str {
         match code as u32 {
@@ -151,13 +393,15 @@ impl VideoEncoder for SvtAv1Encoder {
         unsafe {
             if let Some(frame) = frame {
                 // Validate frame format
-                if frame.format() != PixelFormat::Yuv420p {
-                    return Err(Error::UnsupportedFormat("SVT-AV1 currently only supports YUV420p".to_string()));
+                if frame.format() != self.pixel_format {
+                    return Err(Error::UnsupportedFormat(format!(
+                        "SVT-AV1 encoder expects {:?} frames, got {:?}",
+                        self.pixel_format,
+                        frame.format()
+                    )));
                 }
 
-                if frame.width() != self.width
-                    || frame.height() != self.height
-                {
+                if frame.width() != self.width || frame.height() != self.height {
                     return Err(Error::InvalidInput(format!(
                         "Frame size {}x{} doesn't match encoder config {}x{}",
                         frame.width(),
@@ -175,12 +419,20 @@ impl VideoEncoder for SvtAv1Encoder {
                 // YUV420: Y plane + U plane + V plane
                 let planes = frame.planes();
                 if planes.len() != 3 {
-                    return Err(Error::InvalidInput(format!("YUV420p requires 3 planes, got {}", planes.len())));
+                    return Err(Error::InvalidInput(format!(
+                        "YUV420p requires 3 planes, got {}",
+                        planes.len()
+                    )));
                 }
 
-                input_picture.y_stride = planes[0].stride() as u32;
-                input_picture.cb_stride = planes[1].stride() as u32;
-                input_picture.cr_stride = planes[2].stride() as u32;
+                // SVT-AV1 reads the planes in place, so padded strides and
+                // cropped views need no repacking. Strides are in samples;
+                // 10-bit input is unpacked 16-bit
+                input_picture.y_stride = (planes[0].stride() / planes[0].bytes_per_sample()) as u32;
+                input_picture.cb_stride =
+                    (planes[1].stride() / planes[1].bytes_per_sample()) as u32;
+                input_picture.cr_stride =
+                    (planes[2].stride() / planes[2].bytes_per_sample()) as u32;
                 input_picture.luma = planes[0].data().as_ptr() as *mut u8;
                 input_picture.cb = planes[1].data().as_ptr() as *mut u8;
                 input_picture.cr = planes[2].data().as_ptr() as *mut u8;
@@ -193,7 +445,7 @@ impl VideoEncoder for SvtAv1Encoder {
                 input_buffer.p_app_private = ptr::null_mut();
                 input_buffer.wrapper_ptr = ptr::null_mut();
                 input_buffer.pic_type = EbAv1PictureType_EB_AV1_INVALID_PICTURE;
-                input_buffer.pts = self.frame_count as i64;
+                input_buffer.pts = frame.pts().unwrap_or(self.frame_count as i64);
 
                 // Send picture
                 let err = svt_av1_enc_send_picture(self.handle, &mut input_buffer);
@@ -232,7 +484,7 @@ impl VideoEncoder for SvtAv1Encoder {
         }
     }
 
-    fn receive_packet(&mut self) -> Result<Option<Vec<u8>>> {
+    fn receive_packet(&mut self) -> Result<Option<EncodedPacket>> {
         unsafe {
             let mut output_buffer: *mut EbBufferHeaderType = ptr::null_mut();
 
@@ -255,15 +507,22 @@ impl VideoEncoder for SvtAv1Encoder {
             if buffer.flags & 1 != 0 {
                 // EOS - release buffer and return None
                 svt_av1_enc_release_out_buffer(&mut output_buffer);
+                if self.first_pass && self.stats_out.is_none() {
+                    self.stats_out = Some(self.read_first_pass_stats()?);
+                }
                 return Ok(None);
             }
 
             // Copy packet data
-            let data = std::slice::from_raw_parts(
-                buffer.p_buffer,
-                buffer.n_filled_len as usize,
-            );
-            let packet = data.to_vec();
+            let data = std::slice::from_raw_parts(buffer.p_buffer, buffer.n_filled_len as usize);
+            let packet = EncodedPacket {
+                data: data.to_vec(),
+                pts: buffer.pts,
+                dts: buffer.pts,
+                frame_type: frame_type(buffer.pic_type),
+                qp: Some(qindex(buffer.qp)),
+                stats: frame_stats(buffer),
+            };
 
             // Release buffer
             svt_av1_enc_release_out_buffer(&mut output_buffer);
@@ -271,6 +530,55 @@ impl VideoEncoder for SvtAv1Encoder {
             Ok(Some(packet))
         }
     }
+
+    fn pass_stats(&self) -> Result<Option<PassStats>> {
+        match &self.stats_out {
+            Some(stats) => Ok(Some(PassStats::new(STATS_ENCODER, stats.clone())?)),
+            None if self.first_pass => Err(Error::InvalidInput(
+                "First pass stats are only available once the encoder is flushed".to_string(),
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Kind of frame an output picture type shows; alt-refs, non-reference
+/// and shown-again frames are all predicted
+fn frame_type(pic_type: EbAv1PictureType) -> FrameType {
+    if pic_type == EbAv1PictureType_EB_AV1_KEY_PICTURE {
+        FrameType::Key
+    } else if pic_type == EbAv1PictureType_EB_AV1_INTRA_ONLY_PICTURE
+        || pic_type == EbAv1PictureType_EB_AV1_FW_KEY_PICTURE
+    {
+        FrameType::IntraOnly
+    } else {
+        FrameType::Inter
+    }
+}
+
+/// SVT-AV1's `quantizer_to_qindex`: steps of 4 up to QP 61, then 249 and 255
+const QUANTIZER_TO_QINDEX: [u8; 64] = [
+    0, 4, 8, 12, 16, 20, 24, 28, 32, 36, 40, 44, 48, 52, 56, 60, 64, 68, 72, 76, 80, 84, 88, 92,
+    96, 100, 104, 108, 112, 116, 120, 124, 128, 132, 136, 140, 144, 148, 152, 156, 160, 164, 168,
+    172, 176, 180, 184, 188, 192, 196, 200, 204, 208, 212, 216, 220, 224, 228, 232, 236, 240, 244,
+    249, 255,
+];
+
+/// AV1 quantizer index for SVT-AV1's 0-63 QP scale
+fn qindex(qp: u32) -> u8 {
+    QUANTIZER_TO_QINDEX[(qp as usize).min(QUANTIZER_TO_QINDEX.len() - 1)]
+}
+
+/// Distortion SVT-AV1 measured, only filled in when stat reports are enabled
+fn frame_stats(buffer: &EbBufferHeaderType) -> FrameStats {
+    if buffer.luma_ssim <= 0.0 {
+        return FrameStats::default();
+    }
+    FrameStats {
+        sse: Some([buffer.luma_sse, buffer.cb_sse, buffer.cr_sse]),
+        ssim: Some([buffer.luma_ssim, buffer.cb_ssim, buffer.cr_ssim]),
+        skip_ratio: None,
+    }
 }
 
 impl Drop for SvtAv1Encoder {
@@ -315,14 +623,108 @@ mod tests {
         };
         assert!(SvtAv1Encoder::new(config).is_err());
 
-        // Invalid QP
+        // Invalid CRF
+        let config = SvtAv1Config {
+            width: 640,
+            height: 480,
+            rate_control: RateControl::Crf(100),
+            ..Default::default()
+        };
+        assert!(SvtAv1Encoder::new(config).is_err());
+
+        // Two passes without a target bitrate
+        let config = SvtAv1Config {
+            width: 640,
+            height: 480,
+            pass: Pass::First,
+            ..Default::default()
+        };
+        assert!(SvtAv1Encoder::new(config).is_err());
+
+        // Second pass stats from another backend
         let config = SvtAv1Config {
             width: 640,
             height: 480,
-            qp: 100,
+            rate_control: RateControl::Vbr {
+                bitrate: 2000,
+                max_bitrate: None,
+                buffer_size: None,
+            },
+            pass: Pass::Second(PassStats::new("rav1e", Vec::new()).unwrap()),
             ..Default::default()
         };
         assert!(SvtAv1Encoder::new(config).is_err());
+
+        // Invalid bit depth
+        let config = SvtAv1Config {
+            width: 640,
+            height: 480,
+            bit_depth: 12,
+            ..Default::default()
+        };
+        assert!(SvtAv1Encoder::new(config).is_err());
+    }
+
+    #[test]
+    fn test_from_preset() {
+        let config = SvtAv1Config::from_preset(Preset::Quality, Some(Tune::Grain));
+        assert_eq!(config.preset, 4);
+        assert_eq!(config.rate_control, RateControl::Crf(26));
+        assert_eq!(
+            SvtAv1Config::from_preset(Preset::Balanced, None).preset,
+            SvtAv1Config::default().preset
+        );
+    }
+
+    #[test]
+    fn test_packet_fields() {
+        assert_eq!(
+            frame_type(EbAv1PictureType_EB_AV1_KEY_PICTURE),
+            FrameType::Key
+        );
+        assert_eq!(
+            frame_type(EbAv1PictureType_EB_AV1_FW_KEY_PICTURE),
+            FrameType::IntraOnly
+        );
+        assert_eq!(
+            frame_type(EbAv1PictureType_EB_AV1_ALT_REF_PICTURE),
+            FrameType::Inter
+        );
+        assert_eq!(
+            frame_type(EbAv1PictureType_EB_AV1_SHOW_EXISTING_PICTURE),
+            FrameType::Inter
+        );
+        assert_eq!(
+            (qindex(0), qindex(35), qindex(62), qindex(63)),
+            (0, 140, 249, 255)
+        );
+    }
+
+    #[test]
+    fn test_from_options() {
+        let options = EncoderOptions {
+            width: 640,
+            height: 480,
+            framerate: (24000, 1001),
+            pixel_format: PixelFormat::Yuv420p10,
+            max_keyframe_interval: Some(120),
+            ..Default::default()
+        };
+        let config = SvtAv1Config::from_options(&options).unwrap();
+        assert_eq!((config.fps_num, config.fps_den), (24000, 1001));
+        assert_eq!(config.bit_depth, 10);
+        assert_eq!(config.keyframe_interval, Some(120));
+        assert_eq!(config.rate_control, SvtAv1Config::default().rate_control);
+
+        // Values past u32 are rejected rather than truncated
+        let options = EncoderOptions {
+            framerate: (1 << 32, 1),
+            ..options
+        };
+        assert!(matches!(
+            SvtAv1Config::from_options(&options),
+            Err(Error::InvalidInput(_))
+        ));
     }
 
     #[test]
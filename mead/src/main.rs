@@ -1,19 +1,50 @@
+mod decoders;
 mod encoders;
 mod output;
 
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use mead_core::container::{mp4::Mp4Demuxer, ivf::IvfMuxer, y4m::Y4mDemuxer, Demuxer, Muxer, Packet};
-use mead_core::codec::opus::OpusDecoderImpl;
-use mead_core::codec::av1::Av1Encoder as Rav1eEncoder;
+use audiopus::{Channels, SampleRate};
+use clap::{Args, Parser, Subcommand};
+use encoders::VideoEncoder;
 use mead_core::codec::AudioDecoder;
-use audiopus::{SampleRate, Channels};
-use std::fs::File;
-use std::io::{Write, BufReader, stdin};
-use std::time::Instant;
-use std::sync::Arc;
+use mead_core::codec::aac::{AacConfig, AacDecoder, AdtsHeader};
+use mead_core::codec::av1::FilmGrain;
+use mead_core::codec::av1::obu::{Av1Parser, SequenceHeader};
+use mead_core::codec::opus::OpusDecoderImpl;
+use mead_core::codec::preset::{Preset, Tune};
+use mead_core::codec::rate::{self, Pass, PassStats, RateControl};
+use mead_core::codec::registry::EncoderOptions;
+use mead_core::color::{
+    ColorPrimaries, ColorRange, ContentLightLevel, MasteringDisplay, MatrixCoefficients,
+    TransferCharacteristics,
+};
+use mead_core::container::mkv::{DocType, MkvDemuxer, MkvMuxer, MkvMuxerConfig, MkvTrackConfig};
+use mead_core::container::mp4::{Mp4Demuxer, Mp4Muxer, Mp4MuxerConfig, Mp4TrackConfig};
+use mead_core::container::obu::{ObuDemuxer, ObuFormat, ObuMuxer};
+use mead_core::container::ogg::OggDemuxer;
+use mead_core::container::{
+    Demuxer, Metadata, Muxer, Packet,
+    ivf::{IvfDemuxer, IvfMuxer},
+    y4m::{Interlace, Y4mDemuxer, Y4mMuxer, Y4mMuxerConfig},
+};
+use mead_core::convert::{ConvertConfig, Converter};
+use mead_core::deinterlace::{DeinterlaceConfig, DeinterlaceFilter, DeinterlaceMode};
+use mead_core::denoise::{DenoiseConfig, Denoiser};
+use mead_core::filter::{
+    CropFilter, Filter, FilterChain, FlipFilter, PadFilter, RotateFilter, ScaleFilter, VideoInfo,
+};
+use mead_core::fps::{self, FpsFilter, FpsMode};
+use mead_core::io::WriteOnlySink;
+use mead_core::scale::{Kernel, ScaleConfig};
+use mead_core::transform::{Borders, Color, CropDetect, Flip, Rotation};
+use mead_core::{ArcFrame, Colorimetry, FieldOrder, Frame, PlaneLayout};
 use output::{OutputConfig, Theme};
-use encoders::{EncoderBackend, VideoEncoder, svtav1::{SvtAv1Config, SvtAv1Encoder}};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read, Seek, Stdout, Write, stdin, stdout};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Instant;
 
 #[derive(Parser)]
 #[command(name = "mead")]
@@ -36,6 +67,8 @@ struct Cli {
     command: Commands,
 }
 
+// Parsed once per run, so the size of the encode options does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Display container and stream information
@@ -47,7 +80,7 @@ enum Commands {
     Encode {
         /// Input file path
         input: String,
-        /// Output file path
+        /// Output file path (.ivf, .mp4, .webm, .mkv, .obu, or - for live WebM on stdout)
         #[arg(short, long)]
         output: String,
         /// Video codec (av1, h264)
@@ -56,17 +89,370 @@ enum Commands {
         /// Encoder backend (svt-av1, rav1e)
         #[arg(long, default_value = "svt-av1")]
         encoder: String,
+        #[command(flatten)]
+        preset: PresetArgs,
+        #[command(flatten)]
+        color: ColorArgs,
+        #[command(flatten)]
+        geometry: GeometryArgs,
+        #[command(flatten)]
+        scale: ScaleArgs,
+        #[command(flatten)]
+        rate: RateArgs,
+        #[command(flatten)]
+        deinterlace: DeinterlaceArgs,
+        #[command(flatten)]
+        denoise: DenoiseArgs,
+        #[command(flatten)]
+        rate_control: RateControlArgs,
+        #[command(flatten)]
+        two_pass: TwoPassArgs,
+        /// Filters run after the options above, e.g. "crop=0:140,scale=1280:-1,format=yuv420p10"
+        #[arg(long)]
+        vf: Option<String>,
     },
     /// Decode video/audio
     Decode {
-        /// Input file path
+        /// Input file path (.mp4, .ivf, .obu, .webm, .mkv, .opus, .ogg or .aac)
         input: String,
-        /// Output file path
+        /// Output file path (.y4m decodes the AV1 video track, anything else
+        /// gets raw interleaved f32le PCM at the audio stream's sample rate)
         #[arg(short, long)]
         output: String,
     },
 }
 
+/// Encoder speed and tuning, the same for every backend
+#[derive(Args)]
+struct PresetArgs {
+    /// Speed against compression (fast, balanced, quality, archival)
+    #[arg(long, default_value = "balanced")]
+    preset: Preset,
+    /// Content to tune for (film, animation, grain, screen); sets the default --crf
+    #[arg(long)]
+    tune: Option<Tune>,
+    /// Print the encoder settings the options resolve to, without encoding
+    #[arg(long)]
+    show_settings: bool,
+}
+
+/// Colorimetry overrides for encoding; Y4M input only carries range and chroma siting
+#[derive(Args)]
+struct ColorArgs {
+    /// Color primaries (bt709, bt2020, smpte432, ...)
+    #[arg(long)]
+    primaries: Option<ColorPrimaries>,
+    /// Transfer characteristics (bt709, smpte2084/pq, arib-std-b67/hlg, ...)
+    #[arg(long)]
+    transfer: Option<TransferCharacteristics>,
+    /// Matrix coefficients (bt709, bt2020nc, identity, ...)
+    #[arg(long)]
+    matrix: Option<MatrixCoefficients>,
+    /// Color range (limited, full)
+    #[arg(long)]
+    range: Option<ColorRange>,
+    /// Mastering display, e.g. G(0.265,0.69)B(0.15,0.06)R(0.68,0.32)WP(0.3127,0.329)L(1000,0.0001)
+    #[arg(long)]
+    master_display: Option<MasteringDisplay>,
+    /// Content light level as MaxCLL,MaxFALL, e.g. 1000,400
+    #[arg(long)]
+    max_cll: Option<ContentLightLevel>,
+}
+
+impl ColorArgs {
+    /// Apply the overrides to the colorimetry read from the input
+    fn apply(&self, input: Colorimetry) -> Colorimetry {
+        Colorimetry {
+            primaries: self.primaries.unwrap_or(input.primaries),
+            transfer: self.transfer.unwrap_or(input.transfer),
+            matrix: self.matrix.unwrap_or(input.matrix),
+            range: self.range.unwrap_or(input.range),
+            chroma_position: input.chroma_position,
+            mastering_display: self.master_display.or(input.mastering_display),
+            content_light: self.max_cll.or(input.content_light),
+        }
+    }
+}
+
+/// Cropping and orientation, applied before scaling, and padding, applied after
+#[derive(Args)]
+struct GeometryArgs {
+    /// Crop borders as left:top:right:bottom, horizontal:vertical or one value
+    #[arg(long)]
+    crop: Option<Borders>,
+    /// Detect and crop black borders by scanning the input (files only)
+    #[arg(long, conflicts_with = "crop")]
+    autocrop: bool,
+    /// Rotate clockwise by 90, 180 or 270 degrees
+    #[arg(long, allow_hyphen_values = true)]
+    rotate: Option<Rotation>,
+    /// Mirror left and right
+    #[arg(long)]
+    hflip: bool,
+    /// Mirror top and bottom
+    #[arg(long)]
+    vflip: bool,
+    /// Pad borders as left:top:right:bottom, horizontal:vertical or one value
+    #[arg(long)]
+    pad: Option<Borders>,
+    /// Padding color (black, white, gray or #rrggbb)
+    #[arg(long, default_value = "black")]
+    pad_color: Color,
+}
+
+/// Frames read when detecting black borders, of which every tenth is analyzed
+const AUTOCROP_FRAMES: u64 = 300;
+
+impl GeometryArgs {
+    /// Borders to crop, scanning the start of the input for `--autocrop`
+    fn crop_borders(&self, input: &str) -> Result<Borders> {
+        if !self.autocrop {
+            return Ok(self.crop.unwrap_or_default());
+        }
+        if input == "-" {
+            return Err(anyhow::anyhow!(
+                "--autocrop needs a file input, stdin can only be read once"
+            ));
+        }
+
+        let mut demuxer = Y4mDemuxer::new(BufReader::new(File::open(input)?))?;
+        let mut detect = CropDetect::default();
+        while demuxer.frame_count() < AUTOCROP_FRAMES {
+            let Some(frame) = demuxer.read_frame()? else {
+                break;
+            };
+            if demuxer.frame_count() % 10 == 1 {
+                detect.analyze(&frame)?;
+            }
+        }
+        Ok(detect.borders())
+    }
+
+    /// Append the crop, rotate and flip filters
+    fn orient_filters(&self, chain: &mut FilterChain, crop: Borders) {
+        if !crop.is_empty() {
+            chain.push(CropFilter::new(crop));
+        }
+        if let Some(rotation) = self.rotate {
+            chain.push(RotateFilter::new(rotation));
+        }
+        if self.hflip {
+            chain.push(FlipFilter::new(Flip::Horizontal));
+        }
+        if self.vflip {
+            chain.push(FlipFilter::new(Flip::Vertical));
+        }
+    }
+
+    /// Append the pad filter
+    fn pad_filter(&self, chain: &mut FilterChain) {
+        if let Some(pad) = self.pad.filter(|pad| !pad.is_empty()) {
+            chain.push(PadFilter::new(pad, self.pad_color));
+        }
+    }
+}
+
+/// Output scaling; a side left unset follows the input aspect ratio
+#[derive(Args)]
+struct ScaleArgs {
+    /// Output size as WxH, e.g. 1280x720; -1 for one side keeps the aspect ratio
+    #[arg(long, value_parser = parse_scale, allow_hyphen_values = true)]
+    scale: Option<(u32, u32)>,
+    /// Output width; with --height, fits inside both keeping the aspect ratio
+    #[arg(long, conflicts_with = "scale")]
+    width: Option<u32>,
+    /// Output height; with --width, fits inside both keeping the aspect ratio
+    #[arg(long, conflicts_with = "scale")]
+    height: Option<u32>,
+    /// Fit inside the --scale size keeping the aspect ratio, instead of stretching
+    #[arg(long, requires = "scale")]
+    fit: bool,
+    /// Scaling kernel (bilinear, bicubic, lanczos, spline)
+    #[arg(long, default_value = "bicubic")]
+    scaler: Kernel,
+}
+
+impl ScaleArgs {
+    /// Scale filter for the options, or `None` when no size was given
+    fn filter(&self, layout: PlaneLayout) -> Option<ScaleFilter> {
+        let (width, height) = match (self.scale, self.width, self.height) {
+            (Some(size), _, _) => size,
+            (None, None, None) => return None,
+            (None, w, h) => (w.unwrap_or(0), h.unwrap_or(0)),
+        };
+        let filter = ScaleFilter::new(ScaleConfig {
+            width,
+            height,
+            kernel: self.scaler,
+            layout,
+            ..Default::default()
+        });
+        // --width with --height always fits inside both
+        Some(if self.fit || self.scale.is_none() {
+            filter.fit()
+        } else {
+            filter
+        })
+    }
+}
+
+/// Output frame rate conversion
+#[derive(Args)]
+struct RateArgs {
+    /// Output frame rate, e.g. 25, 30000/1001 or 29.97; frames are dropped or repeated
+    #[arg(long, value_parser = fps::parse_rate)]
+    fps: Option<(u64, u64)>,
+    /// How --fps fills output frames (nearest, blend)
+    #[arg(long, default_value = "nearest", requires = "fps")]
+    fps_mode: FpsMode,
+}
+
+impl RateArgs {
+    /// Frame rate filter for the options, or `None` to keep the input rate
+    fn filter(&self) -> Result<Option<FpsFilter>> {
+        Ok(match self.fps {
+            Some(rate) => Some(FpsFilter::new(rate, self.fps_mode)?),
+            None => None,
+        })
+    }
+}
+
+/// Deinterlacing, enabled automatically for input flagged as interlaced
+#[derive(Args)]
+struct DeinterlaceArgs {
+    /// Deinterlacing method (adaptive, bob)
+    #[arg(long, default_value = "adaptive")]
+    deinterlace: DeinterlaceMode,
+    /// Encode interlaced input as is
+    #[arg(long, conflicts_with_all = ["field_rate", "field_order"])]
+    no_deinterlace: bool,
+    /// Output a frame per field, doubling the frame rate
+    #[arg(long)]
+    field_rate: bool,
+    /// Field order (tff, bff) of input that does not signal it; forces deinterlacing
+    #[arg(long)]
+    field_order: Option<FieldOrder>,
+}
+
+impl DeinterlaceArgs {
+    /// Deinterlace filter for input with the given interlacing, if it needs one
+    fn filter(&self, interlace: Interlace) -> Option<DeinterlaceFilter> {
+        if self.no_deinterlace
+            || (interlace == Interlace::Progressive && self.field_order.is_none())
+        {
+            return None;
+        }
+        Some(DeinterlaceFilter::new(DeinterlaceConfig {
+            mode: self.deinterlace,
+            field_rate: self.field_rate,
+            field_order: self.field_order,
+        }))
+    }
+}
+
+/// Rate control, constant quality unless a bitrate is given
+#[derive(Args)]
+struct RateControlArgs {
+    /// Constant quality, 0-63 (lower is better)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=63), conflicts_with = "bitrate")]
+    crf: Option<u8>,
+    /// Average bitrate, e.g. 2500k or 2.5M (plain numbers are kbps)
+    #[arg(long, value_parser = rate::parse_bitrate)]
+    bitrate: Option<u32>,
+    /// Peak bitrate; the same as --bitrate for constant bitrate (CBR)
+    #[arg(long, value_parser = rate::parse_bitrate, requires = "bitrate")]
+    maxrate: Option<u32>,
+    /// Rate buffer size in kilobits, e.g. 5M
+    #[arg(long, value_parser = rate::parse_bitrate, requires = "bitrate")]
+    bufsize: Option<u32>,
+}
+
+impl RateControlArgs {
+    /// Rate control for the options, or `None` for the encoder's default
+    fn rate_control(&self) -> Result<Option<RateControl>> {
+        let rate_control = match (self.crf, self.bitrate) {
+            (Some(crf), _) => RateControl::Crf(crf),
+            (None, Some(bitrate)) if self.maxrate == Some(bitrate) => RateControl::Cbr {
+                bitrate,
+                buffer_size: self.bufsize,
+            },
+            (None, Some(bitrate)) => RateControl::Vbr {
+                bitrate,
+                max_bitrate: self.maxrate,
+                buffer_size: self.bufsize,
+            },
+            (None, None) => return Ok(None),
+        };
+        rate_control.validate()?;
+        Ok(Some(rate_control))
+    }
+}
+
+/// Two-pass encoding, which needs a VBR --bitrate
+#[derive(Args)]
+struct TwoPassArgs {
+    /// Run only the first pass (writing --passlog, not the output) or the second
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=2), requires = "bitrate")]
+    pass: Option<u8>,
+    /// Run both passes, reading the input twice
+    #[arg(long, conflicts_with = "pass", requires = "bitrate")]
+    two_pass: bool,
+    /// First pass stats file for --pass
+    #[arg(
+        long,
+        value_name = "FILE",
+        default_value = "mead2pass.stats",
+        requires = "pass"
+    )]
+    passlog: String,
+}
+
+/// Denoised frames measured for film grain before the encoder starts
+const GRAIN_ANALYSIS_FRAMES: usize = 10;
+
+/// Denoising before encoding
+#[derive(Args)]
+struct DenoiseArgs {
+    /// Denoise, with a strength in 8-bit sample steps (default 4)
+    #[arg(long, value_name = "STRENGTH", num_args = 0..=1, default_missing_value = "4")]
+    denoise: Option<f32>,
+    /// Replace the removed noise with matching AV1 film grain (rav1e)
+    #[arg(long, requires = "denoise")]
+    film_grain: bool,
+}
+
+impl DenoiseArgs {
+    /// Denoiser for the options, or `None` to keep the noise
+    fn filter(&self) -> Result<Option<Denoiser>> {
+        Ok(match self.denoise {
+            Some(strength) => Some(Denoiser::new(DenoiseConfig {
+                grain: self.film_grain,
+                ..DenoiseConfig::with_strength(strength)
+            })?),
+            None => None,
+        })
+    }
+}
+
+/// Parse a `WxH` size, where -1 (stored as 0) derives that side
+fn parse_scale(s: &str) -> std::result::Result<(u32, u32), String> {
+    let (width, height) = s
+        .split_once(['x', 'X', ':'])
+        .ok_or_else(|| format!("Expected WxH, got {}", s))?;
+    let side = |value: &str| match value {
+        "-1" => Ok(0),
+        _ => value
+            .parse::<u32>()
+            .ok()
+            .filter(|&v| v > 0)
+            .ok_or_else(|| format!("Invalid size {}", value)),
+    };
+    match (side(width)?, side(height)?) {
+        (0, 0) => Err("Only one side of --scale can be -1".to_string()),
+        size => Ok(size),
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
@@ -90,8 +476,40 @@ fn main() -> Result<()> {
             }
             Ok(())
         }
-        Commands::Encode { input, output, codec, encoder } => {
-            handle_encode(&input, &output, &codec, &encoder, &output_config, &theme)?;
+        Commands::Encode {
+            input,
+            output,
+            codec,
+            encoder,
+            preset,
+            color,
+            geometry,
+            scale,
+            rate,
+            deinterlace,
+            denoise,
+            rate_control,
+            two_pass,
+            vf,
+        } => {
+            handle_encode(
+                &input,
+                &output,
+                &codec,
+                &encoder,
+                &preset,
+                &color,
+                &geometry,
+                &scale,
+                &rate,
+                &deinterlace,
+                &denoise,
+                &rate_control,
+                &two_pass,
+                vf.as_deref(),
+                &output_config,
+                &theme,
+            )?;
             Ok(())
         }
         Commands::Decode { input, output } => {
@@ -102,8 +520,38 @@ fn main() -> Result<()> {
 }
 
 fn handle_info_json(input: &str) -> Result<()> {
+    if let Some((metadata, streams)) = probe_streams(input)? {
+        let json = serde_json::json!({
+            "file": input,
+            "format": metadata.format,
+            "stream_count": metadata.stream_count,
+            "duration_ms": metadata.duration_ms,
+            "tracks": streams.iter().enumerate().map(|(index, stream)| match stream {
+                StreamInfo::Video { codec, width, height, av1 } => serde_json::json!({
+                    "id": index,
+                    "type": "Video",
+                    "codec": codec,
+                    "width": width,
+                    "height": height,
+                    "av1": av1.as_ref().map(av1_json),
+                }),
+                StreamInfo::Audio { codec, sample_rate, channels } => serde_json::json!({
+                    "id": index,
+                    "type": "Audio",
+                    "codec": codec,
+                    "sample_rate": sample_rate,
+                    "channels": channels,
+                }),
+            }).collect::<Vec<_>>(),
+        });
+
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(());
+    }
+
     let file = File::open(input)?;
-    let demuxer = Mp4Demuxer::new(file)?;
+    let mut demuxer = Mp4Demuxer::new(file)?;
+    let av1 = mp4_av1_headers(&mut demuxer);
     let metadata = demuxer.metadata();
 
     let json = serde_json::json!({
@@ -120,6 +568,7 @@ fn handle_info_json(input: &str) -> Result<()> {
                 "sample_count": track.sample_count(),
                 "width": track.width(),
                 "height": track.height(),
+                "av1": av1.get(track_id).map(av1_json),
             })
         }).collect::<Vec<_>>(),
     });
@@ -129,30 +578,46 @@ fn handle_info_json(input: &str) -> Result<()> {
 }
 
 fn handle_info_human(input: &str, theme: &Theme) -> Result<()> {
-    let file = File::open(input)?;
-    let demuxer = Mp4Demuxer::new(file)?;
-    let metadata = demuxer.metadata();
+    if let Some((metadata, streams)) = probe_streams(input)? {
+        print_info_header(input, &metadata, theme);
 
-    println!("{}: {}", theme.highlight("File"), input);
-    println!("{}: {}", theme.highlight("Format"), metadata.format);
-    println!("{}: {}", theme.highlight("Streams"), metadata.stream_count);
-
-    if let Some(duration_ms) = metadata.duration_ms {
-        let seconds = duration_ms / 1000;
-        let minutes = seconds / 60;
-        let hours = minutes / 60;
-        println!(
-            "{}: {}:{:02}:{:02}.{:03}",
-            theme.highlight("Duration"),
-            hours,
-            minutes % 60,
-            seconds % 60,
-            duration_ms % 1000
-        );
-    } else {
-        println!("{}: Unknown", theme.highlight("Duration"));
+        println!("\n{}:", theme.highlight("Tracks"));
+        for (index, stream) in streams.iter().enumerate() {
+            match stream {
+                StreamInfo::Video {
+                    codec,
+                    width,
+                    height,
+                    av1,
+                } => {
+                    println!("  Track {}: Video", index);
+                    println!("    Codec: {}", codec);
+                    println!("    Width: {}", width);
+                    println!("    Height: {}", height);
+                    if let Some(header) = av1 {
+                        print_av1_info(header);
+                    }
+                }
+                StreamInfo::Audio {
+                    codec,
+                    sample_rate,
+                    channels,
+                } => {
+                    println!("  Track {}: Audio", index);
+                    println!("    Codec: {}", codec);
+                    println!("    Sample Rate: {}", sample_rate);
+                    println!("    Channels: {}", channels);
+                }
+            }
+        }
+        return Ok(());
     }
 
+    let file = File::open(input)?;
+    let mut demuxer = Mp4Demuxer::new(file)?;
+    let av1 = mp4_av1_headers(&mut demuxer);
+    print_info_header(input, demuxer.metadata(), theme);
+
     println!("\n{}:", theme.highlight("Tracks"));
     for (track_id, track) in demuxer.tracks().iter() {
         println!("  Track {}: {:?}", track_id, track.track_type());
@@ -167,6 +632,9 @@ fn handle_info_human(input: &str, theme: &Theme) -> Result<()> {
                 }
                 println!("    Width: {}", track.width());
                 println!("    Height: {}", track.height());
+                if let Some(header) = av1.get(track_id) {
+                    print_av1_info(header);
+                }
             }
             Ok(mp4::TrackType::Audio) => {
                 if let Ok(audio_profile) = track.audio_profile() {
@@ -180,52 +648,281 @@ fn handle_info_human(input: &str, theme: &Theme) -> Result<()> {
     Ok(())
 }
 
-fn handle_decode(
-    input: &str,
-    output: &str,
-    config: &OutputConfig,
-    theme: &Theme,
-) -> Result<()> {
-    let start_time = Instant::now();
+fn print_info_header(input: &str, metadata: &Metadata, theme: &Theme) {
+    println!("{}: {}", theme.highlight("File"), input);
+    println!("{}: {}", theme.highlight("Format"), metadata.format);
+    println!("{}: {}", theme.highlight("Streams"), metadata.stream_count);
 
-    let file = File::open(input)?;
-    let mut demuxer = Mp4Demuxer::new(file)?;
+    if let Some(duration_ms) = metadata.duration_ms {
+        let seconds = duration_ms / 1000;
+        let minutes = seconds / 60;
+        let hours = minutes / 60;
+        println!(
+            "{}: {}:{:02}:{:02}.{:03}",
+            theme.highlight("Duration"),
+            hours,
+            minutes % 60,
+            seconds % 60,
+            duration_ms % 1000
+        );
+    } else {
+        println!("{}: Unknown", theme.highlight("Duration"));
+    }
+}
+
+/// Stream description for containers other than MP4
+enum StreamInfo {
+    Video {
+        codec: String,
+        width: u32,
+        height: u32,
+        av1: Option<SequenceHeader>,
+    },
+    Audio {
+        codec: String,
+        sample_rate: u32,
+        channels: u32,
+    },
+}
+
+/// Probe IVF, raw OBU, Matroska and Ogg inputs, selected by extension
+///
+/// Returns `None` for anything else, which is read as MP4.
+fn probe_streams(input: &str) -> Result<Option<(Metadata, Vec<StreamInfo>)>> {
+    let extension = Path::new(input)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+
+    let probed = match extension.as_deref() {
+        Some("ivf") => {
+            // The first temporal unit carries the sequence header
+            let mut demuxer = IvfDemuxer::new(File::open(input)?)?;
+            demuxer.read_packet()?;
+            let stream = StreamInfo::Video {
+                codec: String::from_utf8_lossy(&demuxer.fourcc()).into_owned(),
+                width: u32::from(demuxer.width()),
+                height: u32::from(demuxer.height()),
+                av1: demuxer
+                    .av1_parser()
+                    .and_then(Av1Parser::sequence_header)
+                    .cloned(),
+            };
+            (demuxer.metadata().clone(), vec![stream])
+        }
+        Some("obu") => {
+            let mut demuxer = ObuDemuxer::new(File::open(input)?)?;
+            demuxer.read_packet()?;
+            let av1 = demuxer.parser().sequence_header().cloned();
+            let (width, height) = av1.as_ref().map_or((0, 0), |header| {
+                (header.max_frame_width, header.max_frame_height)
+            });
+            let stream = StreamInfo::Video {
+                codec: "AV1".to_string(),
+                width,
+                height,
+                av1,
+            };
+            (demuxer.metadata().clone(), vec![stream])
+        }
+        Some("webm" | "mkv") => {
+            let demuxer = MkvDemuxer::new(File::open(input)?)?;
+            let streams = demuxer
+                .tracks()
+                .iter()
+                .filter_map(|track| {
+                    if track.is_video() {
+                        // av1C: 4 bytes of flags, then the sequence header OBU
+                        let av1 = (track.codec_id == "V_AV1")
+                            .then(|| track.codec_private.get(4..).and_then(av1_sequence_header))
+                            .flatten();
+                        Some(StreamInfo::Video {
+                            codec: track.codec_id.clone(),
+                            width: track.width.unwrap_or(0),
+                            height: track.height.unwrap_or(0),
+                            av1,
+                        })
+                    } else if track.is_audio() {
+                        Some(StreamInfo::Audio {
+                            codec: track.codec_id.clone(),
+                            sample_rate: track.sample_rate.unwrap_or(0.0) as u32,
+                            channels: track.channels.unwrap_or(1),
+                        })
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            (demuxer.metadata().clone(), streams)
+        }
+        Some("opus" | "ogg" | "oga") => {
+            let demuxer = OggDemuxer::new(File::open(input)?)?;
+            let head = demuxer.head();
+            let stream = StreamInfo::Audio {
+                codec: "Opus".to_string(),
+                sample_rate: head.input_sample_rate,
+                channels: u32::from(head.channels),
+            };
+            (demuxer.metadata().clone(), vec![stream])
+        }
+        _ => return Ok(None),
+    };
+
+    Ok(Some(probed))
+}
+
+/// Read the AV1 sequence header from the first sample of each MP4 video track
+fn mp4_av1_headers(demuxer: &mut Mp4Demuxer<File>) -> HashMap<u32, SequenceHeader> {
+    let track_ids: Vec<u32> = demuxer.video_tracks().iter().map(|(id, _)| *id).collect();
+
+    let mut headers = HashMap::new();
+    for track_id in track_ids {
+        if demuxer.select_track(track_id).is_err() {
+            continue;
+        }
+        let packet = demuxer.read_packet().ok().flatten();
+        if let Some(header) = packet.and_then(|p| av1_sequence_header(&p.data)) {
+            headers.insert(track_id, header);
+        }
+    }
+    headers
+}
+
+/// Parse the sequence header from a run of low-overhead AV1 OBUs
+fn av1_sequence_header(data: &[u8]) -> Option<SequenceHeader> {
+    let mut parser = Av1Parser::new();
+    parser.parse_temporal_unit(data).ok()?;
+    parser.sequence_header().cloned()
+}
 
-    // Try to select an audio track
-    if demuxer.select_audio_track().is_err() {
-        return Err(anyhow::anyhow!("No audio tracks found in file"));
+fn av1_profile_name(profile: u8) -> &'static str {
+    match profile {
+        0 => "Main",
+        1 => "High",
+        _ => "Professional",
     }
+}
+
+fn av1_chroma_format(header: &SequenceHeader) -> &'static str {
+    let color = &header.color_config;
+    match (color.mono_chrome, color.subsampling_x, color.subsampling_y) {
+        (true, _, _) => "4:0:0",
+        (false, true, true) => "4:2:0",
+        (false, true, false) => "4:2:2",
+        _ => "4:4:4",
+    }
+}
+
+fn av1_level_name(header: &SequenceHeader) -> String {
+    match header.level_number() {
+        Some((major, minor)) => format!(
+            "{}.{} ({} tier)",
+            major,
+            minor,
+            if header.tier() { "High" } else { "Main" }
+        ),
+        None => "Unconstrained".to_string(),
+    }
+}
 
-    // Get total sample count for progress bar
-    let total_samples = demuxer
-        .audio_tracks()
-        .first()
-        .map(|(_, track)| track.sample_count() as u64)
-        .unwrap_or(0);
+fn av1_json(header: &SequenceHeader) -> serde_json::Value {
+    let color = &header.color_config;
+    serde_json::json!({
+        "profile": av1_profile_name(header.profile),
+        "level": av1_level_name(header),
+        "bit_depth": color.bit_depth,
+        "chroma_format": av1_chroma_format(header),
+        "color_primaries": color.color_primaries,
+        "transfer_characteristics": color.transfer_characteristics,
+        "matrix_coefficients": color.matrix_coefficients,
+        "full_range": color.full_range,
+        "max_width": header.max_frame_width,
+        "max_height": header.max_frame_height,
+        "frame_rate": header.frame_rate(),
+        "still_picture": header.still_picture,
+        "film_grain": header.film_grain_params_present,
+    })
+}
+
+fn print_av1_info(header: &SequenceHeader) {
+    let color = &header.color_config;
+    println!("    AV1 Profile: {}", av1_profile_name(header.profile));
+    println!("    AV1 Level: {}", av1_level_name(header));
+    println!("    Bit Depth: {}", color.bit_depth);
+    println!("    Chroma Format: {}", av1_chroma_format(header));
+    println!(
+        "    Color: primaries {}, transfer {}, matrix {}, {} range",
+        color.color_primaries,
+        color.transfer_characteristics,
+        color.matrix_coefficients,
+        if color.full_range { "full" } else { "limited" }
+    );
+    if let Some(fps) = header.frame_rate() {
+        println!("    Frame Rate: {:.3}", fps);
+    }
+    if header.film_grain_params_present {
+        println!("    Film Grain: yes");
+    }
+}
+
+fn handle_decode(input: &str, output: &str, config: &OutputConfig, theme: &Theme) -> Result<()> {
+    if is_video_decode(input, output) {
+        return handle_video_decode(input, output, config, theme);
+    }
+
+    let start_time = Instant::now();
+
+    let mut input_demuxer = AudioInput::open(input)?;
+    let AudioSetup {
+        mut decoder,
+        codec,
+        channels,
+        sample_rate,
+        pre_skip,
+        gain,
+    } = input_demuxer.setup()?;
+
+    if !config.quiet {
+        eprintln!(
+            "{}",
+            theme.info(&format!(
+                "Input: {}, {} Hz, {} channel(s)",
+                codec, sample_rate, channels
+            ))
+        );
+    }
 
     // Create output file
     let mut output_file = File::create(output)?;
 
-    // Create audio decoder (assume Opus for now)
-    let mut audio_decoder = OpusDecoderImpl::new(SampleRate::Hz48000, Channels::Stereo)?;
-
     // Create progress bar if appropriate
     let pb = if config.show_progress() {
-        Some(output::create_progress_bar(total_samples, "Decoding"))
+        match input_demuxer.packet_count() {
+            Some(total) => Some(output::create_progress_bar(total, "Decoding")),
+            None => Some(output::create_spinner("Decoding")),
+        }
     } else {
         None
     };
 
+    // Samples (interleaved) still to drop for the encoder's pre-skip
+    let mut skip = usize::from(pre_skip) * channels;
+
     // Decode packets
     let mut packet_count = 0;
-    while let Some(packet) = demuxer.read_packet()? {
+    while let Some(packet) = input_demuxer.read_packet()? {
         packet_count += 1;
 
         // Decode the audio packet
-        if let Some(samples) = audio_decoder.decode(&packet.data)? {
+        if let Some(mut samples) = decoder.decode(&packet.data)? {
+            let trim = input_demuxer.end_trim() as usize * channels;
+            samples.truncate(samples.len().saturating_sub(trim));
+            let dropped = skip.min(samples.len());
+            skip -= dropped;
+
             // Write raw PCM samples (little-endian f32)
-            for &sample in &samples {
-                output_file.write_all(&sample.to_le_bytes())?;
+            for &sample in &samples[dropped..] {
+                output_file.write_all(&(sample * gain).to_le_bytes())?;
             }
         }
 
@@ -257,26 +954,145 @@ fn handle_decode(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn handle_encode(
     input: &str,
     output: &str,
     codec: &str,
     encoder_name: &str,
+    preset: &PresetArgs,
+    color: &ColorArgs,
+    geometry: &GeometryArgs,
+    scale: &ScaleArgs,
+    rate: &RateArgs,
+    deinterlace: &DeinterlaceArgs,
+    denoise: &DenoiseArgs,
+    rate_control: &RateControlArgs,
+    two_pass: &TwoPassArgs,
+    vf: Option<&str>,
     config: &OutputConfig,
     theme: &Theme,
 ) -> Result<()> {
+    let encode_pass = |pass: Pass| {
+        encode_pass(
+            input,
+            output,
+            codec,
+            encoder_name,
+            preset,
+            color,
+            geometry,
+            scale,
+            rate,
+            deinterlace,
+            denoise,
+            rate_control,
+            vf,
+            pass,
+            config,
+            theme,
+        )
+    };
+    let first_pass = || {
+        encode_pass(Pass::First)?
+            .ok_or_else(|| anyhow::anyhow!("The encoder produced no first pass stats"))
+    };
+
+    match two_pass.pass {
+        _ if preset.show_settings => {
+            encode_pass(Pass::Single)?;
+        }
+        Some(1) => {
+            let stats = first_pass()?;
+            stats.save(&two_pass.passlog)?;
+            if !config.quiet {
+                eprintln!(
+                    "{}",
+                    theme.success(&format!("Wrote first pass stats to {}", two_pass.passlog))
+                );
+            }
+        }
+        Some(_) => {
+            let stats = PassStats::load(&two_pass.passlog).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to read first pass stats {}: {}",
+                    two_pass.passlog,
+                    e
+                )
+            })?;
+            encode_pass(Pass::Second(stats))?;
+        }
+        // The input is read once per pass, so it has to be a file
+        None if two_pass.two_pass && input == "-" => {
+            return Err(anyhow::anyhow!(
+                "--two-pass needs a file input; pipe the input to --pass 1 and --pass 2 instead"
+            ));
+        }
+        None if two_pass.two_pass => {
+            let stats = first_pass()?;
+            encode_pass(Pass::Second(stats))?;
+        }
+        None => {
+            encode_pass(Pass::Single)?;
+        }
+    }
+    Ok(())
+}
+
+/// Encode the input once; a first pass writes no output and returns its stats
+#[allow(clippy::too_many_arguments)]
+fn encode_pass(
+    input: &str,
+    output: &str,
+    codec: &str,
+    encoder_name: &str,
+    preset: &PresetArgs,
+    color: &ColorArgs,
+    geometry: &GeometryArgs,
+    scale: &ScaleArgs,
+    rate: &RateArgs,
+    deinterlace: &DeinterlaceArgs,
+    denoise: &DenoiseArgs,
+    rate_control: &RateControlArgs,
+    vf: Option<&str>,
+    pass: Pass,
+    config: &OutputConfig,
+    theme: &Theme,
+) -> Result<Option<PassStats>> {
     if codec != "av1" {
         return Err(anyhow::anyhow!("Only AV1 codec is supported currently"));
     }
 
-    // Parse encoder backend
-    let backend = EncoderBackend::from_str(encoder_name)
-        .ok_or_else(|| anyhow::anyhow!("Unknown encoder: {}. Use 'svt-av1' or 'rav1e'", encoder_name))?;
+    // Look up the encoder backend
+    let registry = encoders::registry();
+    let backend = registry.get(encoder_name).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Unknown encoder: {}. Use one of: {}",
+            encoder_name,
+            registry.names().collect::<Vec<_>>().join(", ")
+        )
+    })?;
+    let capabilities = backend.capabilities();
 
     let start_time = Instant::now();
 
     if !config.quiet {
-        eprintln!("{}", theme.info(&format!("Encoding {} -> {} (codec: {}, encoder: {})", input, output, codec, backend.as_str())));
+        let pass_name = match pass {
+            Pass::Single => "",
+            Pass::First => "First pass: ",
+            Pass::Second(_) => "Second pass: ",
+        };
+        eprintln!(
+            "{}",
+            theme.info(&format!(
+                "{}Encoding {} -> {} (codec: {}, encoder: {})",
+                pass_name,
+                input,
+                output,
+                codec,
+                backend.name()
+            ))
+        );
     }
 
     // Open Y4M input (file or stdin)
@@ -297,38 +1113,205 @@ fn handle_encode(
             "{}",
             theme.info(&format!(
                 "Input: {}x{} @ {}/{} fps ({:?})",
-                width, height, fps_num, fps_den, demuxer.pixel_format()
+                width,
+                height,
+                fps_num,
+                fps_den,
+                demuxer.pixel_format()
             ))
         );
     }
 
-    // Create encoder based on selection
-    let mut encoder: Box<dyn VideoEncoder> = match backend {
-        EncoderBackend::SvtAv1 => {
-            let svt_config = SvtAv1Config {
-                width,
-                height,
-                fps_num: fps_num as u32,
-                fps_den: fps_den as u32,
-                preset: 8, // Balanced preset
-                ..Default::default()
-            };
-            Box::new(SvtAv1Encoder::new(svt_config)?)
+    // Allocate frames the way the backend reads them best
+    let layout = capabilities.plane_layout;
+    demuxer.set_plane_layout(layout);
+
+    let crop = geometry.crop_borders(input)?;
+    if !crop.is_empty() && !config.quiet {
+        eprintln!(
+            "{}",
+            theme.info(&format!(
+                "Cropping {}:{}:{}:{}",
+                crop.left, crop.top, crop.right, crop.bottom
+            ))
+        );
+    }
+
+    // Deinterlace while fields are intact, drop frames before any other
+    // work, orient, then scale, denoise at the output size so measured
+    // grain matches it, pad, and finally run --vf
+    let mut filters = FilterChain::new();
+    if let Some(deinterlacer) = deinterlace.filter(demuxer.interlace()) {
+        if !config.quiet {
+            eprintln!(
+                "{}",
+                theme.info(&format!(
+                    "Deinterlacing {:?} input ({:?})",
+                    demuxer.interlace(),
+                    deinterlace.deinterlace
+                ))
+            );
+        }
+        filters.push(deinterlacer);
+    }
+    if let Some(fps) = rate.filter()? {
+        filters.push(fps);
+    }
+    geometry.orient_filters(&mut filters, crop);
+    if let Some(scaler) = scale.filter(layout) {
+        filters.push(scaler);
+    }
+    let mut noise = None;
+    if let Some(denoiser) = denoise.filter()? {
+        noise = denoiser.noise();
+        filters.push(denoiser);
+    }
+    geometry.pad_filter(&mut filters);
+    if let Some(spec) = vf {
+        filters.extend(spec.parse()?);
+    }
+
+    let filtered = filters.configure(VideoInfo {
+        width,
+        height,
+        format: demuxer.pixel_format(),
+        framerate: (fps_num, fps_den),
+    })?;
+    if !filters.is_empty() && !config.quiet {
+        eprintln!(
+            "{}",
+            theme.info(&format!(
+                "Filters: {} -> {}x{} @ {}/{} fps ({:?})",
+                filters.names().collect::<Vec<_>>().join(", "),
+                filtered.width,
+                filtered.height,
+                filtered.framerate.0,
+                filtered.framerate.1,
+                filtered.format
+            ))
+        );
+    }
+
+    // Convert output the encoder can't take, keeping the bit depth where possible
+    let pixel_format = capabilities.pixel_format_for(filtered.format);
+    let mut converter = Converter::new(
+        pixel_format,
+        ConvertConfig {
+            layout,
+            ..Default::default()
+        },
+    );
+    let VideoInfo {
+        width,
+        height,
+        framerate: (fps_num, fps_den),
+        ..
+    } = converter.configure(filtered)?;
+    if pixel_format != filtered.format {
+        if !config.quiet {
+            eprintln!(
+                "{}",
+                theme.info(&format!(
+                    "Converting {:?} -> {:?} for {}",
+                    filtered.format,
+                    pixel_format,
+                    backend.name()
+                ))
+            );
+        }
+        filters.push(converter);
+    }
+
+    if noise.is_some() && !capabilities.film_grain {
+        if !config.quiet {
+            eprintln!(
+                "{}",
+                theme.warning(&format!(
+                    "{} has no film grain synthesis, denoising without it",
+                    backend.name()
+                ))
+            );
         }
-        EncoderBackend::Rav1e => {
-            Box::new(Rav1eEncoder::new(width, height)?)
+        noise = None;
+    }
+
+    let mut rate_control = rate_control.rate_control()?;
+    match &mut rate_control {
+        Some(RateControl::Vbr {
+            max_bitrate: max @ Some(_),
+            ..
+        }) if !capabilities.max_bitrate => {
+            if !config.quiet {
+                eprintln!(
+                    "{}",
+                    theme.warning(&format!(
+                        "{} cannot cap the bitrate, ignoring --maxrate",
+                        backend.name()
+                    ))
+                );
+            }
+            *max = None;
         }
+        _ => {}
+    }
+    if let Some(rate_control) = rate_control.filter(|_| !config.quiet) {
+        eprintln!("{}", theme.info(&format!("Rate control: {}", rate_control)));
+    }
+    if let Some(rate_control) = &rate_control {
+        pass.validate(rate_control)?;
+    }
+
+    if !config.quiet {
+        let tune = preset
+            .tune
+            .map_or(String::new(), |tune| format!(", tuned for {}", tune));
+        eprintln!(
+            "{}",
+            theme.info(&format!("Preset: {}{}", preset.preset, tune))
+        );
+    }
+
+    // Configure the selected backend
+    let options = EncoderOptions {
+        width,
+        height,
+        framerate: (fps_num, fps_den),
+        pixel_format,
+        colorimetry: color.apply(demuxer.colorimetry()),
+        preset: preset.preset,
+        tune: preset.tune,
+        rate_control,
+        pass: pass.clone(),
+        ..Default::default()
+    };
+    if preset.show_settings {
+        // Film grain is only measured once encoding starts
+        println!("{}", backend.settings(&options));
+        return Ok(None);
+    }
+    let create_encoder = |film_grain: Option<FilmGrain>| {
+        registry.create(
+            backend.name(),
+            &EncoderOptions {
+                film_grain,
+                ..options.clone()
+            },
+        )
+    };
+    // Film grain is measured on the first denoised frames, so the encoder
+    // waits for them
+    let mut encoder = match noise {
+        Some(_) => None,
+        None => Some(create_encoder(None)?),
     };
 
-    // Create IVF muxer
-    let output_file = File::create(output)?;
-    let mut muxer = IvfMuxer::new(
-        output_file,
-        width as u16,
-        height as u16,
-        fps_num as u32,
-        fps_den as u32,
-    )?;
+    // Create muxer based on output extension; a first pass only keeps stats
+    let mut muxer = match pass {
+        Pass::First => None,
+        _ => Some(OutputMuxer::create(
+            output, width, height, fps_num, fps_den,
+        )?),
+    };
 
     // Create progress bar (indeterminate if stdin, since we don't know frame count)
     let pb = if config.show_progress() {
@@ -342,26 +1325,31 @@ fn handle_encode(
         None
     };
 
-    // Read and encode frames from Y4M
+    // Encode one filtered frame, or flush with `None`, and write the
+    // packets it completes
     let mut frame_count = 0u64;
-    while let Some(frame) = demuxer.read_frame()? {
-        // Wrap frame in Arc for zero-copy
-        let arc_frame = Arc::new(frame);
-
-        // Encode frame
-        encoder.send_frame(Some(arc_frame))?;
+    let mut encode = |encoder: &mut dyn VideoEncoder, frame: Option<ArcFrame>| -> Result<()> {
+        let flushing = frame.is_none();
+        encoder.send_frame(frame)?;
 
         // Receive encoded packets
-        while let Some(packet_data) = encoder.receive_packet()? {
+        while let Some(encoded) = encoder.receive_packet()? {
+            let Some(muxer) = muxer.as_mut() else {
+                continue;
+            };
+            let is_keyframe = encoded.is_keyframe();
             let packet = Packet {
                 stream_index: 0,
-                data: packet_data,
-                pts: Some(frame_count as i64),
-                dts: None,
-                is_keyframe: frame_count == 0,
+                data: encoded.data,
+                pts: Some(encoded.pts),
+                dts: Some(encoded.dts),
+                is_keyframe,
             };
             muxer.write_packet(packet)?;
         }
+        if flushing {
+            return Ok(());
+        }
 
         frame_count += 1;
 
@@ -373,19 +1361,55 @@ fn handle_encode(
                 pb.set_message(format!("{} frames ({:.1} fps)", frame_count, fps_actual));
             }
         }
-    }
+        Ok(())
+    };
 
-    // Flush encoder
-    encoder.send_frame(None)?;
-    while let Some(packet_data) = encoder.receive_packet()? {
-        let packet = Packet {
-            stream_index: 0,
-            data: packet_data,
-            pts: Some(frame_count as i64),
-            dts: None,
-            is_keyframe: false,
-        };
-        muxer.write_packet(packet)?;
+    // Read, filter and encode frames from Y4M
+    let mut pending = Vec::new();
+    loop {
+        let input_frame = demuxer.read_frame()?;
+        let end_of_input = input_frame.is_none();
+        match input_frame {
+            Some(frame) => pending.extend(filters.filter(Arc::new(frame))?),
+            None => pending.extend(filters.flush()?),
+        }
+
+        if encoder.is_none() && (end_of_input || pending.len() >= GRAIN_ANALYSIS_FRAMES) {
+            let film_grain = noise.as_ref().and_then(|noise| noise.film_grain());
+            if !config.quiet {
+                let measured = noise.as_ref().map_or(0, |noise| noise.frames());
+                let message = match &film_grain {
+                    Some(grain) => format!(
+                        "Film grain from {} denoised frames, luma strength up to {}",
+                        measured,
+                        grain
+                            .luma
+                            .iter()
+                            .map(|&(_, scaling)| scaling)
+                            .max()
+                            .unwrap_or(0)
+                    ),
+                    None => format!(
+                        "No noise measured in {} denoised frames, encoding without film grain",
+                        measured
+                    ),
+                };
+                eprintln!("{}", theme.info(&message));
+            }
+            encoder = Some(create_encoder(film_grain)?);
+        }
+        if let Some(encoder) = encoder.as_deref_mut() {
+            for frame in pending.drain(..) {
+                encode(encoder, Some(frame))?;
+            }
+            if end_of_input {
+                // Flush encoder
+                encode(encoder, None)?;
+            }
+        }
+        if end_of_input {
+            break;
+        }
     }
 
     if let Some(pb) = pb {
@@ -393,23 +1417,531 @@ fn handle_encode(
     }
 
     // Finalize muxer
-    muxer.finalize()?;
+    if let Some(muxer) = muxer {
+        muxer.finalize()?;
+    }
 
     let elapsed = start_time.elapsed();
     let actual_fps = frame_count as f64 / elapsed.as_secs_f64();
 
     if !config.quiet {
+        let destination = match pass {
+            Pass::First => "first pass stats".to_string(),
+            _ => output.to_string(),
+        };
         eprintln!(
             "{}",
             theme.success(&format!(
                 "Encoded {} frames to {} in {} ({:.1} fps)",
                 frame_count,
-                output,
+                destination,
                 output::format_duration(elapsed),
                 actual_fps
             ))
         );
     }
 
+    // The encoder has been flushed, so a first pass has its stats
+    Ok(match encoder {
+        Some(encoder) => encoder.pass_stats()?,
+        None => None,
+    })
+}
+
+/// Returns true when `decode` should produce video: Y4M output, or an
+/// input container that only carries video
+fn is_video_decode(input: &str, output: &str) -> bool {
+    let extension = |path: &str| {
+        Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase())
+    };
+
+    matches!(extension(output).as_deref(), Some("y4m"))
+        || matches!(extension(input).as_deref(), Some("ivf" | "obu"))
+}
+
+fn handle_video_decode(
+    input: &str,
+    output: &str,
+    config: &OutputConfig,
+    theme: &Theme,
+) -> Result<()> {
+    let start_time = Instant::now();
+
+    let mut input_demuxer = VideoInput::open(input)?;
+    let mut decoder = decoders::create_av1_decoder()?;
+
+    // Create progress bar if appropriate
+    let pb = if config.show_progress() {
+        match input_demuxer.packet_count() {
+            Some(total) => Some(output::create_progress_bar(total, "Decoding")),
+            None => Some(output::create_spinner("Decoding")),
+        }
+    } else {
+        None
+    };
+
+    // Created from the first frame, once size and bit depth are known
+    let mut muxer = None;
+    let mut frame_count = 0u64;
+
+    while let Some(packet) = input_demuxer.read_packet()? {
+        if let Some(frame) = decoder.decode(&packet.data)? {
+            write_y4m_frame(&mut muxer, &frame, output, input_demuxer.framerate())?;
+            frame_count += 1;
+        }
+
+        // Update progress
+        if let Some(ref pb) = pb {
+            pb.inc(1);
+        }
+    }
+
+    // Flush decoder
+    for frame in decoder.flush()? {
+        write_y4m_frame(&mut muxer, &frame, output, input_demuxer.framerate())?;
+        frame_count += 1;
+    }
+
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+
+    let Some(muxer) = muxer else {
+        return Err(anyhow::anyhow!("No frames decoded from {}", input));
+    };
+    muxer.finalize()?;
+
+    let elapsed = start_time.elapsed();
+
+    if !config.quiet {
+        eprintln!(
+            "{}",
+            theme.success(&format!(
+                "Decoded {} frames to {} in {} ({:.1} fps)",
+                frame_count,
+                output,
+                output::format_duration(elapsed),
+                frame_count as f64 / elapsed.as_secs_f64()
+            ))
+        );
+    }
+
     Ok(())
 }
+
+/// Write a decoded frame, creating the Y4M output on the first call
+fn write_y4m_frame(
+    muxer: &mut Option<Y4mMuxer<BufWriter<File>>>,
+    frame: &Frame,
+    output: &str,
+    framerate: (u64, u64),
+) -> Result<()> {
+    if muxer.is_none() {
+        let config = Y4mMuxerConfig {
+            width: frame.width(),
+            height: frame.height(),
+            framerate,
+            pixel_format: frame.format(),
+            colorimetry: *frame.colorimetry(),
+            ..Default::default()
+        };
+        *muxer = Some(Y4mMuxer::new(
+            BufWriter::new(File::create(output)?),
+            config,
+        )?);
+    }
+
+    if let Some(muxer) = muxer {
+        muxer.write_frame(frame)?;
+    }
+    Ok(())
+}
+
+/// AV1 video input container, selected from the input file extension
+enum VideoInput {
+    Ivf(IvfDemuxer<File>),
+    Obu(ObuDemuxer<File>),
+    /// Demuxer and the index of its AV1 track
+    Mkv(MkvDemuxer<File>, usize),
+    Mp4(Mp4Demuxer<File>),
+}
+
+impl VideoInput {
+    fn open(path: &str) -> Result<Self> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        let file = File::open(path)?;
+
+        match extension.as_deref() {
+            Some("ivf") => {
+                let demuxer = IvfDemuxer::new(file)?;
+                if demuxer.av1_parser().is_none() {
+                    return Err(anyhow::anyhow!(
+                        "Unsupported IVF codec: {}",
+                        String::from_utf8_lossy(&demuxer.fourcc())
+                    ));
+                }
+                Ok(Self::Ivf(demuxer))
+            }
+            Some("obu") => Ok(Self::Obu(ObuDemuxer::new(file)?)),
+            Some("webm" | "mkv") => {
+                let demuxer = MkvDemuxer::new(file)?;
+                let track = demuxer
+                    .tracks()
+                    .iter()
+                    .position(|track| track.codec_id == "V_AV1")
+                    .ok_or_else(|| anyhow::anyhow!("No AV1 video track found in file"))?;
+                Ok(Self::Mkv(demuxer, track))
+            }
+            _ => {
+                let mut demuxer = Mp4Demuxer::new(file)?;
+                if demuxer.select_video_track().is_err() {
+                    return Err(anyhow::anyhow!("No video tracks found in file"));
+                }
+                Ok(Self::Mp4(demuxer))
+            }
+        }
+    }
+
+    /// Frame rate as (numerator, denominator), 25 fps if the input has none
+    fn framerate(&self) -> (u64, u64) {
+        let framerate = match self {
+            // The IVF timebase is seconds per frame
+            Self::Ivf(demuxer) => {
+                let (num, den) = demuxer.timebase();
+                (u64::from(den), u64::from(num))
+            }
+            Self::Obu(demuxer) => demuxer
+                .parser()
+                .sequence_header()
+                .and_then(|header| header.timing_info.as_ref())
+                .map_or((0, 0), |timing| {
+                    let ticks = timing.num_ticks_per_picture.unwrap_or(1);
+                    (
+                        u64::from(timing.time_scale),
+                        u64::from(timing.num_units_in_display_tick) * u64::from(ticks),
+                    )
+                }),
+            Self::Mkv(demuxer, track) => demuxer.tracks()[*track]
+                .default_duration_ns
+                .map_or((0, 0), |duration| (1_000_000_000, duration)),
+            Self::Mp4(demuxer) => demuxer.video_tracks().first().map_or((0, 0), |(_, track)| {
+                ((track.frame_rate() * 1000.0).round() as u64, 1000)
+            }),
+        };
+
+        match framerate {
+            (0, _) | (_, 0) => (25, 1),
+            framerate => framerate,
+        }
+    }
+
+    fn packet_count(&self) -> Option<u64> {
+        match self {
+            Self::Ivf(demuxer) => Some(u64::from(demuxer.frame_count())).filter(|&count| count > 0),
+            Self::Mp4(demuxer) => demuxer
+                .video_tracks()
+                .first()
+                .map(|(_, track)| track.sample_count() as u64),
+            Self::Obu(_) | Self::Mkv(..) => None,
+        }
+    }
+
+    fn read_packet(&mut self) -> mead_core::Result<Option<Packet>> {
+        match self {
+            Self::Ivf(demuxer) => demuxer.read_packet(),
+            Self::Obu(demuxer) => demuxer.read_packet(),
+            Self::Mkv(demuxer, track) => {
+                // Skip packets from the other tracks
+                while let Some(packet) = demuxer.read_packet()? {
+                    if packet.stream_index == *track {
+                        return Ok(Some(packet));
+                    }
+                }
+                Ok(None)
+            }
+            Self::Mp4(demuxer) => demuxer.read_packet(),
+        }
+    }
+}
+
+/// Audio input container, selected from the input file extension
+enum AudioInput {
+    Mp4(Mp4Demuxer<File>),
+    Ogg(OggDemuxer<File>),
+    /// Raw ADTS stream, fed to the decoder in fixed-size chunks
+    Adts(File),
+}
+
+/// Decoder and output parameters for the selected audio track
+struct AudioSetup {
+    decoder: Box<dyn AudioDecoder>,
+    codec: &'static str,
+    channels: usize,
+    sample_rate: u32,
+    /// Samples per channel to drop from the start
+    pre_skip: u16,
+    /// Linear output gain
+    gain: f32,
+}
+
+/// Bytes read per ADTS input chunk
+const ADTS_CHUNK_SIZE: usize = 16 * 1024;
+
+impl AudioInput {
+    fn open(path: &str) -> Result<Self> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+        let file = File::open(path)?;
+
+        match extension.as_deref() {
+            Some("opus" | "ogg" | "oga") => Ok(Self::Ogg(OggDemuxer::new(file)?)),
+            Some("aac" | "adts") => Ok(Self::Adts(file)),
+            _ => {
+                let mut demuxer = Mp4Demuxer::new(file)?;
+                if demuxer.select_audio_track().is_err() {
+                    return Err(anyhow::anyhow!("No audio tracks found in file"));
+                }
+                Ok(Self::Mp4(demuxer))
+            }
+        }
+    }
+
+    /// Create the decoder: AAC for MP4 tracks with an `esds` box, otherwise
+    /// Opus (stereo for MP4, per the OpusHead for Ogg)
+    fn setup(&self) -> Result<AudioSetup> {
+        let (channels, pre_skip, gain) = match self {
+            Self::Mp4(demuxer) => {
+                let esds = demuxer.audio_tracks().first().and_then(|(_, track)| {
+                    match track.media_type() {
+                        Ok(mp4::MediaType::AAC) => {
+                            track.trak.mdia.minf.stbl.stsd.mp4a.as_ref()?.esds.clone()
+                        }
+                        _ => None,
+                    }
+                });
+                if let Some(esds) = esds {
+                    // The mp4 crate exposes the AudioSpecificConfig fields
+                    let specific = &esds.es_desc.dec_config.dec_specific;
+                    let config = AacConfig::from_indices(
+                        specific.profile,
+                        specific.freq_index,
+                        specific.chan_conf,
+                    )?;
+                    return Ok(AudioSetup {
+                        decoder: Box::new(AacDecoder::new(config)?),
+                        codec: "AAC",
+                        channels: usize::from(config.channels),
+                        sample_rate: config.sample_rate,
+                        pre_skip: 0,
+                        gain: 1.0,
+                    });
+                }
+                (2, 0, 1.0)
+            }
+            Self::Ogg(demuxer) => {
+                let head = demuxer.head();
+                // Surround streams need a multistream decoder
+                if head.channels > 2 || head.mapping_family != 0 {
+                    return Err(mead_core::Error::UnsupportedFormat(format!(
+                        "Opus with {} channels (mapping family {})",
+                        head.channels, head.mapping_family
+                    ))
+                    .into());
+                }
+                (head.channels, head.pre_skip, head.gain_factor())
+            }
+            Self::Adts(file) => {
+                // Peek at the first header for the output parameters
+                let mut header = [0u8; 7];
+                let mut reader = file;
+                reader.read_exact(&mut header)?;
+                reader.rewind()?;
+                let config = AdtsHeader::parse(&header)?.config;
+                return Ok(AudioSetup {
+                    decoder: Box::new(AacDecoder::new_adts()),
+                    codec: "AAC (ADTS)",
+                    channels: usize::from(config.channels),
+                    sample_rate: config.sample_rate,
+                    pre_skip: 0,
+                    gain: 1.0,
+                });
+            }
+        };
+
+        let decoder = OpusDecoderImpl::new(
+            SampleRate::Hz48000,
+            if channels == 1 {
+                Channels::Mono
+            } else {
+                Channels::Stereo
+            },
+        )?;
+        Ok(AudioSetup {
+            channels: decoder.channels(),
+            decoder: Box::new(decoder),
+            codec: "Opus",
+            sample_rate: 48000,
+            pre_skip,
+            gain,
+        })
+    }
+
+    fn packet_count(&self) -> Option<u64> {
+        match self {
+            Self::Mp4(demuxer) => demuxer
+                .audio_tracks()
+                .first()
+                .map(|(_, track)| track.sample_count() as u64),
+            Self::Ogg(_) | Self::Adts(_) => None,
+        }
+    }
+
+    fn read_packet(&mut self) -> mead_core::Result<Option<Packet>> {
+        match self {
+            Self::Mp4(demuxer) => demuxer.read_packet(),
+            Self::Ogg(demuxer) => demuxer.read_packet(),
+            Self::Adts(file) => {
+                let mut data = Vec::with_capacity(ADTS_CHUNK_SIZE);
+                file.take(ADTS_CHUNK_SIZE as u64).read_to_end(&mut data)?;
+                if data.is_empty() {
+                    return Ok(None);
+                }
+                Ok(Some(Packet {
+                    stream_index: 0,
+                    data,
+                    pts: None,
+                    dts: None,
+                    is_keyframe: true,
+                }))
+            }
+        }
+    }
+
+    /// Samples per channel to drop from the end of the packet just read
+    fn end_trim(&self) -> u32 {
+        match self {
+            Self::Mp4(_) | Self::Adts(_) => 0,
+            Self::Ogg(demuxer) => demuxer.end_trim(),
+        }
+    }
+}
+
+/// Output container, selected from the output file extension
+enum OutputMuxer {
+    Ivf(IvfMuxer<File>),
+    Mp4(Mp4Muxer<File>),
+    Mkv(MkvMuxer<File>),
+    Obu(ObuMuxer<File>),
+    LiveWebm(MkvMuxer<WriteOnlySink<Stdout>>),
+}
+
+impl OutputMuxer {
+    fn create(path: &str, width: u32, height: u32, fps_num: u64, fps_den: u64) -> Result<Self> {
+        let (Ok(fps_num), Ok(fps_den)) = (u32::try_from(fps_num), u32::try_from(fps_den)) else {
+            return Err(anyhow::anyhow!(
+                "Frame rate {}/{} is out of range for the output",
+                fps_num,
+                fps_den
+            ));
+        };
+        let tracks = vec![MkvTrackConfig::Av1 {
+            width,
+            height,
+            fps_num,
+            fps_den,
+        }];
+
+        if path == "-" {
+            let config = MkvMuxerConfig {
+                tracks,
+                live: true,
+                ..Default::default()
+            };
+            return Ok(Self::LiveWebm(MkvMuxer::new(
+                WriteOnlySink::new(stdout()),
+                config,
+            )?));
+        }
+
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_lowercase());
+
+        match extension.as_deref() {
+            Some("mp4") => {
+                // Faststart rewrites the file in place, so it must be readable too
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(path)?;
+                let config = Mp4MuxerConfig {
+                    tracks: vec![Mp4TrackConfig::Av1 {
+                        width,
+                        height,
+                        fps_num,
+                        fps_den,
+                    }],
+                };
+                Ok(Self::Mp4(Mp4Muxer::new_faststart(file, config)?))
+            }
+            Some(ext @ ("webm" | "mkv")) => {
+                let doc_type = if ext == "webm" {
+                    DocType::WebM
+                } else {
+                    DocType::Matroska
+                };
+                let config = MkvMuxerConfig {
+                    tracks,
+                    doc_type,
+                    live: false,
+                };
+                Ok(Self::Mkv(MkvMuxer::new(File::create(path)?, config)?))
+            }
+            Some("obu") => Ok(Self::Obu(ObuMuxer::new(
+                File::create(path)?,
+                ObuFormat::LowOverhead,
+            ))),
+            _ => {
+                let file = File::create(path)?;
+                Ok(Self::Ivf(IvfMuxer::new(
+                    file,
+                    width as u16,
+                    height as u16,
+                    fps_num,
+                    fps_den,
+                )?))
+            }
+        }
+    }
+
+    fn write_packet(&mut self, packet: Packet) -> mead_core::Result<()> {
+        match self {
+            Self::Ivf(muxer) => muxer.write_packet(packet),
+            Self::Mp4(muxer) => muxer.write_packet(packet),
+            Self::Mkv(muxer) => muxer.write_packet(packet),
+            Self::Obu(muxer) => muxer.write_packet(packet),
+            Self::LiveWebm(muxer) => muxer.write_packet(packet),
+        }
+    }
+
+    fn finalize(self) -> mead_core::Result<()> {
+        match self {
+            Self::Ivf(muxer) => muxer.finalize(),
+            Self::Mp4(muxer) => muxer.finalize(),
+            Self::Mkv(muxer) => muxer.finalize(),
+            Self::Obu(muxer) => muxer.finalize(),
+            Self::LiveWebm(muxer) => muxer.finalize(),
+        }
+    }
+}
@@ -6,7 +6,7 @@
 //! - Human-readable formatting
 //! - Respects NO_COLOR and --no-color
 
-use console::{style, Term};
+use console::{Term, style};
 use indicatif::{HumanBytes, HumanDuration, ProgressBar, ProgressStyle};
 use std::time::Duration;
 
@@ -104,11 +104,10 @@ pub fn create_progress_bar(total: u64, operation: &str) -> ProgressBar {
     let pb = ProgressBar::new(total);
 
     // Custom style matching FFmpeg's output but prettier
-    let style = ProgressStyle::with_template(
-        "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}"
-    )
-    .unwrap()
-    .progress_chars("█▓░");
+    let style =
+        ProgressStyle::with_template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("█▓░");
 
     pb.set_style(style);
     pb.set_message(format!("{} frames", operation));
@@ -176,7 +175,9 @@ mod tests {
     #[test]
     fn test_format_bytes() {
         assert!(format_bytes(1024).contains("KiB") || format_bytes(1024).contains("KB"));
-        assert!(format_bytes(1024 * 1024).contains("MiB") || format_bytes(1024 * 1024).contains("MB"));
+        assert!(
+            format_bytes(1024 * 1024).contains("MiB") || format_bytes(1024 * 1024).contains("MB")
+        );
     }
 
     #[test]